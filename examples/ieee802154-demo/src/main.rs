@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+#[cfg(not(any(feature = "52833", feature = "52840")))]
+compile_error!(
+    "This example requires one of the following device features enabled:
+        52833
+        52840"
+);
+
+// Import the right HAL/PAC crate, depending on the target chip
+#[cfg(feature = "52833")]
+pub use nrf52833_hal as hal;
+#[cfg(feature = "52840")]
+pub use nrf52840_hal as hal;
+
+use {
+    core::{
+        panic::PanicInfo,
+        sync::atomic::{compiler_fence, Ordering},
+    },
+    cortex_m_rt::entry,
+    hal::{
+        ppi,
+        prelude::*,
+        radio::ieee802154::{CsmaConfig, Mac, Packet, Radio},
+        timer::Timer,
+        Clocks, Rng,
+    },
+    rtt_target::{rprintln, rtt_init_print},
+};
+
+#[entry]
+fn main() -> ! {
+    let p = hal::pac::Peripherals::take().unwrap();
+
+    let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+    rtt_init_print!();
+
+    let ppi_channels = ppi::Parts::new(p.PPI);
+    let seed = Rng::new(p.RNG).random_u32();
+
+    // The largest backoff exponent, giving backoffs of up to 255 * 320 µs.
+    let config = CsmaConfig {
+        min_be: 8,
+        max_be: 8,
+        ..CsmaConfig::default()
+    };
+    let radio = Radio::init(p.RADIO, &clocks);
+    let mut mac = Mac::new(
+        radio,
+        p.TIMER0,
+        (ppi_channels.ppi0, ppi_channels.ppi1),
+        config,
+        seed,
+    );
+
+    let mut delay = Timer::new(p.TIMER1);
+    let mut packet = Packet::new();
+    let mut sequence = 0u8;
+
+    loop {
+        // Data frame without acknowledgement request to the broadcast address, with PAN ID
+        // compression and short addresses.
+        packet.copy_from_slice(&[
+            0x41, 0x88, sequence, 0xCD, 0xAB, 0xFF, 0xFF, 0x01, 0x00, b'h', b'i',
+        ]);
+
+        match mac.send(&mut packet) {
+            Ok(_) => rprintln!("Sent frame {}", sequence),
+            Err(e) => rprintln!("Failed to send frame {}: {:?}", sequence, e),
+        }
+        sequence = sequence.wrapping_add(1);
+
+        delay.delay_ms(1000u32);
+    }
+}
+
+#[inline(never)]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    rprintln!("{}", info);
+    loop {
+        compiler_fence(Ordering::SeqCst);
+    }
+}
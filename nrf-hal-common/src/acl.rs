@@ -0,0 +1,129 @@
+//! HAL interface to the ACL peripheral.
+//!
+//! The access control lists (ACL) protect regions of the flash against writes, or against any
+//! access, until the next reset. A bootloader locks its own code and its key storage this way
+//! before starting the application, which can then neither modify nor read them. There is no
+//! separate execute permission: a region protected against reads can't be executed either.
+//!
+//! A region can only be configured once: after `Acl::protect` its permissions stay in effect,
+//! and can't be relaxed, until the next reset. Violations raise a bus fault or hard fault.
+//!
+//! See product specification, chapter ACL.
+
+use core::ops::Range;
+
+use crate::error::ErrorKind;
+use crate::pac::ACL;
+
+/// Number of regions.
+pub const REGIONS: usize = 8;
+
+/// Size of a flash page, to which the regions are aligned.
+pub const PAGE_SIZE: u32 = 4096;
+
+// Bits of the `PERM` register.
+const PERM_WRITE_DISABLE: u32 = 1 << 1;
+const PERM_READ_DISABLE: u32 = 1 << 2;
+
+/// Protection of a flash region.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Protection {
+    /// The region can be read and executed, but not written or erased.
+    ReadOnly,
+    /// The region can't be accessed at all.
+    NoAccess,
+}
+
+/// Interface to the ACL peripheral.
+pub struct Acl(ACL);
+
+impl Acl {
+    pub fn new(acl: ACL) -> Self {
+        Self(acl)
+    }
+
+    /// Returns the raw `ACL` peripheral.
+    pub fn free(self) -> ACL {
+        self.0
+    }
+
+    /// Protects the flash in `range`, which must be aligned to `PAGE_SIZE`, using `region`.
+    ///
+    /// The protection stays in effect until the next reset.
+    pub fn protect(
+        &mut self,
+        region: usize,
+        range: Range<u32>,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        if region >= REGIONS {
+            return Err(Error::InvalidRegion);
+        }
+        if range.start % PAGE_SIZE != 0 || range.end % PAGE_SIZE != 0 || range.end <= range.start {
+            return Err(Error::Unaligned);
+        }
+        if self.is_in_use(region) {
+            return Err(Error::RegionInUse);
+        }
+
+        let acl = &self.0.acl[region];
+        acl.addr.write(|w| unsafe { w.bits(range.start) });
+        acl.size
+            .write(|w| unsafe { w.bits(range.end - range.start) });
+        acl.perm.write(|w| unsafe {
+            w.bits(match protection {
+                Protection::ReadOnly => PERM_WRITE_DISABLE,
+                Protection::NoAccess => PERM_WRITE_DISABLE | PERM_READ_DISABLE,
+            })
+        });
+        Ok(())
+    }
+
+    /// Returns `true` if `region` has been configured since the last reset.
+    pub fn is_in_use(&self, region: usize) -> bool {
+        self.0.acl[region].size.read().bits() != 0
+    }
+
+    /// Returns the range and protection of `region`, or `None` if it is not in use.
+    pub fn region(&self, region: usize) -> Option<(Range<u32>, Protection)> {
+        let acl = &self.0.acl[region];
+        let size = acl.size.read().bits();
+        if size == 0 {
+            return None;
+        }
+        let start = acl.addr.read().bits();
+        let protection = if acl.perm.read().bits() & PERM_READ_DISABLE != 0 {
+            Protection::NoAccess
+        } else {
+            Protection::ReadOnly
+        };
+        Some((start..start + size, protection))
+    }
+
+    /// Returns the first region that is not in use yet.
+    pub fn free_region(&self) -> Option<usize> {
+        (0..REGIONS).find(|&region| !self.is_in_use(region))
+    }
+}
+
+/// ACL error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The region does not exist.
+    InvalidRegion,
+    /// The range is empty or not aligned to the page size.
+    Unaligned,
+    /// The region has already been configured since the last reset.
+    RegionInUse,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::InvalidRegion | Error::Unaligned => ErrorKind::InvalidArgument,
+            Error::RegionInUse => ErrorKind::Busy,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Access port protection (APPROTECT).
+//!
+//! Access port protection keeps a debugger from accessing the CPU, memory and peripherals. It is
+//! configured in the UICR, and only lifted again by a full chip erase through the CTRL-AP.
+//!
+//! Newer, hardened revisions of the nRF52 chips enable the protection at every reset, unless
+//! both the UICR holds `HwDisabled` and the firmware writes the `APPROTECT.DISABLE` register
+//! early at startup. The functions here hide that difference:
+//!
+//! ```ignore
+//! // At the start of `main`, before anything else.
+//! let mut uicr = Uicr::new(p.UICR);
+//! let written = approtect::ensure(&mut uicr, &mut nvmc, Protection::Disabled);
+//! if written == Written::ResetRequired {
+//!     cortex_m::peripheral::SCB::sys_reset();
+//! }
+//! approtect::apply(&uicr);
+//! ```
+
+use core::ptr;
+
+use crate::pac::{FICR, NVMC};
+use crate::uicr::{Error, Register, Uicr, Written};
+
+/// Address of the `APPROTECT.FORCEPROTECT` register of hardened revisions.
+const FORCEPROTECT: *mut u32 = 0x4000_0550 as *mut u32;
+
+/// Address of the `APPROTECT.DISABLE` register of hardened revisions.
+const DISABLE: *mut u32 = 0x4000_0558 as *mut u32;
+
+/// `PALL` field of `UICR.APPROTECT`.
+const PALL_MASK: u32 = 0xff;
+/// `PALL` value disabling the protection on older revisions.
+const PALL_DISABLED: u32 = 0xff;
+/// `PALL` value allowing the firmware to disable the protection on hardened revisions.
+const PALL_HW_DISABLED: u32 = 0x5a;
+/// `PALL` value enabling the protection.
+const PALL_ENABLED: u32 = 0x00;
+
+/// Value of `APPROTECT.DISABLE` disabling the protection until the next reset.
+const SW_DISABLE: u32 = 0x5a;
+/// Value of `APPROTECT.FORCEPROTECT` enabling the protection until the next reset.
+const FORCE: u32 = 0x00;
+
+/// First build code, the third character of `FICR.INFO.VARIANT`, with hardened protection.
+#[cfg(feature = "52805")]
+const HARDENED_BUILD_CODE: u8 = b'B';
+#[cfg(feature = "52810")]
+const HARDENED_BUILD_CODE: u8 = b'E';
+#[cfg(feature = "52820")]
+const HARDENED_BUILD_CODE: u8 = b'D';
+#[cfg(feature = "52832")]
+const HARDENED_BUILD_CODE: u8 = b'G';
+#[cfg(feature = "52833")]
+const HARDENED_BUILD_CODE: u8 = b'B';
+#[cfg(feature = "52840")]
+const HARDENED_BUILD_CODE: u8 = b'F';
+
+/// Access port protection setting.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Protection {
+    /// A debugger can access the chip.
+    Disabled,
+    /// Debug access is blocked, until the chip is fully erased.
+    Enabled,
+}
+
+/// Returns `true` if this chip is a hardened revision, which enables the protection at reset
+/// unless the firmware disables it.
+pub fn is_hardened() -> bool {
+    let variant = unsafe { (*FICR::ptr()).info.variant.read().bits() };
+    (variant >> 8) as u8 >= HARDENED_BUILD_CODE
+}
+
+/// Returns the protection configured in the UICR.
+pub fn configured(uicr: &Uicr) -> Protection {
+    let pall = uicr.read(Register::Approtect) & PALL_MASK;
+    let disabled = if is_hardened() {
+        pall == PALL_HW_DISABLED
+    } else {
+        pall == PALL_DISABLED
+    };
+    if disabled {
+        Protection::Disabled
+    } else {
+        Protection::Enabled
+    }
+}
+
+/// Makes sure the UICR configures `protection`, keeping all other registers if the UICR has to
+/// be erased.
+///
+/// Enabling the protection never needs an erase, disabling it does if it was enabled before. On
+/// `Written::ResetRequired`, reset the chip for the setting to take effect.
+pub fn ensure(uicr: &mut Uicr, nvmc: &mut NVMC, protection: Protection) -> Written {
+    if configured(uicr) == protection {
+        return Written::Unchanged;
+    }
+    let pall = match protection {
+        Protection::Enabled => PALL_ENABLED,
+        Protection::Disabled if is_hardened() => PALL_HW_DISABLED,
+        Protection::Disabled => PALL_DISABLED,
+    };
+    let value = (uicr.read(Register::Approtect) & !PALL_MASK) | pall;
+    match uicr.write(nvmc, Register::Approtect, value) {
+        Ok(written) => written,
+        Err(Error::EraseRequired) => {
+            uicr.erase_and_write(nvmc, Register::Approtect, !PALL_MASK | pall)
+        }
+    }
+}
+
+/// Applies the protection configured in the UICR on hardened revisions, by disabling the
+/// protection until the next reset if the UICR allows it. Does nothing on older revisions.
+///
+/// Must be called early after every reset, as the debugger is locked out until then.
+pub fn apply(uicr: &Uicr) {
+    if is_hardened() && configured(uicr) == Protection::Disabled {
+        unsafe { ptr::write_volatile(DISABLE, SW_DISABLE) };
+    }
+}
+
+/// Enables the protection until the next reset, whatever the UICR configures, e.g. while
+/// secrets are held in RAM. Only available on hardened revisions, returns `false` otherwise.
+pub fn force_until_reset() -> bool {
+    if !is_hardened() {
+        return false;
+    }
+    unsafe { ptr::write_volatile(FORCEPROTECT, FORCE) };
+    true
+}
@@ -0,0 +1,141 @@
+//! Conversions between audio samples and the buffer layouts of the I2S and PDM peripherals.
+//!
+//! I2S buffers are 32 bit words holding one sample of 24 bits, two samples of 16 bits or four
+//! samples of 8 bits, the first sample in the least significant bits. PDM buffers hold 16 bit
+//! samples. Stereo samples are interleaved, left first.
+//!
+//! The conversions stop at the end of the shorter slice and return the number of samples or
+//! words written.
+
+/// Packs 8 bit samples into I2S words, four per word. Returns the number of words written.
+///
+/// If the number of samples is not a multiple of four, the last word is padded with silence.
+pub fn pack_i8(samples: &[i8], words: &mut [u32]) -> usize {
+    let mut n = 0;
+    for (word, chunk) in words.iter_mut().zip(samples.chunks(4)) {
+        let mut bytes = [0; 4];
+        for (byte, &sample) in bytes.iter_mut().zip(chunk) {
+            *byte = sample as u8;
+        }
+        *word = u32::from_le_bytes(bytes);
+        n += 1;
+    }
+    n
+}
+
+/// Unpacks I2S words into 8 bit samples, four per word. Returns the number of samples written.
+pub fn unpack_i8(words: &[u32], samples: &mut [i8]) -> usize {
+    let mut n = 0;
+    for (chunk, &word) in samples.chunks_mut(4).zip(words) {
+        for (sample, &byte) in chunk.iter_mut().zip(word.to_le_bytes().iter()) {
+            *sample = byte as i8;
+            n += 1;
+        }
+    }
+    n
+}
+
+/// Packs 16 bit samples into I2S words, two per word. Returns the number of words written.
+///
+/// If the number of samples is odd, the last word is padded with silence.
+pub fn pack_i16(samples: &[i16], words: &mut [u32]) -> usize {
+    let mut n = 0;
+    for (word, chunk) in words.iter_mut().zip(samples.chunks(2)) {
+        let low = chunk[0] as u16 as u32;
+        let high = chunk.get(1).map_or(0, |&s| s as u16 as u32);
+        *word = high << 16 | low;
+        n += 1;
+    }
+    n
+}
+
+/// Unpacks I2S words into 16 bit samples, two per word. Returns the number of samples written.
+pub fn unpack_i16(words: &[u32], samples: &mut [i16]) -> usize {
+    let mut n = 0;
+    for (chunk, &word) in samples.chunks_mut(2).zip(words) {
+        chunk[0] = word as i16;
+        n += 1;
+        if let Some(sample) = chunk.get_mut(1) {
+            *sample = (word >> 16) as i16;
+            n += 1;
+        }
+    }
+    n
+}
+
+/// Packs 24 bit samples, held in the low bits of `i32`s, into I2S words. Returns the number of
+/// words written.
+pub fn pack_i24(samples: &[i32], words: &mut [u32]) -> usize {
+    let mut n = 0;
+    for (word, &sample) in words.iter_mut().zip(samples) {
+        *word = sample as u32 & 0x00ff_ffff;
+        n += 1;
+    }
+    n
+}
+
+/// Unpacks I2S words into sign-extended 24 bit samples. Returns the number of samples written.
+pub fn unpack_i24(words: &[u32], samples: &mut [i32]) -> usize {
+    let mut n = 0;
+    for (sample, &word) in samples.iter_mut().zip(words) {
+        *sample = ((word << 8) as i32) >> 8;
+        n += 1;
+    }
+    n
+}
+
+/// Interleaves mono samples into stereo samples, the same on both channels. Returns the number
+/// of mono samples read.
+pub fn mono_to_stereo(mono: &[i16], stereo: &mut [i16]) -> usize {
+    let mut n = 0;
+    for (frame, &sample) in stereo.chunks_exact_mut(2).zip(mono) {
+        frame[0] = sample;
+        frame[1] = sample;
+        n += 1;
+    }
+    n
+}
+
+/// Mixes interleaved stereo samples down to mono samples, averaging both channels. Returns the
+/// number of mono samples written.
+pub fn stereo_to_mono(stereo: &[i16], mono: &mut [i16]) -> usize {
+    let mut n = 0;
+    for (sample, frame) in mono.iter_mut().zip(stereo.chunks_exact(2)) {
+        *sample = ((frame[0] as i32 + frame[1] as i32) >> 1) as i16;
+        n += 1;
+    }
+    n
+}
+
+/// Extracts one channel of interleaved stereo samples, 0 for left and 1 for right. Returns the
+/// number of mono samples written.
+pub fn extract_channel(stereo: &[i16], channel: usize, mono: &mut [i16]) -> usize {
+    let mut n = 0;
+    for (sample, frame) in mono.iter_mut().zip(stereo.chunks_exact(2)) {
+        *sample = frame[channel & 1];
+        n += 1;
+    }
+    n
+}
+
+/// Unity volume for [`scale`].
+pub const VOLUME_UNITY: u16 = 256;
+
+/// Scales samples in place by `volume / 256`, saturating. `VOLUME_UNITY` leaves them unchanged,
+/// larger values amplify them.
+pub fn scale(samples: &mut [i16], volume: u16) {
+    for sample in samples {
+        let scaled = (*sample as i32 * volume as i32) >> 8;
+        *sample = scaled.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    }
+}
+
+/// Scales 24 bit samples in place by `volume / 256`, saturating to 24 bits.
+pub fn scale_i24(samples: &mut [i32], volume: u16) {
+    const MIN: i64 = -(1 << 23);
+    const MAX: i64 = (1 << 23) - 1;
+    for sample in samples {
+        let scaled = (*sample as i64 * volume as i64) >> 8;
+        *sample = scaled.clamp(MIN, MAX) as i32;
+    }
+}
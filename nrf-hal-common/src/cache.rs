@@ -0,0 +1,82 @@
+//! HAL interface to the CACHE peripheral.
+//!
+//! The nRF5340 application core caches instructions and data fetched from flash in the CACHE
+//! peripheral, which replaces the instruction cache configured through the NVMC on the nRF52
+//! series. The cache is only accessible from the secure domain.
+//!
+//! See nRF5340 product specification, chapter CACHE.
+
+use crate::pac::CACHE_S as CACHE;
+
+/// Interface to the CACHE peripheral.
+pub struct Cache(CACHE);
+
+impl Cache {
+    pub fn new(cache: CACHE) -> Self {
+        Self(cache)
+    }
+
+    /// Returns the raw `CACHE` peripheral.
+    pub fn free(self) -> CACHE {
+        self.0
+    }
+
+    /// Enables the cache.
+    pub fn enable(&mut self) {
+        self.0.enable.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Disables the cache.
+    pub fn disable(&mut self) {
+        self.0.enable.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Returns `true` if the cache is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.0.enable.read().bits() & 1 != 0
+    }
+
+    /// Invalidates the whole cache, e.g. after the flash has been written through the NVMC.
+    ///
+    /// The cache must be disabled.
+    pub fn invalidate(&mut self) {
+        self.0.tasks_invalidatecache.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Erases the content of the cache, including its data and tag RAMs.
+    ///
+    /// The cache must be disabled.
+    pub fn erase(&mut self) {
+        self.0.tasks_erase.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Enables counting cache hits and misses.
+    pub fn enable_profiling(&mut self) {
+        self.0.profilingenable.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Disables counting cache hits and misses.
+    pub fn disable_profiling(&mut self) {
+        self.0.profilingenable.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Returns the number of instruction fetches that hit the cache.
+    pub fn instruction_hits(&self) -> u32 {
+        self.0.profiling.ihit.read().bits()
+    }
+
+    /// Returns the number of instruction fetches that missed the cache.
+    pub fn instruction_misses(&self) -> u32 {
+        self.0.profiling.imiss.read().bits()
+    }
+
+    /// Returns the number of data reads that hit the cache.
+    pub fn data_hits(&self) -> u32 {
+        self.0.profiling.dhit.read().bits()
+    }
+
+    /// Returns the number of data reads that missed the cache.
+    pub fn data_misses(&self) -> u32 {
+        self.0.profiling.dmiss.read().bits()
+    }
+}
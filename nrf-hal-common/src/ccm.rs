@@ -45,6 +45,7 @@
 //! encryption/decryption. The scratch slice must have a minimum length of 43 bytes, or
 //! (16 + `Packet Length`) bytes, whatever is largest.
 
+use crate::error::ErrorKind;
 use crate::{
     pac::{AAR, CCM},
     slice_in_ram,
@@ -65,6 +66,7 @@ const MAXIMUM_COUNTER: u64 = 0x7F_FFFF_FFFF;
 
 /// Data rate that CCM peripheral shall run in sync with.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DataRate {
     _1Mbit,
     #[cfg(not(feature = "51"))]
@@ -84,6 +86,7 @@ impl From<DataRate> for DATARATE_A {
 
 /// CCM error.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum CcmError {
     /// One or more buffers passed to CCM are not in RAM.
     BufferNotInRAM,
@@ -98,6 +101,18 @@ pub enum CcmError {
     InvalidMIC,
 }
 
+impl crate::error::Error for CcmError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            CcmError::BufferNotInRAM => ErrorKind::BufferNotInRam,
+            CcmError::EasyDMAError => ErrorKind::Dma,
+            CcmError::WrongPacketLength => ErrorKind::InvalidArgument,
+            CcmError::InsufficientScratchArea => ErrorKind::BufferLength,
+            CcmError::InvalidMIC => ErrorKind::Corrupted,
+        }
+    }
+}
+
 /// Data used for encryption/decryption.
 ///
 /// It consists of a 128-bits key, a 39-bits counter, a direction bit and a 8-bytes initialization
@@ -167,6 +182,19 @@ impl CcmData {
     }
 }
 
+// The key and initialization vector are secrets and are left out of the log.
+#[cfg(feature = "defmt")]
+impl defmt::Format for CcmData {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "CcmData {{ key: <redacted>, packet_counter: {=u64}, direction: {=u8}, initialization_vector: <redacted> }}",
+            u64::from_le_bytes(self.packet_counter),
+            self.direction,
+        )
+    }
+}
+
 /// A safe, blocking wrapper around the AES-CCM peripheral.
 pub struct Ccm {
     regs: CCM,
@@ -249,7 +277,13 @@ impl Ccm {
         let length_variant = if payload_len <= MAXIMUM_LENGTH_5BITS - MIC_SIZE {
             LENGTH_A::DEFAULT
         } else {
-            #[cfg(any(feature = "52840", feature = "52833", feature = "52810"))]
+            #[cfg(any(
+                feature = "52840",
+                feature = "52833",
+                feature = "52820",
+                feature = "52810",
+                feature = "52805"
+            ))]
             // NOTE(unsafe) Any 8bits pattern is safe to write to this register
             self.regs
                 .maxpacketsize
@@ -373,7 +407,13 @@ impl Ccm {
         let length_variant = if payload_len <= MAXIMUM_LENGTH_5BITS {
             LENGTH_A::DEFAULT
         } else {
-            #[cfg(any(feature = "52840", feature = "52833", feature = "52810"))]
+            #[cfg(any(
+                feature = "52840",
+                feature = "52833",
+                feature = "52820",
+                feature = "52810",
+                feature = "52805"
+            ))]
             // NOTE(unsafe) Any 8bits pattern is safe to write to this register
             self.regs
                 .maxpacketsize
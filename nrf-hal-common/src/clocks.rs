@@ -1,11 +1,25 @@
 //! Configuration and control of the High and Low Frequency Clock sources.
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
 use crate::pac::CLOCK_NS as CLOCK;
 
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 use crate::pac::CLOCK;
 
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+use crate::{hal::blocking::delay::DelayMs, temp::Temp};
+
+use core::cell::Cell;
+use cortex_m::interrupt::{self, Mutex};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static HFCLK_WAKER: WakerSlot = WakerSlot::new();
+
 // ZST Type States
 
 /// Internal/RC Oscillator.
@@ -50,6 +64,7 @@ impl Clocks<Internal, Internal, LfOscStopped> {
 impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
     /// Use an external oscillator as the high frequency clock source.
     pub fn enable_ext_hfosc(self) -> Clocks<ExternalOscillator, L, LSTAT> {
+        interrupt::free(|cs| HFCLK_USERS.borrow(cs).pinned.set(true));
         self.periph.tasks_hfclkstart.write(|w| unsafe { w.bits(1) });
 
         // Datasheet says this is likely to take 0.36ms
@@ -67,8 +82,16 @@ impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
     }
 
     /// Use the internal oscillator as the high frequency clock source.
+    ///
+    /// The crystal keeps running as long as an [`HfclkRequest`] is alive.
     pub fn disable_ext_hfosc(self) -> Clocks<Internal, L, LSTAT> {
-        self.periph.tasks_hfclkstop.write(|w| unsafe { w.bits(1) });
+        interrupt::free(|cs| {
+            let users = HFCLK_USERS.borrow(cs);
+            users.pinned.set(false);
+            if users.requests.get() == 0 {
+                self.periph.tasks_hfclkstop.write(|w| unsafe { w.bits(1) });
+            }
+        });
         Clocks {
             hfclk: Internal,
             lfclk: self.lfclk,
@@ -97,14 +120,172 @@ impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
     }
 }
 
+/// Users of the high frequency crystal.
+struct HfclkUsers {
+    /// Number of live `HfclkRequest`s.
+    requests: Cell<usize>,
+    /// Whether the crystal was started with `Clocks::enable_ext_hfosc`.
+    pinned: Cell<bool>,
+}
+
+static HFCLK_USERS: Mutex<HfclkUsers> = Mutex::new(HfclkUsers {
+    requests: Cell::new(0),
+    pinned: Cell::new(false),
+});
+
+/// A request keeping the high frequency crystal oscillator running.
+///
+/// Drivers needing an accurate clock, like RADIO or high accuracy timing, hold a request for as
+/// long as they need the crystal, without coordinating with each other. The crystal is started
+/// by the first request and stopped when the last one is dropped, unless it has been enabled
+/// permanently with `Clocks::enable_ext_hfosc`.
+pub struct HfclkRequest {
+    _private: (),
+}
+
+impl HfclkRequest {
+    /// Returns `true` once the crystal is running.
+    pub fn is_running(&self) -> bool {
+        hfxo_running()
+    }
+
+    /// Blocks until the crystal is running.
+    pub fn wait(&self) {
+        while !hfxo_running() {}
+    }
+
+    /// Waits until the crystal is running.
+    ///
+    /// Requires [`on_interrupt`] to be called from the `POWER_CLOCK` interrupt handler.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(&self) {
+        poll_fn(|cx| {
+            HFCLK_WAKER.register(cx.waker());
+            if hfxo_running() {
+                return Poll::Ready(());
+            }
+            unsafe { (*CLOCK::ptr()).intenset.write(|w| w.hfclkstarted().set()) };
+            // The crystal may have started before the interrupt was enabled.
+            if hfxo_running() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl Clone for HfclkRequest {
+    fn clone(&self) -> Self {
+        interrupt::free(|cs| {
+            let requests = &HFCLK_USERS.borrow(cs).requests;
+            requests.set(requests.get() + 1);
+        });
+        HfclkRequest { _private: () }
+    }
+}
+
+impl Drop for HfclkRequest {
+    fn drop(&mut self) {
+        interrupt::free(|cs| {
+            let users = HFCLK_USERS.borrow(cs);
+            users.requests.set(users.requests.get() - 1);
+            if users.requests.get() == 0 && !users.pinned.get() {
+                unsafe { (*CLOCK::ptr()).tasks_hfclkstop.write(|w| w.bits(1)) };
+            }
+        });
+    }
+}
+
+fn hfxo_running() -> bool {
+    let stat = unsafe { (*CLOCK::ptr()).hfclkstat.read() };
+    #[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+    let xtal = stat.src().is_hfxo();
+    #[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+    let xtal = stat.src().is_xtal();
+    xtal && stat.state().is_running()
+}
+
+/// Handles the `HFCLKSTARTED` event for `HfclkRequest::wait_async`.
+///
+/// Must be called from the `POWER_CLOCK` interrupt handler.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let clock = unsafe { &*CLOCK::ptr() };
+    if clock.events_hfclkstarted.read().bits() != 0 {
+        clock.intenclr.write(|w| w.hfclkstarted().clear());
+        HFCLK_WAKER.wake();
+    }
+}
+
+impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
+    /// Requests the high frequency crystal oscillator, starting it if it is not running yet.
+    ///
+    /// This does not wait for the crystal to start, see `HfclkRequest::wait` and
+    /// `HfclkRequest::wait_async`.
+    pub fn request_hfclk(&self) -> HfclkRequest {
+        interrupt::free(|cs| {
+            let users = HFCLK_USERS.borrow(cs);
+            users.requests.set(users.requests.get() + 1);
+            if users.requests.get() == 1 && !hfxo_running() {
+                self.periph.events_hfclkstarted.reset();
+                self.periph.tasks_hfclkstart.write(|w| unsafe { w.bits(1) });
+            }
+        });
+        HfclkRequest { _private: () }
+    }
+}
+
 /// Allowable configuration options for the low frequency oscillator when
 /// driven fron an external crystal.
 pub enum LfOscConfiguration {
+    /// A 32.768 kHz crystal connected to XL1 and XL2.
     NoExternalNoBypass,
+    /// A low-swing external clock signal applied to XL1, with XL2 grounded.
     ExternalNoBypass,
+    /// A full-swing (rail-to-rail) external clock signal applied to XL1, e.g. from a TCXO or a
+    /// clock shared with another chip.
     ExternalAndBypass,
 }
 
+/// Frequency of the crystal or external clock driving the high frequency clock.
+#[cfg(feature = "51")]
+pub enum HfxoFrequency {
+    _16MHz,
+    _32MHz,
+}
+
+impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
+    /// Sets the frequency of the crystal or external clock driving the high frequency clock.
+    ///
+    /// Must be set before the crystal is started.
+    #[cfg(feature = "51")]
+    pub fn set_hfxo_frequency(&mut self, freq: HfxoFrequency) {
+        self.periph.xtalfreq.write(|w| match freq {
+            HfxoFrequency::_16MHz => w.xtalfreq()._16mhz(),
+            HfxoFrequency::_32MHz => w.xtalfreq()._32mhz(),
+        });
+    }
+
+    /// Sets the time the crystal is given to stabilize after being started, in µs, before
+    /// `HFCLKSTARTED` is generated.
+    ///
+    /// The time is rounded down to a multiple of 16 µs. The default of 256 µs suits most
+    /// crystals, while slow starting crystals and TCXOs may need up to 1024 µs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `us` is not in the range 16 to 4080.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn set_hfxo_debounce(&mut self, us: u16) {
+        assert!((16..=4080).contains(&us));
+        self.periph
+            .hfxodebounce
+            .write(|w| unsafe { w.bits(us as u32 / 16) });
+    }
+}
+
 impl<H, L> Clocks<H, L, LfOscStarted> {
     /// Stop the Low Frequency clock.
     pub fn stop_lfclk(self) -> Clocks<H, L, LfOscStopped> {
@@ -156,7 +337,12 @@ impl<H, L> Clocks<H, L, LfOscStopped> {
     }
 
     /// Use the internal RC Oscillator for the low frequency clock source.
-    #[cfg(not(any(feature = "9160", feature = "51")))]
+    #[cfg(not(any(
+        feature = "9160",
+        feature = "5340-app",
+        feature = "5340-net",
+        feature = "51"
+    )))]
     pub fn set_lfclk_src_rc(self) -> Clocks<H, Internal, LfOscStopped> {
         self.periph
             .lfclksrc
@@ -170,7 +356,12 @@ impl<H, L> Clocks<H, L, LfOscStopped> {
     }
 
     /// Generate the Low Frequency clock from the high frequency clock source.
-    #[cfg(not(any(feature = "9160", feature = "51")))]
+    #[cfg(not(any(
+        feature = "9160",
+        feature = "5340-app",
+        feature = "5340-net",
+        feature = "51"
+    )))]
     pub fn set_lfclk_src_synth(self) -> Clocks<H, LfOscSynthesized, LfOscStopped> {
         self.periph
             .lfclksrc
@@ -184,7 +375,12 @@ impl<H, L> Clocks<H, L, LfOscStopped> {
     }
 
     /// Use an external crystal to drive the low frequency clock.
-    #[cfg(not(any(feature = "9160", feature = "51")))]
+    #[cfg(not(any(
+        feature = "9160",
+        feature = "5340-app",
+        feature = "5340-net",
+        feature = "51"
+    )))]
     pub fn set_lfclk_src_external(
         self,
         cfg: LfOscConfiguration,
@@ -205,3 +401,215 @@ impl<H, L> Clocks<H, L, LfOscStopped> {
         }
     }
 }
+
+/// Source of the running low frequency clock.
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LfclkSource {
+    /// Internal RC oscillator.
+    Rc,
+    /// External crystal or clock signal.
+    Xtal,
+    /// Synthesized from the high frequency clock.
+    Synth,
+}
+
+/// Outcome of `Clocks::start_lfclk_or_rc`.
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+pub enum LfclkStartup<H> {
+    /// The crystal started.
+    Xtal(Clocks<H, ExternalOscillator, LfOscStarted>),
+    /// The crystal did not start in time, the RC oscillator is used instead.
+    RcFallback(Clocks<H, Internal, LfOscStarted>),
+}
+
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
+    /// Returns the source of the low frequency clock, or `None` if it is not running.
+    pub fn lfclk_source(&self) -> Option<LfclkSource> {
+        let stat = self.periph.lfclkstat.read();
+        if !stat.state().is_running() {
+            return None;
+        }
+        Some(if stat.src().is_rc() {
+            LfclkSource::Rc
+        } else if stat.src().is_xtal() {
+            LfclkSource::Xtal
+        } else {
+            LfclkSource::Synth
+        })
+    }
+}
+
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+impl<H> Clocks<H, ExternalOscillator, LfOscStopped> {
+    /// Starts the Low Frequency clock from the external crystal, falling back to the internal RC
+    /// oscillator if the crystal has not started within `timeout_ms` milliseconds.
+    ///
+    /// Unlike `start_lfclk`, this does not hang on boards with an unpopulated or broken crystal.
+    /// Crystals usually start within 250 ms.
+    pub fn start_lfclk_or_rc<D>(self, delay: &mut D, timeout_ms: u32) -> LfclkStartup<H>
+    where
+        D: DelayMs<u32>,
+    {
+        self.periph.events_lfclkstarted.reset();
+        self.periph.tasks_lfclkstart.write(|w| unsafe { w.bits(1) });
+
+        for _ in 0..timeout_ms {
+            if self.periph.events_lfclkstarted.read().bits() != 0 {
+                self.periph.events_lfclkstarted.reset();
+                return LfclkStartup::Xtal(Clocks {
+                    hfclk: self.hfclk,
+                    lfclk: self.lfclk,
+                    lfstat: LfOscStarted,
+                    periph: self.periph,
+                });
+            }
+            delay.delay_ms(1);
+        }
+
+        self.periph.tasks_lfclkstop.write(|w| unsafe { w.bits(1) });
+        while self.periph.lfclkstat.read().state().is_running() {}
+        self.periph.events_lfclkstarted.reset();
+        LfclkStartup::RcFallback(self.set_lfclk_src_rc().start_lfclk())
+    }
+}
+
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+impl<LSTAT> Clocks<ExternalOscillator, Internal, LSTAT> {
+    /// Calibrates the LFRC oscillator against the high frequency crystal, blocking until the
+    /// calibration is done.
+    pub fn calibrate_lfrc(&mut self) {
+        self.start_calibration();
+        while !self.is_calibration_done() {}
+    }
+
+    /// Starts a calibration of the LFRC oscillator without waiting for its completion.
+    ///
+    /// Completion is signalled by the `DONE` event, see `is_calibration_done`.
+    pub fn start_calibration(&mut self) {
+        self.periph.events_done.reset();
+        self.periph.tasks_cal.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns `true` and clears the `DONE` event if a calibration has completed.
+    pub fn is_calibration_done(&mut self) -> bool {
+        if self.periph.events_done.read().bits() != 0 {
+            self.periph.events_done.reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Starts the calibration timer, which generates the `CTTO` event after `interval` units of
+    /// 0.25 s, 1 to 127. The timer has to be restarted after each timeout.
+    pub fn start_calibration_timer(&mut self, interval: u8) {
+        assert!((1..=127).contains(&interval));
+        self.periph
+            .ctiv
+            .write(|w| unsafe { w.ctiv().bits(interval) });
+        self.periph.events_ctto.reset();
+        self.periph.tasks_ctstart.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stops the calibration timer.
+    pub fn stop_calibration_timer(&mut self) {
+        self.periph.tasks_ctstop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns `true` and clears the `CTTO` event if the calibration timer has expired.
+    pub fn is_calibration_timeout(&mut self) -> bool {
+        if self.periph.events_ctto.read().bits() != 0 {
+            self.periph.events_ctto.reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enables the `POWER_CLOCK` interrupt on the `DONE` and `CTTO` events.
+    pub fn enable_calibration_interrupts(&mut self) {
+        self.periph.intenset.write(|w| w.done().set().ctto().set());
+    }
+
+    /// Disables the `POWER_CLOCK` interrupt on the `DONE` and `CTTO` events.
+    pub fn disable_calibration_interrupts(&mut self) {
+        self.periph
+            .intenclr
+            .write(|w| w.done().clear().ctto().clear());
+    }
+}
+
+/// Periodic LFRC calibration, skipped while the die temperature is stable.
+///
+/// The LFRC drifts mostly with temperature. Like Nordic's recommended scheme, the temperature is
+/// measured on each calibration timer timeout and the oscillator is only recalibrated when the
+/// temperature has changed by at least the given threshold, saving the energy of needless
+/// calibrations.
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+pub struct LfrcCalibration {
+    interval: u8,
+    threshold: i32,
+    last: Option<i32>,
+}
+
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+impl LfrcCalibration {
+    /// Calibrates the LFRC, then starts the calibration timer with `interval` units of 0.25 s.
+    ///
+    /// `threshold` is the temperature change triggering a recalibration, in units of 0.25 °C.
+    /// A threshold of 0 recalibrates on every timeout.
+    pub fn start<LSTAT>(
+        clocks: &mut Clocks<ExternalOscillator, Internal, LSTAT>,
+        temp: &mut Temp,
+        interval: u8,
+        threshold: u8,
+    ) -> Self {
+        let mut calibration = LfrcCalibration {
+            interval,
+            threshold: threshold as i32,
+            last: None,
+        };
+        calibration.calibrate(clocks, temp);
+        clocks.start_calibration_timer(interval);
+        calibration
+    }
+
+    /// Handles the calibration timer timeout. Call this from the `POWER_CLOCK` interrupt handler,
+    /// or poll it from the main loop.
+    ///
+    /// Returns `true` if the LFRC was recalibrated.
+    pub fn on_timeout<LSTAT>(
+        &mut self,
+        clocks: &mut Clocks<ExternalOscillator, Internal, LSTAT>,
+        temp: &mut Temp,
+    ) -> bool {
+        if !clocks.is_calibration_timeout() {
+            return false;
+        }
+
+        let calibrated = self.calibrate(clocks, temp);
+        clocks.start_calibration_timer(self.interval);
+        calibrated
+    }
+
+    fn calibrate<LSTAT>(
+        &mut self,
+        clocks: &mut Clocks<ExternalOscillator, Internal, LSTAT>,
+        temp: &mut Temp,
+    ) -> bool {
+        let now = temp.measure().to_raw();
+        let changed = match self.last {
+            Some(last) => (now - last).abs() >= self.threshold,
+            None => true,
+        };
+
+        if changed {
+            clocks.calibrate_lfrc();
+            self.last = Some(now);
+        }
+        changed
+    }
+}
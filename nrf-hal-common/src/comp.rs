@@ -0,0 +1,277 @@
+//! HAL interface for the COMP peripheral.
+//!
+//! The comparator (COMP) compares the voltage on an analog input pin against a reference voltage
+//! and reports whether it is above or below the configured thresholds.
+//!
+//! With the `async` feature, a task can wait for the input to cross a threshold:
+//!
+//! ```ignore
+//! comp.start();
+//! comp.wait_for(Transition::Down).await;
+//! ```
+
+use crate::{
+    gpio::AnalogPin,
+    pac::comp::{_EVENTS_CROSS, _EVENTS_DOWN, _EVENTS_UP},
+    pac::{generic::Reg, COMP},
+};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static WAKER: WakerSlot = WakerSlot::new();
+
+pub use crate::pac::comp::{
+    extrefsel::EXTREFSEL_A as ExternalReference, refsel::REFSEL_A as Reference,
+};
+
+/// Maximum threshold value, see [`Comp::thresholds`].
+pub const MAX_THRESHOLD: u8 = 63;
+
+/// A safe wrapper around the `COMP` peripheral.
+///
+/// `P` is the analog input pin in single-ended mode, or the pair of positive and negative input
+/// pins in differential mode.
+pub struct Comp<P> {
+    comp: COMP,
+    pin: P,
+}
+
+impl<P> Comp<P>
+where
+    P: AnalogPin,
+{
+    /// Takes ownership of the `COMP` peripheral and the analog input pin, returning a safe
+    /// wrapper.
+    ///
+    /// The comparator is configured in single-ended mode, comparing the pin against half of VDD.
+    pub fn new(comp: COMP, pin: P) -> Self {
+        comp.psel.write(|w| unsafe { w.psel().bits(P::AIN) });
+        comp.mode.write(|w| w.sp().normal().main().se());
+        comp.refsel.write(|w| w.refsel().vdd());
+        // VUP = (THUP + 1) / 64 * VREF, VDOWN = (THDOWN + 1) / 64 * VREF
+        comp.th
+            .write(|w| unsafe { w.thup().bits(32).thdown().bits(31) });
+        comp.enable.write(|w| w.enable().enabled());
+
+        Self { comp, pin }
+    }
+}
+
+impl<P, N> Comp<(P, N)>
+where
+    P: AnalogPin,
+    N: AnalogPin,
+{
+    /// Takes ownership of the `COMP` peripheral and two analog input pins, returning a safe
+    /// wrapper comparing them in differential mode.
+    ///
+    /// The output is above when `positive` is above `negative`. Thresholds don't apply in this
+    /// mode; use `hysteresis` against noise.
+    pub fn new_differential(comp: COMP, positive: P, negative: N) -> Self {
+        comp.psel.write(|w| unsafe { w.psel().bits(P::AIN) });
+        comp.extrefsel
+            .write(|w| unsafe { w.bits(u32::from(N::AIN)) });
+        comp.mode.write(|w| w.sp().normal().main().diff());
+        comp.enable.write(|w| w.enable().enabled());
+
+        Self {
+            comp,
+            pin: (positive, negative),
+        }
+    }
+}
+
+impl<P> Comp<P> {
+    /// Selects the reference voltage: an internal reference of 1.2, 1.8 or 2.4 V, VDD, or the
+    /// pin selected with `external_reference`.
+    ///
+    /// The comparator must be stopped.
+    pub fn reference(&mut self, reference: Reference) -> &mut Self {
+        self.comp.refsel.write(|w| w.refsel().variant(reference));
+        self
+    }
+
+    /// Selects the pin used as reference with `Reference::AREF`.
+    pub fn external_reference(&mut self, reference: ExternalReference) -> &mut Self {
+        self.comp
+            .extrefsel
+            .write(|w| w.extrefsel().variant(reference));
+        self
+    }
+
+    /// Sets the upper and lower thresholds in single-ended mode, as `(TH + 1) / 64` of the
+    /// reference voltage. The thresholds are clamped to `MAX_THRESHOLD`.
+    ///
+    /// The output goes up when the input rises above the upper threshold, and down when it falls
+    /// below the lower one, so a lower threshold below the upper one adds hysteresis.
+    pub fn thresholds(&mut self, up: u8, down: u8) -> &mut Self {
+        let up = up.min(MAX_THRESHOLD);
+        let down = down.min(MAX_THRESHOLD);
+        self.comp
+            .th
+            .write(|w| unsafe { w.thup().bits(up).thdown().bits(down) });
+        self
+    }
+
+    /// Enables or disables the hysteresis of 50 mV in differential mode.
+    pub fn hysteresis(&mut self, enable: bool) -> &mut Self {
+        self.comp.hyst.write(|w| w.hyst().bit(enable));
+        self
+    }
+
+    /// Starts the comparator and blocks until it is ready.
+    pub fn start(&mut self) {
+        self.comp.events_ready.reset();
+        self.comp.tasks_start.write(|w| unsafe { w.bits(1) });
+        while self.comp.events_ready.read().bits() == 0 {}
+        self.comp.events_ready.reset();
+    }
+
+    /// Stops the comparator.
+    pub fn stop(&mut self) {
+        self.comp.tasks_stop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Samples the comparator output.
+    ///
+    /// The comparator must have been started with `start`.
+    pub fn read(&mut self) -> CompResult {
+        self.comp.tasks_sample.write(|w| unsafe { w.bits(1) });
+        if self.comp.result.read().result().is_above() {
+            CompResult::Above
+        } else {
+            CompResult::Below
+        }
+    }
+
+    /// Enables the COMP interrupt for the given transition.
+    pub fn enable_interrupt(&mut self, transition: Transition) -> &mut Self {
+        self.comp.intenset.write(|w| match transition {
+            Transition::Up => w.up().set(),
+            Transition::Down => w.down().set(),
+            Transition::Cross => w.cross().set(),
+        });
+        self
+    }
+
+    /// Disables the COMP interrupt for the given transition.
+    pub fn disable_interrupt(&mut self, transition: Transition) -> &mut Self {
+        self.comp.intenclr.write(|w| match transition {
+            Transition::Up => w.up().clear(),
+            Transition::Down => w.down().clear(),
+            Transition::Cross => w.cross().clear(),
+        });
+        self
+    }
+
+    /// Checks if the event for the given transition has been triggered.
+    pub fn is_event_triggered(&self, transition: Transition) -> bool {
+        match transition {
+            Transition::Up => self.comp.events_up.read().bits() != 0,
+            Transition::Down => self.comp.events_down.read().bits() != 0,
+            Transition::Cross => self.comp.events_cross.read().bits() != 0,
+        }
+    }
+
+    /// Marks all transition events as handled.
+    pub fn reset_events(&mut self) {
+        self.comp.events_up.reset();
+        self.comp.events_down.reset();
+        self.comp.events_cross.reset();
+    }
+
+    /// Waits until the comparator output makes the given transition.
+    ///
+    /// Transitions before the call are ignored. The comparator must have been started with
+    /// `start`. The `COMP_LPCOMP` interrupt must be unmasked in the NVIC and its handler has to
+    /// call [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn wait_for(&mut self, transition: Transition) {
+        self.reset_event(transition);
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if self.is_event_triggered(transition) {
+                self.reset_event(transition);
+                Poll::Ready(())
+            } else {
+                self.enable_interrupt(transition);
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[cfg(feature = "async")]
+    fn reset_event(&self, transition: Transition) {
+        match transition {
+            Transition::Up => self.comp.events_up.reset(),
+            Transition::Down => self.comp.events_down.reset(),
+            Transition::Cross => self.comp.events_cross.reset(),
+        }
+    }
+
+    /// Returns reference to the `UP` event endpoint for PPI.
+    pub fn event_up(&self) -> &Reg<u32, _EVENTS_UP> {
+        &self.comp.events_up
+    }
+
+    /// Returns reference to the `DOWN` event endpoint for PPI.
+    pub fn event_down(&self) -> &Reg<u32, _EVENTS_DOWN> {
+        &self.comp.events_down
+    }
+
+    /// Returns reference to the `CROSS` event endpoint for PPI.
+    pub fn event_cross(&self) -> &Reg<u32, _EVENTS_CROSS> {
+        &self.comp.events_cross
+    }
+
+    /// Stops and disables the comparator, returning the raw `COMP` peripheral and the input pins.
+    pub fn free(self) -> (COMP, P) {
+        self.comp.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.comp.enable.write(|w| w.enable().disabled());
+        (self.comp, self.pin)
+    }
+}
+
+/// Output of the comparator.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CompResult {
+    /// The input voltage is above the threshold.
+    Above,
+    /// The input voltage is below the threshold.
+    Below,
+}
+
+/// Transitions of the comparator output that can generate events.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Transition {
+    /// The input crossed the upper threshold going up.
+    Up,
+    /// The input crossed the lower threshold going down.
+    Down,
+    /// The input crossed a threshold in either direction.
+    Cross,
+}
+
+/// Handles the COMP events for `Comp::wait_for`.
+///
+/// Must be called from the `COMP_LPCOMP` interrupt handler.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let comp = unsafe { &*COMP::ptr() };
+    let triggered = comp.events_up.read().bits() != 0
+        || comp.events_down.read().bits() != 0
+        || comp.events_cross.read().bits() != 0;
+    if triggered {
+        // Leave the events set for the waiting task to observe, but keep them from firing again.
+        comp.intenclr
+            .write(|w| w.up().clear().down().clear().cross().clear());
+        WAKER.wake();
+    }
+}
@@ -82,3 +82,15 @@ impl DelayUs<u8> for Delay {
         self.delay_us(u32(us))
     }
 }
+
+/// Delays are rounded up to whole microseconds.
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        DelayUs::delay_us(self, ns / 1_000 + u32::from(ns % 1_000 != 0));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        DelayUs::delay_us(self, us);
+    }
+}
@@ -0,0 +1,285 @@
+//! Settings page of the Nordic nRF5 SDK secure bootloader.
+//!
+//! The bootloader keeps the state of the installed images in a settings page at the end of the
+//! flash. Reading and updating it lets an application mark itself as valid after an update, or
+//! request the bootloader to enter DFU mode, in a way the bootloader understands.
+//!
+//! The layout is version 2 of `nrf_dfu_settings_t`, used by the nRF5 SDK 15.3 to 17.1.
+//!
+//! ```ignore
+//! let mut nvmc = Nvmc::new(p.NVMC, settings_page);
+//! let mut settings = DfuSettings::read(&mut nvmc, 0)?;
+//! settings.request_dfu();
+//! settings.write(&mut nvmc, 0)?;
+//! cortex_m::peripheral::SCB::sys_reset();
+//! ```
+
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+use crate::nvmc::crc32_update;
+
+/// Address of the bootloader settings page.
+#[cfg(feature = "51")]
+pub const SETTINGS_ADDR: u32 = 0x0003_fc00;
+
+/// Address of the bootloader settings page.
+#[cfg(any(feature = "52805", feature = "52810"))]
+pub const SETTINGS_ADDR: u32 = 0x0002_f000;
+
+/// Address of the bootloader settings page.
+#[cfg(feature = "52820")]
+pub const SETTINGS_ADDR: u32 = 0x0003_f000;
+
+/// Address of the bootloader settings page.
+#[cfg(any(feature = "52832", feature = "52833"))]
+pub const SETTINGS_ADDR: u32 = 0x0007_f000;
+
+/// Address of the bootloader settings page.
+#[cfg(feature = "52840")]
+pub const SETTINGS_ADDR: u32 = 0x000f_f000;
+
+/// Number of bytes of the page held by `DfuSettings`, covering the whole settings structure.
+pub const SETTINGS_LEN: usize = 1024;
+
+/// Settings version supported by this module.
+pub const SETTINGS_VERSION: u32 = 2;
+
+const SETTINGS_VERSION_OFFSET: usize = 4;
+const APP_VERSION_OFFSET: usize = 8;
+const BOOTLOADER_VERSION_OFFSET: usize = 12;
+const BANK_0_OFFSET: usize = 24;
+const BANK_1_OFFSET: usize = 36;
+const ENTER_BUTTONLESS_DFU_OFFSET: usize = 88;
+const INIT_COMMAND_OFFSET: usize = 92;
+const BOOT_VALIDATION_CRC_OFFSET: usize = 604;
+const BOOT_VALIDATION_OFFSET: usize = 608;
+/// Size of a `boot_validation_t`: a type byte and 64 bytes of validation data.
+const BOOT_VALIDATION_LEN: usize = 65;
+
+/// Layout of version 2 of `nrf_dfu_settings_t` in `nrf_dfu_types.h`, from which the offsets above
+/// are checked, one field at a time.
+mod layout {
+    /// Size of a `nrf_dfu_bank_t`: image size, CRC and bank code.
+    const BANK_SIZE: usize = 12;
+    /// Size of the `dfu_progress_t` union.
+    const PROGRESS_SIZE: usize = 32;
+    /// `INIT_COMMAND_MAX_SIZE` of settings version 2.
+    const INIT_COMMAND_SIZE: usize = 512;
+
+    const CRC: usize = 0;
+    pub(super) const SETTINGS_VERSION: usize = CRC + 4;
+    pub(super) const APP_VERSION: usize = SETTINGS_VERSION + 4;
+    pub(super) const BOOTLOADER_VERSION: usize = APP_VERSION + 4;
+    const BANK_LAYOUT: usize = BOOTLOADER_VERSION + 4;
+    const BANK_CURRENT: usize = BANK_LAYOUT + 4;
+    pub(super) const BANK_0: usize = BANK_CURRENT + 4;
+    pub(super) const BANK_1: usize = BANK_0 + BANK_SIZE;
+    const WRITE_OFFSET: usize = BANK_1 + BANK_SIZE;
+    const SD_SIZE: usize = WRITE_OFFSET + 4;
+    const PROGRESS: usize = SD_SIZE + 4;
+    pub(super) const ENTER_BUTTONLESS_DFU: usize = PROGRESS + PROGRESS_SIZE;
+    pub(super) const INIT_COMMAND: usize = ENTER_BUTTONLESS_DFU + 4;
+    pub(super) const BOOT_VALIDATION_CRC: usize = INIT_COMMAND + INIT_COMMAND_SIZE;
+    pub(super) const BOOT_VALIDATION: usize = BOOT_VALIDATION_CRC + 4;
+    /// End of `boot_validation_softdevice`, `boot_validation_app` and
+    /// `boot_validation_bootloader`.
+    pub(super) const BOOT_VALIDATION_END: usize = BOOT_VALIDATION + 3 * super::BOOT_VALIDATION_LEN;
+}
+
+// Fail to compile if an offset does not match the SDK layout, or if the structure does not fit.
+const _: [(); SETTINGS_VERSION_OFFSET] = [(); layout::SETTINGS_VERSION];
+const _: [(); APP_VERSION_OFFSET] = [(); layout::APP_VERSION];
+const _: [(); BOOTLOADER_VERSION_OFFSET] = [(); layout::BOOTLOADER_VERSION];
+const _: [(); BANK_0_OFFSET] = [(); layout::BANK_0];
+const _: [(); BANK_1_OFFSET] = [(); layout::BANK_1];
+const _: [(); ENTER_BUTTONLESS_DFU_OFFSET] = [(); layout::ENTER_BUTTONLESS_DFU];
+const _: [(); INIT_COMMAND_OFFSET] = [(); layout::INIT_COMMAND];
+const _: [(); BOOT_VALIDATION_CRC_OFFSET] = [(); layout::BOOT_VALIDATION_CRC];
+const _: [(); BOOT_VALIDATION_OFFSET] = [(); layout::BOOT_VALIDATION];
+const _: [(); 0] = [(); (layout::BOOT_VALIDATION_END > SETTINGS_LEN) as usize];
+
+/// State of a bank, the `bank_code` of `nrf_dfu_bank_t`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BankCode {
+    /// No valid image.
+    Invalid,
+    /// A valid application.
+    ValidApp,
+    /// A valid SoftDevice.
+    ValidSd,
+    /// A valid bootloader.
+    ValidBl,
+    /// A valid SoftDevice and bootloader.
+    ValidSdBl,
+    /// A valid external application.
+    ValidExtApp,
+    /// An unknown bank code.
+    Other(u32),
+}
+
+impl BankCode {
+    fn from_raw(raw: u32) -> Self {
+        match raw {
+            0x00 => BankCode::Invalid,
+            0x01 => BankCode::ValidApp,
+            0xa5 => BankCode::ValidSd,
+            0xaa => BankCode::ValidBl,
+            0xac => BankCode::ValidSdBl,
+            0xb1 => BankCode::ValidExtApp,
+            other => BankCode::Other(other),
+        }
+    }
+
+    fn to_raw(self) -> u32 {
+        match self {
+            BankCode::Invalid => 0x00,
+            BankCode::ValidApp => 0x01,
+            BankCode::ValidSd => 0xa5,
+            BankCode::ValidBl => 0xaa,
+            BankCode::ValidSdBl => 0xac,
+            BankCode::ValidExtApp => 0xb1,
+            BankCode::Other(raw) => raw,
+        }
+    }
+}
+
+/// An image bank, `nrf_dfu_bank_t`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Bank {
+    pub image_size: u32,
+    pub image_crc: u32,
+    pub bank_code: BankCode,
+}
+
+/// A copy of the bootloader settings.
+pub struct DfuSettings {
+    data: [u8; SETTINGS_LEN],
+}
+
+impl DfuSettings {
+    /// Reads the settings from `flash` at `offset`, usually the start of the settings page.
+    pub fn read<S: ReadNorFlash>(flash: &mut S, offset: u32) -> Result<Self, S::Error> {
+        let mut settings = DfuSettings {
+            data: [0; SETTINGS_LEN],
+        };
+        flash.read(offset, &mut settings.data)?;
+        Ok(settings)
+    }
+
+    /// Erases the settings page at `offset` of `flash` and writes the settings, after updating
+    /// their CRCs.
+    pub fn write<S: NorFlash>(&mut self, flash: &mut S, offset: u32) -> Result<(), S::Error> {
+        self.update_crcs();
+        flash.erase(offset, offset + S::ERASE_SIZE as u32)?;
+        flash.write(offset, &self.data)
+    }
+
+    /// Returns `true` if the settings have the supported version and a valid CRC.
+    pub fn is_valid(&self) -> bool {
+        self.settings_version() == SETTINGS_VERSION && self.word(0) == self.crc()
+    }
+
+    /// Returns the version of the settings layout.
+    pub fn settings_version(&self) -> u32 {
+        self.word(SETTINGS_VERSION_OFFSET)
+    }
+
+    /// Returns the version of the application.
+    pub fn app_version(&self) -> u32 {
+        self.word(APP_VERSION_OFFSET)
+    }
+
+    /// Sets the version of the application, checked by the bootloader to prevent downgrades.
+    pub fn set_app_version(&mut self, version: u32) {
+        self.set_word(APP_VERSION_OFFSET, version);
+    }
+
+    /// Returns the version of the bootloader.
+    pub fn bootloader_version(&self) -> u32 {
+        self.word(BOOTLOADER_VERSION_OFFSET)
+    }
+
+    /// Returns bank 0, holding the installed application.
+    pub fn bank_0(&self) -> Bank {
+        self.bank(BANK_0_OFFSET)
+    }
+
+    /// Returns bank 1, holding a received update in dual bank mode.
+    pub fn bank_1(&self) -> Bank {
+        self.bank(BANK_1_OFFSET)
+    }
+
+    /// Marks the application of `image_size` bytes with CRC-32 `image_crc` as valid, so the
+    /// bootloader starts it.
+    ///
+    /// Also sets its boot validation to a CRC check.
+    pub fn mark_app_valid(&mut self, image_size: u32, image_crc: u32) {
+        self.set_word(BANK_0_OFFSET, image_size);
+        self.set_word(BANK_0_OFFSET + 4, image_crc);
+        self.set_word(BANK_0_OFFSET + 8, BankCode::ValidApp.to_raw());
+
+        // `VALIDATE_CRC`, followed by the CRC.
+        let app = BOOT_VALIDATION_OFFSET + BOOT_VALIDATION_LEN;
+        self.data[app..app + BOOT_VALIDATION_LEN]
+            .iter_mut()
+            .for_each(|b| *b = 0);
+        self.data[app] = 1;
+        self.data[app + 1..app + 5].copy_from_slice(&image_crc.to_le_bytes());
+    }
+
+    /// Marks the application as invalid, so the bootloader stays in DFU mode.
+    pub fn invalidate_app(&mut self) {
+        self.set_word(BANK_0_OFFSET + 8, BankCode::Invalid.to_raw());
+    }
+
+    /// Requests the bootloader to enter DFU mode on the next reset, like the buttonless DFU
+    /// service does.
+    pub fn request_dfu(&mut self) {
+        self.set_word(ENTER_BUTTONLESS_DFU_OFFSET, 1);
+    }
+
+    /// Returns `true` if DFU mode has been requested.
+    pub fn is_dfu_requested(&self) -> bool {
+        self.word(ENTER_BUTTONLESS_DFU_OFFSET) != 0
+    }
+
+    /// Returns the raw settings.
+    pub fn as_bytes(&self) -> &[u8; SETTINGS_LEN] {
+        &self.data
+    }
+
+    fn bank(&self, offset: usize) -> Bank {
+        Bank {
+            image_size: self.word(offset),
+            image_crc: self.word(offset + 4),
+            bank_code: BankCode::from_raw(self.word(offset + 8)),
+        }
+    }
+
+    /// CRC of the settings, covering the fields between the CRC and the init command.
+    fn crc(&self) -> u32 {
+        !crc32_update(!0, &self.data[SETTINGS_VERSION_OFFSET..INIT_COMMAND_OFFSET])
+    }
+
+    fn update_crcs(&mut self) {
+        let crc = self.crc();
+        self.set_word(0, crc);
+
+        let validation =
+            &self.data[BOOT_VALIDATION_OFFSET..BOOT_VALIDATION_OFFSET + 3 * BOOT_VALIDATION_LEN];
+        let crc = !crc32_update(!0, validation);
+        self.set_word(BOOT_VALIDATION_CRC_OFFSET, crc);
+    }
+
+    fn word(&self, offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.data[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn set_word(&mut self, offset: usize, value: u32) {
+        self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
@@ -0,0 +1,196 @@
+//! HAL interface to the DPPI peripheral.
+//!
+//! The distributed programmable peripheral interconnect (DPPI) takes the place of the PPI of the
+//! nRF52 series. Rather than a central table of endpoints, each event of a peripheral publishes
+//! to a channel through its `PUBLISH` register, and each task subscribes to a channel through its
+//! `SUBSCRIBE` register, so a channel connects any number of events to any number of tasks. The
+//! DPPI controller (DPPIC) enables the channels and groups them.
+//!
+//! The `PUBLISH` and `SUBSCRIBE` registers sit at a fixed offset from their event and task
+//! registers, so [`Dppi::publish`] and [`Dppi::subscribe`] take the same endpoints the drivers
+//! hand out for PPI, e.g. `Rtc::event_tick` or `Timer::task_capture`:
+//!
+//! ```ignore
+//! let mut dppi = Dppi::new(p.DPPIC_NS);
+//! dppi.publish(0, rtc.event_tick());
+//! dppi.subscribe(0, timer.task_capture(TimerCcReg::Cc2));
+//! dppi.enable(0);
+//! ```
+//!
+//! See product specification, chapter DPPI.
+
+use core::ptr;
+
+use crate::pac::{dppic_ns as dppic, ipc_ns, rtc0_ns, timer0_ns, DPPIC_NS as DPPIC};
+
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+use crate::pac::saadc_ns;
+
+#[cfg(feature = "5340-net")]
+use crate::pac::radio_ns;
+
+/// Number of channels.
+#[cfg(feature = "5340-app")]
+pub const CHANNELS: usize = 32;
+
+/// Number of channels.
+#[cfg(any(feature = "9160", feature = "5340-net"))]
+pub const CHANNELS: usize = 16;
+
+/// Number of channel groups.
+pub const GROUPS: usize = 6;
+
+/// Offset of the `SUBSCRIBE` and `PUBLISH` registers from their task and event registers.
+const ENDPOINT_OFFSET: usize = 0x80;
+
+/// Bit connecting a `SUBSCRIBE` or `PUBLISH` register to its channel.
+const ENDPOINT_EN: u32 = 1 << 31;
+
+mod sealed {
+    pub trait Task {
+        #[inline(always)]
+        fn subscribe_addr(&self) -> *mut u32 {
+            (self as *const _ as usize + super::ENDPOINT_OFFSET) as *mut u32
+        }
+    }
+
+    pub trait Event {
+        #[inline(always)]
+        fn publish_addr(&self) -> *mut u32 {
+            (self as *const _ as usize + super::ENDPOINT_OFFSET) as *mut u32
+        }
+    }
+}
+use sealed::{Event, Task};
+
+macro_rules! impl_endpoints {
+    ($trait:ident: $($reg:path,)+) => {
+        $(
+            impl $trait for $reg {}
+        )+
+    };
+}
+
+impl_endpoints!(Task:
+    dppic::tasks_chg::EN,
+    dppic::tasks_chg::DIS,
+    ipc_ns::TASKS_SEND,
+    rtc0_ns::TASKS_START,
+    rtc0_ns::TASKS_STOP,
+    rtc0_ns::TASKS_CLEAR,
+    rtc0_ns::TASKS_TRIGOVRFLW,
+    timer0_ns::TASKS_START,
+    timer0_ns::TASKS_STOP,
+    timer0_ns::TASKS_COUNT,
+    timer0_ns::TASKS_CLEAR,
+    timer0_ns::TASKS_CAPTURE,
+);
+
+impl_endpoints!(Event:
+    ipc_ns::EVENTS_RECEIVE,
+    rtc0_ns::EVENTS_TICK,
+    rtc0_ns::EVENTS_OVRFLW,
+    rtc0_ns::EVENTS_COMPARE,
+    timer0_ns::EVENTS_COMPARE,
+);
+
+#[cfg(feature = "5340-net")]
+impl_endpoints!(Task:
+    radio_ns::TASKS_TXEN,
+    radio_ns::TASKS_RXEN,
+    radio_ns::TASKS_DISABLE,
+    radio_ns::TASKS_BCSTART,
+    radio_ns::TASKS_BCSTOP,
+);
+
+#[cfg(feature = "5340-net")]
+impl_endpoints!(Event:
+    radio_ns::EVENTS_READY,
+    radio_ns::EVENTS_ADDRESS,
+    radio_ns::EVENTS_END,
+    radio_ns::EVENTS_DISABLED,
+    radio_ns::EVENTS_BCMATCH,
+);
+
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+impl_endpoints!(Task:
+    saadc_ns::TASKS_START,
+    saadc_ns::TASKS_SAMPLE,
+    saadc_ns::TASKS_STOP,
+);
+
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+impl_endpoints!(Event:
+    saadc_ns::EVENTS_END,
+    saadc_ns::EVENTS_DONE,
+);
+
+/// Interface to the DPPI controller.
+pub struct Dppi(DPPIC);
+
+impl Dppi {
+    /// Takes the controller, with all channels disabled.
+    pub fn new(dppic: DPPIC) -> Self {
+        dppic.chenclr.write(|w| unsafe { w.bits(!0) });
+        Dppi(dppic)
+    }
+
+    /// Returns the raw `DPPIC` peripheral.
+    pub fn free(self) -> DPPIC {
+        self.0
+    }
+
+    /// Enables `channel`.
+    pub fn enable(&mut self, channel: usize) {
+        assert!(channel < CHANNELS);
+        self.0.chenset.write(|w| unsafe { w.bits(1 << channel) });
+    }
+
+    /// Disables `channel`. Its events are no longer forwarded to its tasks.
+    pub fn disable(&mut self, channel: usize) {
+        assert!(channel < CHANNELS);
+        self.0.chenclr.write(|w| unsafe { w.bits(1 << channel) });
+    }
+
+    /// Returns `true` if `channel` is enabled.
+    pub fn is_enabled(&self, channel: usize) -> bool {
+        self.0.chen.read().bits() & (1 << channel) != 0
+    }
+
+    /// Publishes `event` to `channel`, replacing the channel it was published to before.
+    pub fn publish<E: Event>(&mut self, channel: usize, event: &E) {
+        assert!(channel < CHANNELS);
+        unsafe { ptr::write_volatile(event.publish_addr(), ENDPOINT_EN | channel as u32) };
+    }
+
+    /// Stops publishing `event`.
+    pub fn unpublish<E: Event>(&mut self, event: &E) {
+        unsafe { ptr::write_volatile(event.publish_addr(), 0) };
+    }
+
+    /// Subscribes `task` to `channel`, replacing the channel it was subscribed to before.
+    pub fn subscribe<T: Task>(&mut self, channel: usize, task: &T) {
+        assert!(channel < CHANNELS);
+        unsafe { ptr::write_volatile(task.subscribe_addr(), ENDPOINT_EN | channel as u32) };
+    }
+
+    /// Unsubscribes `task`.
+    pub fn unsubscribe<T: Task>(&mut self, task: &T) {
+        unsafe { ptr::write_volatile(task.subscribe_addr(), 0) };
+    }
+
+    /// Sets the channels of `group`, as a bit mask.
+    pub fn set_group(&mut self, group: usize, channels: u32) {
+        self.0.chg[group].write(|w| unsafe { w.bits(channels) });
+    }
+
+    /// Returns reference to the task endpoint enabling the channels of `group`.
+    pub fn task_group_enable(&self, group: usize) -> &dppic::tasks_chg::EN {
+        &self.0.tasks_chg[group].en
+    }
+
+    /// Returns reference to the task endpoint disabling the channels of `group`.
+    pub fn task_group_disable(&self, group: usize) -> &dppic::tasks_chg::DIS {
+        &self.0.tasks_chg[group].dis
+    }
+}
@@ -2,13 +2,21 @@
 //!
 //! The ECB encryption block supports 128 bit AES encryption (encryption only, not decryption).
 
-use crate::pac::ECB;
+use crate::error::ErrorKind;
+use crate::pac::{ecb, ECB};
 use core::sync::atomic::{compiler_fence, Ordering};
 
 /// Error type to represent a sharing conflict during encryption.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct EncryptionError {}
 
+impl crate::error::Error for EncryptionError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
 /// A safe, blocking wrapper around the AES-ECB peripheral.
 ///
 /// It's really just blockwise AES and not an ECB stream cipher. Blocks can be
@@ -29,6 +37,26 @@ impl Ecb {
         Self { regs }
     }
 
+    /// Returns reference to the `STARTECB` task endpoint for PPI.
+    pub fn task_start(&self) -> &ecb::TASKS_STARTECB {
+        &self.regs.tasks_startecb
+    }
+
+    /// Returns reference to the `STOPECB` task endpoint for PPI.
+    pub fn task_stop(&self) -> &ecb::TASKS_STOPECB {
+        &self.regs.tasks_stopecb
+    }
+
+    /// Returns reference to the `ENDECB` event endpoint for PPI.
+    pub fn event_end(&self) -> &ecb::EVENTS_ENDECB {
+        &self.regs.events_endecb
+    }
+
+    /// Returns reference to the `ERRORECB` event endpoint for PPI.
+    pub fn event_error(&self) -> &ecb::EVENTS_ERRORECB {
+        &self.regs.events_errorecb
+    }
+
     /// Destroys `self`, giving the `ECB` peripheral back.
     pub fn into_inner(self) -> ECB {
         // Clear all events
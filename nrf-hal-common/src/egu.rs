@@ -0,0 +1,290 @@
+//! HAL interface to the EGU peripheral.
+//!
+//! The event generator unit (EGU) turns tasks into events: triggering one of its channels, from
+//! software or through PPI, generates the channel's `TRIGGERED` event and optionally its
+//! interrupt. Software can so inject events into PPI chains, and PPI can raise software
+//! interrupts.
+//!
+//! With the `async` feature, a channel also works as a signal between contexts: a task waits for
+//! the channel with `Egu::wait`, and an interrupt handler triggers it through a [`Trigger`]. The
+//! event stays pending in hardware, so a trigger before the wait is not lost:
+//!
+//! ```ignore
+//! let trigger = egu.trigger_handle();
+//! // In an interrupt handler:
+//! trigger.trigger(3);
+//! // In a task:
+//! egu.wait(3).await;
+//! ```
+//!
+//! [`SoftIrq`] reserves an instance to run deferred work at the priority of its interrupt: a
+//! high priority handler pends work items with a [`Trigger`], and the handler of the EGU
+//! interrupt runs them later, with [`take_pending`] telling which ones.
+//!
+//! See product specification, chapter 20.
+
+use core::{marker::PhantomData, ops::Deref};
+
+use cortex_m::peripheral::NVIC;
+
+#[cfg(not(feature = "9160"))]
+use crate::pac::{egu0, Interrupt, EGU0, EGU1};
+
+#[cfg(feature = "9160")]
+use crate::pac::{
+    egu0_ns as egu0, Interrupt, EGU0_NS as EGU0, EGU1_NS as EGU1, EGU2_NS as EGU2, EGU3_NS as EGU3,
+    EGU4_NS as EGU4, EGU5_NS as EGU5,
+};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WAKER: WakerSlot = WakerSlot::new();
+#[cfg(feature = "async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WAKERS: [WakerSlot; CHANNELS] = [EMPTY_WAKER; CHANNELS];
+
+/// Wakers of the channels of each instance.
+#[cfg(feature = "async")]
+static WAKERS: [[WakerSlot; CHANNELS]; 6] = [EMPTY_WAKERS; 6];
+
+#[cfg(not(any(feature = "52805", feature = "52810", feature = "9160")))]
+use crate::pac::{EGU2, EGU3, EGU4, EGU5};
+
+/// Number of channels of an EGU instance.
+pub const CHANNELS: usize = 16;
+
+/// Interface to an EGU instance.
+pub struct Egu<T>(T);
+
+impl<T> Egu<T>
+where
+    T: Instance,
+{
+    pub fn new(egu: T) -> Self {
+        Egu(egu)
+    }
+
+    /// Returns the EGU instance, with its interrupts disabled.
+    pub fn free(self) -> T {
+        self.0.intenclr.write(|w| unsafe { w.bits(!0) });
+        self.0
+    }
+
+    /// Triggers `channel`, generating its `TRIGGERED` event.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` is not below `CHANNELS`.
+    pub fn trigger(&self, channel: usize) {
+        self.0.tasks_trigger[channel].write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns a handle triggering the channels of this instance, which can be copied to other
+    /// contexts like interrupt handlers.
+    pub fn trigger_handle(&self) -> Trigger<T> {
+        Trigger(PhantomData)
+    }
+
+    /// Waits until `channel` has been triggered, then clears its event.
+    ///
+    /// Returns right away if the channel has been triggered since its event was last cleared.
+    /// The interrupt of the instance must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn wait(&mut self, channel: usize) {
+        assert!(channel < CHANNELS);
+        poll_fn(|cx| {
+            WAKERS[T::INDEX][channel].register(cx.waker());
+            if self.is_triggered(channel) {
+                self.reset_event(channel);
+                Poll::Ready(())
+            } else {
+                self.enable_interrupt(channel);
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Returns `true` if the `TRIGGERED` event of `channel` has been generated.
+    pub fn is_triggered(&self, channel: usize) -> bool {
+        self.0.events_triggered[channel].read().bits() != 0
+    }
+
+    /// Clears the `TRIGGERED` event of `channel`.
+    pub fn reset_event(&mut self, channel: usize) {
+        self.0.events_triggered[channel].reset();
+    }
+
+    /// Enables the interrupt of the instance for `channel`.
+    pub fn enable_interrupt(&mut self, channel: usize) -> &mut Self {
+        assert!(channel < CHANNELS);
+        self.0.intenset.write(|w| unsafe { w.bits(1 << channel) });
+        self
+    }
+
+    /// Disables the interrupt of the instance for `channel`.
+    pub fn disable_interrupt(&mut self, channel: usize) -> &mut Self {
+        assert!(channel < CHANNELS);
+        self.0.intenclr.write(|w| unsafe { w.bits(1 << channel) });
+        self
+    }
+
+    /// Returns reference to the `TRIGGER` task endpoint of `channel` for PPI.
+    pub fn task_trigger(&self, channel: usize) -> &egu0::TASKS_TRIGGER {
+        &self.0.tasks_trigger[channel]
+    }
+
+    /// Returns reference to the `TRIGGERED` event endpoint of `channel` for PPI.
+    pub fn event_triggered(&self, channel: usize) -> &egu0::EVENTS_TRIGGERED {
+        &self.0.events_triggered[channel]
+    }
+}
+
+/// Triggers the channels of an EGU instance from any context.
+#[derive(Clone, Copy)]
+pub struct Trigger<T>(PhantomData<T>);
+
+// Triggering only writes a task register, which is atomic.
+unsafe impl<T> Send for Trigger<T> {}
+unsafe impl<T> Sync for Trigger<T> {}
+
+impl<T> Trigger<T>
+where
+    T: Instance,
+{
+    /// Triggers `channel`, generating its `TRIGGERED` event.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` is not below `CHANNELS`.
+    pub fn trigger(&self, channel: usize) {
+        let egu = unsafe { &*T::ptr() };
+        egu.tasks_trigger[channel].write(|w| unsafe { w.bits(1) });
+    }
+}
+
+/// Handles the EGU events for `Egu::wait`.
+///
+/// Must be called from the interrupt handler of the instance `T`.
+#[cfg(feature = "async")]
+pub fn on_interrupt<T: Instance>() {
+    let egu = unsafe { &*T::ptr() };
+    let enabled = egu.inten.read().bits();
+    for channel in 0..CHANNELS {
+        if enabled & (1 << channel) != 0 && egu.events_triggered[channel].read().bits() != 0 {
+            // Leave the event set for the task to observe, but keep it from firing again.
+            egu.intenclr.write(|w| unsafe { w.bits(1 << channel) });
+            WAKERS[T::INDEX][channel].wake();
+        }
+    }
+}
+
+/// An EGU instance reserved for deferring work to the priority of its interrupt, with one work
+/// item per channel.
+pub struct SoftIrq<T>(T);
+
+impl<T> SoftIrq<T>
+where
+    T: Instance,
+{
+    /// Enables the interrupt of all channels, and unmasks the interrupt of the instance at
+    /// `priority` in the NVIC. As for `NVIC::set_priority`, only the upper 3 bits of `priority`
+    /// are used, and lower values mean higher priorities.
+    pub fn new(egu: T, nvic: &mut NVIC, priority: u8) -> Self {
+        for event in egu.events_triggered.iter() {
+            event.reset();
+        }
+        egu.intenset
+            .write(|w| unsafe { w.bits((1 << CHANNELS) - 1) });
+        unsafe {
+            nvic.set_priority(T::INTERRUPT, priority);
+            NVIC::unmask(T::INTERRUPT);
+        }
+        SoftIrq(egu)
+    }
+
+    /// Returns a handle pending work items, i.e. triggering channels, from any context.
+    pub fn pender(&self) -> Trigger<T> {
+        Trigger(PhantomData)
+    }
+
+    /// Masks the interrupt, and returns the EGU instance.
+    pub fn free(self) -> T {
+        NVIC::mask(T::INTERRUPT);
+        self.0.intenclr.write(|w| unsafe { w.bits(!0) });
+        self.0
+    }
+}
+
+/// Clears the triggered events of the instance `T`, and returns them as a bit mask of
+/// channels.
+///
+/// Called from the interrupt handler of a [`SoftIrq`] to find the pending work items.
+pub fn take_pending<T: Instance>() -> u16 {
+    let egu = unsafe { &*T::ptr() };
+    let mut pending = 0;
+    for (channel, event) in egu.events_triggered.iter().enumerate() {
+        if event.read().bits() != 0 {
+            event.reset();
+            pending |= 1 << channel;
+        }
+    }
+    pending
+}
+
+/// Implemented by all EGU instances.
+pub trait Instance: Deref<Target = egu0::RegisterBlock> {
+    /// The interrupt associated with this EGU instance, shared with a software interrupt.
+    const INTERRUPT: Interrupt;
+
+    /// Number of the instance.
+    const INDEX: usize;
+
+    /// Returns the registers of the instance.
+    fn ptr() -> *const egu0::RegisterBlock;
+}
+
+macro_rules! impl_instance {
+    ($($name:ident => $interrupt:ident, $index:expr,)*) => {
+        $(
+            impl Instance for $name {
+                const INTERRUPT: Interrupt = Interrupt::$interrupt;
+                const INDEX: usize = $index;
+
+                fn ptr() -> *const egu0::RegisterBlock {
+                    $name::ptr()
+                }
+            }
+        )*
+    }
+}
+
+#[cfg(not(feature = "9160"))]
+impl_instance!(
+    EGU0 => SWI0_EGU0, 0,
+    EGU1 => SWI1_EGU1, 1,
+);
+
+#[cfg(not(any(feature = "52805", feature = "52810", feature = "9160")))]
+impl_instance!(
+    EGU2 => SWI2_EGU2, 2,
+    EGU3 => SWI3_EGU3, 3,
+    EGU4 => SWI4_EGU4, 4,
+    EGU5 => SWI5_EGU5, 5,
+);
+
+#[cfg(feature = "9160")]
+impl_instance!(
+    EGU0 => EGU0, 0,
+    EGU1 => EGU1, 1,
+    EGU2 => EGU2, 2,
+    EGU3 => EGU3, 3,
+    EGU4 => EGU4, 4,
+    EGU5 => EGU5, 5,
+);
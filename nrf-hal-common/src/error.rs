@@ -0,0 +1,91 @@
+//! Error classes shared by all drivers.
+//!
+//! Each driver has its own error type, but all of them implement [`Error`], which sorts them into
+//! the classes of [`ErrorKind`]. Generic code can handle the errors of different peripherals
+//! alike this way:
+//!
+//! ```ignore
+//! use nrf_hal_common::error::{Error, ErrorKind};
+//!
+//! fn retry<E: Error>(result: Result<(), E>) -> bool {
+//!     matches!(
+//!         result.map_err(|e| e.kind()),
+//!         Err(ErrorKind::Nack) | Err(ErrorKind::Dma)
+//!     )
+//! }
+//! ```
+
+/// Class of a driver error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A buffer used by EasyDMA is not located in RAM.
+    BufferNotInRam,
+    /// A buffer is too long or too short for the operation, or buffers differ in length.
+    BufferLength,
+    /// An EasyDMA transfer did not transfer all bytes, or failed with a bus conflict.
+    Dma,
+    /// The other side did not acknowledge an address, a byte or a packet.
+    Nack,
+    /// The operation did not complete in time.
+    Timeout,
+    /// Received data failed its CRC or integrity check.
+    Corrupted,
+    /// An argument, like an address, an alignment or a range, is not valid.
+    InvalidArgument,
+    /// A resource, like a channel, is already in use.
+    Busy,
+    /// The operation is not supported by the peripheral or its configuration.
+    Unsupported,
+    /// Any other error.
+    Other,
+}
+
+/// Implemented by the error types of all drivers.
+pub trait Error: core::fmt::Debug {
+    /// Returns the class of the error.
+    fn kind(&self) -> ErrorKind;
+}
+
+impl Error for ErrorKind {
+    fn kind(&self) -> ErrorKind {
+        *self
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::spi::Error for ErrorKind {
+    fn kind(&self) -> embedded_hal_1::spi::ErrorKind {
+        embedded_hal_1::spi::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::i2c::Error for ErrorKind {
+    fn kind(&self) -> embedded_hal_1::i2c::ErrorKind {
+        use embedded_hal_1::i2c::{ErrorKind as I2cErrorKind, NoAcknowledgeSource};
+
+        match self {
+            ErrorKind::Nack => I2cErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            _ => I2cErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for ErrorKind {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        use embedded_io::ErrorKind as IoErrorKind;
+
+        match self {
+            ErrorKind::BufferNotInRam | ErrorKind::BufferLength | ErrorKind::InvalidArgument => {
+                IoErrorKind::InvalidInput
+            }
+            ErrorKind::Timeout => IoErrorKind::TimedOut,
+            ErrorKind::Corrupted => IoErrorKind::InvalidData,
+            ErrorKind::Unsupported => IoErrorKind::Unsupported,
+            _ => IoErrorKind::Other,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! HAL interface to the FICR.
+//!
+//! The factory information configuration registers (FICR) are programmed by Nordic during
+//! production and hold the identity of the chip, like its unique device ID and BLE device
+//! address, its part code and memory sizes, and calibration values of its peripherals.
+//!
+//! See product specification, chapter FICR.
+
+use core::fmt;
+
+use crate::pac::FICR;
+use crate::radio::ble::AddressType;
+
+/// Value of the `INFO` registers that are not specified for a chip.
+const UNSPECIFIED: u32 = 0xffff_ffff;
+
+/// A device address for BLE, unique to the chip.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceAddress {
+    /// The address in little endian byte order, as it is transmitted over the air.
+    pub bytes: [u8; 6],
+    pub address_type: AddressType,
+}
+
+impl DeviceAddress {
+    /// Returns the address formatted as a BLE random static address, whose two most significant
+    /// bits are set, in little endian byte order.
+    ///
+    /// The factory programmed address is random, but does not follow that format by itself.
+    pub fn random_static(&self) -> [u8; 6] {
+        let mut bytes = self.bytes;
+        bytes[5] |= 0xc0;
+        bytes
+    }
+}
+
+/// Formats the address as usual for BLE, most significant byte first, e.g. `C2:8F:11:4A:90:3E`.
+impl fmt::Display for DeviceAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.bytes;
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            b[5], b[4], b[3], b[2], b[1], b[0]
+        )
+    }
+}
+
+/// Part information from the `INFO` registers.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Info {
+    /// Part code, e.g. `0x52840`.
+    pub part: u32,
+    /// Variant, e.g. `*b"AAD0"`: the build code is the last two characters.
+    pub variant: [u8; 4],
+    /// Package code, e.g. `0x2004` for the QIxx package of the nRF52840.
+    pub package: u32,
+    /// RAM size in KiB, if specified.
+    pub ram_kib: Option<u32>,
+    /// Flash size in KiB, if specified.
+    pub flash_kib: Option<u32>,
+}
+
+/// Production calibration of the temperature sensor, for the registers of the TEMP peripheral.
+///
+/// The chip loads these into the TEMP peripheral at reset, they are only needed to restore its
+/// calibration after changing those registers.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TempCalibration {
+    /// Slopes of the piecewise linear function, `A0` to `A5`.
+    pub a: [u32; 6],
+    /// Intercepts of the piecewise linear function, `B0` to `B5`.
+    pub b: [u32; 6],
+    /// End points of the segments, `T0` to `T4`.
+    pub t: [u32; 5],
+}
+
+/// Interface to the FICR.
+pub struct Ficr(FICR);
+
+impl Ficr {
+    pub fn new(ficr: FICR) -> Self {
+        Self(ficr)
+    }
+
+    /// Returns the raw `FICR` peripheral.
+    pub fn free(self) -> FICR {
+        self.0
+    }
+
+    /// Returns the unique 64 bit device ID.
+    pub fn device_id(&self) -> u64 {
+        u64::from(self.0.deviceid[0].read().bits())
+            | u64::from(self.0.deviceid[1].read().bits()) << 32
+    }
+
+    /// Returns the device address for BLE.
+    pub fn device_address(&self) -> DeviceAddress {
+        let low = self.0.deviceaddr[0].read().bits().to_le_bytes();
+        let high = self.0.deviceaddr[1].read().bits().to_le_bytes();
+        let address_type = if self.0.deviceaddrtype.read().bits() & 1 == 0 {
+            AddressType::Public
+        } else {
+            AddressType::Random
+        };
+        DeviceAddress {
+            bytes: [low[0], low[1], low[2], low[3], high[0], high[1]],
+            address_type,
+        }
+    }
+
+    /// Returns the encryption root, used to derive BLE keys.
+    pub fn encryption_root(&self) -> [u8; 16] {
+        let mut root = [0; 16];
+        for (chunk, er) in root.chunks_mut(4).zip(self.0.er.iter()) {
+            chunk.copy_from_slice(&er.read().bits().to_le_bytes());
+        }
+        root
+    }
+
+    /// Returns the identity root, used to derive the BLE identity resolving key.
+    pub fn identity_root(&self) -> [u8; 16] {
+        let mut root = [0; 16];
+        for (chunk, ir) in root.chunks_mut(4).zip(self.0.ir.iter()) {
+            chunk.copy_from_slice(&ir.read().bits().to_le_bytes());
+        }
+        root
+    }
+
+    /// Returns the size of a flash page in bytes.
+    pub fn code_page_size(&self) -> u32 {
+        self.0.codepagesize.read().bits()
+    }
+
+    /// Returns the size of the flash in bytes.
+    pub fn code_size(&self) -> u32 {
+        self.0.codesize.read().bits() * self.code_page_size()
+    }
+
+    /// Returns the part information.
+    pub fn info(&self) -> Info {
+        let info = &self.0.info;
+        let specified = |bits| {
+            if bits == UNSPECIFIED {
+                None
+            } else {
+                Some(bits)
+            }
+        };
+        Info {
+            part: info.part.read().bits(),
+            variant: info.variant.read().bits().to_be_bytes(),
+            package: info.package.read().bits(),
+            ram_kib: specified(info.ram.read().bits()),
+            flash_kib: specified(info.flash.read().bits()),
+        }
+    }
+
+    /// Returns the production calibration of the temperature sensor.
+    pub fn temp_calibration(&self) -> TempCalibration {
+        let temp = &self.0.temp;
+        TempCalibration {
+            a: [
+                temp.a0.read().bits(),
+                temp.a1.read().bits(),
+                temp.a2.read().bits(),
+                temp.a3.read().bits(),
+                temp.a4.read().bits(),
+                temp.a5.read().bits(),
+            ],
+            b: [
+                temp.b0.read().bits(),
+                temp.b1.read().bits(),
+                temp.b2.read().bits(),
+                temp.b3.read().bits(),
+                temp.b4.read().bits(),
+                temp.b5.read().bits(),
+            ],
+            t: [
+                temp.t0.read().bits(),
+                temp.t1.read().bits(),
+                temp.t2.read().bits(),
+                temp.t3.read().bits(),
+                temp.t4.read().bits(),
+            ],
+        }
+    }
+}
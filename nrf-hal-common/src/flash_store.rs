@@ -0,0 +1,375 @@
+//! A small key-value store on internal flash.
+//!
+//! Values of up to [`MAX_VALUE_LEN`] bytes are stored under 16 bit keys in a log of records,
+//! appended to one of two flash pages. Each record carries a CRC, so records torn by a reset
+//! during a write are ignored. When the active page is full, the latest value of each key is
+//! copied to the other page, which becomes the active one. Alternating the pages spreads the
+//! erases evenly over both.
+//!
+//! The store works on any [`NorFlash`], usually an [`Nvmc`](crate::nvmc::Nvmc) region of two
+//! pages.
+//!
+//! ```ignore
+//! let mut store = FlashStore::new(Nvmc::new(p.NVMC, storage), 0)?;
+//! store.set(KEY_NAME, b"sensor-1")?;
+//! let boots = store.increment(KEY_BOOT_COUNT)?;
+//! ```
+
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+
+use crate::error::ErrorKind;
+use crate::nvmc::crc32_update;
+
+/// Maximum length of a value, in bytes.
+pub const MAX_VALUE_LEN: usize = 256;
+
+/// Marks the first word of an initialized page.
+const PAGE_MAGIC: u32 = 0x4b56_5354;
+
+/// Page header: magic and sequence number.
+const PAGE_HEADER_LEN: u32 = 8;
+
+/// Value of erased flash words.
+const ERASED: u32 = 0xffff_ffff;
+
+/// Flag in the length field of a record marking a removed key.
+const REMOVED: u16 = 0x8000;
+
+/// Key-value store error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// Error of the underlying flash.
+    Flash(E),
+    /// Key 0xFFFF is reserved.
+    InvalidKey,
+    /// The value is longer than `MAX_VALUE_LEN`.
+    ValueTooLong,
+    /// The buffer is too small for the stored value.
+    BufferTooSmall,
+    /// The latest values of all keys don't fit into a page.
+    Full,
+}
+
+impl<E: NorFlashError> crate::error::Error for Error<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Flash(e) => match e.kind() {
+                NorFlashErrorKind::NotAligned | NorFlashErrorKind::OutOfBounds => {
+                    ErrorKind::InvalidArgument
+                }
+                _ => ErrorKind::Other,
+            },
+            Error::InvalidKey => ErrorKind::InvalidArgument,
+            Error::ValueTooLong | Error::BufferTooSmall => ErrorKind::BufferLength,
+            Error::Full => ErrorKind::Other,
+        }
+    }
+}
+
+/// A record of the log.
+struct Record {
+    offset: u32,
+    key: u16,
+    len: u16,
+    removed: bool,
+    valid: bool,
+}
+
+impl Record {
+    /// Offset of the record following this one.
+    fn next(&self) -> u32 {
+        self.offset + record_len(self.len as usize)
+    }
+}
+
+/// Size of a record with a value of `len` bytes: header, value padded to words, and CRC.
+fn record_len(len: usize) -> u32 {
+    (4 + (len + 3) / 4 * 4 + 4) as u32
+}
+
+/// Key-value store on two pages of flash.
+pub struct FlashStore<S> {
+    flash: S,
+    pages: [u32; 2],
+    active: usize,
+    sequence: u32,
+    /// Offset of the next record in the active page.
+    end: u32,
+}
+
+impl<S> FlashStore<S>
+where
+    S: NorFlash,
+{
+    /// Opens the store on the two pages of `flash` starting at `offset`, formatting it if no
+    /// valid page is found.
+    pub fn new(flash: S, offset: u32) -> Result<Self, Error<S::Error>> {
+        assert!(4 % S::READ_SIZE == 0 && 4 % S::WRITE_SIZE == 0);
+
+        let page_size = S::ERASE_SIZE as u32;
+        let mut store = FlashStore {
+            flash,
+            pages: [offset, offset + page_size],
+            active: 0,
+            sequence: 0,
+            end: PAGE_HEADER_LEN,
+        };
+
+        let headers = [store.page_sequence(0)?, store.page_sequence(1)?];
+        match headers {
+            [Some(a), Some(b)] if b > a => store.open(1, b)?,
+            [Some(a), _] => store.open(0, a)?,
+            [None, Some(b)] => store.open(1, b)?,
+            [None, None] => {
+                store.erase_page(0)?;
+                store.write_page_header(0, 0)?;
+                store.open(0, 0)?;
+            }
+        }
+        Ok(store)
+    }
+
+    /// Returns the flash.
+    pub fn free(self) -> S {
+        self.flash
+    }
+
+    /// Reads the value of `key` into `buf`.
+    ///
+    /// Returns the length of the value, or `None` if the key is not set.
+    pub fn get(&mut self, key: u16, buf: &mut [u8]) -> Result<Option<usize>, Error<S::Error>> {
+        let record = match self.find(key)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let len = record.len as usize;
+        if buf.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        self.read_value(self.pages[self.active] + record.offset, &mut buf[..len])?;
+        Ok(Some(len))
+    }
+
+    /// Sets the value of `key`.
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error<S::Error>> {
+        if key == 0xffff {
+            return Err(Error::InvalidKey);
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::ValueTooLong);
+        }
+        self.append(key, value.len() as u16, value)
+    }
+
+    /// Removes `key` from the store.
+    pub fn remove(&mut self, key: u16) -> Result<(), Error<S::Error>> {
+        if self.find(key)?.is_some() {
+            self.append(key, REMOVED, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Increments the counter stored as a little-endian `u32` under `key`, starting from 0 if
+    /// it is not set, and returns its new value.
+    pub fn increment(&mut self, key: u16) -> Result<u32, Error<S::Error>> {
+        let mut buf = [0; 4];
+        let value = match self.get(key, &mut buf) {
+            Ok(Some(4)) => u32::from_le_bytes(buf),
+            Ok(_) | Err(Error::BufferTooSmall) => 0,
+            Err(e) => return Err(e),
+        };
+        let value = value.wrapping_add(1);
+        self.set(key, &value.to_le_bytes())?;
+        Ok(value)
+    }
+
+    /// Makes `page` the active one and finds the end of its log.
+    fn open(&mut self, page: usize, sequence: u32) -> Result<(), Error<S::Error>> {
+        self.active = page;
+        self.sequence = sequence;
+        let mut offset = PAGE_HEADER_LEN;
+        while let Some(record) = self.record(page, offset)? {
+            offset = record.next();
+        }
+        self.end = offset;
+        Ok(())
+    }
+
+    /// Returns the latest record of `key` in the active page, unless it has been removed.
+    fn find(&mut self, key: u16) -> Result<Option<Record>, Error<S::Error>> {
+        let mut found = None;
+        let mut offset = PAGE_HEADER_LEN;
+        while let Some(record) = self.record(self.active, offset)? {
+            offset = record.next();
+            if record.valid && record.key == key {
+                found = Some(record);
+            }
+        }
+        Ok(found.filter(|record| !record.removed))
+    }
+
+    /// Appends a record to the active page, compacting the store if it is full.
+    fn append(&mut self, key: u16, len: u16, value: &[u8]) -> Result<(), Error<S::Error>> {
+        let size = record_len(value.len());
+        if self.end + size > S::ERASE_SIZE as u32 {
+            self.compact()?;
+            if self.end + size > S::ERASE_SIZE as u32 {
+                return Err(Error::Full);
+            }
+        }
+        self.end = self.write_record(self.active, self.end, key, len, value)?;
+        Ok(())
+    }
+
+    /// Copies the latest value of each key to the other page and makes it the active one.
+    fn compact(&mut self) -> Result<(), Error<S::Error>> {
+        let from = self.active;
+        let to = 1 - from;
+        self.erase_page(to)?;
+
+        let mut buf = [0; MAX_VALUE_LEN];
+        let mut end = PAGE_HEADER_LEN;
+        let mut offset = PAGE_HEADER_LEN;
+        while let Some(record) = self.record(from, offset)? {
+            offset = record.next();
+            if !record.valid || record.removed || self.superseded(from, &record)? {
+                continue;
+            }
+            let value = &mut buf[..record.len as usize];
+            self.read_value(self.pages[from] + record.offset, value)?;
+            if end + record_len(value.len()) > S::ERASE_SIZE as u32 {
+                return Err(Error::Full);
+            }
+            end = self.write_record(to, end, record.key, record.len, value)?;
+        }
+
+        // The header is written last, so a reset during the copy leaves the old page active.
+        self.write_page_header(to, self.sequence + 1)?;
+        self.active = to;
+        self.sequence += 1;
+        self.end = end;
+        Ok(())
+    }
+
+    /// Returns `true` if a later valid record of the same key follows `record` in `page`.
+    fn superseded(&mut self, page: usize, record: &Record) -> Result<bool, Error<S::Error>> {
+        let mut offset = record.next();
+        while let Some(later) = self.record(page, offset)? {
+            offset = later.next();
+            if later.valid && later.key == record.key {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Reads the record at `offset` of `page`, or `None` at the end of the log.
+    fn record(&mut self, page: usize, offset: u32) -> Result<Option<Record>, Error<S::Error>> {
+        if offset + 4 > S::ERASE_SIZE as u32 {
+            return Ok(None);
+        }
+        let base = self.pages[page];
+        let header = self.read_word(base + offset)?;
+        if header == ERASED {
+            return Ok(None);
+        }
+
+        let key = header as u16;
+        let len_field = (header >> 16) as u16;
+        let len = len_field & !REMOVED;
+        let mut record = Record {
+            offset,
+            key,
+            len,
+            removed: len_field & REMOVED != 0,
+            valid: false,
+        };
+        if len as usize > MAX_VALUE_LEN || record.next() > S::ERASE_SIZE as u32 {
+            // A corrupted header, nothing after it can be trusted.
+            return Ok(None);
+        }
+
+        let mut crc = crc32_update(!0, &header.to_le_bytes());
+        let mut remaining = len as usize;
+        let mut data = base + offset + 4;
+        while remaining > 0 {
+            let word = self.read_word(data)?.to_le_bytes();
+            let n = remaining.min(4);
+            crc = crc32_update(crc, &word[..n]);
+            remaining -= n;
+            data += 4;
+        }
+        record.valid = self.read_word(data)? == !crc;
+        Ok(Some(record))
+    }
+
+    /// Reads the value of the record at flash offset `record`.
+    fn read_value(&mut self, record: u32, value: &mut [u8]) -> Result<(), Error<S::Error>> {
+        for (i, chunk) in value.chunks_mut(4).enumerate() {
+            let word = self.read_word(record + 4 + 4 * i as u32)?.to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+        Ok(())
+    }
+
+    /// Writes a record at `offset` of `page`, returning the offset following it.
+    fn write_record(
+        &mut self,
+        page: usize,
+        offset: u32,
+        key: u16,
+        len: u16,
+        value: &[u8],
+    ) -> Result<u32, Error<S::Error>> {
+        let base = self.pages[page];
+        let header = key as u32 | ((len as u32) << 16);
+        let mut crc = crc32_update(!0, &header.to_le_bytes());
+        self.write_word(base + offset, header)?;
+
+        let mut data = base + offset + 4;
+        for chunk in value.chunks(4) {
+            let mut word = [0xff; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            crc = crc32_update(crc, chunk);
+            self.write_word(data, u32::from_le_bytes(word))?;
+            data += 4;
+        }
+        self.write_word(data, !crc)?;
+        Ok(data + 4 - base)
+    }
+
+    /// Returns the sequence number of `page`, or `None` if it is not initialized.
+    fn page_sequence(&mut self, page: usize) -> Result<Option<u32>, Error<S::Error>> {
+        let base = self.pages[page];
+        if self.read_word(base)? != PAGE_MAGIC {
+            return Ok(None);
+        }
+        Ok(Some(self.read_word(base + 4)?))
+    }
+
+    fn write_page_header(&mut self, page: usize, sequence: u32) -> Result<(), Error<S::Error>> {
+        let base = self.pages[page];
+        self.write_word(base + 4, sequence)?;
+        self.write_word(base, PAGE_MAGIC)
+    }
+
+    fn erase_page(&mut self, page: usize) -> Result<(), Error<S::Error>> {
+        let base = self.pages[page];
+        self.flash
+            .erase(base, base + S::ERASE_SIZE as u32)
+            .map_err(Error::Flash)
+    }
+
+    fn read_word(&mut self, offset: u32) -> Result<u32, Error<S::Error>> {
+        let mut word = [0; 4];
+        self.flash.read(offset, &mut word).map_err(Error::Flash)?;
+        Ok(u32::from_le_bytes(word))
+    }
+
+    fn write_word(&mut self, offset: u32, word: u32) -> Result<(), Error<S::Error>> {
+        self.flash
+            .write(offset, &word.to_le_bytes())
+            .map_err(Error::Flash)
+    }
+}
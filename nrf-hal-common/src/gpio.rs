@@ -22,26 +22,44 @@ pub struct PushPull;
 /// Open drain output (type state).
 pub struct OpenDrain;
 
+/// Analog mode (type state).
+///
+/// The digital input buffer of the pin is disconnected, so the pin can be used by the analog
+/// peripherals (SAADC, COMP, LPCOMP).
+pub struct Analog;
+
 /// Represents a digital input or output level.
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Level {
     Low,
     High,
 }
 
 /// A GPIO port with up to 32 pins.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Port {
     /// Port 0, available on all nRF52 and nRF51 MCUs.
     Port0,
 
     /// Port 1, only available on some nRF52 MCUs.
-    #[cfg(any(feature = "52833", feature = "52840"))]
+    #[cfg(any(
+        feature = "52833",
+        feature = "52840",
+        feature = "5340-app",
+        feature = "5340-net"
+    ))]
     Port1,
 }
 
 impl Port {
-    #[cfg(any(feature = "52833", feature = "52840"))]
+    #[cfg(any(
+        feature = "52833",
+        feature = "52840",
+        feature = "5340-app",
+        feature = "5340-net"
+    ))]
     pub(crate) fn bit(&self) -> bool {
         match self {
             Port::Port0 => false,
@@ -56,6 +74,10 @@ impl Port {
 // across all of the possible pins
 // ===============================================================
 /// Generic $PX pin
+///
+/// Both the pin number and the port are only known at runtime, which allows pins of the same mode
+/// to be stored in arrays or board configuration tables. Use `TryFrom` to upgrade a generic pin
+/// back to its typed counterpart (e.g. `p0::P0_13`).
 pub struct Pin<MODE> {
     // Bit 7: Port, Bits 0-6: Pin
     pin_port: u8,
@@ -65,23 +87,45 @@ pub struct Pin<MODE> {
 #[cfg(feature = "51")]
 use crate::pac::{gpio, GPIO as P0};
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
 use crate::pac::{p0_ns as gpio, P0_NS as P0};
 
-#[cfg(not(any(feature = "9160", feature = "51")))]
+#[cfg(not(any(
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "51"
+)))]
 use crate::pac::{p0 as gpio, P0};
 
 #[cfg(any(feature = "52833", feature = "52840"))]
 use crate::pac::P1;
 
+#[cfg(any(feature = "5340-app", feature = "5340-net"))]
+use crate::pac::P1_NS as P1;
+
 use crate::hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin};
+#[cfg(feature = "embedded-hal-1")]
+use core::convert::Infallible;
+#[cfg(feature = "embedded-hal-1")]
+use void::ResultVoidExt;
 use void::Void;
 
+/// A pin whose port and pin number are only known at runtime.
+///
+/// This is the same type as the generic [`Pin`], named for use in board pin maps.
+pub type AnyPin<MODE> = Pin<MODE>;
+
 impl<MODE> Pin<MODE> {
     fn new(port: Port, pin: u8) -> Self {
         let port_bits = match port {
             Port::Port0 => 0x00,
-            #[cfg(any(feature = "52833", feature = "52840"))]
+            #[cfg(any(
+                feature = "52833",
+                feature = "52840",
+                feature = "5340-app",
+                feature = "5340-net"
+            ))]
             Port::Port1 => 0x80,
         };
         Self {
@@ -90,14 +134,80 @@ impl<MODE> Pin<MODE> {
         }
     }
 
+    /// Create a generic pin from its PSEL representation, as returned by `psel_bits`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must make sure that the pin is not owned by anything else, and that it is
+    /// actually in the state described by `MODE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `psel_bits` does not describe a pin that exists on this chip.
+    pub unsafe fn from_psel_bits(psel_bits: u32) -> Self {
+        #[cfg(any(
+            feature = "52833",
+            feature = "52840",
+            feature = "5340-app",
+            feature = "5340-net"
+        ))]
+        {
+            assert!(psel_bits & !0x3f == 0 && (psel_bits & 0x20 == 0 || psel_bits & 0x1f < 16));
+            let port = if psel_bits & 0x20 == 0 {
+                Port::Port0
+            } else {
+                Port::Port1
+            };
+            Self::new(port, (psel_bits & 0x1f) as u8)
+        }
+
+        #[cfg(not(any(
+            feature = "52833",
+            feature = "52840",
+            feature = "5340-app",
+            feature = "5340-net"
+        )))]
+        {
+            assert!(psel_bits < 32);
+            Self::new(Port::Port0, psel_bits as u8)
+        }
+    }
+
+    /// Returns the pin number and port in the format expected by the `PSEL` registers of the
+    /// peripherals (bits 0-4: pin, bit 5: port).
+    #[inline]
+    pub fn psel_bits(&self) -> u32 {
+        let port_bit = match self.port() {
+            Port::Port0 => 0,
+            #[cfg(any(
+                feature = "52833",
+                feature = "52840",
+                feature = "5340-app",
+                feature = "5340-net"
+            ))]
+            Port::Port1 => 1 << 5,
+        };
+        port_bit | self.pin() as u32
+    }
+
     #[inline]
     pub fn pin(&self) -> u8 {
-        #[cfg(any(feature = "52833", feature = "52840"))]
+        #[cfg(any(
+            feature = "52833",
+            feature = "52840",
+            feature = "5340-app",
+            feature = "5340-net"
+        ))]
         {
             self.pin_port & 0x7f
         }
 
-        #[cfg(not(any(feature = "52833", feature = "52840")))]
+        #[cfg(not(any(
+            feature = "52833",
+            feature = "52840",
+            feature = "5340-app",
+            feature = "5340-net"
+        )))]
         {
             self.pin_port
         }
@@ -105,7 +215,12 @@ impl<MODE> Pin<MODE> {
 
     #[inline]
     pub fn port(&self) -> Port {
-        #[cfg(any(feature = "52833", feature = "52840"))]
+        #[cfg(any(
+            feature = "52833",
+            feature = "52840",
+            feature = "5340-app",
+            feature = "5340-net"
+        ))]
         {
             if self.pin_port & 0x80 == 0 {
                 Port::Port0
@@ -114,7 +229,12 @@ impl<MODE> Pin<MODE> {
             }
         }
 
-        #[cfg(not(any(feature = "52833", feature = "52840")))]
+        #[cfg(not(any(
+            feature = "52833",
+            feature = "52840",
+            feature = "5340-app",
+            feature = "5340-net"
+        )))]
         {
             Port::Port0
         }
@@ -123,7 +243,12 @@ impl<MODE> Pin<MODE> {
     fn block(&self) -> &gpio::RegisterBlock {
         let ptr = match self.port() {
             Port::Port0 => P0::ptr(),
-            #[cfg(any(feature = "52833", feature = "52840"))]
+            #[cfg(any(
+                feature = "52833",
+                feature = "52840",
+                feature = "5340-app",
+                feature = "5340-net"
+            ))]
             Port::Port1 => P1::ptr(),
         };
 
@@ -235,6 +360,14 @@ impl<MODE> Pin<MODE> {
     }
 }
 
+/// Formats the pin as in the product specification, e.g. `P0.13`.
+#[cfg(feature = "defmt")]
+impl<MODE> defmt::Format for Pin<MODE> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "P{=u32}.{=u8}", self.psel_bits() >> 5, self.pin())
+    }
+}
+
 impl<MODE> InputPin for Pin<Input<MODE>> {
     type Error = Void;
 
@@ -285,6 +418,184 @@ impl<MODE> StatefulOutputPin for Pin<Output<MODE>> {
     }
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl<MODE> embedded_hal_1::digital::ErrorType for Pin<MODE> {
+    type Error = Infallible;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<MODE> embedded_hal_1::digital::InputPin for Pin<Input<MODE>> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(InputPin::is_high(self).void_unwrap())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(InputPin::is_low(self).void_unwrap())
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<MODE> embedded_hal_1::digital::OutputPin for Pin<Output<MODE>> {
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(OutputPin::set_high(self).void_unwrap())
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(OutputPin::set_low(self).void_unwrap())
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<MODE> embedded_hal_1::digital::StatefulOutputPin for Pin<Output<MODE>> {
+    fn is_set_high(&mut self) -> Result<bool, Infallible> {
+        Ok(StatefulOutputPin::is_set_high(self).void_unwrap())
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Infallible> {
+        Ok(StatefulOutputPin::is_set_low(self).void_unwrap())
+    }
+}
+
+/// A group of up to 32 pins on the same port that are read and written together.
+///
+/// Bit `n` of the values passed to and returned from the group corresponds to the `n`-th pin given
+/// to `PinGroup::new`, which makes it possible to drive parallel buses (e.g. 8-bit LCD interfaces)
+/// from arbitrarily placed pins.
+pub struct PinGroup<MODE, const N: usize> {
+    pins: [Pin<MODE>; N],
+}
+
+impl<MODE, const N: usize> PinGroup<MODE, N> {
+    /// Create a new pin group.
+    ///
+    /// Returns the pins back if the group is empty, has more than 32 pins or if the pins do not
+    /// all belong to the same port.
+    pub fn new(pins: [Pin<MODE>; N]) -> Result<Self, [Pin<MODE>; N]> {
+        if N == 0 || N > 32 || pins.iter().any(|pin| pin.port() != pins[0].port()) {
+            return Err(pins);
+        }
+
+        Ok(Self { pins })
+    }
+
+    /// Release the pins of the group.
+    pub fn free(self) -> [Pin<MODE>; N] {
+        self.pins
+    }
+
+    /// Bit mask of the group's pins within the port registers.
+    pub fn mask(&self) -> u32 {
+        self.pins.iter().fold(0, |mask, pin| mask | 1 << pin.pin())
+    }
+
+    fn block(&self) -> &gpio::RegisterBlock {
+        self.pins[0].block()
+    }
+
+    /// Spread the bits of `value` over the port bit positions of the group's pins.
+    fn scatter(&self, value: u32) -> u32 {
+        self.pins
+            .iter()
+            .enumerate()
+            .filter(|(n, _)| value & (1 << n) != 0)
+            .fold(0, |bits, (_, pin)| bits | 1 << pin.pin())
+    }
+
+    /// Collect the port bit positions of the group's pins into a packed value.
+    fn gather(&self, bits: u32) -> u32 {
+        self.pins
+            .iter()
+            .enumerate()
+            .filter(|(_, pin)| bits & (1 << pin.pin()) != 0)
+            .fold(0, |value, (n, _)| value | 1 << n)
+    }
+
+    fn configure<NEW>(self, pull: pin_cnf::PULL_A) -> PinGroup<NEW, N> {
+        for pin in self.pins.iter() {
+            pin.block().pin_cnf[pin.pin() as usize].modify(|_, w| {
+                w.input().connect();
+                w.pull().variant(pull);
+                w.drive().s0s1();
+                w.sense().disabled();
+                w
+            });
+        }
+
+        PinGroup {
+            pins: self.pins.map(|pin| Pin {
+                pin_port: pin.pin_port,
+                _mode: PhantomData,
+            }),
+        }
+    }
+
+    /// Convert all pins of the group into floating inputs with a single direction change.
+    pub fn into_floating_input(self) -> PinGroup<Input<Floating>, N> {
+        let mask = self.mask();
+        // NOTE(unsafe) atomic write to a stateless register, only touching the group's pins.
+        self.block().dirclr.write(|w| unsafe { w.bits(mask) });
+        self.configure(pin_cnf::PULL_A::DISABLED)
+    }
+
+    /// Convert all pins of the group into pulled up inputs with a single direction change.
+    pub fn into_pullup_input(self) -> PinGroup<Input<PullUp>, N> {
+        let mask = self.mask();
+        // NOTE(unsafe) atomic write to a stateless register, only touching the group's pins.
+        self.block().dirclr.write(|w| unsafe { w.bits(mask) });
+        self.configure(pin_cnf::PULL_A::PULLUP)
+    }
+
+    /// Convert all pins of the group into pulled down inputs with a single direction change.
+    pub fn into_pulldown_input(self) -> PinGroup<Input<PullDown>, N> {
+        let mask = self.mask();
+        // NOTE(unsafe) atomic write to a stateless register, only touching the group's pins.
+        self.block().dirclr.write(|w| unsafe { w.bits(mask) });
+        self.configure(pin_cnf::PULL_A::PULLDOWN)
+    }
+
+    /// Convert all pins of the group into push-pull outputs, driving `initial_value` once the
+    /// direction of all pins has been changed at the same time.
+    pub fn into_push_pull_output(self, initial_value: u32) -> PinGroup<Output<PushPull>, N> {
+        let mut group: PinGroup<Output<PushPull>, N> = self.configure(pin_cnf::PULL_A::DISABLED);
+        group.write(initial_value);
+
+        let mask = group.mask();
+        // NOTE(unsafe) atomic write to a stateless register, only touching the group's pins.
+        group.block().dirset.write(|w| unsafe { w.bits(mask) });
+        group
+    }
+}
+
+impl<MODE, const N: usize> PinGroup<Input<MODE>, N> {
+    /// Read the levels of all pins of the group at the same instant.
+    pub fn read(&self) -> u32 {
+        self.gather(self.block().in_.read().bits())
+    }
+}
+
+impl<MODE, const N: usize> PinGroup<Output<MODE>, N> {
+    /// Drive all pins of the group to the levels given by the lower `N` bits of `value`.
+    ///
+    /// All pins change with a single write to the `OUT` register, so no intermediate values
+    /// are ever visible on the bus.
+    pub fn write(&mut self, value: u32) {
+        let mask = self.mask();
+        let bits = self.scatter(value);
+        // The read-modify-write of `OUT` is made atomic with respect to interrupt handlers that
+        // might be driving other pins of the same port.
+        cortex_m::interrupt::free(|_| {
+            self.block()
+                .out
+                .modify(|r, w| unsafe { w.bits((r.bits() & !mask) | bits) });
+        });
+    }
+
+    /// Returns the value currently being driven by the group.
+    pub fn output_value(&self) -> u32 {
+        self.gather(self.block().out.read().bits())
+    }
+}
+
 /// Pin configuration for open-drain mode.
 pub enum OpenDrainConfig {
     Disconnect0Standard1,
@@ -296,10 +607,15 @@ pub enum OpenDrainConfig {
 #[cfg(feature = "51")]
 use crate::pac::gpio::pin_cnf;
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
 use crate::pac::p0_ns::pin_cnf;
 
-#[cfg(not(any(feature = "9160", feature = "51")))]
+#[cfg(not(any(
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "51"
+)))]
 use crate::pac::p0::pin_cnf;
 
 impl OpenDrainConfig {
@@ -327,6 +643,7 @@ macro_rules! gpio {
                 Pin,
                 Port,
 
+                Analog,
                 Floating,
                 Input,
                 Level,
@@ -342,7 +659,12 @@ macro_rules! gpio {
             };
 
             use crate::hal::digital::v2::{OutputPin, StatefulOutputPin, InputPin};
+            use core::convert::TryFrom;
             use void::Void;
+            #[cfg(feature = "embedded-hal-1")]
+            use core::convert::Infallible;
+            #[cfg(feature = "embedded-hal-1")]
+            use void::ResultVoidExt;
 
 
             // ===============================================================
@@ -424,6 +746,23 @@ macro_rules! gpio {
                         }
                     }
 
+                    /// Convert the pin to be an analog input, disconnecting its digital input
+                    /// buffer
+                    pub fn into_analog(self) -> $PXi<Analog> {
+                        unsafe { &(*$PX::ptr()).pin_cnf[$i] }.write(|w| {
+                            w.dir().input();
+                            w.input().disconnect();
+                            w.pull().disabled();
+                            w.drive().s0s1();
+                            w.sense().disabled();
+                            w
+                        });
+
+                        $PXi {
+                            _mode: PhantomData,
+                        }
+                    }
+
                     /// Convert the pin to bepin a push-pull output with normal drive
                     pub fn into_push_pull_output(self, initial_output: Level)
                         -> $PXi<Output<PushPull>>
@@ -491,6 +830,22 @@ macro_rules! gpio {
                     }
                 }
 
+                impl<MODE> TryFrom<Pin<MODE>> for $PXi<MODE> {
+                    type Error = Pin<MODE>;
+
+                    /// Upgrade a generic pin to this typed pin, returning the generic pin back
+                    /// if it refers to a different port or pin number.
+                    fn try_from(pin: Pin<MODE>) -> Result<Self, Self::Error> {
+                        if pin.port() == $port_value && pin.pin() == $i {
+                            Ok($PXi {
+                                _mode: PhantomData,
+                            })
+                        } else {
+                            Err(pin)
+                        }
+                    }
+                }
+
                 impl<MODE> InputPin for $PXi<Input<MODE>> {
                     type Error = Void;
 
@@ -536,6 +891,44 @@ macro_rules! gpio {
                         Ok(unsafe { ((*$PX::ptr()).out.read().bits() & (1 << $i)) == 0 })
                     }
                 }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> embedded_hal_1::digital::ErrorType for $PXi<MODE> {
+                    type Error = Infallible;
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> embedded_hal_1::digital::InputPin for $PXi<Input<MODE>> {
+                    fn is_high(&mut self) -> Result<bool, Infallible> {
+                        Ok(InputPin::is_high(self).void_unwrap())
+                    }
+
+                    fn is_low(&mut self) -> Result<bool, Infallible> {
+                        Ok(InputPin::is_low(self).void_unwrap())
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> embedded_hal_1::digital::OutputPin for $PXi<Output<MODE>> {
+                    fn set_high(&mut self) -> Result<(), Infallible> {
+                        Ok(OutputPin::set_high(self).void_unwrap())
+                    }
+
+                    fn set_low(&mut self) -> Result<(), Infallible> {
+                        Ok(OutputPin::set_low(self).void_unwrap())
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> embedded_hal_1::digital::StatefulOutputPin for $PXi<Output<MODE>> {
+                    fn is_set_high(&mut self) -> Result<bool, Infallible> {
+                        Ok(StatefulOutputPin::is_set_high(self).void_unwrap())
+                    }
+
+                    fn is_set_low(&mut self) -> Result<bool, Infallible> {
+                        Ok(StatefulOutputPin::is_set_low(self).void_unwrap())
+                    }
+                }
             )+
         }
     }
@@ -584,7 +977,12 @@ gpio!(P0, p0, p0, Port::Port0, [
 
 // The p1 types are present in the p0 module generated from the
 // svd, but we want to export them in a p1 module from this crate.
-#[cfg(any(feature = "52833", feature = "52840"))]
+#[cfg(any(
+    feature = "52833",
+    feature = "52840",
+    feature = "5340-app",
+    feature = "5340-net"
+))]
 gpio!(P1, p0, p1, Port::Port1, [
     P1_00: (p1_00,  0, Input<Floating>),
     P1_01: (p1_01,  1, Input<Floating>),
@@ -603,3 +1001,99 @@ gpio!(P1, p0, p1, Port::Port1, [
     P1_14: (p1_14, 14, Input<Floating>),
     P1_15: (p1_15, 15, Input<Floating>),
 ]);
+
+/// A pin in [`Analog`] mode that is connected to one of the analog inputs of the chip.
+///
+/// The analog peripherals take ownership of pins implementing this trait, so using a pin that is
+/// not connected to an analog input is caught at compile time.
+#[cfg(not(feature = "51"))]
+pub trait AnalogPin {
+    /// The analog input (`AINx`) this pin is connected to.
+    const AIN: u8;
+}
+
+#[cfg(not(feature = "51"))]
+macro_rules! analog_pins {
+    ($($ain:expr => $pin:ident,)+) => {
+        $(
+            impl AnalogPin for p0::$pin<Analog> {
+                const AIN: u8 = $ain;
+            }
+        )+
+    };
+}
+
+/// Invokes the macro `$m` with the analog inputs of the chip, as `AINx => pin` pairs.
+///
+/// This is the only place the pins of the analog inputs are listed; `AnalogPin` and the SAADC
+/// channels are both generated from it.
+#[cfg(feature = "5340-app")]
+macro_rules! analog_pin_table {
+    ($m:ident) => {
+        $m! {
+            0 => P0_04,
+            1 => P0_05,
+            2 => P0_06,
+            3 => P0_07,
+            4 => P0_25,
+            5 => P0_26,
+            6 => P0_27,
+            7 => P0_28,
+        }
+    };
+}
+
+#[cfg(feature = "9160")]
+macro_rules! analog_pin_table {
+    ($m:ident) => {
+        $m! {
+            0 => P0_13,
+            1 => P0_14,
+            2 => P0_15,
+            3 => P0_16,
+            4 => P0_17,
+            5 => P0_18,
+            6 => P0_19,
+            7 => P0_20,
+        }
+    };
+}
+
+// Only AIN2 and AIN3 are bonded out on the nRF52805.
+#[cfg(feature = "52805")]
+macro_rules! analog_pin_table {
+    ($m:ident) => {
+        $m! {
+            2 => P0_04,
+            3 => P0_05,
+        }
+    };
+}
+
+#[cfg(not(any(
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "51",
+    feature = "52805"
+)))]
+macro_rules! analog_pin_table {
+    ($m:ident) => {
+        $m! {
+            0 => P0_02,
+            1 => P0_03,
+            2 => P0_04,
+            3 => P0_05,
+            4 => P0_28,
+            5 => P0_29,
+            6 => P0_30,
+            7 => P0_31,
+        }
+    };
+}
+
+#[cfg(not(any(feature = "51", feature = "5340-net")))]
+pub(crate) use analog_pin_table;
+
+#[cfg(not(any(feature = "51", feature = "5340-net")))]
+analog_pin_table!(analog_pins);
@@ -0,0 +1,169 @@
+//! Debounced buttons.
+//!
+//! [`DebouncedInput`] is driven by two interrupts: the pin interrupt (a GPIOTE channel event or a
+//! [`PortInterrupts`](super::PortInterrupts) handler) opens a debounce window on an RTC compare
+//! register, and the RTC interrupt samples the settled level once the window has passed.
+
+use crate::{
+    gpio::Level,
+    hal::digital::v2::InputPin,
+    rtc::{Instance, Rtc, RtcCompareReg, RtcInterrupt, Started},
+};
+use void::Void;
+
+/// Mask of the 24 bit RTC counter.
+const COUNTER_MASK: u32 = 0x00FF_FFFF;
+
+/// Timing and polarity of a [`DebouncedInput`].
+///
+/// All times are given in RTC ticks, which depend on the prescaler of the RTC.
+pub struct DebounceConfig {
+    /// Time the pin must be stable before a change is reported.
+    pub debounce_ticks: u32,
+    /// Time the button must be held to report a `LongPress`. Zero disables long presses.
+    pub long_press_ticks: u32,
+    /// Level of the pin while the button is pressed.
+    pub active_level: Level,
+}
+
+impl Default for DebounceConfig {
+    /// 20 ms debounce and 1 s long press at an RTC frequency of 32.768 kHz, active low.
+    fn default() -> Self {
+        DebounceConfig {
+            debounce_ticks: 655,
+            long_press_ticks: 32_768,
+            active_level: Level::Low,
+        }
+    }
+}
+
+/// Events reported by a [`DebouncedInput`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ButtonEvent {
+    /// The button has been pressed.
+    Pressed,
+    /// The button has been held for the configured long press time.
+    LongPress,
+    /// The button has been released before a long press was reported.
+    Click,
+    /// The button has been released after a long press was reported.
+    Released,
+}
+
+/// A button input debounced with an RTC compare register.
+///
+/// Uses `CC[0]` and the `COMPARE0` interrupt of the RTC.
+pub struct DebouncedInput<P, R> {
+    pin: P,
+    rtc: Rtc<R, Started>,
+    config: DebounceConfig,
+    pressed: bool,
+    settling: bool,
+    long_press_reported: bool,
+    pressed_at: u32,
+}
+
+impl<P, R> DebouncedInput<P, R>
+where
+    P: InputPin<Error = Void>,
+    R: Instance,
+{
+    /// Creates a debounced input and enables the `COMPARE0` interrupt of the RTC.
+    ///
+    /// The RTC interrupt must also be unmasked in the NVIC.
+    pub fn new(pin: P, mut rtc: Rtc<R, Started>, config: DebounceConfig) -> Self {
+        rtc.get_event_triggered(RtcInterrupt::Compare0, true);
+        rtc.enable_interrupt(RtcInterrupt::Compare0, None);
+
+        let mut input = DebouncedInput {
+            pin,
+            rtc,
+            config,
+            pressed: false,
+            settling: false,
+            long_press_reported: false,
+            pressed_at: 0,
+        };
+        input.pressed = input.is_active();
+        input
+    }
+
+    /// Returns the debounced state of the button.
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Must be called when the pin interrupt fires. Opens a new debounce window.
+    pub fn on_pin_interrupt(&mut self) {
+        self.settling = true;
+        self.schedule(self.config.debounce_ticks);
+    }
+
+    /// Must be called when the RTC interrupt fires. Returns the event detected, if any.
+    pub fn on_rtc_interrupt(&mut self) -> Option<ButtonEvent> {
+        if !self.rtc.get_event_triggered(RtcInterrupt::Compare0, true) {
+            return None;
+        }
+        let now = self.rtc.get_counter();
+
+        if self.settling {
+            self.settling = false;
+            let active = self.is_active();
+
+            if active && !self.pressed {
+                self.pressed = true;
+                self.pressed_at = now;
+                self.long_press_reported = false;
+                if self.config.long_press_ticks != 0 {
+                    self.schedule(self.config.long_press_ticks);
+                }
+                Some(ButtonEvent::Pressed)
+            } else if !active && self.pressed {
+                self.pressed = false;
+                if self.long_press_reported {
+                    Some(ButtonEvent::Released)
+                } else {
+                    Some(ButtonEvent::Click)
+                }
+            } else {
+                // A bounce while held overwrote the long press deadline, restore it.
+                if self.pressed && !self.long_press_reported && self.config.long_press_ticks != 0 {
+                    let elapsed = now.wrapping_sub(self.pressed_at) & COUNTER_MASK;
+                    self.schedule(self.config.long_press_ticks.saturating_sub(elapsed).max(1));
+                }
+                None
+            }
+        } else if self.pressed && !self.long_press_reported {
+            let elapsed = now.wrapping_sub(self.pressed_at) & COUNTER_MASK;
+            if elapsed >= self.config.long_press_ticks {
+                self.long_press_reported = true;
+                Some(ButtonEvent::LongPress)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Disables the RTC interrupt and returns the pin and RTC.
+    pub fn free(mut self) -> (P, Rtc<R, Started>) {
+        self.rtc.disable_interrupt(RtcInterrupt::Compare0, None);
+        (self.pin, self.rtc)
+    }
+
+    fn is_active(&self) -> bool {
+        let high = match self.pin.is_high() {
+            Ok(high) => high,
+            Err(void) => void::unreachable(void),
+        };
+        high == (self.config.active_level == Level::High)
+    }
+
+    fn schedule(&mut self, ticks: u32) {
+        let deadline = self.rtc.get_counter().wrapping_add(ticks) & COUNTER_MASK;
+        // The value is masked to 24 bits, so this cannot fail.
+        let _ = self.rtc.set_compare(RtcCompareReg::Compare0, deadline);
+    }
+}
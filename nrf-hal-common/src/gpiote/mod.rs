@@ -12,22 +12,41 @@ use crate::pac::P0;
 #[cfg(any(feature = "52833", feature = "52840"))]
 use crate::pac::P1;
 
+use crate::error::ErrorKind;
 use {
     crate::gpio::{
         Floating, Input, Level, OpenDrain, Output, Pin, Port, PullDown, PullUp, PushPull,
     },
     crate::pac::gpiote::{_EVENTS_IN, _EVENTS_PORT, _TASKS_OUT},
     crate::pac::{generic::Reg, GPIOTE},
+    core::cell::Cell,
+    cortex_m::interrupt::{self, Mutex},
 };
 
 #[cfg(not(feature = "51"))]
 use crate::pac::gpiote::{_TASKS_CLR, _TASKS_SET};
 
+mod debounce;
+pub use debounce::{ButtonEvent, DebounceConfig, DebouncedInput};
+
+#[cfg(not(feature = "51"))]
+mod port_interrupts;
+#[cfg(not(feature = "51"))]
+pub use port_interrupts::{Handler, PortInterrupts};
+
+#[cfg(feature = "async")]
+mod wait;
+#[cfg(feature = "async")]
+pub use wait::{on_interrupt, WaitPin};
+
 #[cfg(not(feature = "51"))]
 const NUM_CHANNELS: usize = 8;
 #[cfg(feature = "51")]
 const NUM_CHANNELS: usize = 4;
 
+/// Bit mask of the channels handed out by `Gpiote::allocate_channel`.
+static ALLOCATED: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
+
 /// A safe wrapper around the GPIOTE peripheral.
 pub struct Gpiote {
     gpiote: GPIOTE,
@@ -74,6 +93,22 @@ impl Gpiote {
         self.channel(7)
     }
 
+    /// Allocates a free GPIOTE channel at runtime.
+    ///
+    /// The channel is released again when the returned handle is dropped. This allows independent
+    /// drivers to share the GPIOTE peripheral without agreeing on channel numbers, as long as
+    /// nothing uses the statically chosen `channelN` methods at the same time.
+    pub fn allocate_channel(&self) -> Result<AllocatedChannel, Error> {
+        interrupt::free(|cs| {
+            let allocated = ALLOCATED.borrow(cs);
+            let channel = (0..NUM_CHANNELS)
+                .find(|ch| allocated.get() & (1 << ch) == 0)
+                .ok_or(Error::NoFreeChannel)?;
+            allocated.set(allocated.get() | 1 << channel);
+            Ok(AllocatedChannel { channel })
+        })
+    }
+
     pub fn port(&self) -> GpiotePort {
         GpiotePort {
             gpiote: &self.gpiote,
@@ -92,6 +127,57 @@ impl Gpiote {
     }
 }
 
+/// A GPIOTE channel allocated at runtime, see [`Gpiote::allocate_channel`].
+///
+/// Dropping the handle disables the channel and makes it available for allocation again.
+pub struct AllocatedChannel {
+    channel: usize,
+}
+
+impl AllocatedChannel {
+    /// Returns the index of the allocated channel.
+    pub fn index(&self) -> usize {
+        self.channel
+    }
+
+    /// Gives access to the allocated channel.
+    pub fn channel<'a>(&self, gpiote: &'a Gpiote) -> GpioteChannel<'a> {
+        gpiote.channel(self.channel)
+    }
+}
+
+impl Drop for AllocatedChannel {
+    fn drop(&mut self) {
+        let gpiote = unsafe { &*GPIOTE::ptr() };
+        gpiote
+            .intenclr
+            .write(|w| unsafe { w.bits(1 << self.channel) });
+        gpiote.config[self.channel].write(|w| w.mode().disabled());
+        gpiote.events_in[self.channel].reset();
+
+        interrupt::free(|cs| {
+            let allocated = ALLOCATED.borrow(cs);
+            allocated.set(allocated.get() & !(1 << self.channel));
+        });
+    }
+}
+
+/// GPIOTE errors.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// All GPIOTE channels are in use.
+    NoFreeChannel,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::NoFreeChannel => ErrorKind::Busy,
+        }
+    }
+}
+
 pub struct GpioteChannel<'a> {
     gpiote: &'a GPIOTE,
     channel: usize,
@@ -257,6 +343,8 @@ fn config_channel_event_pin<P: GpioteInputPin>(
             EventPolarity::None => w.mode().event().polarity().none(),
             EventPolarity::Toggle => w.mode().event().polarity().toggle(),
         };
+        #[cfg(any(feature = "52833", feature = "52840"))]
+        w.port().bit(pin.port().bit());
         unsafe { w.psel().bits(pin.pin()) }
     });
 }
@@ -328,10 +416,51 @@ impl<'a, P: GpioteOutputPin> GpioteTask<'_, P> {
         );
     }
     /// Configures polarity of the `task out` operation.
+    ///
+    /// Takes effect with the next call to `init_high` or `init_low`.
     pub fn task_out_polarity(&mut self, polarity: TaskOutPolarity) -> &mut Self {
         self.task_out_polarity = polarity;
         self
     }
+
+    /// Triggers `task out` (as configured with `task_out_polarity`, defaults to Toggle).
+    pub fn out(&self) {
+        self.gpiote.tasks_out[self.channel].write(|w| unsafe { w.bits(1) });
+    }
+    /// Triggers `task set` (set associated pin high).
+    #[cfg(not(feature = "51"))]
+    pub fn set(&self) {
+        self.gpiote.tasks_set[self.channel].write(|w| unsafe { w.bits(1) });
+    }
+    /// Triggers `task clear` (set associated pin low).
+    #[cfg(not(feature = "51"))]
+    pub fn clear(&self) {
+        self.gpiote.tasks_clr[self.channel].write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns reference to task_out endpoint for PPI.
+    pub fn task_out(&self) -> &Reg<u32, _TASKS_OUT> {
+        &self.gpiote.tasks_out[self.channel]
+    }
+
+    /// Returns reference to task_clr endpoint for PPI.
+    #[cfg(not(feature = "51"))]
+    pub fn task_clr(&self) -> &Reg<u32, _TASKS_CLR> {
+        &self.gpiote.tasks_clr[self.channel]
+    }
+
+    /// Returns reference to task_set endpoint for PPI.
+    #[cfg(not(feature = "51"))]
+    pub fn task_set(&self) -> &Reg<u32, _TASKS_SET> {
+        &self.gpiote.tasks_set[self.channel]
+    }
+
+    /// Disables task mode on the channel and returns the pin, which is controlled by the GPIO
+    /// peripheral again.
+    pub fn free(self) -> P {
+        self.gpiote.config[self.channel].write(|w| w.mode().disabled());
+        self.pin
+    }
 }
 
 fn config_channel_task_pin<P: GpioteOutputPin>(
@@ -352,14 +481,19 @@ fn config_channel_task_pin<P: GpioteOutputPin>(
             TaskOutPolarity::Clear => w.polarity().hi_to_lo(),
             TaskOutPolarity::Toggle => w.polarity().toggle(),
         };
+        #[cfg(any(feature = "52833", feature = "52840"))]
+        w.port().bit(pin.port().bit());
         unsafe { w.psel().bits(pin.pin()) }
     });
 }
 
 /// Polarity of the `task out` operation.
 pub enum TaskOutPolarity {
+    /// `task out` drives the pin high.
     Set,
+    /// `task out` drives the pin low.
     Clear,
+    /// `task out` toggles the pin.
     Toggle,
 }
 
@@ -412,16 +546,23 @@ impl GpioteInputPin for Pin<Input<Floating>> {
 /// Trait to represent task output pin.
 pub trait GpioteOutputPin {
     fn pin(&self) -> u8;
+    fn port(&self) -> Port;
 }
 
 impl GpioteOutputPin for Pin<Output<OpenDrain>> {
     fn pin(&self) -> u8 {
         self.pin()
     }
+    fn port(&self) -> Port {
+        self.port()
+    }
 }
 
 impl GpioteOutputPin for Pin<Output<PushPull>> {
     fn pin(&self) -> u8 {
         self.pin()
     }
+    fn port(&self) -> Port {
+        self.port()
+    }
 }
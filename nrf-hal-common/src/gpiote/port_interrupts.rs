@@ -0,0 +1,207 @@
+//! Multi-pin interrupts through the GPIOTE `PORT` event.
+//!
+//! The GPIOTE peripheral only has a handful of channels, but any number of pins can use their
+//! `SENSE` mechanism to raise the single `PORT` event. [`PortInterrupts`] switches the GPIO ports
+//! to latched detect mode and uses the `LATCH` registers to find out which pins changed, calling
+//! the handler registered for each of them.
+
+use super::{Gpiote, GpioteInputPin, P0};
+use crate::{
+    gpio::{Level, Port},
+    pac::{p0, GPIOTE},
+};
+use core::cell::RefCell;
+use cortex_m::interrupt::{self, Mutex};
+
+#[cfg(any(feature = "52833", feature = "52840"))]
+use super::P1;
+
+#[cfg(feature = "async")]
+use {
+    crate::waker::WakerSlot,
+    core::{cell::Cell, future::poll_fn, task::Poll},
+};
+
+#[cfg(not(any(feature = "52833", feature = "52840")))]
+const NUM_PORTS: usize = 1;
+#[cfg(any(feature = "52833", feature = "52840"))]
+const NUM_PORTS: usize = 2;
+const NUM_PINS: usize = NUM_PORTS * 32;
+
+/// A function called from the interrupt handler with the new level of the pin.
+pub type Handler = fn(Level);
+
+static HANDLERS: Mutex<RefCell<[Option<Handler>; NUM_PINS]>> =
+    Mutex::new(RefCell::new([None; NUM_PINS]));
+
+#[cfg(feature = "async")]
+const NEW_SLOT: WakerSlot = WakerSlot::new();
+#[cfg(feature = "async")]
+static WAKERS: [WakerSlot; NUM_PINS] = [NEW_SLOT; NUM_PINS];
+/// Bit mask of the pins that changed since they were last awaited.
+#[cfg(feature = "async")]
+static CHANGED: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+/// Dispatches sense-based pin interrupts from the `PORT` event.
+pub struct PortInterrupts {
+    _private: (),
+}
+
+impl PortInterrupts {
+    /// Switches the GPIO ports to latched detect mode and enables the `PORT` interrupt.
+    ///
+    /// The GPIOTE interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`PortInterrupts::on_interrupt`].
+    pub fn new(gpiote: &Gpiote) -> Self {
+        for index in 0..NUM_PORTS {
+            let block = port_block(index);
+            block.detectmode.write(|w| w.detectmode().ldetect());
+            block.latch.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        }
+
+        gpiote.port().reset_events();
+        gpiote.port().enable_interrupt();
+
+        Self { _private: () }
+    }
+
+    /// Calls `handler` whenever the level of `pin` changes.
+    ///
+    /// Replaces any handler previously registered for the same pin.
+    pub fn register<P: GpioteInputPin>(&mut self, pin: &P, handler: Handler) {
+        let index = pin_index(pin.port(), pin.pin());
+        interrupt::free(|cs| HANDLERS.borrow(cs).borrow_mut()[index] = Some(handler));
+        arm(pin.port(), pin.pin());
+    }
+
+    /// Stops watching `pin`.
+    pub fn unregister<P: GpioteInputPin>(&mut self, pin: &P) {
+        let index = pin_index(pin.port(), pin.pin());
+        port_block(index / 32).pin_cnf[pin.pin() as usize].modify(|_, w| w.sense().disabled());
+        interrupt::free(|cs| HANDLERS.borrow(cs).borrow_mut()[index] = None);
+    }
+
+    /// Waits until the level of `pin` changes and returns the new level.
+    ///
+    /// A registered handler for the pin is still called.
+    #[cfg(feature = "async")]
+    pub async fn wait_for_change<P: GpioteInputPin>(&self, pin: &P) -> Level {
+        let index = pin_index(pin.port(), pin.pin());
+        interrupt::free(|cs| {
+            let changed = CHANGED.borrow(cs);
+            changed.set(changed.get() & !(1 << index));
+        });
+        arm(pin.port(), pin.pin());
+
+        poll_fn(|cx| {
+            WAKERS[index].register(cx.waker());
+            let changed = interrupt::free(|cs| {
+                let changed = CHANGED.borrow(cs);
+                let was_changed = changed.get() & (1 << index) != 0;
+                changed.set(changed.get() & !(1 << index));
+                was_changed
+            });
+
+            if changed {
+                Poll::Ready(level(pin.port(), pin.pin()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Handles the `PORT` event.
+    ///
+    /// Must be called from the `GPIOTE` interrupt handler.
+    pub fn on_interrupt() {
+        let gpiote = unsafe { &*GPIOTE::ptr() };
+        if gpiote.events_port.read().bits() == 0 {
+            return;
+        }
+        gpiote.events_port.reset();
+
+        for port in 0..NUM_PORTS {
+            let block = port_block(port);
+
+            // Pins latched while we are handling the current ones don't generate a new `PORT`
+            // event, so keep going until the latch is empty.
+            loop {
+                let latch = block.latch.read().bits();
+                if latch == 0 {
+                    break;
+                }
+
+                for pin in (0..32).filter(|pin| latch & (1 << pin) != 0) {
+                    let index = port * 32 + pin;
+                    let high = block.in_.read().bits() & (1 << pin) != 0;
+
+                    // Sense the opposite level next, so both edges are reported. The sense has to
+                    // be changed before clearing the latch, otherwise it is latched again.
+                    block.pin_cnf[pin].modify(|_, w| {
+                        if high {
+                            w.sense().low()
+                        } else {
+                            w.sense().high()
+                        }
+                    });
+                    block.latch.write(|w| unsafe { w.bits(1 << pin) });
+
+                    let handler = interrupt::free(|cs| HANDLERS.borrow(cs).borrow()[index]);
+                    if let Some(handler) = handler {
+                        handler(if high { Level::High } else { Level::Low });
+                    }
+
+                    #[cfg(feature = "async")]
+                    {
+                        interrupt::free(|cs| {
+                            let changed = CHANGED.borrow(cs);
+                            changed.set(changed.get() | 1 << index);
+                        });
+                        WAKERS[index].wake();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn pin_index(port: Port, pin: u8) -> usize {
+    match port {
+        Port::Port0 => pin as usize,
+        #[cfg(any(feature = "52833", feature = "52840"))]
+        Port::Port1 => 32 + pin as usize,
+    }
+}
+
+fn port_block(port: usize) -> &'static p0::RegisterBlock {
+    match port {
+        0 => unsafe { &*P0::ptr() },
+        #[cfg(any(feature = "52833", feature = "52840"))]
+        _ => unsafe { &*P1::ptr() },
+        #[cfg(not(any(feature = "52833", feature = "52840")))]
+        _ => unreachable!(),
+    }
+}
+
+fn level(port: Port, pin: u8) -> Level {
+    let block = port_block(pin_index(port, pin) / 32);
+    if block.in_.read().bits() & (1 << pin) != 0 {
+        Level::High
+    } else {
+        Level::Low
+    }
+}
+
+/// Senses the level opposite to the current one, so the next change of `pin` is latched.
+fn arm(port: Port, pin: u8) {
+    let block = port_block(pin_index(port, pin) / 32);
+    let high = block.in_.read().bits() & (1 << pin) != 0;
+    block.pin_cnf[pin as usize].modify(|_, w| {
+        if high {
+            w.sense().low()
+        } else {
+            w.sense().high()
+        }
+    });
+}
@@ -0,0 +1,134 @@
+//! Async edge and level waiting on input pins.
+//!
+//! Each [`WaitPin`] occupies one GPIOTE channel, which is allocated through
+//! [`Gpiote::allocate_channel`] when it is created and released again by [`WaitPin::free`]. The
+//! GPIOTE interrupt must be unmasked in the NVIC and its handler has to call [`on_interrupt`].
+
+use super::{
+    config_channel_event_pin, AllocatedChannel, Error, EventPolarity, Gpiote, GpioteInputPin,
+    NUM_CHANNELS, P0,
+};
+use crate::{gpio::Port, pac::GPIOTE, waker::WakerSlot};
+use core::{convert::Infallible, future::poll_fn, task::Poll};
+
+#[cfg(any(feature = "52833", feature = "52840"))]
+use super::P1;
+
+const NEW_SLOT: WakerSlot = WakerSlot::new();
+static WAKERS: [WakerSlot; NUM_CHANNELS] = [NEW_SLOT; NUM_CHANNELS];
+
+/// Handles the GPIOTE interrupt for all `WaitPin`s.
+///
+/// Must be called from the `GPIOTE` interrupt handler.
+pub fn on_interrupt() {
+    let gpiote = unsafe { &*GPIOTE::ptr() };
+    let enabled = gpiote.intenset.read().bits();
+
+    for (ch, waker) in WAKERS.iter().enumerate() {
+        if enabled & (1 << ch) != 0 && gpiote.events_in[ch].read().bits() != 0 {
+            // Leave the event set for the future to observe, but keep it from firing again.
+            gpiote.intenclr.write(|w| unsafe { w.bits(1 << ch) });
+            waker.wake();
+        }
+    }
+}
+
+/// An input pin bound to a GPIOTE channel, which can be awaited.
+pub struct WaitPin<P> {
+    pin: P,
+    channel: AllocatedChannel,
+}
+
+impl<P> WaitPin<P>
+where
+    P: GpioteInputPin,
+{
+    /// Binds `pin` to a free GPIOTE channel.
+    ///
+    /// Returns the pin back if all channels are in use.
+    pub fn new(gpiote: &Gpiote, pin: P) -> Result<Self, (Error, P)> {
+        match gpiote.allocate_channel() {
+            Ok(channel) => Ok(Self { pin, channel }),
+            Err(e) => Err((e, pin)),
+        }
+    }
+
+    /// Returns the GPIOTE channel used by this pin.
+    pub fn channel(&self) -> usize {
+        self.channel.index()
+    }
+
+    /// Releases the GPIOTE channel and returns the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+
+    fn is_high(&self) -> bool {
+        let block = unsafe {
+            &*match self.pin.port() {
+                Port::Port0 => P0::ptr(),
+                #[cfg(any(feature = "52833", feature = "52840"))]
+                Port::Port1 => P1::ptr(),
+            }
+        };
+        block.in_.read().bits() & (1 << self.pin.pin()) != 0
+    }
+
+    /// Arms the channel for `polarity`, then waits until either the edge has been seen or
+    /// `done` returns `true`.
+    async fn wait(&mut self, polarity: EventPolarity, done: impl Fn(&Self) -> bool) {
+        let gpiote = unsafe { &*GPIOTE::ptr() };
+        let ch = self.channel.index();
+
+        config_channel_event_pin(gpiote, ch, &self.pin, polarity);
+        gpiote.events_in[ch].reset();
+        gpiote.intenset.write(|w| unsafe { w.bits(1 << ch) });
+
+        poll_fn(|cx| {
+            WAKERS[ch].register(cx.waker());
+
+            if gpiote.events_in[ch].read().bits() != 0 || done(self) {
+                gpiote.intenclr.write(|w| unsafe { w.bits(1 << ch) });
+                gpiote.events_in[ch].reset();
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<P> embedded_hal_1::digital::ErrorType for WaitPin<P> {
+    type Error = Infallible;
+}
+
+impl<P> embedded_hal_async::digital::Wait for WaitPin<P>
+where
+    P: GpioteInputPin,
+{
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.wait(EventPolarity::LoToHi, |pin| pin.is_high()).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.wait(EventPolarity::HiToLo, |pin| !pin.is_high()).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait(EventPolarity::LoToHi, |_| false).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait(EventPolarity::HiToLo, |_| false).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait(EventPolarity::Toggle, |_| false).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,773 @@
+//! HAL interface to the I2S peripheral.
+//!
+//! The I2S peripheral streams audio samples between RAM and an external codec, amplifier or
+//! microphone. Samples are transferred by EasyDMA in 32 bit words, each holding one sample of
+//! 24 or 32 bits, two samples of 16 bits or four samples of 8 bits. In stereo mode, the left
+//! sample comes first. The [`audio`](crate::audio) module converts samples to and from this
+//! layout.
+//!
+//! Continuous streams use two buffers per direction: while EasyDMA plays or fills one, the other
+//! is refilled or processed, see [`TxStream`], [`RxStream`] and [`DuplexStream`].
+//!
+//! With the `async` feature, the streams can also be driven from an async task, which is
+//! suspended until the next buffer is due:
+//!
+//! ```ignore
+//! let mut stream = i2s.start_tx(first, second).map_err(|(e, _)| e)?;
+//! loop {
+//!     stream.fill_next_async(|buf| synth.render(buf)).await;
+//! }
+//! ```
+//!
+//! See product specification, chapter 42.
+
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::error::ErrorKind;
+use crate::{
+    gpio::{Floating, Input, Output, Pin, PushPull},
+    pac::{i2s, I2S as I2S_PAC},
+    slice_in_ram,
+};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static WAKER: WakerSlot = WakerSlot::new();
+
+pub use i2s::config::{
+    channels::CHANNELS_A as Channels, mckfreq::MCKFREQ_A as MckFreq, ratio::RATIO_A as Ratio,
+    swidth::SWIDTH_A as SampleWidth,
+};
+
+/// Maximum length of a buffer, in words.
+pub const MAX_BUFFER_LEN: usize = (1 << 14) - 1;
+
+/// Format of the frames.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Format {
+    /// Original I2S format: the first bit of the left sample follows the falling edge of LRCK
+    /// by one SCK period.
+    I2S,
+    /// Left justified: the samples start at the edges of LRCK, which is high for the left
+    /// sample.
+    LeftJustified,
+    /// Right justified: the samples end at the edges of LRCK, which is high for the left
+    /// sample.
+    RightJustified,
+}
+
+/// Configuration of the I2S interface.
+///
+/// The sample rate is the MCK frequency divided by the ratio, see [`SampleRate`] to compute
+/// them.
+pub struct Config {
+    pub mck_freq: MckFreq,
+    pub ratio: Ratio,
+    pub sample_width: SampleWidth,
+    pub format: Format,
+    pub channels: Channels,
+}
+
+impl Default for Config {
+    /// 16 bit stereo samples in I2S format at 32 MHz / 23 / 32, about 43.5 kHz.
+    fn default() -> Self {
+        Config {
+            mck_freq: MckFreq::_32MDIV23,
+            ratio: Ratio::_32X,
+            sample_width: SampleWidth::_16BIT,
+            format: Format::I2S,
+            channels: Channels::STEREO,
+        }
+    }
+}
+
+/// MCK frequencies, in order of their `MCKFREQ` values.
+const MCK_FREQS: [MckFreq; 13] = [
+    MckFreq::_32MDIV125,
+    MckFreq::_32MDIV63,
+    MckFreq::_32MDIV42,
+    MckFreq::_32MDIV32,
+    MckFreq::_32MDIV31,
+    MckFreq::_32MDIV30,
+    MckFreq::_32MDIV23,
+    MckFreq::_32MDIV21,
+    MckFreq::_32MDIV16,
+    MckFreq::_32MDIV15,
+    MckFreq::_32MDIV11,
+    MckFreq::_32MDIV10,
+    MckFreq::_32MDIV8,
+];
+
+/// Ratios, with their values.
+const RATIOS: [(Ratio, u32); 9] = [
+    (Ratio::_32X, 32),
+    (Ratio::_48X, 48),
+    (Ratio::_64X, 64),
+    (Ratio::_96X, 96),
+    (Ratio::_128X, 128),
+    (Ratio::_192X, 192),
+    (Ratio::_256X, 256),
+    (Ratio::_384X, 384),
+    (Ratio::_512X, 512),
+];
+
+/// MCK frequency and ratio giving the sample rate closest to a requested one.
+///
+/// ```ignore
+/// let rate = SampleRate::new(44_100, SampleWidth::_16BIT);
+/// let config = Config {
+///     mck_freq: rate.mck_freq,
+///     ratio: rate.ratio,
+///     ..Config::default()
+/// };
+/// ```
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct SampleRate {
+    pub mck_freq: MckFreq,
+    pub ratio: Ratio,
+    /// Achieved sample rate, rounded to Hz.
+    pub rate_hz: u32,
+    /// Error of the achieved sample rate relative to the requested one, in ppm.
+    pub error_ppm: i32,
+}
+
+impl SampleRate {
+    /// Searches the combinations of MCK frequency and ratio valid for `sample_width`, the ratio
+    /// being a multiple of twice the sample width, for the closest to `requested_hz`.
+    ///
+    /// `requested_hz` must not be 0.
+    pub fn new(requested_hz: u32, sample_width: SampleWidth) -> Self {
+        let frame_bits = match sample_width {
+            SampleWidth::_8BIT => 16,
+            SampleWidth::_16BIT => 32,
+            SampleWidth::_24BIT => 48,
+        };
+        let requested = requested_hz as u128;
+
+        // The MCK frequency is 32 MHz * MCKFREQ / 2^32, the sample rate is the MCK frequency
+        // divided by the ratio, so it is compared as the fraction `num / den`.
+        let mut best: Option<(MckFreq, Ratio, u128, u128)> = None;
+        for &mck_freq in MCK_FREQS.iter() {
+            for &(ratio, ratio_value) in RATIOS.iter() {
+                if ratio_value % frame_bits != 0 {
+                    continue;
+                }
+                let num = 32_000_000 * u32::from(mck_freq) as u128;
+                let den = (ratio_value as u128) << 32;
+                let better = match best {
+                    None => true,
+                    Some((_, _, best_num, best_den)) => {
+                        let diff = num.abs_diff(requested * den);
+                        let best_diff = best_num.abs_diff(requested * best_den);
+                        diff * best_den < best_diff * den
+                    }
+                };
+                if better {
+                    best = Some((mck_freq, ratio, num, den));
+                }
+            }
+        }
+
+        let (mck_freq, ratio, num, den) = best.unwrap();
+        let rate_hz = ((num + den / 2) / den) as u32;
+        let error = num as i128 - (requested * den) as i128;
+        let error_ppm = (error * 1_000_000 / (requested * den) as i128) as i32;
+        SampleRate {
+            mck_freq,
+            ratio,
+            rate_hz,
+            error_ppm,
+        }
+    }
+}
+
+/// Pins of the I2S interface in master mode.
+pub struct Pins {
+    /// Master clock output, if the codec needs it.
+    pub mck: Option<Pin<Output<PushPull>>>,
+    pub sck: Pin<Output<PushPull>>,
+    pub lrck: Pin<Output<PushPull>>,
+    /// Serial data output, to transmit.
+    pub sdout: Option<Pin<Output<PushPull>>>,
+    /// Serial data input, to receive.
+    pub sdin: Option<Pin<Input<Floating>>>,
+}
+
+/// Pins of the I2S interface in slave mode.
+pub struct SlavePins {
+    pub sck: Pin<Input<Floating>>,
+    pub lrck: Pin<Input<Floating>>,
+    /// Serial data output, to transmit.
+    pub sdout: Option<Pin<Output<PushPull>>>,
+    /// Serial data input, to receive.
+    pub sdin: Option<Pin<Input<Floating>>>,
+}
+
+/// `PSEL` value of a disconnected pin.
+const DISCONNECTED: u32 = 1 << 31;
+
+mod sealed {
+    pub trait Pins {
+        /// `PSEL` values of MCK, SCK, LRCK, SDOUT and SDIN.
+        fn psel(&self) -> [u32; 5];
+    }
+}
+
+impl sealed::Pins for Pins {
+    fn psel(&self) -> [u32; 5] {
+        [
+            self.mck.as_ref().map_or(DISCONNECTED, Pin::psel_bits),
+            self.sck.psel_bits(),
+            self.lrck.psel_bits(),
+            self.sdout.as_ref().map_or(DISCONNECTED, Pin::psel_bits),
+            self.sdin.as_ref().map_or(DISCONNECTED, Pin::psel_bits),
+        ]
+    }
+}
+
+impl sealed::Pins for SlavePins {
+    fn psel(&self) -> [u32; 5] {
+        [
+            DISCONNECTED,
+            self.sck.psel_bits(),
+            self.lrck.psel_bits(),
+            self.sdout.as_ref().map_or(DISCONNECTED, Pin::psel_bits),
+            self.sdin.as_ref().map_or(DISCONNECTED, Pin::psel_bits),
+        ]
+    }
+}
+
+/// I2S error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A buffer is empty or longer than `MAX_BUFFER_LEN` words.
+    BufferLength,
+    /// The buffers of a stream differ in length.
+    BufferLengthMismatch,
+    /// EasyDMA can only access data memory.
+    DMABufferNotInDataMemory,
+    /// The direction of the stream has not been enabled by the pins.
+    DirectionDisabled,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::BufferLength | Error::BufferLengthMismatch => ErrorKind::BufferLength,
+            Error::DMABufferNotInDataMemory => ErrorKind::BufferNotInRam,
+            Error::DirectionDisabled => ErrorKind::Unsupported,
+        }
+    }
+}
+
+/// Interface to the I2S peripheral, in master mode with [`Pins`] or in slave mode with
+/// [`SlavePins`].
+pub struct I2s<P = Pins> {
+    i2s: I2S_PAC,
+    pins: P,
+}
+
+impl I2s<Pins> {
+    /// Configures the interface as a master, transmitting if `pins.sdout` is set and receiving
+    /// if `pins.sdin` is set.
+    pub fn new(i2s: I2S_PAC, pins: Pins, config: Config) -> Self {
+        i2s.config.mode.write(|w| w.mode().master());
+        i2s.config.mcken.write(|w| w.mcken().enabled());
+        Self::configure(i2s, pins, config)
+    }
+}
+
+impl I2s<SlavePins> {
+    /// Configures the interface as a slave, clocked by SCK and LRCK of the master, transmitting
+    /// if `pins.sdout` is set and receiving if `pins.sdin` is set.
+    ///
+    /// The MCK frequency of `config` is not used, but its ratio must be the one of the master.
+    pub fn new_slave(i2s: I2S_PAC, pins: SlavePins, config: Config) -> Self {
+        i2s.config.mode.write(|w| w.mode().slave());
+        i2s.config.mcken.write(|w| w.mcken().disabled());
+        Self::configure(i2s, pins, config)
+    }
+}
+
+impl<P> I2s<P>
+where
+    P: sealed::Pins,
+{
+    fn configure(i2s: I2S_PAC, pins: P, config: Config) -> Self {
+        let [mck, sck, lrck, sdout, sdin] = pins.psel();
+        unsafe {
+            i2s.psel.mck.write(|w| w.bits(mck));
+            i2s.psel.sck.write(|w| w.bits(sck));
+            i2s.psel.lrck.write(|w| w.bits(lrck));
+            i2s.psel.sdout.write(|w| w.bits(sdout));
+            i2s.psel.sdin.write(|w| w.bits(sdin));
+        }
+
+        i2s.config
+            .mckfreq
+            .write(|w| w.mckfreq().variant(config.mck_freq));
+        i2s.config.ratio.write(|w| w.ratio().variant(config.ratio));
+        i2s.config
+            .swidth
+            .write(|w| w.swidth().variant(config.sample_width));
+        match config.format {
+            Format::I2S => {
+                i2s.config.format.write(|w| w.format().i2s());
+                i2s.config.align.write(|w| w.align().left());
+            }
+            Format::LeftJustified => {
+                i2s.config.format.write(|w| w.format().aligned());
+                i2s.config.align.write(|w| w.align().left());
+            }
+            Format::RightJustified => {
+                i2s.config.format.write(|w| w.format().aligned());
+                i2s.config.align.write(|w| w.align().right());
+            }
+        }
+        i2s.config
+            .channels
+            .write(|w| w.channels().variant(config.channels));
+        i2s.config
+            .txen
+            .write(|w| w.txen().bit(sdout != DISCONNECTED));
+        i2s.config
+            .rxen
+            .write(|w| w.rxen().bit(sdin != DISCONNECTED));
+
+        i2s.enable.write(|w| w.enable().enabled());
+        I2s { i2s, pins }
+    }
+
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &i2s::TASKS_START {
+        &self.i2s.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &i2s::TASKS_STOP {
+        &self.i2s.tasks_stop
+    }
+
+    /// Returns reference to the `RXPTRUPD` event endpoint for PPI.
+    pub fn event_rx_ptr_updated(&self) -> &i2s::EVENTS_RXPTRUPD {
+        &self.i2s.events_rxptrupd
+    }
+
+    /// Returns reference to the `TXPTRUPD` event endpoint for PPI.
+    pub fn event_tx_ptr_updated(&self) -> &i2s::EVENTS_TXPTRUPD {
+        &self.i2s.events_txptrupd
+    }
+
+    /// Returns reference to the `STOPPED` event endpoint for PPI.
+    pub fn event_stopped(&self) -> &i2s::EVENTS_STOPPED {
+        &self.i2s.events_stopped
+    }
+
+    /// Disables the interface, and returns the peripheral and the pins.
+    pub fn free(self) -> (I2S_PAC, P) {
+        self.i2s.enable.write(|w| w.enable().disabled());
+        (self.i2s, self.pins)
+    }
+
+    /// Starts transmitting a continuous stream from two buffers of the same length, which must
+    /// have been filled with the first samples.
+    ///
+    /// The first buffer is played first, then the second, then the first again once it has been
+    /// refilled with `TxStream::fill_next`, and so on.
+    pub fn start_tx(
+        mut self,
+        first: &'static mut [u32],
+        second: &'static mut [u32],
+    ) -> Result<TxStream<P>, (Error, Self)> {
+        if let Err(e) = self.check(true, false, first, second) {
+            return Err((e, self));
+        }
+        let mut tx = Buffers::new(first, second);
+        self.start(Some(&mut tx), None);
+        Ok(TxStream { i2s: self, tx })
+    }
+
+    /// Starts receiving a continuous stream into two buffers of the same length.
+    ///
+    /// The first buffer is filled first, then the second, then the first again once it has been
+    /// processed with `RxStream::read_next`, and so on.
+    pub fn start_rx(
+        mut self,
+        first: &'static mut [u32],
+        second: &'static mut [u32],
+    ) -> Result<RxStream<P>, (Error, Self)> {
+        if let Err(e) = self.check(false, true, first, second) {
+            return Err((e, self));
+        }
+        let mut rx = Buffers::new(first, second);
+        self.start(None, Some(&mut rx));
+        Ok(RxStream { i2s: self, rx })
+    }
+
+    /// Starts transmitting and receiving continuous streams. All four buffers must have the same
+    /// length, and the transmit buffers must have been filled with the first samples.
+    pub fn start_duplex(
+        mut self,
+        tx: [&'static mut [u32]; 2],
+        rx: [&'static mut [u32]; 2],
+    ) -> Result<DuplexStream<P>, (Error, Self)> {
+        let checked = self
+            .check(true, true, &tx[0], &tx[1])
+            .and_then(|_| self.check(true, true, &rx[0], &rx[1]))
+            .and_then(|_| {
+                if tx[0].len() == rx[0].len() {
+                    Ok(())
+                } else {
+                    Err(Error::BufferLengthMismatch)
+                }
+            });
+        if let Err(e) = checked {
+            return Err((e, self));
+        }
+        let [tx0, tx1] = tx;
+        let [rx0, rx1] = rx;
+        let mut tx = Buffers::new(tx0, tx1);
+        let mut rx = Buffers::new(rx0, rx1);
+        self.start(Some(&mut tx), Some(&mut rx));
+        Ok(DuplexStream { i2s: self, tx, rx })
+    }
+
+    /// Checks that the directions are enabled, and that the buffers can be used by EasyDMA.
+    fn check(&self, tx: bool, rx: bool, first: &[u32], second: &[u32]) -> Result<(), Error> {
+        let [_, _, _, sdout, sdin] = self.pins.psel();
+        if (tx && sdout == DISCONNECTED) || (rx && sdin == DISCONNECTED) {
+            return Err(Error::DirectionDisabled);
+        }
+        if first.is_empty() || first.len() > MAX_BUFFER_LEN {
+            return Err(Error::BufferLength);
+        }
+        if first.len() != second.len() {
+            return Err(Error::BufferLengthMismatch);
+        }
+        for buffer in [first, second].iter() {
+            let bytes = unsafe {
+                core::slice::from_raw_parts(buffer.as_ptr() as *const u8, buffer.len() * 4)
+            };
+            if !slice_in_ram(bytes) {
+                return Err(Error::DMABufferNotInDataMemory);
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts the peripheral on the first buffers, and queues the second ones.
+    fn start(&mut self, tx: Option<&mut Buffers>, rx: Option<&mut Buffers>) {
+        let regs = &self.i2s;
+        let len = tx
+            .as_ref()
+            .or(rx.as_ref())
+            .map_or(0, |b| b.buffers[0].len());
+        regs.rxtxd.maxcnt.write(|w| unsafe { w.bits(len as u32) });
+        if let Some(tx) = &tx {
+            regs.txd.ptr.write(|w| unsafe { w.bits(tx.ptr(0)) });
+        }
+        if let Some(rx) = &rx {
+            regs.rxd.ptr.write(|w| unsafe { w.bits(rx.ptr(0)) });
+        }
+        regs.events_txptrupd.reset();
+        regs.events_rxptrupd.reset();
+        regs.events_stopped.reset();
+        compiler_fence(SeqCst);
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        // The first pointers are taken over right away, so the second ones can be queued.
+        if let Some(tx) = tx {
+            while regs.events_txptrupd.read().bits() == 0 {}
+            regs.events_txptrupd.reset();
+            regs.txd.ptr.write(|w| unsafe { w.bits(tx.ptr(1)) });
+        }
+        if let Some(rx) = rx {
+            while regs.events_rxptrupd.read().bits() == 0 {}
+            regs.events_rxptrupd.reset();
+            regs.rxd.ptr.write(|w| unsafe { w.bits(rx.ptr(1)) });
+        }
+    }
+
+    /// Stops the stream and waits until the peripheral has stopped.
+    fn stop(&mut self) {
+        self.i2s.intenclr.write(|w| unsafe { w.bits(!0) });
+        self.i2s.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.i2s.events_stopped.read().bits() == 0 {}
+        self.i2s.events_stopped.reset();
+        compiler_fence(SeqCst);
+    }
+
+    /// Takes the `TXPTRUPD` event, returning `false` if it has not happened.
+    fn take_txptrupd(&self) -> bool {
+        let pending = self.i2s.events_txptrupd.read().bits() != 0;
+        if pending {
+            self.i2s.events_txptrupd.reset();
+        }
+        pending
+    }
+
+    /// Takes the `RXPTRUPD` event, returning `false` if it has not happened.
+    fn take_rxptrupd(&self) -> bool {
+        let pending = self.i2s.events_rxptrupd.read().bits() != 0;
+        if pending {
+            self.i2s.events_rxptrupd.reset();
+        }
+        pending
+    }
+}
+
+/// Two buffers used alternately by EasyDMA.
+struct Buffers {
+    buffers: [&'static mut [u32]; 2],
+    /// Index of the buffer whose pointer has been written last.
+    queued: usize,
+}
+
+impl Buffers {
+    fn new(first: &'static mut [u32], second: &'static mut [u32]) -> Self {
+        Buffers {
+            buffers: [first, second],
+            queued: 1,
+        }
+    }
+
+    fn ptr(&self, index: usize) -> u32 {
+        self.buffers[index].as_ptr() as u32
+    }
+
+    /// Returns the buffer EasyDMA is done with, after it has taken over the queued one. Its
+    /// pointer is the next to be queued.
+    fn next(&mut self) -> &mut [u32] {
+        self.queued = 1 - self.queued;
+        compiler_fence(SeqCst);
+        &mut *self.buffers[self.queued]
+    }
+
+    fn into_inner(self) -> [&'static mut [u32]; 2] {
+        self.buffers
+    }
+}
+
+/// A continuous output stream, started with `I2s::start_tx`.
+pub struct TxStream<P = Pins> {
+    i2s: I2s<P>,
+    tx: Buffers,
+}
+
+impl<P> TxStream<P>
+where
+    P: sealed::Pins,
+{
+    /// Refills the buffer that has just been played with `fill`, and queues it after the one
+    /// playing now.
+    ///
+    /// Returns `WouldBlock` until the peripheral has started playing the previously queued
+    /// buffer. `fill` must return before that buffer has been played, or the stream repeats
+    /// the old samples.
+    pub fn fill_next(&mut self, fill: impl FnOnce(&mut [u32])) -> nb::Result<(), void::Void> {
+        if !self.i2s.take_txptrupd() {
+            return Err(nb::Error::WouldBlock);
+        }
+        fill(self.tx.next());
+        let ptr = self.tx.ptr(self.tx.queued);
+        compiler_fence(SeqCst);
+        self.i2s.i2s.txd.ptr.write(|w| unsafe { w.bits(ptr) });
+        Ok(())
+    }
+
+    /// Enables the `I2S` interrupt when a buffer can be refilled.
+    pub fn enable_interrupt(&mut self) {
+        self.i2s.i2s.intenset.write(|w| w.txptrupd().set());
+    }
+
+    /// Disables the `I2S` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.i2s.i2s.intenclr.write(|w| w.txptrupd().clear());
+    }
+
+    /// Waits until the buffer that has just been played can be refilled, then refills and queues
+    /// it like `fill_next`.
+    ///
+    /// The `I2S` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn fill_next_async(&mut self, fill: impl FnOnce(&mut [u32])) {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if self.i2s.i2s.events_txptrupd.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.enable_interrupt();
+                Poll::Pending
+            }
+        })
+        .await;
+        self.fill_next(fill).ok();
+    }
+
+    /// Stops the stream, and returns the interface and the buffers.
+    pub fn stop(mut self) -> (I2s<P>, [&'static mut [u32]; 2]) {
+        self.i2s.stop();
+        (self.i2s, self.tx.into_inner())
+    }
+}
+
+/// A continuous input stream, started with `I2s::start_rx`.
+pub struct RxStream<P = Pins> {
+    i2s: I2s<P>,
+    rx: Buffers,
+}
+
+impl<P> RxStream<P>
+where
+    P: sealed::Pins,
+{
+    /// Passes the buffer that has just been filled to `read`, and queues it after the one being
+    /// filled now.
+    ///
+    /// Returns `WouldBlock` until the peripheral has started filling the previously queued
+    /// buffer. `read` must return before that buffer is full, or samples are lost.
+    pub fn read_next(&mut self, read: impl FnOnce(&[u32])) -> nb::Result<(), void::Void> {
+        if !self.i2s.take_rxptrupd() {
+            return Err(nb::Error::WouldBlock);
+        }
+        read(self.rx.next());
+        let ptr = self.rx.ptr(self.rx.queued);
+        compiler_fence(SeqCst);
+        self.i2s.i2s.rxd.ptr.write(|w| unsafe { w.bits(ptr) });
+        Ok(())
+    }
+
+    /// Enables the `I2S` interrupt when a buffer has been filled.
+    pub fn enable_interrupt(&mut self) {
+        self.i2s.i2s.intenset.write(|w| w.rxptrupd().set());
+    }
+
+    /// Disables the `I2S` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.i2s.i2s.intenclr.write(|w| w.rxptrupd().clear());
+    }
+
+    /// Waits until a buffer has been filled, then passes it to `read` and queues it like
+    /// `read_next`.
+    ///
+    /// The `I2S` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn read_next_async(&mut self, read: impl FnOnce(&[u32])) {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if self.i2s.i2s.events_rxptrupd.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.enable_interrupt();
+                Poll::Pending
+            }
+        })
+        .await;
+        self.read_next(read).ok();
+    }
+
+    /// Stops the stream, and returns the interface and the buffers.
+    pub fn stop(mut self) -> (I2s<P>, [&'static mut [u32]; 2]) {
+        self.i2s.stop();
+        (self.i2s, self.rx.into_inner())
+    }
+}
+
+/// Continuous output and input streams, started with `I2s::start_duplex`.
+///
+/// Both directions share the buffer length and the frame clock, so their buffers are swapped at
+/// the same time.
+pub struct DuplexStream<P = Pins> {
+    i2s: I2s<P>,
+    tx: Buffers,
+    rx: Buffers,
+}
+
+impl<P> DuplexStream<P>
+where
+    P: sealed::Pins,
+{
+    /// Passes the transmit buffer that has just been played and the receive buffer that has
+    /// just been filled to `process`, and queues them.
+    ///
+    /// Returns `WouldBlock` until the peripheral has swapped the buffers of both directions.
+    pub fn process_next(
+        &mut self,
+        process: impl FnOnce(&mut [u32], &[u32]),
+    ) -> nb::Result<(), void::Void> {
+        let regs = &self.i2s.i2s;
+        if regs.events_txptrupd.read().bits() == 0 || regs.events_rxptrupd.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.i2s.take_txptrupd();
+        self.i2s.take_rxptrupd();
+
+        process(self.tx.next(), self.rx.next());
+        let (tx, rx) = (self.tx.ptr(self.tx.queued), self.rx.ptr(self.rx.queued));
+        compiler_fence(SeqCst);
+        regs.txd.ptr.write(|w| unsafe { w.bits(tx) });
+        regs.rxd.ptr.write(|w| unsafe { w.bits(rx) });
+        Ok(())
+    }
+
+    /// Enables the `I2S` interrupt when the buffers have been swapped.
+    pub fn enable_interrupt(&mut self) {
+        self.i2s.i2s.intenset.write(|w| w.rxptrupd().set());
+    }
+
+    /// Disables the `I2S` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.i2s.i2s.intenclr.write(|w| w.rxptrupd().clear());
+    }
+
+    /// Waits until the buffers of both directions have been swapped, then processes and queues
+    /// them like `process_next`.
+    ///
+    /// The `I2S` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn process_next_async(&mut self, process: impl FnOnce(&mut [u32], &[u32])) {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            let regs = &self.i2s.i2s;
+            if regs.events_txptrupd.read().bits() != 0 && regs.events_rxptrupd.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.enable_interrupt();
+                Poll::Pending
+            }
+        })
+        .await;
+        self.process_next(process).ok();
+    }
+
+    /// Stops the streams, and returns the interface with the transmit and receive buffers.
+    pub fn stop(mut self) -> (I2s<P>, [&'static mut [u32]; 2], [&'static mut [u32]; 2]) {
+        self.i2s.stop();
+        (self.i2s, self.tx.into_inner(), self.rx.into_inner())
+    }
+}
+
+/// Handles the I2S events for the async methods of the streams.
+///
+/// Must be called from the `I2S` interrupt handler.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let i2s = unsafe { &*I2S_PAC::ptr() };
+    let tx = i2s.events_txptrupd.read().bits() != 0;
+    let rx = i2s.events_rxptrupd.read().bits() != 0;
+    if tx || rx {
+        // Leave the events set for the stream to observe, but keep them from firing again.
+        i2s.intenclr
+            .write(|w| w.txptrupd().clear().rxptrupd().clear());
+        WAKER.wake();
+    }
+}
@@ -0,0 +1,115 @@
+//! HAL interface to the IPC peripheral.
+//!
+//! The interprocessor communication peripheral (IPC) signals events between the cores of the
+//! chip: on the nRF9160 between the application core and the modem, on the nRF5340 between the
+//! application and network cores. Triggering the `SEND` task `n` signals the channels enabled in
+//! `SEND_CNF[n]`; a signal on one of the channels enabled in `RECEIVE_CNF[m]` generates the
+//! `RECEIVE` event `m` on the other side.
+//!
+//! The IPC only carries the signals; the data is exchanged in shared RAM, e.g. through the
+//! message queue of the [`queue`] module.
+//!
+//! See product specification, chapter 5.15.
+
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+use crate::pac::{ipc_ns as ipc, IPC_NS as IPC};
+
+pub mod queue;
+
+/// Number of `SEND` tasks and `RECEIVE` events, and of channels.
+#[cfg(feature = "9160")]
+pub const CHANNELS: usize = 8;
+
+/// Number of `SEND` tasks and `RECEIVE` events, and of channels.
+#[cfg(any(feature = "5340-app", feature = "5340-net"))]
+pub const CHANNELS: usize = 16;
+
+/// Interface to the IPC peripheral.
+pub struct Ipc(IPC);
+
+impl Ipc {
+    /// Takes the peripheral, with no task or event connected to a channel.
+    pub fn new(ipc: IPC) -> Self {
+        for n in 0..CHANNELS {
+            ipc.send_cnf[n].reset();
+            ipc.receive_cnf[n].reset();
+            ipc.events_receive[n].reset();
+        }
+        Ipc(ipc)
+    }
+
+    /// Returns the raw peripheral, with its interrupts disabled.
+    pub fn free(self) -> IPC {
+        self.0.intenclr.write(|w| unsafe { w.bits(!0) });
+        self.0
+    }
+
+    /// Selects the channels signalled by the `SEND` task `task`, as a bit mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `task` is not below `CHANNELS`.
+    pub fn configure_send(&mut self, task: usize, channels: u16) -> &mut Self {
+        self.0.send_cnf[task].write(|w| unsafe { w.bits(channels as u32) });
+        self
+    }
+
+    /// Selects the channels generating the `RECEIVE` event `event`, as a bit mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `event` is not below `CHANNELS`.
+    pub fn configure_receive(&mut self, event: usize, channels: u16) -> &mut Self {
+        self.0.receive_cnf[event].write(|w| unsafe { w.bits(channels as u32) });
+        self
+    }
+
+    /// Triggers the `SEND` task `task`, signalling its channels.
+    pub fn send(&self, task: usize) {
+        self.0.tasks_send[task].write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns reference to the `SEND` task endpoint `task` for DPPI.
+    pub fn task_send(&self, task: usize) -> &ipc::TASKS_SEND {
+        &self.0.tasks_send[task]
+    }
+
+    /// Returns reference to the `RECEIVE` event endpoint `event` for DPPI.
+    pub fn event_receive(&self, event: usize) -> &ipc::EVENTS_RECEIVE {
+        &self.0.events_receive[event]
+    }
+
+    /// Returns `true` if the `RECEIVE` event `event` has been generated.
+    pub fn is_received(&self, event: usize) -> bool {
+        self.0.events_receive[event].read().bits() != 0
+    }
+
+    /// Clears the `RECEIVE` event `event`, returning `true` if it was set.
+    pub fn take_received(&mut self, event: usize) -> bool {
+        let received = self.is_received(event);
+        if received {
+            self.0.events_receive[event].reset();
+        }
+        received
+    }
+
+    /// Enables the `IPC` interrupt for the `RECEIVE` event `event`.
+    pub fn enable_interrupt(&mut self, event: usize) -> &mut Self {
+        assert!(event < CHANNELS);
+        self.0.intenset.write(|w| unsafe { w.bits(1 << event) });
+        self
+    }
+
+    /// Disables the `IPC` interrupt for the `RECEIVE` event `event`.
+    pub fn disable_interrupt(&mut self, event: usize) -> &mut Self {
+        assert!(event < CHANNELS);
+        self.0.intenclr.write(|w| unsafe { w.bits(1 << event) });
+        self
+    }
+
+    /// Returns the `RECEIVE` events that are pending with their interrupt enabled, as a bit
+    /// mask.
+    pub fn pending_interrupts(&self) -> u16 {
+        self.0.intpend.read().bits() as u16
+    }
+}
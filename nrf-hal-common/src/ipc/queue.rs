@@ -0,0 +1,218 @@
+//! Single producer, single consumer message queue in shared RAM.
+//!
+//! Both sides agree on a region of RAM, placed at the same address by their linker scripts. Its
+//! layout is:
+//!
+//! | Offset | Size    | Content                                         |
+//! |--------|---------|-------------------------------------------------|
+//! | 0      | 4       | Write index, only written by the producer       |
+//! | 4      | 4       | Read index, only written by the consumer        |
+//! | 8      | len - 8 | Ring buffer of messages                         |
+//!
+//! The indices are little endian byte counts that wrap around at 2^32; the position in the ring
+//! buffer is the index modulo its length, which must be a power of two. Each message is stored
+//! as its length, a little endian `u16`, followed by its bytes, and may wrap around the end of
+//! the ring buffer.
+//!
+//! After pushing a message, the producer triggers an IPC `SEND` task, which the consumer
+//! configures to generate a `RECEIVE` event as its doorbell.
+
+use crate::error::ErrorKind;
+use core::{
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use super::IPC;
+
+/// Length of the indices at the start of the region.
+pub const HEADER_LEN: usize = 8;
+
+/// Length of the prefix of each message.
+const PREFIX_LEN: usize = 2;
+
+/// Queue error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The message is longer than the ring buffer can hold, or than 65535 bytes.
+    MessageTooLong,
+    /// The buffer is too short for the next message, which stays in the queue.
+    BufferTooShort,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::MessageTooLong | Error::BufferTooShort => ErrorKind::BufferLength,
+        }
+    }
+}
+
+/// A region of shared RAM holding the queue.
+struct Ring {
+    region: *mut u8,
+    capacity: usize,
+}
+
+impl Ring {
+    unsafe fn new(region: *mut u8, len: usize) -> Self {
+        assert!(len > HEADER_LEN + PREFIX_LEN);
+        assert!((len - HEADER_LEN).is_power_of_two());
+        assert!(region as usize % 4 == 0);
+        Ring {
+            region,
+            capacity: len - HEADER_LEN,
+        }
+    }
+
+    fn write_index(&self) -> &AtomicU32 {
+        unsafe { &*(self.region as *const AtomicU32) }
+    }
+
+    fn read_index(&self) -> &AtomicU32 {
+        unsafe { &*(self.region.add(4) as *const AtomicU32) }
+    }
+
+    /// Copies `data` into the ring buffer at `index`, wrapping around its end.
+    fn copy_in(&self, index: u32, data: &[u8]) {
+        let start = index as usize % self.capacity;
+        let first = data.len().min(self.capacity - start);
+        unsafe {
+            let ring = self.region.add(HEADER_LEN);
+            ptr::copy_nonoverlapping(data.as_ptr(), ring.add(start), first);
+            ptr::copy_nonoverlapping(data.as_ptr().add(first), ring, data.len() - first);
+        }
+    }
+
+    /// Copies bytes from the ring buffer at `index` into `data`, wrapping around its end.
+    fn copy_out(&self, index: u32, data: &mut [u8]) {
+        let start = index as usize % self.capacity;
+        let first = data.len().min(self.capacity - start);
+        unsafe {
+            let ring = self.region.add(HEADER_LEN);
+            ptr::copy_nonoverlapping(ring.add(start), data.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(ring, data.as_mut_ptr().add(first), data.len() - first);
+        }
+    }
+}
+
+/// Clears the indices of the queue in `region`, emptying it.
+///
+/// # Safety
+///
+/// `region` must point to `len` bytes of RAM, aligned to 4 bytes, that are only used for the
+/// queue. `len - HEADER_LEN` must be a power of two. Neither side may use the queue while it is
+/// initialized.
+pub unsafe fn init(region: *mut u8, len: usize) {
+    let ring = Ring::new(region, len);
+    ring.write_index().store(0, Ordering::SeqCst);
+    ring.read_index().store(0, Ordering::SeqCst);
+}
+
+/// The sending side of a queue.
+pub struct Producer {
+    ring: Ring,
+    doorbell: usize,
+}
+
+impl Producer {
+    /// Creates the producer of the queue in `region`, which triggers the IPC `SEND` task
+    /// `doorbell` after each message.
+    ///
+    /// # Safety
+    ///
+    /// `region` must point to `len` bytes of RAM, aligned to 4 bytes, that have been initialized
+    /// with `init` and are only used for the queue. There must be a single producer.
+    pub unsafe fn new(region: *mut u8, len: usize, doorbell: usize) -> Self {
+        Producer {
+            ring: Ring::new(region, len),
+            doorbell,
+        }
+    }
+
+    /// Pushes `message` to the queue, and rings the doorbell.
+    ///
+    /// Returns `WouldBlock` if the queue has not enough room for it.
+    pub fn push(&mut self, message: &[u8]) -> nb::Result<(), Error> {
+        let needed = PREFIX_LEN + message.len();
+        if needed > self.ring.capacity || message.len() > u16::MAX as usize {
+            return Err(nb::Error::Other(Error::MessageTooLong));
+        }
+        let write = self.ring.write_index().load(Ordering::Relaxed);
+        let read = self.ring.read_index().load(Ordering::Acquire);
+        let used = write.wrapping_sub(read) as usize;
+        if self.ring.capacity - used < needed {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.ring
+            .copy_in(write, &(message.len() as u16).to_le_bytes());
+        self.ring
+            .copy_in(write.wrapping_add(PREFIX_LEN as u32), message);
+        self.ring
+            .write_index()
+            .store(write.wrapping_add(needed as u32), Ordering::Release);
+
+        let ipc = unsafe { &*IPC::ptr() };
+        ipc.tasks_send[self.doorbell].write(|w| unsafe { w.bits(1) });
+        Ok(())
+    }
+}
+
+// The producer only accesses the region through its own index and the free part of the ring.
+unsafe impl Send for Producer {}
+
+/// The receiving side of a queue.
+pub struct Consumer {
+    ring: Ring,
+}
+
+impl Consumer {
+    /// Creates the consumer of the queue in `region`.
+    ///
+    /// # Safety
+    ///
+    /// `region` must point to `len` bytes of RAM, aligned to 4 bytes, that have been initialized
+    /// with `init` and are only used for the queue. There must be a single consumer.
+    pub unsafe fn new(region: *mut u8, len: usize) -> Self {
+        Consumer {
+            ring: Ring::new(region, len),
+        }
+    }
+
+    /// Returns `true` if no message is waiting.
+    pub fn is_empty(&self) -> bool {
+        let write = self.ring.write_index().load(Ordering::Acquire);
+        let read = self.ring.read_index().load(Ordering::Relaxed);
+        write == read
+    }
+
+    /// Pops the next message into `buf`, and returns its length.
+    ///
+    /// Returns `WouldBlock` if the queue is empty.
+    pub fn pop(&mut self, buf: &mut [u8]) -> nb::Result<usize, Error> {
+        let write = self.ring.write_index().load(Ordering::Acquire);
+        let read = self.ring.read_index().load(Ordering::Relaxed);
+        if write == read {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let mut prefix = [0; PREFIX_LEN];
+        self.ring.copy_out(read, &mut prefix);
+        let len = u16::from_le_bytes(prefix) as usize;
+        if len > buf.len() {
+            return Err(nb::Error::Other(Error::BufferTooShort));
+        }
+        self.ring
+            .copy_out(read.wrapping_add(PREFIX_LEN as u32), &mut buf[..len]);
+        self.ring.read_index().store(
+            read.wrapping_add((PREFIX_LEN + len) as u32),
+            Ordering::Release,
+        );
+        Ok(len)
+    }
+}
+
+// The consumer only accesses the region through its own index and the used part of the ring.
+unsafe impl Send for Consumer {}
@@ -6,11 +6,17 @@
 use embedded_hal as hal;
 
 #[cfg(feature = "51")]
-pub use nrf51 as pac;
+pub use nrf51_pac as pac;
+
+#[cfg(feature = "52805")]
+pub use nrf52805_pac as pac;
 
 #[cfg(feature = "52810")]
 pub use nrf52810_pac as pac;
 
+#[cfg(feature = "52820")]
+pub use nrf52820_pac as pac;
+
 #[cfg(feature = "52832")]
 pub use nrf52832_pac as pac;
 
@@ -23,30 +29,124 @@ pub use nrf52840_pac as pac;
 #[cfg(feature = "9160")]
 pub use nrf9160_pac as pac;
 
+#[cfg(feature = "5340-app")]
+pub use nrf5340_app_pac as pac;
+
+#[cfg(feature = "5340-net")]
+pub use nrf5340_net_pac as pac;
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub mod acl;
 #[cfg(feature = "51")]
 pub mod adc;
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net"
+)))]
+pub mod approtect;
+#[cfg(not(feature = "51"))]
+pub mod audio;
+#[cfg(feature = "5340-app")]
+pub mod cache;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod ccm;
 pub mod clocks;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805"
+)))]
+pub mod comp;
 #[cfg(not(feature = "51"))]
 pub mod delay;
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+pub mod dfu_settings;
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+pub mod dppi;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod ecb;
+#[cfg(not(any(feature = "51", feature = "5340-app", feature = "5340-net")))]
+pub mod egu;
+pub mod error;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net"
+)))]
+pub mod ficr;
+#[cfg(feature = "flash-store")]
+pub mod flash_store;
 pub mod gpio;
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod gpiote;
-#[cfg(not(feature = "9160"))]
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+pub mod i2s;
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+pub mod ipc;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805",
+    feature = "52810",
+    feature = "52820"
+)))]
+pub mod lpcomp;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805"
+)))]
+pub mod mwu;
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+pub mod nfct;
+pub mod nvmc;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805",
+    feature = "52820"
+)))]
+pub mod pdm;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+pub mod power;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod ppi;
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net"
+)))]
+pub mod qdec;
+#[cfg(feature = "52840")]
+pub mod qspi;
+#[cfg(not(any(feature = "9160", feature = "5340-app")))]
+pub mod radio;
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+pub mod regulators;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod rng;
 pub mod rtc;
-#[cfg(not(feature = "51"))]
+#[cfg(not(any(feature = "51", feature = "5340-net", feature = "52820")))]
 pub mod saadc;
 #[cfg(feature = "51")]
 pub mod spi;
 #[cfg(not(feature = "51"))]
 pub mod spim;
-#[cfg(not(feature = "9160"))]
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+pub mod spu;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod temp;
 pub mod time;
 pub mod timer;
@@ -58,17 +158,22 @@ pub mod twim;
 pub mod uart;
 #[cfg(not(feature = "51"))]
 pub mod uarte;
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub mod uicr;
-#[cfg(not(feature = "9160"))]
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub mod usbd;
+#[cfg(not(any(feature = "5340-app", feature = "5340-net")))]
 pub mod wdt;
 
+#[cfg(feature = "async")]
+mod waker;
+
 pub mod prelude {
     pub use crate::hal::digital::v2::*;
     pub use crate::hal::prelude::*;
 
-    #[cfg(not(feature = "9160"))]
-    pub use crate::ppi::{ConfigurablePpi, Ppi};
+    #[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+    pub use crate::ppi::{ConfigurablePpi, Ppi, PpiChannelGroup};
     pub use crate::time::U32Ext;
 }
 
@@ -84,9 +189,37 @@ pub mod target_constants {
     const _CHECK_FORCE_COPY_BUFFER_SIZE: usize = EASY_DMA_SIZE - FORCE_COPY_BUFFER_SIZE;
     // ERROR: FORCE_COPY_BUFFER_SIZE must be <= EASY_DMA_SIZE
 }
-#[cfg(any(feature = "52840", feature = "52833", feature = "9160"))]
+#[cfg(feature = "52805")]
+pub mod target_constants {
+    // NRF52805 14 bits 1..0x3FFF
+    pub const EASY_DMA_SIZE: usize = 16383;
+    // Easy DMA can only read from data ram
+    pub const SRAM_LOWER: usize = 0x2000_0000;
+    pub const SRAM_UPPER: usize = 0x3000_0000;
+    pub const FORCE_COPY_BUFFER_SIZE: usize = 255;
+    const _CHECK_FORCE_COPY_BUFFER_SIZE: usize = EASY_DMA_SIZE - FORCE_COPY_BUFFER_SIZE;
+    // ERROR: FORCE_COPY_BUFFER_SIZE must be <= EASY_DMA_SIZE
+}
+#[cfg(feature = "52820")]
 pub mod target_constants {
-    // NRF52840 and NRF9160 16 bits 1..0xFFFF
+    // NRF52820 15 bits 1..0x7FFF
+    pub const EASY_DMA_SIZE: usize = 32767;
+    // Easy DMA can only read from data ram
+    pub const SRAM_LOWER: usize = 0x2000_0000;
+    pub const SRAM_UPPER: usize = 0x3000_0000;
+    pub const FORCE_COPY_BUFFER_SIZE: usize = 1024;
+    const _CHECK_FORCE_COPY_BUFFER_SIZE: usize = EASY_DMA_SIZE - FORCE_COPY_BUFFER_SIZE;
+    // ERROR: FORCE_COPY_BUFFER_SIZE must be <= EASY_DMA_SIZE
+}
+#[cfg(any(
+    feature = "52840",
+    feature = "52833",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net"
+))]
+pub mod target_constants {
+    // NRF52840, NRF9160 and NRF5340 16 bits 1..0xFFFF
     pub const EASY_DMA_SIZE: usize = 65535;
     // Limits for Easy DMA - it can only read from data ram
     pub const SRAM_LOWER: usize = 0x2000_0000;
@@ -97,11 +230,21 @@ pub mod target_constants {
 }
 
 /// Does this slice reside entirely within RAM?
+///
+/// Slices in internal flash or in the XIP window of the QSPI flash are not, and EasyDMA cannot
+/// read them.
 pub(crate) fn slice_in_ram(slice: &[u8]) -> bool {
     let ptr = slice.as_ptr() as usize;
     ptr >= target_constants::SRAM_LOWER && (ptr + slice.len()) < target_constants::SRAM_UPPER
 }
 
+/// Does this slice overlap the XIP window of the QSPI flash?
+#[cfg(feature = "52840")]
+pub(crate) fn slice_in_xip(slice: &[u8]) -> bool {
+    let ptr = slice.as_ptr() as usize;
+    ptr < qspi::XIP_END && ptr + slice.len() > qspi::XIP_START
+}
+
 /// Return an error if slice is not in RAM.
 #[cfg(not(feature = "51"))]
 pub(crate) fn slice_in_ram_or<T>(slice: &[u8], err: T) -> Result<(), T> {
@@ -135,23 +278,95 @@ impl DmaSlice {
     }
 }
 
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub use crate::acl::Acl;
+#[cfg(feature = "5340-app")]
+pub use crate::cache::Cache;
 pub use crate::clocks::Clocks;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805"
+)))]
+pub use crate::comp::Comp;
 #[cfg(not(feature = "51"))]
 pub use crate::delay::Delay;
-#[cfg(not(feature = "9160"))]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+pub use crate::dppi::Dppi;
+#[cfg(not(any(feature = "51", feature = "5340-app", feature = "5340-net")))]
+pub use crate::egu::Egu;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net"
+)))]
+pub use crate::ficr::Ficr;
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+pub use crate::i2s::I2s;
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+pub use crate::ipc::Ipc;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805",
+    feature = "52810",
+    feature = "52820"
+)))]
+pub use crate::lpcomp::LpComp;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805"
+)))]
+pub use crate::mwu::Mwu;
+#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+pub use crate::nfct::Nfct;
+pub use crate::nvmc::Nvmc;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net",
+    feature = "52805",
+    feature = "52820"
+)))]
+pub use crate::pdm::Pdm;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+pub use crate::power::Power;
+#[cfg(not(any(
+    feature = "51",
+    feature = "9160",
+    feature = "5340-app",
+    feature = "5340-net"
+)))]
+pub use crate::qdec::Qdec;
+#[cfg(feature = "52840")]
+pub use crate::qspi::{Qspi, QspiFlash};
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+pub use crate::regulators::Regulators;
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 pub use crate::rng::Rng;
 pub use crate::rtc::Rtc;
 pub use crate::timer::Timer;
 
 #[cfg(feature = "51")]
 pub use crate::adc::Adc;
-#[cfg(not(feature = "51"))]
+#[cfg(not(any(feature = "51", feature = "5340-net", feature = "52820")))]
 pub use crate::saadc::Saadc;
 
 #[cfg(feature = "51")]
 pub use crate::spi::Spi;
 #[cfg(not(feature = "51"))]
 pub use crate::spim::Spim;
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+pub use crate::spu::Spu;
 
 #[cfg(feature = "51")]
 pub use crate::twi::Twi;
@@ -162,3 +377,6 @@ pub use crate::twim::Twim;
 pub use crate::uart::Uart;
 #[cfg(not(feature = "51"))]
 pub use crate::uarte::Uarte;
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub use crate::usbd::Usbd;
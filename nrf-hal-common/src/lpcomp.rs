@@ -0,0 +1,157 @@
+//! HAL interface for the LPCOMP peripheral.
+//!
+//! The low-power comparator (LPCOMP) compares the voltage on an analog input pin against a
+//! fraction of VDD or an external reference.
+//!
+//! It draws little enough current to stay running in System OFF, and can wake the chip when the
+//! input crosses the threshold:
+//!
+//! ```ignore
+//! let mut lpcomp = LpComp::new(p.LPCOMP, p0.p0_02.into_analog(), Reference::REF4_8);
+//! lpcomp.hysteresis(true).wake_on(Transition::Up);
+//! lpcomp.start();
+//! power.system_off();
+//! ```
+
+use crate::{
+    comp::{CompResult, Transition},
+    gpio::AnalogPin,
+    pac::lpcomp::{_EVENTS_CROSS, _EVENTS_DOWN, _EVENTS_UP},
+    pac::{generic::Reg, LPCOMP},
+};
+
+pub use crate::pac::lpcomp::{
+    extrefsel::EXTREFSEL_A as ExternalReference, refsel::REFSEL_A as Reference,
+};
+
+/// A safe wrapper around the `LPCOMP` peripheral.
+pub struct LpComp<P> {
+    lpcomp: LPCOMP,
+    pin: P,
+}
+
+impl<P> LpComp<P>
+where
+    P: AnalogPin,
+{
+    /// Takes ownership of the `LPCOMP` peripheral and the analog input pin, returning a safe
+    /// wrapper comparing the pin against `reference`, a fraction of VDD in steps of 1/16 or the
+    /// external reference.
+    pub fn new(lpcomp: LPCOMP, pin: P, reference: Reference) -> Self {
+        lpcomp.psel.write(|w| unsafe { w.psel().bits(P::AIN) });
+        lpcomp.refsel.write(|w| w.refsel().variant(reference));
+        lpcomp.enable.write(|w| w.enable().enabled());
+
+        Self { lpcomp, pin }
+    }
+
+    /// Selects the pin used as reference with `Reference::AREF`.
+    pub fn external_reference(&mut self, reference: ExternalReference) -> &mut Self {
+        self.lpcomp
+            .extrefsel
+            .write(|w| w.extrefsel().variant(reference));
+        self
+    }
+
+    /// Enables or disables the hysteresis of 50 mV, which keeps a noisy input from toggling the
+    /// output around the threshold.
+    pub fn hysteresis(&mut self, enable: bool) -> &mut Self {
+        self.lpcomp.hyst.write(|w| w.hyst().bit(enable));
+        self
+    }
+
+    /// Selects the transition waking the chip from System OFF. The comparator must be started
+    /// before entering System OFF.
+    pub fn wake_on(&mut self, transition: Transition) -> &mut Self {
+        self.lpcomp.anadetect.write(|w| match transition {
+            Transition::Up => w.anadetect().up(),
+            Transition::Down => w.anadetect().down(),
+            Transition::Cross => w.anadetect().cross(),
+        });
+        self
+    }
+
+    /// Starts the comparator and blocks until it is ready.
+    pub fn start(&mut self) {
+        self.lpcomp.events_ready.reset();
+        self.lpcomp.tasks_start.write(|w| unsafe { w.bits(1) });
+        while self.lpcomp.events_ready.read().bits() == 0 {}
+        self.lpcomp.events_ready.reset();
+    }
+
+    /// Stops the comparator.
+    pub fn stop(&mut self) {
+        self.lpcomp.tasks_stop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Samples the comparator output.
+    ///
+    /// The comparator must have been started with `start`.
+    pub fn read(&mut self) -> CompResult {
+        self.lpcomp.tasks_sample.write(|w| unsafe { w.bits(1) });
+        if self.lpcomp.result.read().result().is_above() {
+            CompResult::Above
+        } else {
+            CompResult::Below
+        }
+    }
+
+    /// Enables the LPCOMP interrupt for the given transition.
+    pub fn enable_interrupt(&mut self, transition: Transition) -> &mut Self {
+        self.lpcomp.intenset.write(|w| match transition {
+            Transition::Up => w.up().set(),
+            Transition::Down => w.down().set(),
+            Transition::Cross => w.cross().set(),
+        });
+        self
+    }
+
+    /// Disables the LPCOMP interrupt for the given transition.
+    pub fn disable_interrupt(&mut self, transition: Transition) -> &mut Self {
+        self.lpcomp.intenclr.write(|w| match transition {
+            Transition::Up => w.up().clear(),
+            Transition::Down => w.down().clear(),
+            Transition::Cross => w.cross().clear(),
+        });
+        self
+    }
+
+    /// Checks if the event for the given transition has been triggered.
+    pub fn is_event_triggered(&self, transition: Transition) -> bool {
+        match transition {
+            Transition::Up => self.lpcomp.events_up.read().bits() != 0,
+            Transition::Down => self.lpcomp.events_down.read().bits() != 0,
+            Transition::Cross => self.lpcomp.events_cross.read().bits() != 0,
+        }
+    }
+
+    /// Marks all transition events as handled.
+    pub fn reset_events(&mut self) {
+        self.lpcomp.events_up.reset();
+        self.lpcomp.events_down.reset();
+        self.lpcomp.events_cross.reset();
+    }
+
+    /// Returns reference to the `UP` event endpoint for PPI.
+    pub fn event_up(&self) -> &Reg<u32, _EVENTS_UP> {
+        &self.lpcomp.events_up
+    }
+
+    /// Returns reference to the `DOWN` event endpoint for PPI.
+    pub fn event_down(&self) -> &Reg<u32, _EVENTS_DOWN> {
+        &self.lpcomp.events_down
+    }
+
+    /// Returns reference to the `CROSS` event endpoint for PPI.
+    pub fn event_cross(&self) -> &Reg<u32, _EVENTS_CROSS> {
+        &self.lpcomp.events_cross
+    }
+
+    /// Stops and disables the comparator, returning the raw `LPCOMP` peripheral and the input
+    /// pin.
+    pub fn free(self) -> (LPCOMP, P) {
+        self.lpcomp.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.lpcomp.enable.write(|w| w.enable().disabled());
+        (self.lpcomp, self.pin)
+    }
+}
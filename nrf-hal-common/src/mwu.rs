@@ -0,0 +1,221 @@
+//! HAL interface to the MWU peripheral.
+//!
+//! The memory watch unit (MWU) generates events when the CPU accesses a watched address range,
+//! without stopping the access. It has four user defined regions, covering any range of RAM or
+//! peripheral addresses, and two peripheral regions, split into subregions of one peripheral
+//! each. Watching the bottom of the stack for writes, with the NMI enabled, catches stack
+//! overflows before they corrupt other data:
+//!
+//! ```ignore
+//! let mut mwu = Mwu::new(p.MWU);
+//! mwu.watch(0, stack_bottom..stack_bottom + 32, Access::Write);
+//! mwu.enable_nmi(0, Access::Write);
+//! ```
+//!
+//! Only CPU accesses are detected, EasyDMA transfers bypass the MWU.
+//!
+//! See product specification, chapter MWU.
+
+use core::ops::Range;
+
+use crate::pac::MWU;
+
+/// Number of user defined regions.
+pub const REGIONS: usize = 4;
+
+/// Number of peripheral regions.
+pub const PERIPHERAL_REGIONS: usize = 2;
+
+/// The accesses to watch, or that triggered an event.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    /// Returns the bits of a region in `REGIONEN`, `INTEN` and `NMIEN`, where each region has a
+    /// write bit followed by a read bit.
+    fn bits(self, first: u32) -> u32 {
+        let (write, read) = (1 << first, 1 << (first + 1));
+        match self {
+            Access::Read => read,
+            Access::Write => write,
+            Access::ReadWrite => read | write,
+        }
+    }
+}
+
+/// Interface to the MWU peripheral.
+pub struct Mwu(MWU);
+
+impl Mwu {
+    /// Takes the MWU, with all regions disabled.
+    pub fn new(mwu: MWU) -> Self {
+        mwu.regionenclr.write(|w| unsafe { w.bits(!0) });
+        Self(mwu)
+    }
+
+    /// Returns the raw `MWU` peripheral, with its interrupts and NMI disabled.
+    pub fn free(self) -> MWU {
+        self.0.intenclr.write(|w| unsafe { w.bits(!0) });
+        self.0.nmienclr.write(|w| unsafe { w.bits(!0) });
+        self.0
+    }
+
+    /// Watches `range` for `access` through the user defined `region`, replacing its previous
+    /// range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region` is not below `REGIONS` or if `range` is empty.
+    pub fn watch(&mut self, region: usize, range: Range<u32>, access: Access) {
+        assert!(region < REGIONS);
+        assert!(range.start < range.end);
+        self.0
+            .regionenclr
+            .write(|w| unsafe { w.bits(Self::region_bits(region, Access::ReadWrite)) });
+        self.0.region[region]
+            .start
+            .write(|w| unsafe { w.bits(range.start) });
+        // `END` is the last watched address.
+        self.0.region[region]
+            .end
+            .write(|w| unsafe { w.bits(range.end - 1) });
+        self.0
+            .regionenset
+            .write(|w| unsafe { w.bits(Self::region_bits(region, access)) });
+    }
+
+    /// Stops watching the user defined `region`.
+    pub fn unwatch(&mut self, region: usize) {
+        self.0
+            .regionenclr
+            .write(|w| unsafe { w.bits(Self::region_bits(region, Access::ReadWrite)) });
+    }
+
+    /// Watches the subregions of the peripheral region `region` in the `subregions` bit mask for
+    /// `access`. Each subregion covers the registers of one peripheral.
+    pub fn watch_peripherals(&mut self, region: usize, subregions: u32, access: Access) {
+        assert!(region < PERIPHERAL_REGIONS);
+        self.0
+            .regionenclr
+            .write(|w| unsafe { w.bits(Self::peripheral_region_bits(region, Access::ReadWrite)) });
+        self.0.pregion[region]
+            .subs
+            .write(|w| unsafe { w.bits(subregions) });
+        self.0
+            .regionenset
+            .write(|w| unsafe { w.bits(Self::peripheral_region_bits(region, access)) });
+    }
+
+    /// Stops watching the peripheral region `region`.
+    pub fn unwatch_peripherals(&mut self, region: usize) {
+        self.0
+            .regionenclr
+            .write(|w| unsafe { w.bits(Self::peripheral_region_bits(region, Access::ReadWrite)) });
+    }
+
+    /// Returns `true` if the user defined `region` has seen `access` since its event was last
+    /// cleared. With `Access::ReadWrite`, either access counts.
+    pub fn is_accessed(&self, region: usize, access: Access) -> bool {
+        let events = &self.0.events_region[region];
+        let read = events.ra.read().bits() != 0;
+        let write = events.wa.read().bits() != 0;
+        match access {
+            Access::Read => read,
+            Access::Write => write,
+            Access::ReadWrite => read || write,
+        }
+    }
+
+    /// Clears the `access` events of the user defined `region`.
+    pub fn reset_events(&mut self, region: usize, access: Access) {
+        let events = &self.0.events_region[region];
+        if access != Access::Write {
+            events.ra.reset();
+        }
+        if access != Access::Read {
+            events.wa.reset();
+        }
+    }
+
+    /// Returns the subregions of the peripheral region `region` that have seen `access`, as a bit
+    /// mask, and clears them along with the region's events.
+    pub fn take_accessed_peripherals(&mut self, region: usize, access: Access) -> u32 {
+        let status = &self.0.perregion[region];
+        let mut subregions = 0;
+        if access != Access::Write {
+            subregions |= status.substatra.read().bits();
+            status.substatra.write(|w| unsafe { w.bits(!0) });
+            self.0.events_pregion[region].ra.reset();
+        }
+        if access != Access::Read {
+            subregions |= status.substatwa.read().bits();
+            status.substatwa.write(|w| unsafe { w.bits(!0) });
+            self.0.events_pregion[region].wa.reset();
+        }
+        subregions
+    }
+
+    /// Enables the MWU interrupt for `access` to the user defined `region`.
+    pub fn enable_interrupt(&mut self, region: usize, access: Access) -> &mut Self {
+        self.0
+            .intenset
+            .write(|w| unsafe { w.bits(Self::region_bits(region, access)) });
+        self
+    }
+
+    /// Disables the MWU interrupt for `access` to the user defined `region`.
+    pub fn disable_interrupt(&mut self, region: usize, access: Access) -> &mut Self {
+        self.0
+            .intenclr
+            .write(|w| unsafe { w.bits(Self::region_bits(region, access)) });
+        self
+    }
+
+    /// Raises the non-maskable interrupt on `access` to the user defined `region`, which is
+    /// handled even while interrupts are disabled.
+    pub fn enable_nmi(&mut self, region: usize, access: Access) -> &mut Self {
+        self.0
+            .nmienset
+            .write(|w| unsafe { w.bits(Self::region_bits(region, access)) });
+        self
+    }
+
+    /// Stops raising the non-maskable interrupt on `access` to the user defined `region`.
+    pub fn disable_nmi(&mut self, region: usize, access: Access) -> &mut Self {
+        self.0
+            .nmienclr
+            .write(|w| unsafe { w.bits(Self::region_bits(region, access)) });
+        self
+    }
+
+    /// Enables the MWU interrupt for `access` to the peripheral region `region`.
+    pub fn enable_peripheral_interrupt(&mut self, region: usize, access: Access) -> &mut Self {
+        self.0
+            .intenset
+            .write(|w| unsafe { w.bits(Self::peripheral_region_bits(region, access)) });
+        self
+    }
+
+    /// Disables the MWU interrupt for `access` to the peripheral region `region`.
+    pub fn disable_peripheral_interrupt(&mut self, region: usize, access: Access) -> &mut Self {
+        self.0
+            .intenclr
+            .write(|w| unsafe { w.bits(Self::peripheral_region_bits(region, access)) });
+        self
+    }
+
+    fn region_bits(region: usize, access: Access) -> u32 {
+        assert!(region < REGIONS);
+        access.bits(2 * region as u32)
+    }
+
+    fn peripheral_region_bits(region: usize, access: Access) -> u32 {
+        assert!(region < PERIPHERAL_REGIONS);
+        access.bits(24 + 2 * region as u32)
+    }
+}
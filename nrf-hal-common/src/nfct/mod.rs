@@ -0,0 +1,321 @@
+//! HAL interface to the NFCT peripheral.
+//!
+//! The NFC tag peripheral (NFCT) lets the chip act as an NFC-A listener, powered by the field of
+//! a reader like a phone. The peripheral handles the anticollision and selection of the tag in
+//! hardware; once selected, [`Nfct`] exchanges frames with the reader through EasyDMA.
+//!
+//! [`Type2Tag`] builds on it to emulate a read-only NFC Forum Type 2 tag:
+//!
+//! ```ignore
+//! static mut BUFFER: [u8; BUFFER_LEN] = [0; BUFFER_LEN];
+//! let nfct = Nfct::new(p.NFCT, Config::new(Uid::Double(uid)), unsafe { &mut BUFFER });
+//! let mut tag = Type2Tag::new(nfct);
+//! loop {
+//!     tag.poll(|page, buf| read_pages(&memory, page, buf));
+//! }
+//! ```
+//!
+//! The [`ndef`] module builds the NDEF message stored in the data area of the tag.
+//!
+//! The peripheral senses for a field with very little current, also in System OFF: entering
+//! System OFF while sensing, see [`Nfct::sense`], lets a reader wake the chip up. Field
+//! detection requires P0.09 and P0.10 to be configured as the NFC antenna in the UICR, see
+//! `Uicr::set_nfc_pins_as_gpio`.
+//!
+//! See product specification, chapter 6.15.
+
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::error::ErrorKind;
+use crate::pac::NFCT;
+
+pub mod ndef;
+mod type2;
+
+pub use type2::{header, read_pages, Event, Type2Tag};
+
+/// Clears the event, evaluating to `true` if it was set.
+macro_rules! take {
+    ($event:expr) => {{
+        let set = $event.read().bits() != 0;
+        if set {
+            $event.reset();
+        }
+        set
+    }};
+}
+
+/// Length of the frame buffer, in bytes.
+pub const BUFFER_LEN: usize = 64;
+
+/// Frame configuration: parity, start of frame and CRC.
+const FRAMECONFIG_PARITY: u32 = 1 << 0;
+const FRAMECONFIG_DISCARD_START: u32 = 1 << 1;
+const FRAMECONFIG_SOF: u32 = 1 << 2;
+const FRAMECONFIG_CRC16: u32 = 1 << 4;
+
+/// Frame reception errors in `FRAMESTATUS.RX`: CRC, parity and overrun.
+const FRAMESTATUS_RX_ERRORS: u32 = 0b1101;
+
+/// Shortcuts activating the peripheral when a field is detected, and sensing again when it is
+/// lost.
+const SHORTS_FIELDDETECTED_ACTIVATE: u32 = 1 << 0;
+const SHORTS_FIELDLOST_SENSE: u32 = 1 << 1;
+
+/// `FRAMEDELAYMODE` value letting the peripheral send a response on the bit grid of the
+/// reader, as soon as it is ready.
+const FRAMEDELAYMODE_WINDOWGRID: u32 = 3;
+
+/// `SELRES` protocol of a Type 2 tag.
+pub const PROTOCOL_TYPE_2: u8 = 0b00;
+
+/// Unique identifier of the tag, sent during anticollision.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Uid {
+    /// 4 byte identifier.
+    Single([u8; 4]),
+    /// 7 byte identifier, used by Type 2 tags.
+    Double([u8; 7]),
+    /// 10 byte identifier.
+    Triple([u8; 10]),
+}
+
+impl Uid {
+    /// Returns the bytes of the identifier.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Uid::Single(uid) => uid,
+            Uid::Double(uid) => uid,
+            Uid::Triple(uid) => uid,
+        }
+    }
+}
+
+/// Configuration of the anticollision and selection.
+pub struct Config {
+    pub uid: Uid,
+    /// Bit frame SDD field of `SENS_RES`, the response to `SENS_REQ`.
+    pub bit_frame_sdd: u8,
+    /// Platform configuration field of `SENS_RES`.
+    pub platform_config: u8,
+    /// Protocol field of `SEL_RES`, the response to the selection.
+    pub protocol: u8,
+}
+
+impl Config {
+    /// Configuration of a Type 2 tag with the given identifier.
+    pub fn new(uid: Uid) -> Self {
+        Config {
+            uid,
+            bit_frame_sdd: 0b00100,
+            platform_config: 0,
+            protocol: PROTOCOL_TYPE_2,
+        }
+    }
+}
+
+/// NFCT error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The frame doesn't fit in the buffer.
+    BufferLength,
+    /// The received frame had a CRC or parity error, or overran the buffer.
+    Frame,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::BufferLength => ErrorKind::BufferLength,
+            Error::Frame => ErrorKind::Corrupted,
+        }
+    }
+}
+
+/// Interface to the NFCT peripheral.
+pub struct Nfct {
+    nfct: NFCT,
+    buffer: &'static mut [u8; BUFFER_LEN],
+}
+
+impl Nfct {
+    /// Configures the peripheral and starts sensing for a field. When one is detected, the
+    /// peripheral is activated and answers the anticollision by itself.
+    pub fn new(nfct: NFCT, config: Config, buffer: &'static mut [u8; BUFFER_LEN]) -> Self {
+        let uid = config.uid.as_bytes();
+        let (size, rest) = match config.uid {
+            Uid::Single(_) => (0, uid),
+            Uid::Double(_) => (1, &uid[3..]),
+            Uid::Triple(_) => (2, &uid[6..]),
+        };
+        let word = |bytes: &[u8]| bytes.iter().fold(0, |acc, &b| acc << 8 | b as u32);
+        unsafe {
+            match config.uid {
+                Uid::Single(_) => {}
+                Uid::Double(_) => nfct.nfcid1_2nd_last.write(|w| w.bits(word(&uid[..3]))),
+                Uid::Triple(_) => {
+                    nfct.nfcid1_3rd_last.write(|w| w.bits(word(&uid[..3])));
+                    nfct.nfcid1_2nd_last.write(|w| w.bits(word(&uid[3..6])));
+                }
+            }
+            nfct.nfcid1_last.write(|w| w.bits(word(rest)));
+
+            nfct.sensres.write(|w| {
+                w.bits(
+                    (config.bit_frame_sdd as u32 & 0x1f)
+                        | size << 6
+                        | (config.platform_config as u32 & 0xf) << 8,
+                )
+            });
+            nfct.selres
+                .write(|w| w.bits((config.protocol as u32 & 0b11) << 5));
+
+            // Enable the automatic collision resolution.
+            nfct.autocolresconfig.modify(|r, w| w.bits(r.bits() & !1));
+            nfct.framedelaymode
+                .write(|w| w.bits(FRAMEDELAYMODE_WINDOWGRID));
+            nfct.maxlen.write(|w| w.bits(BUFFER_LEN as u32));
+            nfct.shorts
+                .write(|w| w.bits(SHORTS_FIELDDETECTED_ACTIVATE | SHORTS_FIELDLOST_SENSE));
+        }
+
+        nfct.events_fielddetected.reset();
+        nfct.events_selected.reset();
+        nfct.events_fieldlost.reset();
+        nfct.events_rxframeend.reset();
+        nfct.events_txframeend.reset();
+        nfct.tasks_sense.write(|w| unsafe { w.bits(1) });
+
+        Nfct { nfct, buffer }
+    }
+
+    /// Returns `true` if a field is present.
+    pub fn is_field_present(&self) -> bool {
+        self.nfct.fieldpresent.read().fieldpresent().bit_is_set()
+    }
+
+    /// Returns `true` if a field has been detected since the last call.
+    pub fn take_field_detected(&mut self) -> bool {
+        take!(self.nfct.events_fielddetected)
+    }
+
+    /// Enables the `NFCT` interrupt when a field is detected or lost.
+    pub fn enable_field_interrupts(&mut self) {
+        self.nfct
+            .intenset
+            .write(|w| w.fielddetected().set().fieldlost().set());
+    }
+
+    /// Disables the field interrupts.
+    pub fn disable_field_interrupts(&mut self) {
+        self.nfct
+            .intenclr
+            .write(|w| w.fielddetected().clear().fieldlost().clear());
+    }
+
+    /// Returns the peripheral to sensing for a field, e.g. before entering System OFF so a
+    /// reader wakes the chip up. The reset reason is then `ResetReason::nfc`.
+    pub fn sense(&mut self) {
+        self.nfct.tasks_sense.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns `true` if the tag has been selected by a reader since the last call.
+    pub fn take_selected(&mut self) -> bool {
+        take!(self.nfct.events_selected)
+    }
+
+    /// Returns `true` if the field has been lost since the last call. The peripheral then
+    /// senses for a field again.
+    pub fn take_field_lost(&mut self) -> bool {
+        take!(self.nfct.events_fieldlost)
+    }
+
+    /// Starts receiving a frame with CRC.
+    pub fn receive(&mut self) {
+        self.nfct
+            .packetptr
+            .write(|w| unsafe { w.bits(self.buffer.as_ptr() as u32) });
+        self.nfct
+            .rxd
+            .frameconfig
+            .write(|w| unsafe { w.bits(FRAMECONFIG_PARITY | FRAMECONFIG_SOF | FRAMECONFIG_CRC16) });
+        self.nfct.events_rxframeend.reset();
+        compiler_fence(SeqCst);
+        self.nfct.tasks_enablerxdata.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns the received frame, without its CRC, once reception has ended.
+    pub fn received(&mut self) -> nb::Result<&[u8], Error> {
+        if !take!(self.nfct.events_rxframeend) {
+            return Err(nb::Error::WouldBlock);
+        }
+        compiler_fence(SeqCst);
+        let status = self.nfct.framestatus.rx.read().bits();
+        if status & FRAMESTATUS_RX_ERRORS != 0 {
+            self.nfct
+                .framestatus
+                .rx
+                .write(|w| unsafe { w.bits(FRAMESTATUS_RX_ERRORS) });
+            return Err(nb::Error::Other(Error::Frame));
+        }
+        let len = (self.nfct.rxd.amount.read().bits() >> 3) as usize & 0x1ff;
+        // The amount includes the CRC, which is checked by the peripheral.
+        Ok(&self.buffer[..len.saturating_sub(2).min(BUFFER_LEN)])
+    }
+
+    /// Starts transmitting `data` as a frame with CRC.
+    pub fn transmit(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > BUFFER_LEN {
+            return Err(Error::BufferLength);
+        }
+        self.buffer[..data.len()].copy_from_slice(data);
+        self.start_tx(
+            FRAMECONFIG_PARITY | FRAMECONFIG_DISCARD_START | FRAMECONFIG_SOF | FRAMECONFIG_CRC16,
+            (data.len() as u32) << 3,
+        );
+        Ok(())
+    }
+
+    /// Starts transmitting a 4 bit frame without parity or CRC, like an ACK or NAK.
+    pub fn transmit_nibble(&mut self, nibble: u8) {
+        self.buffer[0] = nibble & 0xf;
+        self.start_tx(FRAMECONFIG_SOF, 4);
+    }
+
+    /// Returns `Ok` once the frame has been transmitted.
+    pub fn transmitted(&mut self) -> nb::Result<(), void::Void> {
+        if take!(self.nfct.events_txframeend) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Puts the tag to sleep until the reader wakes it up with `ALL_REQ`, after a `HLTA`
+    /// command.
+    pub fn sleep(&mut self) {
+        self.nfct.tasks_gosleep.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Disables the peripheral, and returns it with the buffer.
+    pub fn free(self) -> (NFCT, &'static mut [u8; BUFFER_LEN]) {
+        self.nfct.shorts.reset();
+        self.nfct.tasks_disable.write(|w| unsafe { w.bits(1) });
+        (self.nfct, self.buffer)
+    }
+
+    fn start_tx(&mut self, frameconfig: u32, amount: u32) {
+        let nfct = &self.nfct;
+        nfct.packetptr
+            .write(|w| unsafe { w.bits(self.buffer.as_ptr() as u32) });
+        nfct.txd
+            .frameconfig
+            .write(|w| unsafe { w.bits(frameconfig) });
+        nfct.txd.amount.write(|w| unsafe { w.bits(amount) });
+        nfct.events_txframeend.reset();
+        compiler_fence(SeqCst);
+        nfct.tasks_starttx.write(|w| unsafe { w.bits(1) });
+    }
+}
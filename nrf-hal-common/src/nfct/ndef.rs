@@ -0,0 +1,203 @@
+//! NDEF messages for tag memory.
+//!
+//! [`MessageBuilder`] writes the records of an NDEF message into a buffer, wrapped in the TLV
+//! block that a Type 2 tag stores in its data area:
+//!
+//! ```ignore
+//! let mut memory = [0; 16 + 64];
+//! let (head, data) = memory.split_at_mut(16);
+//! let mut message = MessageBuilder::new(data);
+//! message.uri("https://example.com")?;
+//! let len = message.finish()?.len();
+//! head.copy_from_slice(&header(&uid, (len + 7) / 8 * 8));
+//! ```
+
+use super::Error;
+
+/// Type name formats of records.
+pub const TNF_WELL_KNOWN: u8 = 0x01;
+pub const TNF_MEDIA: u8 = 0x02;
+pub const TNF_EXTERNAL: u8 = 0x04;
+
+/// Record header flags: message begin, message end and short record.
+const FLAG_MB: u8 = 0x80;
+const FLAG_ME: u8 = 0x40;
+const FLAG_SR: u8 = 0x10;
+
+/// TLV types of a Type 2 tag: NDEF message and terminator.
+const TLV_NDEF: u8 = 0x03;
+const TLV_TERMINATOR: u8 = 0xfe;
+
+/// Space reserved for the TLV tag and a 3 byte length.
+const TLV_HEADER_LEN: usize = 4;
+
+/// URI prefixes abbreviated by the URI record, with their codes.
+const URI_PREFIXES: [(&str, u8); 6] = [
+    ("http://www.", 0x01),
+    ("https://www.", 0x02),
+    ("http://", 0x03),
+    ("https://", 0x04),
+    ("tel:", 0x05),
+    ("mailto:", 0x06),
+];
+
+/// AD types of the BLE OOB record: LE device address and LE role.
+const AD_LE_ADDRESS: u8 = 0x1b;
+const AD_LE_ROLE: u8 = 0x1c;
+
+/// LE role advertised in a BLE OOB record.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LeRole {
+    /// Only peripheral role supported.
+    Peripheral = 0x00,
+    /// Only central role supported.
+    Central = 0x01,
+    /// Both roles supported, peripheral preferred.
+    PeripheralPreferred = 0x02,
+    /// Both roles supported, central preferred.
+    CentralPreferred = 0x03,
+}
+
+/// Builds an NDEF message in a TLV block, without allocating.
+pub struct MessageBuilder<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    /// Position of the header of the last record, to set its message end flag.
+    last_header: Option<usize>,
+}
+
+impl<'a> MessageBuilder<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        MessageBuilder {
+            buf,
+            len: TLV_HEADER_LEN,
+            last_header: None,
+        }
+    }
+
+    /// Adds a record with a payload made of `payload` parts.
+    pub fn record(
+        &mut self,
+        tnf: u8,
+        record_type: &[u8],
+        payload: &[&[u8]],
+    ) -> Result<&mut Self, Error> {
+        let payload_len: usize = payload.iter().map(|part| part.len()).sum();
+        let short = payload_len < 0x100;
+        let header_len = if short { 3 } else { 6 };
+        let total = header_len + record_type.len() + payload_len;
+        // The TLV terminator follows the message.
+        if self.len + total + 1 > self.buf.len() || record_type.len() > 0xff {
+            return Err(Error::BufferLength);
+        }
+
+        let mut flags = tnf & 0x07;
+        if self.last_header.is_none() {
+            flags |= FLAG_MB;
+        }
+        if short {
+            flags |= FLAG_SR;
+        }
+        let header = self.len;
+        self.push(&[flags, record_type.len() as u8]);
+        if short {
+            self.push(&[payload_len as u8]);
+        } else {
+            self.push(&(payload_len as u32).to_be_bytes());
+        }
+        self.push(record_type);
+        for part in payload {
+            self.push(part);
+        }
+        self.last_header = Some(header);
+        Ok(self)
+    }
+
+    /// Adds a URI record, abbreviating a common prefix.
+    pub fn uri(&mut self, uri: &str) -> Result<&mut Self, Error> {
+        let (code, rest) = URI_PREFIXES
+            .iter()
+            .find(|(prefix, _)| uri.starts_with(prefix))
+            .map_or((0, uri), |(prefix, code)| (*code, &uri[prefix.len()..]));
+        self.record(TNF_WELL_KNOWN, b"U", &[&[code], rest.as_bytes()])
+    }
+
+    /// Adds a text record in UTF-8, with an IANA language code like `en`.
+    pub fn text(&mut self, language: &str, text: &str) -> Result<&mut Self, Error> {
+        let status = language.len() as u8 & 0x3f;
+        self.record(
+            TNF_WELL_KNOWN,
+            b"T",
+            &[&[status], language.as_bytes(), text.as_bytes()],
+        )
+    }
+
+    /// Adds an Android application record, making Android open or install the app with the
+    /// given package name.
+    pub fn android_app(&mut self, package: &str) -> Result<&mut Self, Error> {
+        self.record(TNF_EXTERNAL, b"android.com:pkg", &[package.as_bytes()])
+    }
+
+    /// Adds a Bluetooth LE out-of-band pairing record with the device address, in little endian
+    /// like on air, and the LE role. `extra` holds further AD structures, like the security
+    /// manager TK value or LE Secure Connections confirmation and random values.
+    pub fn ble_oob(
+        &mut self,
+        address: &[u8; 6],
+        random_address: bool,
+        role: LeRole,
+        extra: &[u8],
+    ) -> Result<&mut Self, Error> {
+        let mut ad = [0; 12];
+        ad[0] = 8;
+        ad[1] = AD_LE_ADDRESS;
+        ad[2..8].copy_from_slice(address);
+        ad[8] = random_address as u8;
+        ad[9] = 2;
+        ad[10] = AD_LE_ROLE;
+        ad[11] = role as u8;
+        self.record(
+            TNF_MEDIA,
+            b"application/vnd.bluetooth.le.oob",
+            &[&ad, extra],
+        )
+    }
+
+    /// Completes the message, and returns the TLV block holding it. The block is at the start
+    /// of the buffer.
+    pub fn finish(self) -> Result<&'a [u8], Error> {
+        let MessageBuilder {
+            buf,
+            len,
+            last_header,
+        } = self;
+        if len + 1 > buf.len() {
+            return Err(Error::BufferLength);
+        }
+        if let Some(header) = last_header {
+            buf[header] |= FLAG_ME;
+        }
+
+        let message_len = len - TLV_HEADER_LEN;
+        let start = if message_len < 0xff {
+            // Short length: move the message next to the 1 byte length.
+            buf.copy_within(TLV_HEADER_LEN..len, 2);
+            buf[1] = message_len as u8;
+            2
+        } else {
+            buf[1] = 0xff;
+            buf[2..4].copy_from_slice(&(message_len as u16).to_be_bytes());
+            TLV_HEADER_LEN
+        };
+        buf[0] = TLV_NDEF;
+        let end = start + message_len;
+        buf[end] = TLV_TERMINATOR;
+        Ok(&buf[..end + 1])
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+    }
+}
@@ -0,0 +1,156 @@
+//! NFC Forum Type 2 tag emulation.
+//!
+//! The memory of a Type 2 tag is made of 4 byte pages. Pages 0 to 2 hold the identifier and lock
+//! bytes, page 3 the capability container, and the data area, usually an NDEF message, starts at
+//! page 4. Readers fetch the memory with `READ` commands returning 4 pages at a time.
+
+use super::Nfct;
+
+/// Commands of a Type 2 tag.
+const CMD_READ: u8 = 0x30;
+const CMD_HLTA: u8 = 0x50;
+
+/// 4 bit negative acknowledgement of an invalid command.
+const NAK_INVALID: u8 = 0x0;
+
+/// Capability container magic number and version 1.0.
+const CC_MAGIC: u8 = 0xe1;
+const CC_VERSION: u8 = 0x10;
+
+/// Capability container access: read-only.
+const CC_READ_ONLY: u8 = 0x0f;
+
+/// Something that happened on the tag, returned by `Type2Tag::poll`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A reader has selected the tag.
+    Selected,
+    /// The reader has read 4 pages, starting at `page`.
+    Read { page: u8 },
+    /// The reader has put the tag to sleep.
+    Halted,
+    /// The field has been lost.
+    FieldLost,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum State {
+    Idle,
+    Receiving,
+    Transmitting,
+}
+
+/// A read-only NFC Forum Type 2 tag.
+pub struct Type2Tag {
+    nfct: Nfct,
+    state: State,
+}
+
+impl Type2Tag {
+    pub fn new(nfct: Nfct) -> Self {
+        Type2Tag {
+            nfct,
+            state: State::Idle,
+        }
+    }
+
+    /// Handles the pending events and commands of the reader, calling `read` to fill in the 16
+    /// bytes of tag memory starting at a page.
+    ///
+    /// Must be called often enough to answer a command within about 5 ms, e.g. from the `NFCT`
+    /// interrupt handler. Writes and other commands are refused.
+    pub fn poll(&mut self, mut read: impl FnMut(u8, &mut [u8; 16])) -> Option<Event> {
+        if self.nfct.take_field_lost() {
+            self.state = State::Idle;
+            return Some(Event::FieldLost);
+        }
+        if self.nfct.take_selected() {
+            self.nfct.receive();
+            self.state = State::Receiving;
+            return Some(Event::Selected);
+        }
+
+        match self.state {
+            State::Idle => None,
+            State::Transmitting => {
+                if self.nfct.transmitted().is_ok() {
+                    self.nfct.receive();
+                    self.state = State::Receiving;
+                }
+                None
+            }
+            State::Receiving => {
+                let (command, argument, len) = match self.nfct.received() {
+                    Ok(frame) => (frame.first().copied(), frame.get(1).copied(), frame.len()),
+                    Err(nb::Error::WouldBlock) => return None,
+                    Err(nb::Error::Other(_)) => {
+                        // Corrupted frames are ignored.
+                        self.nfct.receive();
+                        return None;
+                    }
+                };
+                match (command, argument, len) {
+                    (Some(CMD_READ), Some(page), 2) => {
+                        let mut data = [0; 16];
+                        read(page, &mut data);
+                        self.nfct.transmit(&data).ok();
+                        self.state = State::Transmitting;
+                        Some(Event::Read { page })
+                    }
+                    (Some(CMD_HLTA), Some(0), 2) => {
+                        self.nfct.sleep();
+                        self.state = State::Idle;
+                        Some(Event::Halted)
+                    }
+                    _ => {
+                        self.nfct.transmit_nibble(NAK_INVALID);
+                        self.state = State::Transmitting;
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the frame layer.
+    pub fn free(self) -> Nfct {
+        self.nfct
+    }
+}
+
+/// Returns pages 0 to 3 of a read-only tag with a 7 byte identifier and a data area of
+/// `data_len` bytes, a multiple of 8.
+pub fn header(uid: &[u8; 7], data_len: usize) -> [u8; 16] {
+    let bcc0 = 0x88 ^ uid[0] ^ uid[1] ^ uid[2];
+    let bcc1 = uid[3] ^ uid[4] ^ uid[5] ^ uid[6];
+    [
+        uid[0],
+        uid[1],
+        uid[2],
+        bcc0,
+        uid[3],
+        uid[4],
+        uid[5],
+        uid[6],
+        bcc1,
+        0x48,
+        // Static lock bytes: all pages locked.
+        0xff,
+        0xff,
+        CC_MAGIC,
+        CC_VERSION,
+        (data_len / 8) as u8,
+        CC_READ_ONLY,
+    ]
+}
+
+/// Copies the 16 bytes of `memory` starting at `page` into `buf`, padding with zeros past its
+/// end. `memory` starts at page 0, with the `header`.
+pub fn read_pages(memory: &[u8], page: u8, buf: &mut [u8; 16]) {
+    let start = (page as usize * 4).min(memory.len());
+    let data = &memory[start..];
+    let len = data.len().min(16);
+    buf[..len].copy_from_slice(&data[..len]);
+    buf[len..].iter_mut().for_each(|b| *b = 0);
+}
@@ -0,0 +1,560 @@
+//! HAL interface to the Non-Volatile Memory Controller (NVMC) peripheral.
+//!
+//! Implements the `embedded-storage` NOR flash traits on a region of the internal flash, so
+//! flash based storage crates and bootloaders can use it directly.
+
+use crate::error::ErrorKind;
+use core::ptr;
+
+use void::Void;
+
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+use embedded_storage::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+use crate::pac::NVMC_NS as NVMC;
+
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+use crate::pac::NVMC;
+
+/// Size of a flash page, the unit of erasure, in bytes.
+#[cfg(feature = "51")]
+pub const PAGE_SIZE: usize = 1024;
+
+/// Size of a flash page, the unit of erasure, in bytes.
+#[cfg(feature = "5340-net")]
+pub const PAGE_SIZE: usize = 2 * 1024;
+
+/// Size of a flash page, the unit of erasure, in bytes.
+#[cfg(not(any(feature = "51", feature = "5340-net")))]
+pub const PAGE_SIZE: usize = 4 * 1024;
+
+/// Size of a flash word, the unit of writing, in bytes.
+pub const WORD_SIZE: usize = 4;
+
+/// Interface to the NVMC peripheral, giving access to a region of the internal flash.
+pub struct Nvmc {
+    nvmc: NVMC,
+    storage: &'static mut [u8],
+}
+
+impl Nvmc {
+    /// Takes ownership of the peripheral and of the flash region `storage`.
+    ///
+    /// `storage` is usually a region reserved in the linker script, turned into a slice with
+    /// `core::slice::from_raw_parts_mut`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `storage` is not aligned to a page or its length is not a multiple of the page
+    /// size.
+    pub fn new(nvmc: NVMC, storage: &'static mut [u8]) -> Self {
+        assert_eq!(storage.as_ptr() as usize % PAGE_SIZE, 0);
+        assert_eq!(storage.len() % PAGE_SIZE, 0);
+        Self { nvmc, storage }
+    }
+
+    /// Returns the raw `NVMC` peripheral and the flash region.
+    pub fn free(self) -> (NVMC, &'static mut [u8]) {
+        (self.nvmc, self.storage)
+    }
+
+    fn enable_erase(&self) {
+        #[cfg(not(any(feature = "9160", feature = "5340-app")))]
+        self.nvmc.config.write(|w| w.wen().een());
+        #[cfg(any(feature = "9160", feature = "5340-app"))]
+        self.nvmc.configns.write(|w| w.wen().een());
+    }
+
+    fn enable_read(&self) {
+        #[cfg(not(any(feature = "9160", feature = "5340-app")))]
+        self.nvmc.config.write(|w| w.wen().ren());
+        #[cfg(any(feature = "9160", feature = "5340-app"))]
+        self.nvmc.configns.write(|w| w.wen().ren());
+    }
+
+    fn enable_write(&self) {
+        #[cfg(not(any(feature = "9160", feature = "5340-app")))]
+        self.nvmc.config.write(|w| w.wen().wen());
+        #[cfg(any(feature = "9160", feature = "5340-app"))]
+        self.nvmc.configns.write(|w| w.wen().wen());
+    }
+
+    #[inline]
+    fn wait_ready(&self) {
+        while !self.nvmc.ready.read().ready().bit_is_set() {}
+    }
+
+    /// Erases the page at `offset` bytes from the start of the region.
+    fn erase_page(&mut self, offset: usize) {
+        let page = &mut self.storage[offset] as *mut u8;
+        self.enable_erase();
+        #[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+        self.nvmc
+            .erasepage()
+            .write(|w| unsafe { w.bits(page as u32) });
+        // The nRF9160 and nRF5340 erase a page when any word of it is written in erase mode.
+        #[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+        unsafe {
+            ptr::write_volatile(page as *mut u32, 0xffff_ffff)
+        };
+        self.wait_ready();
+        self.enable_read();
+    }
+
+    /// Checks the bounds and alignment of an erase, returning them as offsets.
+    fn check_erase(&self, from: u32, to: u32) -> Result<(usize, usize), Error> {
+        let (from, to) = (from as usize, to as usize);
+        if from > to || to > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+        if from % PAGE_SIZE != 0 || to % PAGE_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+        Ok((from, to))
+    }
+
+    /// Checks the bounds and alignment of a write, returning its offset.
+    fn check_write(&self, offset: u32, bytes: &[u8]) -> Result<usize, Error> {
+        let offset = offset as usize;
+        if offset
+            .checked_add(bytes.len())
+            .map_or(true, |end| end > self.storage.len())
+        {
+            return Err(Error::OutOfBounds);
+        }
+        if offset % WORD_SIZE != 0 || bytes.len() % WORD_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+        Ok(offset)
+    }
+
+    /// Returns `true` if the NVMC can accept the next word to write.
+    fn is_ready_next(&self) -> bool {
+        #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+        return self.nvmc.readynext.read().readynext().bit_is_set();
+        #[cfg(not(any(feature = "52820", feature = "52833", feature = "52840")))]
+        return self.nvmc.ready.read().ready().bit_is_set();
+    }
+
+    /// Starts erasing the pages from `from` to `to`, one page, or one partial erase slice on the
+    /// nRF52820, nRF52833 and nRF52840, per call of `Erase::step`.
+    pub fn start_erase(&mut self, from: u32, to: u32) -> Result<Erase, Error> {
+        let (from, to) = self.check_erase(from, to)?;
+        Ok(Erase {
+            next: from,
+            end: to,
+            #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+            current: None,
+        })
+    }
+
+    /// Starts writing `bytes` at `offset`, one word per call of `Write::step`.
+    pub fn start_write<'a>(&mut self, offset: u32, bytes: &'a [u8]) -> Result<Write<'a>, Error> {
+        let offset = self.check_write(offset, bytes)?;
+        Ok(Write {
+            offset,
+            remaining: bytes,
+        })
+    }
+
+    /// Erases the pages from `from` to `to`, yielding to other tasks between pages or partial
+    /// erase slices.
+    #[cfg(feature = "async")]
+    pub async fn erase_async(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        let mut erase = self.start_erase(from, to)?;
+        loop {
+            match erase.step(self) {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::WouldBlock) => yield_now().await,
+                Err(nb::Error::Other(void)) => match void {},
+            }
+        }
+    }
+
+    /// Writes `bytes` at `offset`, yielding to other tasks between words.
+    #[cfg(feature = "async")]
+    pub async fn write_async(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        let mut write = self.start_write(offset, bytes)?;
+        loop {
+            match write.step(self) {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::WouldBlock) => yield_now().await,
+                Err(nb::Error::Other(void)) => match void {},
+            }
+        }
+    }
+
+    /// Writes `word` at `offset` bytes from the start of the region.
+    fn write_word(&mut self, offset: usize, word: u32) {
+        let target = &mut self.storage[offset] as *mut u8 as *mut u32;
+        self.enable_write();
+        self.wait_ready();
+        unsafe { ptr::write_volatile(target, word) };
+        self.wait_ready();
+        self.enable_read();
+    }
+}
+
+/// Maximum time needed to erase a page, in ms.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub const PAGE_ERASE_TIME_MS: u32 = 85;
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+impl Nvmc {
+    /// Sets the duration of a partial erase slice in ms, 1 to 127. Defaults to 10 ms.
+    pub fn set_partial_erase_duration(&mut self, ms: u8) {
+        assert!((1..=127).contains(&ms));
+        self.nvmc
+            .erasepagepartialcfg
+            .write(|w| unsafe { w.duration().bits(ms) });
+    }
+
+    /// Returns the duration of a partial erase slice in ms.
+    pub fn partial_erase_duration(&self) -> u8 {
+        self.nvmc.erasepagepartialcfg.read().duration().bits()
+    }
+
+    /// Starts erasing the page at `offset` in slices, keeping the CPU blocked for one slice only
+    /// per call of `PartialErase::step`.
+    ///
+    /// Splitting an erase keeps time critical work, like BLE connection events or watchdog
+    /// feeding, running in between slices.
+    pub fn start_partial_erase(&mut self, offset: u32) -> Result<PartialErase, Error> {
+        let offset = offset as usize;
+        if offset >= self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+        if offset % PAGE_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+        Ok(PartialErase {
+            offset,
+            elapsed_ms: 0,
+        })
+    }
+
+    /// Runs one partial erase slice on the page at `offset`, blocking for its duration.
+    fn erase_page_partial(&mut self, offset: usize) {
+        let page = &mut self.storage[offset] as *mut u8;
+        self.enable_erase();
+        self.nvmc
+            .erasepagepartial
+            .write(|w| unsafe { w.bits(page as u32) });
+        self.wait_ready();
+        self.enable_read();
+    }
+}
+
+/// A page erase split into slices, started with `Nvmc::start_partial_erase`.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub struct PartialErase {
+    offset: usize,
+    elapsed_ms: u32,
+}
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+impl PartialErase {
+    /// Runs the next erase slice, blocking for the configured slice duration.
+    ///
+    /// Returns `WouldBlock` until the accumulated erase time has reached `PAGE_ERASE_TIME_MS`
+    /// and the page is erased.
+    pub fn step(&mut self, nvmc: &mut Nvmc) -> nb::Result<(), Void> {
+        if self.elapsed_ms >= PAGE_ERASE_TIME_MS {
+            return Ok(());
+        }
+        nvmc.erase_page_partial(self.offset);
+        self.elapsed_ms += nvmc.partial_erase_duration() as u32;
+        if self.elapsed_ms >= PAGE_ERASE_TIME_MS {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+/// Instruction cache of the flash, configured through the NVMC.
+///
+/// Enabling the cache speeds up code executed from flash and reduces its current consumption.
+#[cfg(any(
+    feature = "52820",
+    feature = "52832",
+    feature = "52833",
+    feature = "52840"
+))]
+pub struct ICache<'a>(&'a mut NVMC);
+
+#[cfg(any(
+    feature = "52820",
+    feature = "52832",
+    feature = "52833",
+    feature = "52840"
+))]
+impl<'a> ICache<'a> {
+    /// Gives access to the cache through the raw `NVMC` peripheral.
+    pub fn new(nvmc: &'a mut NVMC) -> Self {
+        ICache(nvmc)
+    }
+
+    /// Enables the cache.
+    pub fn enable(&mut self) {
+        self.0.icachecnf.modify(|_, w| w.cacheen().enabled());
+    }
+
+    /// Disables the cache.
+    pub fn disable(&mut self) {
+        self.0.icachecnf.modify(|_, w| w.cacheen().disabled());
+    }
+
+    /// Returns `true` if the cache is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.0.icachecnf.read().cacheen().is_enabled()
+    }
+
+    /// Enables counting cache hits and misses.
+    pub fn enable_profiling(&mut self) {
+        self.0.icachecnf.modify(|_, w| w.cacheprofen().enabled());
+    }
+
+    /// Disables counting cache hits and misses.
+    pub fn disable_profiling(&mut self) {
+        self.0.icachecnf.modify(|_, w| w.cacheprofen().disabled());
+    }
+
+    /// Returns the number of cache hits since the counters were reset.
+    pub fn hits(&self) -> u32 {
+        self.0.ihit.read().bits()
+    }
+
+    /// Returns the number of cache misses since the counters were reset.
+    pub fn misses(&self) -> u32 {
+        self.0.imiss.read().bits()
+    }
+
+    /// Resets the hit and miss counters.
+    pub fn reset_counters(&mut self) {
+        self.0.ihit.write(|w| unsafe { w.bits(0) });
+        self.0.imiss.write(|w| unsafe { w.bits(0) });
+    }
+}
+
+#[cfg(any(
+    feature = "52820",
+    feature = "52832",
+    feature = "52833",
+    feature = "52840"
+))]
+impl Nvmc {
+    /// Gives access to the instruction cache.
+    pub fn icache(&mut self) -> ICache<'_> {
+        ICache(&mut self.nvmc)
+    }
+}
+
+/// An erase split into steps, started with `Nvmc::start_erase`.
+pub struct Erase {
+    next: usize,
+    end: usize,
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    current: Option<PartialErase>,
+}
+
+impl Erase {
+    /// Erases the next page, or runs the next partial erase slice on the nRF52820, nRF52833 and
+    /// nRF52840, blocking for its duration.
+    ///
+    /// Returns `WouldBlock` until all pages are erased.
+    pub fn step(&mut self, nvmc: &mut Nvmc) -> nb::Result<(), Void> {
+        if self.next >= self.end {
+            return Ok(());
+        }
+
+        #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+        {
+            let next = self.next;
+            let current = self.current.get_or_insert(PartialErase {
+                offset: next,
+                elapsed_ms: 0,
+            });
+            if current.step(nvmc).is_ok() {
+                self.current = None;
+                self.next += PAGE_SIZE;
+            }
+        }
+        #[cfg(not(any(feature = "52820", feature = "52833", feature = "52840")))]
+        {
+            nvmc.erase_page(self.next);
+            self.next += PAGE_SIZE;
+        }
+
+        if self.next >= self.end {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+/// A write split into single word writes, started with `Nvmc::start_write`.
+pub struct Write<'a> {
+    offset: usize,
+    remaining: &'a [u8],
+}
+
+impl Write<'_> {
+    /// Writes the next word once the NVMC is ready for it, without blocking.
+    ///
+    /// Returns `WouldBlock` until all words are written.
+    pub fn step(&mut self, nvmc: &mut Nvmc) -> nb::Result<(), Void> {
+        if self.remaining.is_empty() {
+            return if nvmc.nvmc.ready.read().ready().bit_is_set() {
+                nvmc.enable_read();
+                Ok(())
+            } else {
+                Err(nb::Error::WouldBlock)
+            };
+        }
+        if !nvmc.is_ready_next() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let (word, rest) = self.remaining.split_at(WORD_SIZE);
+        let target = &mut nvmc.storage[self.offset] as *mut u8 as *mut u32;
+        nvmc.enable_write();
+        unsafe { ptr::write_volatile(target, to_word(word)) };
+        self.offset += WORD_SIZE;
+        self.remaining = rest;
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+impl Drop for Write<'_> {
+    /// Makes the flash read-only again, also if the write is abandoned before it has completed.
+    fn drop(&mut self) {
+        let nvmc = unsafe { &*NVMC::ptr() };
+        while !nvmc.ready.read().ready().bit_is_set() {}
+        #[cfg(not(any(feature = "9160", feature = "5340-app")))]
+        nvmc.config.write(|w| w.wen().ren());
+        #[cfg(any(feature = "9160", feature = "5340-app"))]
+        nvmc.configns.write(|w| w.wen().ren());
+    }
+}
+
+/// Updates the CRC-32 (IEEE) `crc` with `bytes`. Start with `!0` and invert the result.
+#[cfg(any(
+    feature = "flash-store",
+    not(any(feature = "9160", feature = "5340-app", feature = "5340-net"))
+))]
+pub(crate) fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn to_word(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Returns `Pending` once, after waking the task, to let other tasks run.
+#[cfg(feature = "async")]
+async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// NVMC error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The offset or length is not aligned to the page size for erasing, or to the word size for
+    /// writing.
+    Unaligned,
+    /// The operation extends beyond the flash region.
+    OutOfBounds,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Unaligned | Error::OutOfBounds => ErrorKind::InvalidArgument,
+        }
+    }
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::Unaligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+        }
+    }
+}
+
+impl ErrorType for Nvmc {
+    type Error = Error;
+}
+
+impl ReadNorFlash for Nvmc {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        let end = offset
+            .checked_add(bytes.len())
+            .filter(|end| *end <= self.storage.len())
+            .ok_or(Error::OutOfBounds)?;
+        bytes.copy_from_slice(&self.storage[offset..end]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+impl NorFlash for Nvmc {
+    const WRITE_SIZE: usize = WORD_SIZE;
+
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let (from, to) = self.check_erase(from, to)?;
+        for offset in (from..to).step_by(PAGE_SIZE) {
+            self.erase_page(offset);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = self.check_write(offset, bytes)?;
+        for (i, word) in bytes.chunks_exact(WORD_SIZE).enumerate() {
+            self.write_word(offset + i * WORD_SIZE, to_word(word));
+        }
+        Ok(())
+    }
+}
+
+// Flash bits can be cleared by repeated writes of a word without erasing it, within the write
+// limits of the product specification.
+impl MultiwriteNorFlash for Nvmc {}
@@ -0,0 +1,377 @@
+//! HAL interface to the PDM peripheral.
+//!
+//! The PDM peripheral clocks one or two digital microphones sharing a data line, and decimates
+//! their pulse density modulated signal into 16 bit PCM samples at 1/64 of the PDM clock, about
+//! 16 kHz with the default clock. EasyDMA writes the samples to RAM; in stereo mode they are
+//! interleaved, left first.
+//!
+//! [`Pdm::capture`] fills a single buffer. For continuous capture, [`Pdm::start`] alternates
+//! between two buffers: while EasyDMA fills one, the other is processed, see [`Stream`].
+//!
+//! With the `async` feature, the stream can also be read from an async task, which is suspended
+//! until the next buffer has been filled:
+//!
+//! ```ignore
+//! let mut stream = pdm.start(first, second).map_err(|(e, _)| e)?;
+//! loop {
+//!     stream.read_next_async(|samples| detector.feed(samples)).await;
+//! }
+//! ```
+//!
+//! See product specification, chapter 44.
+
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::error::ErrorKind;
+use crate::{
+    gpio::{Floating, Input, Output, Pin, PushPull},
+    pac::{pdm, PDM},
+    slice_in_ram,
+};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static WAKER: WakerSlot = WakerSlot::new();
+
+pub use pdm::pdmclkctrl::FREQ_A as Frequency;
+
+/// Maximum length of a buffer, in samples.
+pub const MAX_BUFFER_LEN: usize = (1 << 15) - 1;
+
+/// Gain setting of 0 dB.
+const GAIN_DEFAULT: i32 = 0x28;
+
+/// Gain setting of +20 dB.
+const GAIN_MAX: i32 = 0x50;
+
+/// Microphones to sample.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channels {
+    /// A single microphone outputting its data on the falling edge of CLK, usually selected by
+    /// tying its L/R pin low.
+    Left,
+    /// A single microphone outputting its data on the rising edge of CLK.
+    Right,
+    /// Two microphones, left and right.
+    Stereo,
+}
+
+/// Configuration of the PDM interface.
+pub struct Config {
+    pub frequency: Frequency,
+    pub channels: Channels,
+    /// Gain of the left channel, or of the mono channel, in steps of 0.5 dB from -20 dB to
+    /// +20 dB.
+    pub gain_left: i8,
+    /// Gain of the right channel, in steps of 0.5 dB from -20 dB to +20 dB.
+    pub gain_right: i8,
+}
+
+impl Default for Config {
+    /// A left microphone clocked at 1.032 MHz, giving 16.125 kHz samples, without gain.
+    fn default() -> Self {
+        Config {
+            frequency: Frequency::DEFAULT,
+            channels: Channels::Left,
+            gain_left: 0,
+            gain_right: 0,
+        }
+    }
+}
+
+/// Pins of the PDM interface.
+pub struct Pins {
+    pub clk: Pin<Output<PushPull>>,
+    pub din: Pin<Input<Floating>>,
+}
+
+/// PDM error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A buffer is empty or longer than `MAX_BUFFER_LEN` samples.
+    BufferLength,
+    /// The buffers of a stream differ in length.
+    BufferLengthMismatch,
+    /// EasyDMA can only access data memory.
+    DMABufferNotInDataMemory,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::BufferLength | Error::BufferLengthMismatch => ErrorKind::BufferLength,
+            Error::DMABufferNotInDataMemory => ErrorKind::BufferNotInRam,
+        }
+    }
+}
+
+/// Interface to the PDM peripheral.
+pub struct Pdm {
+    pdm: PDM,
+    pins: Pins,
+}
+
+impl Pdm {
+    pub fn new(pdm: PDM, pins: Pins, config: Config) -> Self {
+        unsafe {
+            pdm.psel.clk.write(|w| w.bits(pins.clk.psel_bits()));
+            pdm.psel.din.write(|w| w.bits(pins.din.psel_bits()));
+        }
+        pdm.pdmclkctrl.write(|w| w.freq().variant(config.frequency));
+        pdm.mode.write(|w| match config.channels {
+            Channels::Left => w.operation().mono().edge().leftfalling(),
+            Channels::Right => w.operation().mono().edge().leftrising(),
+            Channels::Stereo => w.operation().stereo().edge().leftfalling(),
+        });
+        pdm.gainl
+            .write(|w| unsafe { w.gainl().bits(gain_bits(config.gain_left)) });
+        pdm.gainr
+            .write(|w| unsafe { w.gainr().bits(gain_bits(config.gain_right)) });
+
+        pdm.enable.write(|w| w.enable().enabled());
+        Pdm { pdm, pins }
+    }
+
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &pdm::TASKS_START {
+        &self.pdm.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &pdm::TASKS_STOP {
+        &self.pdm.tasks_stop
+    }
+
+    /// Returns reference to the `STARTED` event endpoint for PPI.
+    pub fn event_started(&self) -> &pdm::EVENTS_STARTED {
+        &self.pdm.events_started
+    }
+
+    /// Returns reference to the `STOPPED` event endpoint for PPI.
+    pub fn event_stopped(&self) -> &pdm::EVENTS_STOPPED {
+        &self.pdm.events_stopped
+    }
+
+    /// Returns reference to the `END` event endpoint for PPI.
+    pub fn event_end(&self) -> &pdm::EVENTS_END {
+        &self.pdm.events_end
+    }
+
+    /// Disables the interface, and returns the peripheral and the pins.
+    pub fn free(self) -> (PDM, Pins) {
+        self.pdm.enable.write(|w| w.enable().disabled());
+        (self.pdm, self.pins)
+    }
+
+    /// Fills `buffer` with samples, blocking until it is full.
+    ///
+    /// The microphones are clocked only during the capture. Most of them need a few milliseconds
+    /// to settle, so the first samples should be discarded.
+    pub fn capture(&mut self, buffer: &mut [i16]) -> Result<(), Error> {
+        check(buffer)?;
+
+        let pdm = &self.pdm;
+        pdm.sample
+            .ptr
+            .write(|w| unsafe { w.sampleptr().bits(buffer.as_mut_ptr() as u32) });
+        pdm.sample
+            .maxcnt
+            .write(|w| unsafe { w.buffsize().bits(buffer.len() as u16) });
+        pdm.events_started.reset();
+        pdm.events_end.reset();
+        pdm.events_stopped.reset();
+        compiler_fence(SeqCst);
+        pdm.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        // Once the buffer has been taken over, don't give the peripheral a new one, so it stops
+        // writing at its end.
+        while pdm.events_end.read().bits() == 0 {}
+        self.stop();
+        Ok(())
+    }
+
+    /// Starts capturing continuously into two buffers of the same length, alternately.
+    pub fn start(
+        self,
+        first: &'static mut [i16],
+        second: &'static mut [i16],
+    ) -> Result<Stream, (Error, Self)> {
+        let checked = check(first).and_then(|_| check(second)).and_then(|_| {
+            if first.len() == second.len() {
+                Ok(())
+            } else {
+                Err(Error::BufferLengthMismatch)
+            }
+        });
+        if let Err(e) = checked {
+            return Err((e, self));
+        }
+
+        let pdm = &self.pdm;
+        let buffers = Buffers::new(first, second);
+        pdm.sample
+            .ptr
+            .write(|w| unsafe { w.sampleptr().bits(buffers.ptr(0)) });
+        pdm.sample
+            .maxcnt
+            .write(|w| unsafe { w.buffsize().bits(buffers.buffers[0].len() as u16) });
+        pdm.events_started.reset();
+        pdm.events_end.reset();
+        pdm.events_stopped.reset();
+        compiler_fence(SeqCst);
+        pdm.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        // The first pointer is taken over right away, so the second one can be queued.
+        while pdm.events_started.read().bits() == 0 {}
+        pdm.events_started.reset();
+        pdm.sample
+            .ptr
+            .write(|w| unsafe { w.sampleptr().bits(buffers.ptr(1)) });
+        Ok(Stream { pdm: self, buffers })
+    }
+
+    /// Stops capturing and waits until the peripheral has stopped.
+    fn stop(&mut self) {
+        let pdm = &self.pdm;
+        pdm.intenclr.write(|w| unsafe { w.bits(!0) });
+        pdm.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while pdm.events_stopped.read().bits() == 0 {}
+        pdm.events_started.reset();
+        pdm.events_end.reset();
+        pdm.events_stopped.reset();
+        compiler_fence(SeqCst);
+    }
+}
+
+/// Two buffers filled alternately by EasyDMA.
+struct Buffers {
+    buffers: [&'static mut [i16]; 2],
+    /// Index of the buffer whose pointer has been written last.
+    queued: usize,
+}
+
+impl Buffers {
+    fn new(first: &'static mut [i16], second: &'static mut [i16]) -> Self {
+        Buffers {
+            buffers: [first, second],
+            queued: 1,
+        }
+    }
+
+    fn ptr(&self, index: usize) -> u32 {
+        self.buffers[index].as_ptr() as u32
+    }
+
+    /// Returns the buffer EasyDMA has filled, after it has taken over the queued one. Its
+    /// pointer is the next to be queued.
+    fn next(&mut self) -> &[i16] {
+        self.queued = 1 - self.queued;
+        compiler_fence(SeqCst);
+        &*self.buffers[self.queued]
+    }
+
+    fn into_inner(self) -> [&'static mut [i16]; 2] {
+        self.buffers
+    }
+}
+
+/// A continuous capture, started with `Pdm::start`.
+pub struct Stream {
+    pdm: Pdm,
+    buffers: Buffers,
+}
+
+impl Stream {
+    /// Passes the buffer that has just been filled to `read`, and queues it after the one being
+    /// filled now.
+    ///
+    /// Returns `WouldBlock` until the peripheral has started filling the previously queued
+    /// buffer. `read` must return before that buffer is full, or samples are lost.
+    pub fn read_next(&mut self, read: impl FnOnce(&[i16])) -> nb::Result<(), void::Void> {
+        let pdm = &self.pdm.pdm;
+        if pdm.events_started.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        pdm.events_started.reset();
+        pdm.events_end.reset();
+
+        read(self.buffers.next());
+        let ptr = self.buffers.ptr(self.buffers.queued);
+        compiler_fence(SeqCst);
+        pdm.sample.ptr.write(|w| unsafe { w.sampleptr().bits(ptr) });
+        Ok(())
+    }
+
+    /// Enables the `PDM` interrupt when a buffer has been filled.
+    pub fn enable_interrupt(&mut self) {
+        self.pdm.pdm.intenset.write(|w| w.started().set());
+    }
+
+    /// Disables the `PDM` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.pdm.pdm.intenclr.write(|w| w.started().clear());
+    }
+
+    /// Waits until a buffer has been filled, then passes it to `read` and queues it like
+    /// `read_next`.
+    ///
+    /// The `PDM` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn read_next_async(&mut self, read: impl FnOnce(&[i16])) {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if self.pdm.pdm.events_started.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.enable_interrupt();
+                Poll::Pending
+            }
+        })
+        .await;
+        self.read_next(read).ok();
+    }
+
+    /// Stops capturing, and returns the interface and the buffers.
+    pub fn stop(mut self) -> (Pdm, [&'static mut [i16]; 2]) {
+        self.pdm.stop();
+        (self.pdm, self.buffers.into_inner())
+    }
+}
+
+/// Handles the PDM events for the async methods of the stream.
+///
+/// Must be called from the `PDM` interrupt handler.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let pdm = unsafe { &*PDM::ptr() };
+    if pdm.events_started.read().bits() != 0 {
+        // Leave the event set for the stream to observe, but keep it from firing again.
+        pdm.intenclr.write(|w| w.started().clear());
+        WAKER.wake();
+    }
+}
+
+/// Checks that the buffer can be used by EasyDMA.
+fn check(buffer: &[i16]) -> Result<(), Error> {
+    if buffer.is_empty() || buffer.len() > MAX_BUFFER_LEN {
+        return Err(Error::BufferLength);
+    }
+    let bytes =
+        unsafe { core::slice::from_raw_parts(buffer.as_ptr() as *const u8, buffer.len() * 2) };
+    if !slice_in_ram(bytes) {
+        return Err(Error::DMABufferNotInDataMemory);
+    }
+    Ok(())
+}
+
+/// Converts a gain in steps of 0.5 dB to its register value.
+fn gain_bits(gain: i8) -> u8 {
+    (GAIN_DEFAULT + gain as i32).clamp(0, GAIN_MAX) as u8
+}
@@ -0,0 +1,812 @@
+//! HAL interface to the POWER peripheral.
+//!
+//! See nRF52840 product specification, chapter 5.3.
+
+use crate::pac::POWER;
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+use crate::clocks::{Clocks, HfclkRequest};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static POFWARN_WAKER: WakerSlot = WakerSlot::new();
+
+#[cfg(all(
+    feature = "async",
+    any(feature = "52820", feature = "52833", feature = "52840")
+))]
+static VBUS_WAKER: WakerSlot = WakerSlot::new();
+
+/// Interface to the POWER peripheral.
+pub struct Power(POWER);
+
+impl Power {
+    pub fn new(power: POWER) -> Self {
+        Self(power)
+    }
+
+    /// Returns the raw `POWER` peripheral.
+    pub fn free(self) -> POWER {
+        self.0
+    }
+
+    /// Returns the causes of the last resets.
+    ///
+    /// The reasons accumulate over resets until cleared with `clear_reset_reason`. No reason is
+    /// set after a power-on or brown-out reset.
+    pub fn reset_reason(&self) -> ResetReason {
+        let bits = self.0.resetreas.read().bits();
+        let bit = |n: u32| bits & (1 << n) != 0;
+        ResetReason {
+            pin: bit(0),
+            watchdog: bit(1),
+            soft: bit(2),
+            lockup: bit(3),
+            off: bit(16),
+            lpcomp: bit(17),
+            debug: bit(18),
+            nfc: bit(19),
+            vbus: bit(20),
+        }
+    }
+
+    /// Clears the reset reasons, so the next call to `reset_reason` only reports new resets.
+    pub fn clear_reset_reason(&mut self) {
+        let bits = self.0.resetreas.read().bits();
+        self.0.resetreas.write(|w| unsafe { w.bits(bits) });
+    }
+
+    /// Switches the main regulator (REG1 on nRF52833/nRF52840) from LDO to DC/DC mode, reducing
+    /// the current drawn by the CPU and the radio.
+    ///
+    /// The external LC filter of the DC/DC converter must be populated. Enabling the converter on
+    /// a board without it stops the chip from working.
+    pub fn enable_dcdc(&mut self) {
+        self.0.dcdcen.write(|w| w.dcdcen().enabled());
+    }
+
+    /// Switches the main regulator (REG1 on nRF52833/nRF52840) back to LDO mode.
+    pub fn disable_dcdc(&mut self) {
+        self.0.dcdcen.write(|w| w.dcdcen().disabled());
+    }
+
+    /// Switches the high voltage regulator REG0 from LDO to DC/DC mode.
+    ///
+    /// REG0 is only used in high voltage mode, when the chip is supplied through VDDH. Its
+    /// external LC filter must be populated.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn enable_dcdc0(&mut self) {
+        self.0.dcdcen0.write(|w| w.dcdcen().enabled());
+    }
+
+    /// Switches REG0 back to LDO mode.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn disable_dcdc0(&mut self) {
+        self.0.dcdcen0.write(|w| w.dcdcen().disabled());
+    }
+
+    /// Returns `true` if the chip is supplied through VDDH, with REG0 in use.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn is_high_voltage_mode(&self) -> bool {
+        self.0.mainregstatus.read().mainregstatus().is_high()
+    }
+}
+
+/// Sub power mode used while the CPU sleeps in System ON.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SleepMode {
+    /// Resources are powered down while unused, at the expense of a longer wake up (default).
+    LowPower,
+    /// Resources are kept ready for a short, constant wake up latency, at a higher idle current.
+    ConstantLatency,
+}
+
+impl Power {
+    /// Selects the sub power mode used while the CPU sleeps.
+    ///
+    /// With a SoftDevice enabled, use the SoftDevice API instead, which owns the POWER peripheral.
+    pub fn set_sleep_mode(&mut self, mode: SleepMode) {
+        match mode {
+            SleepMode::LowPower => self.0.tasks_lowpwr.write(|w| unsafe { w.bits(1) }),
+            SleepMode::ConstantLatency => self.0.tasks_constlat.write(|w| unsafe { w.bits(1) }),
+        }
+    }
+
+    /// Enters System OFF, the deepest power saving mode.
+    ///
+    /// Only the configured wake up sources, like a GPIO `DETECT` signal or LPCOMP, are kept
+    /// running. Waking up resets the chip, see `reset_reason`. While a debugger is attached,
+    /// System OFF is emulated and the CPU keeps spinning here.
+    pub fn system_off(&mut self) -> ! {
+        self.0.systemoff.write(|w| w.systemoff().enter());
+        loop {
+            cortex_m::asm::wfe();
+        }
+    }
+}
+
+/// Sleeps until an event, then clears the event register.
+///
+/// A plain `WFE` returns immediately if the event register is set, e.g. by an interrupt that has
+/// already been handled, so idle loops built on it may spin. This sleeps with `WFE` and then
+/// clears the event register with `SEV` and `WFE`, so the next call really sleeps.
+///
+/// Pending but disabled interrupts only wake the CPU if `SEVONPEND` is set in the `SCB`. Only CPU
+/// instructions are used, so this is safe to use with a SoftDevice enabled.
+pub fn wait_for_event() {
+    cortex_m::asm::wfe();
+    clear_event_register();
+}
+
+/// Sleeps until an enabled interrupt is pending.
+pub fn wait_for_interrupt() {
+    cortex_m::asm::wfi();
+}
+
+/// Clears the event register of the CPU without sleeping.
+pub fn clear_event_register() {
+    cortex_m::asm::sev();
+    cortex_m::asm::wfe();
+}
+
+/// Sleeps in System ON until `condition` returns `true`.
+///
+/// `condition` is checked before each sleep, with interrupts disabled, so a wake up from an
+/// interrupt changing it cannot be missed. Interrupts are left enabled or disabled as they were
+/// on entry.
+pub fn sleep_until<F>(mut condition: F)
+where
+    F: FnMut() -> bool,
+{
+    let primask = cortex_m::register::primask::read();
+    loop {
+        cortex_m::interrupt::disable();
+        if condition() {
+            break;
+        }
+        // A pending interrupt wakes the CPU even while interrupts are disabled, and is handled
+        // once they are enabled again.
+        cortex_m::asm::wfi();
+        if primask.is_active() {
+            unsafe { cortex_m::interrupt::enable() };
+        }
+    }
+    if primask.is_active() {
+        unsafe { cortex_m::interrupt::enable() };
+    }
+}
+
+/// A general purpose retention register, kept across all resets except power-on and brown-out.
+///
+/// These registers are the usual mailbox between an application and its bootloader.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Gpregret {
+    Gpregret,
+    #[cfg(not(feature = "51"))]
+    Gpregret2,
+}
+
+/// `GPREGRET` value requesting the Nordic nRF5 SDK bootloader to enter DFU mode.
+pub const GPREGRET_DFU_START: u8 = 0xB1;
+
+/// `GPREGRET2` value requesting the Nordic nRF5 SDK bootloader to skip the CRC check of the
+/// application.
+#[cfg(not(feature = "51"))]
+pub const GPREGRET2_SKIP_CRC: u8 = 0xA9;
+
+impl Power {
+    /// Reads a general purpose retention register.
+    pub fn gpregret(&self, reg: Gpregret) -> u8 {
+        match reg {
+            Gpregret::Gpregret => self.0.gpregret.read().bits() as u8,
+            #[cfg(not(feature = "51"))]
+            Gpregret::Gpregret2 => self.0.gpregret2.read().bits() as u8,
+        }
+    }
+
+    /// Writes a general purpose retention register.
+    pub fn set_gpregret(&mut self, reg: Gpregret, value: u8) {
+        match reg {
+            Gpregret::Gpregret => self.0.gpregret.write(|w| unsafe { w.bits(value as u32) }),
+            #[cfg(not(feature = "51"))]
+            Gpregret::Gpregret2 => self.0.gpregret2.write(|w| unsafe { w.bits(value as u32) }),
+        }
+    }
+
+    /// Sets the bits of `mask` in a general purpose retention register.
+    pub fn set_gpregret_bits(&mut self, reg: Gpregret, mask: u8) {
+        let value = self.gpregret(reg) | mask;
+        self.set_gpregret(reg, value);
+    }
+
+    /// Clears the bits of `mask` in a general purpose retention register.
+    pub fn clear_gpregret_bits(&mut self, reg: Gpregret, mask: u8) {
+        let value = self.gpregret(reg) & !mask;
+        self.set_gpregret(reg, value);
+    }
+
+    /// Requests the bootloader to enter DFU mode after the next reset, using the convention of
+    /// the Nordic nRF5 SDK bootloader.
+    pub fn request_dfu(&mut self) {
+        self.set_gpregret(Gpregret::Gpregret, GPREGRET_DFU_START);
+    }
+
+    /// Returns `true` if DFU mode has been requested with `request_dfu`.
+    pub fn is_dfu_requested(&self) -> bool {
+        self.gpregret(Gpregret::Gpregret) == GPREGRET_DFU_START
+    }
+}
+
+/// Causes of a reset, as returned by `Power::reset_reason`.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResetReason {
+    /// Reset from the reset pin.
+    pub pin: bool,
+    /// Reset from the watchdog.
+    pub watchdog: bool,
+    /// Soft reset, e.g. from `SCB::sys_reset`.
+    pub soft: bool,
+    /// Reset from a CPU lock-up.
+    pub lockup: bool,
+    /// Wake up from System OFF by a GPIO `DETECT` signal.
+    pub off: bool,
+    /// Wake up from System OFF by LPCOMP.
+    pub lpcomp: bool,
+    /// Wake up from System OFF by entering debug interface mode.
+    pub debug: bool,
+    /// Wake up from System OFF by an NFC field.
+    pub nfc: bool,
+    /// Wake up from System OFF by VBUS rising into the valid range.
+    pub vbus: bool,
+}
+
+impl ResetReason {
+    /// Returns `true` if no reason is set, i.e. the chip has been reset by power-on or brown-out.
+    pub fn is_power_on(&self) -> bool {
+        *self == ResetReason::default()
+    }
+}
+
+/// Supply voltage below which the power-fail comparator generates the `POFWARN` event.
+#[cfg(not(feature = "51"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PofThreshold {
+    V17 = 4,
+    V18 = 5,
+    V19 = 6,
+    V20 = 7,
+    V21 = 8,
+    V22 = 9,
+    V23 = 10,
+    V24 = 11,
+    V25 = 12,
+    V26 = 13,
+    V27 = 14,
+    V28 = 15,
+}
+
+/// Supply voltage below which the power-fail comparator generates the `POFWARN` event.
+#[cfg(feature = "51")]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PofThreshold {
+    V21 = 0,
+    V23 = 1,
+    V25 = 2,
+    V27 = 3,
+}
+
+/// VDDH voltage below which the power-fail comparator generates the `POFWARN` event, in high
+/// voltage mode.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PofThresholdVddh {
+    V27 = 0,
+    V28 = 1,
+    V29 = 2,
+    V30 = 3,
+    V31 = 4,
+    V32 = 5,
+    V33 = 6,
+    V34 = 7,
+    V35 = 8,
+    V36 = 9,
+    V37 = 10,
+    V38 = 11,
+    V39 = 12,
+    V40 = 13,
+    V41 = 14,
+    V42 = 15,
+}
+
+impl Power {
+    /// Enables the power-fail comparator, warning when the supply drops below `threshold`.
+    ///
+    /// The warning leaves time to finish flash writes and park the application safely before
+    /// the brown-out reset.
+    pub fn enable_pof(&mut self, threshold: PofThreshold) {
+        self.0.pofcon.modify(|r, w| unsafe {
+            w.bits((r.bits() & !0b1_1111) | ((threshold as u32) << 1) | 1)
+        });
+    }
+
+    /// Sets the VDDH threshold of the power-fail comparator, used in high voltage mode.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn set_pof_threshold_vddh(&mut self, threshold: PofThresholdVddh) {
+        self.0.pofcon.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b1111 << 8)) | ((threshold as u32) << 8))
+        });
+    }
+
+    /// Disables the power-fail comparator.
+    pub fn disable_pof(&mut self) {
+        self.0
+            .pofcon
+            .modify(|r, w| unsafe { w.bits(r.bits() & !1) });
+    }
+
+    /// Returns `true` and clears the `POFWARN` event if the supply has dropped below the
+    /// threshold.
+    pub fn is_pof_warning(&mut self) -> bool {
+        if self.0.events_pofwarn.read().bits() != 0 {
+            self.0.events_pofwarn.reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enables the `POWER_CLOCK` interrupt on the `POFWARN` event.
+    pub fn enable_pof_interrupt(&mut self) {
+        self.0.intenset.write(|w| w.pofwarn().set());
+    }
+
+    /// Disables the `POWER_CLOCK` interrupt on the `POFWARN` event.
+    pub fn disable_pof_interrupt(&mut self) {
+        self.0.intenclr.write(|w| w.pofwarn().clear());
+    }
+
+    /// Waits until the supply drops below the threshold of the power-fail comparator.
+    ///
+    /// The `POWER_CLOCK` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn wait_pof_warning(&mut self) {
+        poll_fn(|cx| {
+            POFWARN_WAKER.register(cx.waker());
+            if self.is_pof_warning() {
+                Poll::Ready(())
+            } else {
+                self.enable_pof_interrupt();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Handles the POWER events for the async methods of `Power`.
+///
+/// Must be called from the `POWER_CLOCK` interrupt handler, along with
+/// [`clocks::on_interrupt`](crate::clocks::on_interrupt).
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let power = unsafe { &*POWER::ptr() };
+    if power.events_pofwarn.read().bits() != 0 {
+        // Leave the event set for the future to observe, but keep it from firing again.
+        power.intenclr.write(|w| w.pofwarn().clear());
+        POFWARN_WAKER.wake();
+    }
+
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    if power.events_usbdetected.read().bits() != 0
+        || power.events_usbremoved.read().bits() != 0
+        || power.events_usbpwrrdy.read().bits() != 0
+    {
+        power.intenclr.write(|w| {
+            w.usbdetected()
+                .clear()
+                .usbremoved()
+                .clear()
+                .usbpwrrdy()
+                .clear()
+        });
+        VBUS_WAKER.wake();
+    }
+}
+
+/// Start of the data RAM.
+#[cfg(not(feature = "51"))]
+const RAM_START: usize = 0x2000_0000;
+
+/// Size of the 4 KiB RAM sections in blocks 0 to 7.
+#[cfg(not(feature = "51"))]
+const SMALL_SECTION: usize = 0x1000;
+
+/// Size of the 32 KiB RAM sections in block 8.
+#[cfg(not(feature = "51"))]
+const LARGE_SECTION: usize = 0x8000;
+
+/// Returns the RAM block and section containing `addr`, or `None` if `addr` is not in the data
+/// RAM.
+#[cfg(not(feature = "51"))]
+fn ram_section(addr: usize) -> Option<(usize, u32)> {
+    let offset = addr.checked_sub(RAM_START)?;
+    if offset < 16 * SMALL_SECTION {
+        Some((
+            offset / (2 * SMALL_SECTION),
+            (offset / SMALL_SECTION % 2) as u32,
+        ))
+    } else {
+        let section = (offset - 16 * SMALL_SECTION) / LARGE_SECTION;
+        if section < 16 {
+            Some((8, section as u32))
+        } else {
+            None
+        }
+    }
+}
+
+/// Power state of the sections of a RAM block.
+#[cfg(not(feature = "51"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RamPower {
+    /// Sections powered in System ON, one bit per section.
+    pub on: u16,
+    /// Sections retained in System OFF, one bit per section.
+    pub retained: u16,
+}
+
+#[cfg(not(feature = "51"))]
+impl Power {
+    /// Returns the power state of RAM block `block`, or `None` if the chip has no such block.
+    pub fn ram_power(&self, block: usize) -> Option<RamPower> {
+        let bits = self.0.ram.get(block)?.power.read().bits();
+        Some(RamPower {
+            on: bits as u16,
+            retained: (bits >> 16) as u16,
+        })
+    }
+
+    /// Keeps the sections of `block` set in `sections` powered in System OFF.
+    ///
+    /// Returns `false` without changing anything if the chip has no such block.
+    pub fn retain_ram(&mut self, block: usize, sections: u16) -> bool {
+        match self.0.ram.get(block) {
+            Some(ram) => {
+                ram.powerset
+                    .write(|w| unsafe { w.bits((sections as u32) << 16) });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lets the sections of `block` set in `sections` lose their content in System OFF.
+    ///
+    /// Returns `false` without changing anything if the chip has no such block.
+    pub fn release_ram(&mut self, block: usize, sections: u16) -> bool {
+        match self.0.ram.get(block) {
+            Some(ram) => {
+                ram.powerclr
+                    .write(|w| unsafe { w.bits((sections as u32) << 16) });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Powers the sections of `block` set in `sections` in System ON.
+    ///
+    /// Returns `false` without changing anything if the chip has no such block.
+    pub fn power_on_ram(&mut self, block: usize, sections: u16) -> bool {
+        match self.0.ram.get(block) {
+            Some(ram) => {
+                ram.powerset.write(|w| unsafe { w.bits(sections as u32) });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Powers down the sections of `block` set in `sections` in System ON, losing their content.
+    ///
+    /// Returns `false` without changing anything if the chip has no such block.
+    ///
+    /// Unused RAM can be powered down to reduce the idle current. Powering down RAM in use by the
+    /// stack or by statics is undefined behavior.
+    pub unsafe fn power_off_ram(&mut self, block: usize, sections: u16) -> bool {
+        match self.0.ram.get(block) {
+            Some(ram) => {
+                ram.powerclr.write(|w| w.bits(sections as u32));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Retains all RAM sections overlapping `value` in System OFF, typically a static declared
+    /// with [`retained!`](crate::retained).
+    ///
+    /// Returns `false` without retaining anything if `value` is not in the data RAM of the chip.
+    pub fn retain<T>(&mut self, value: *const T) -> bool {
+        let start = value as usize;
+        let sections = start
+            .checked_add(core::mem::size_of::<T>().max(1) - 1)
+            .and_then(|end| Some((ram_section(start)?, ram_section(end)?)));
+        let ((first_block, first_section), (last_block, last_section)) = match sections {
+            Some(sections) => sections,
+            None => return false,
+        };
+        if last_block >= self.0.ram.len() {
+            return false;
+        }
+
+        for block in first_block..=last_block {
+            let from = if block == first_block {
+                first_section
+            } else {
+                0
+            };
+            let to = if block == last_block {
+                last_section
+            } else {
+                15
+            };
+            let sections = ((1u32 << (to + 1)) - 1) & !((1u32 << from) - 1);
+            self.retain_ram(block, sections as u16);
+        }
+        true
+    }
+}
+
+/// Declares a `static mut` of type `MaybeUninit<$ty>` that is neither zeroed nor initialized at
+/// boot, so it keeps its value across System OFF, as long as its RAM is retained with
+/// `Power::retain`, and across soft resets.
+///
+/// The static is placed in the `.uninit` section of `cortex-m-rt`. Its content is garbage after a
+/// power-on reset, so it should carry a magic value or checksum to detect a warm boot.
+///
+/// ```ignore
+/// nrf52840_hal::retained! {
+///     static mut STATE: State;
+/// }
+///
+/// power.retain(unsafe { STATE.as_ptr() });
+/// ```
+#[macro_export]
+macro_rules! retained {
+    ($(#[$attr:meta])* $vis:vis static mut $name:ident: $ty:ty;) => {
+        $(#[$attr])*
+        #[link_section = ".uninit.retained"]
+        $vis static mut $name: core::mem::MaybeUninit<$ty> = core::mem::MaybeUninit::uninit();
+    };
+}
+
+/// State of the USB supply VBUS.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VbusState {
+    /// VBUS is below the detection threshold.
+    Absent,
+    /// VBUS is present, the USB regulator is starting.
+    Present,
+    /// VBUS is present and the USB regulator output is ready.
+    Ready,
+}
+
+/// A VBUS event.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VbusEvent {
+    /// `USBDETECTED`: VBUS has risen into the valid range.
+    Detected,
+    /// `USBPWRRDY`: the USB regulator output is ready.
+    PowerReady,
+    /// `USBREMOVED`: VBUS has dropped below the valid range.
+    Removed,
+}
+
+/// VBUS detection, e.g. to detect a charger without using USB.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+impl Power {
+    /// Returns the current VBUS state.
+    pub fn vbus_state(&self) -> VbusState {
+        let status = self.0.usbregstatus.read();
+        if !status.vbusdetect().is_vbus_present() {
+            VbusState::Absent
+        } else if status.outputrdy().is_ready() {
+            VbusState::Ready
+        } else {
+            VbusState::Present
+        }
+    }
+
+    /// Returns and clears the oldest pending VBUS event, if any.
+    ///
+    /// Events are checked in their natural order: detected, power ready, removed.
+    pub fn vbus_event(&mut self) -> Option<VbusEvent> {
+        let regs = &self.0;
+        if regs.events_usbdetected.read().bits() != 0 {
+            regs.events_usbdetected.reset();
+            Some(VbusEvent::Detected)
+        } else if regs.events_usbpwrrdy.read().bits() != 0 {
+            regs.events_usbpwrrdy.reset();
+            Some(VbusEvent::PowerReady)
+        } else if regs.events_usbremoved.read().bits() != 0 {
+            regs.events_usbremoved.reset();
+            Some(VbusEvent::Removed)
+        } else {
+            None
+        }
+    }
+
+    /// Enables the `POWER_CLOCK` interrupt on the VBUS events.
+    pub fn enable_vbus_interrupts(&mut self) {
+        self.0
+            .intenset
+            .write(|w| w.usbdetected().set().usbpwrrdy().set().usbremoved().set());
+    }
+
+    /// Disables the `POWER_CLOCK` interrupt on the VBUS events.
+    pub fn disable_vbus_interrupts(&mut self) {
+        self.0.intenclr.write(|w| {
+            w.usbdetected()
+                .clear()
+                .usbpwrrdy()
+                .clear()
+                .usbremoved()
+                .clear()
+        });
+    }
+
+    /// Waits for the next VBUS event.
+    ///
+    /// The `POWER_CLOCK` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn wait_vbus_event(&mut self) -> VbusEvent {
+        poll_fn(|cx| {
+            VBUS_WAKER.register(cx.waker());
+            match self.vbus_event() {
+                Some(event) => Poll::Ready(event),
+                None => {
+                    self.enable_vbus_interrupts();
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+/// An event of the USB power sequence, reported by `UsbPower::poll`.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsbPowerEvent {
+    /// VBUS has been detected. The USBD peripheral should be enabled now, so it can start its
+    /// own power up while the USB regulator settles.
+    Detected,
+    /// The USB regulator and the high frequency crystal are ready. The USBD pull-up can be
+    /// enabled to attach to the bus.
+    Ready,
+    /// VBUS has been removed. The USBD peripheral should be disabled.
+    Removed,
+}
+
+/// State of the USB power supply.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsbPowerState {
+    /// No VBUS.
+    Disconnected,
+    /// VBUS is present, waiting for the USB regulator and the crystal.
+    PoweringUp,
+    /// The USB regulator and the crystal are running.
+    Ready,
+}
+
+/// Sequences the USB regulator and the high frequency crystal on VBUS events.
+///
+/// USB requires the crystal, which is requested as soon as VBUS is detected and released when it
+/// is removed. The events returned by `poll` tell the USBD driver when to enable the peripheral
+/// and when to attach to the bus, in the order required by the hardware.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub struct UsbPower {
+    state: UsbPowerState,
+    hfclk: Option<HfclkRequest>,
+}
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+impl UsbPower {
+    /// Creates the state machine in the `Disconnected` state.
+    ///
+    /// If VBUS is already present, e.g. because the device has been powered from USB, the first
+    /// call to `poll` reports it as `Detected`.
+    pub fn new() -> Self {
+        UsbPower {
+            state: UsbPowerState::Disconnected,
+            hfclk: None,
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> UsbPowerState {
+        self.state
+    }
+
+    /// Enables the `POWER_CLOCK` interrupt on the USB power events.
+    pub fn enable_interrupts(&mut self, power: &mut Power) {
+        power.enable_vbus_interrupts();
+    }
+
+    /// Disables the `POWER_CLOCK` interrupt on the USB power events.
+    pub fn disable_interrupts(&mut self, power: &mut Power) {
+        power.disable_vbus_interrupts();
+    }
+
+    /// Handles the pending USB power events and advances the state machine.
+    ///
+    /// Call this from the `POWER_CLOCK` interrupt handler, or poll it from the main loop, until
+    /// it returns `None`. Once the regulator is ready, this blocks until the crystal is running,
+    /// which takes less than a millisecond.
+    pub fn poll<H, L, LSTAT>(
+        &mut self,
+        power: &mut Power,
+        clocks: &Clocks<H, L, LSTAT>,
+    ) -> Option<UsbPowerEvent> {
+        let regs = &power.0;
+        let detected = regs.events_usbdetected.read().bits() != 0;
+        let removed = regs.events_usbremoved.read().bits() != 0;
+        regs.events_usbdetected.reset();
+        regs.events_usbremoved.reset();
+        let status = regs.usbregstatus.read();
+        let vbus = status.vbusdetect().is_vbus_present();
+
+        match self.state {
+            UsbPowerState::PoweringUp | UsbPowerState::Ready if removed || !vbus => {
+                self.state = UsbPowerState::Disconnected;
+                self.hfclk = None;
+                Some(UsbPowerEvent::Removed)
+            }
+            UsbPowerState::Disconnected if detected || vbus => {
+                self.state = UsbPowerState::PoweringUp;
+                self.hfclk = Some(clocks.request_hfclk());
+                Some(UsbPowerEvent::Detected)
+            }
+            UsbPowerState::PoweringUp if status.outputrdy().is_ready() => {
+                regs.events_usbpwrrdy.reset();
+                if let Some(hfclk) = &self.hfclk {
+                    hfclk.wait();
+                }
+                self.state = UsbPowerState::Ready;
+                Some(UsbPowerEvent::Ready)
+            }
+            _ => {
+                regs.events_usbpwrrdy.reset();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+impl Default for UsbPower {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -8,6 +8,11 @@
 //!
 //! On nRF52 devices, there is also a fork task endpoint, where the user can configure one more task
 //! to be triggered by the same event, even fixed PPI channels have a configurable fork task.
+//!
+//! Endpoints are only accepted as references to the task and event registers of the peripherals,
+//! which are implemented for the `Task` and `Event` traits respectively. The drivers hand them out
+//! through their `task_*` and `event_*` methods, e.g. `Timer::event_compare` or
+//! `Saadc::task_sample`, so a task can't be connected where an event is expected and vice versa.
 
 use crate::pac::PPI;
 use cfg_if::cfg_if;
@@ -16,9 +21,15 @@ cfg_if! {
     if #[cfg(feature = "51")] {
         mod event_nrf51;
         mod task_nrf51;
+    } else if #[cfg(feature = "52805")] {
+        mod event_nrf52805;
+        mod task_nrf52805;
     } else if #[cfg(feature = "52810")] {
         mod event_nrf52810;
         mod task_nrf52810;
+    } else if #[cfg(feature = "52820")] {
+        mod event_nrf52820;
+        mod task_nrf52820;
     } else if #[cfg(feature = "52832")] {
         mod event_nrf52832;
         mod task_nrf52832;
@@ -52,8 +63,15 @@ mod sealed {
     }
 
     pub trait NotFixed {}
+
+    pub trait Group {
+        const GRP: usize;
+    }
 }
-use sealed::{Channel, Event, NotFixed, Task};
+use sealed::{Channel, Event, Group, NotFixed, Task};
+
+impl Task for crate::pac::ppi::tasks_chg::EN {}
+impl Task for crate::pac::ppi::tasks_chg::DIS {}
 
 pub struct TaskAddr(pub(crate) u32);
 pub struct EventAddr(pub(crate) u32);
@@ -67,9 +85,16 @@ pub trait Ppi {
     fn disable(&mut self);
 
     #[cfg(not(feature = "51"))]
-    /// Sets the fork task that must be triggered when the configured event occurs. The user must
-    /// provide a reference to the task.
+    /// Sets the fork task that must be triggered when the configured event occurs, in addition to
+    /// the channel's main task. The user must provide a reference to the task.
+    ///
+    /// This is available for fixed channels too, e.g. to clear a TIMER on the same event that
+    /// captures it.
     fn set_fork_task_endpoint<T: Task>(&mut self, task: &T);
+
+    #[cfg(not(feature = "51"))]
+    /// Removes the fork task, so only the channel's main task is triggered by the event.
+    fn clear_fork_task_endpoint(&mut self);
 }
 
 /// Traits that extends the [Ppi](trait.Ppi.html) trait, marking a channel as fully configurable.
@@ -106,6 +131,13 @@ impl<P: Channel> Ppi for P {
             .tep
             .write(|w| unsafe { w.bits(task.task_addr().0) });
     }
+
+    #[cfg(not(feature = "51"))]
+    #[inline(always)]
+    fn clear_fork_task_endpoint(&mut self) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.fork[P::CH].tep.write(|w| unsafe { w.bits(0) });
+    }
 }
 
 // All unsafe `ptr` calls only uses registers atomically, and only changes the resources owned by
@@ -128,6 +160,138 @@ impl<P: Channel + NotFixed> ConfigurablePpi for P {
     }
 }
 
+/// Trait to represent a PPI channel group.
+///
+/// The channels of a group can be enabled and disabled all at once, either from software or
+/// through the group's tasks, which can themselves be triggered by PPI. This allows an event to
+/// switch between whole sets of connections.
+pub trait PpiChannelGroup {
+    /// Adds a channel to the group.
+    fn add_channel<C: Ppi + Channel>(&mut self, channel: &C);
+
+    /// Removes a channel from the group.
+    fn remove_channel<C: Ppi + Channel>(&mut self, channel: &C);
+
+    /// Removes all channels from the group.
+    fn clear(&mut self);
+
+    /// Enables all channels of the group.
+    fn enable(&mut self);
+
+    /// Disables all channels of the group.
+    fn disable(&mut self);
+
+    /// Returns reference to the `CHG[n].EN` task endpoint for PPI.
+    fn task_enable(&self) -> &crate::pac::ppi::tasks_chg::EN;
+
+    /// Returns reference to the `CHG[n].DIS` task endpoint for PPI.
+    fn task_disable(&self) -> &crate::pac::ppi::tasks_chg::DIS;
+}
+
+// All unsafe `ptr` calls only uses registers atomically, and only changes the resources owned by
+// the type (guaranteed by the abstraction).
+impl<G: Group> PpiChannelGroup for G {
+    #[inline(always)]
+    fn add_channel<C: Ppi + Channel>(&mut self, _channel: &C) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chg[G::GRP].modify(|r, w| unsafe { w.bits(r.bits() | 1 << C::CH) });
+    }
+
+    #[inline(always)]
+    fn remove_channel<C: Ppi + Channel>(&mut self, _channel: &C) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chg[G::GRP].modify(|r, w| unsafe { w.bits(r.bits() & !(1 << C::CH)) });
+    }
+
+    #[inline(always)]
+    fn clear(&mut self) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.chg[G::GRP].write(|w| unsafe { w.bits(0) });
+    }
+
+    #[inline(always)]
+    fn enable(&mut self) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.tasks_chg[G::GRP].en.write(|w| unsafe { w.bits(1) });
+    }
+
+    #[inline(always)]
+    fn disable(&mut self) {
+        let regs = unsafe { &*PPI::ptr() };
+        regs.tasks_chg[G::GRP].dis.write(|w| unsafe { w.bits(1) });
+    }
+
+    #[inline(always)]
+    fn task_enable(&self) -> &crate::pac::ppi::tasks_chg::EN {
+        let regs = unsafe { &*PPI::ptr() };
+        &regs.tasks_chg[G::GRP].en
+    }
+
+    #[inline(always)]
+    fn task_disable(&self) -> &crate::pac::ppi::tasks_chg::DIS {
+        let regs = unsafe { &*PPI::ptr() };
+        &regs.tasks_chg[G::GRP].dis
+    }
+}
+
+/// Connects a set of events to tasks through the channels of a [`Parts`] instance.
+///
+/// Each line names a channel field of `parts`, so using a channel twice fails to compile with a
+/// "use of moved value" error. Fixed channels don't implement [`ConfigurablePpi`], so they can only
+/// be given a fork task. Every channel is enabled after it has been configured, and the configured
+/// channels are returned as a tuple in the order they were listed.
+///
+/// ```ignore
+/// let ppi = ppi::Parts::new(p.PPI);
+/// let (capture, _radio) = ppi_network!(ppi, {
+///     ppi0: gpiote.channel0().event() => timer.task_capture(TimerCcReg::Cc2),
+///         fork timer.task_clear();
+///     ppi20: fork gpiote.channel1().task_out();
+/// });
+/// ```
+#[macro_export]
+macro_rules! ppi_network {
+    ($parts:ident, { $($connections:tt)* }) => {
+        $crate::ppi_network!(@connect $parts, (), $($connections)*)
+    };
+    (@connect $parts:ident, ($($done:expr,)*), ) => {
+        ($($done,)*)
+    };
+    (@connect $parts:ident, ($($done:expr,)*),
+        $ch:ident: fork $fork:expr; $($rest:tt)*
+    ) => {
+        $crate::ppi_network!(@connect $parts, ($($done,)* {
+            let mut channel = $parts.$ch;
+            $crate::ppi::Ppi::set_fork_task_endpoint(&mut channel, $fork);
+            $crate::ppi::Ppi::enable(&mut channel);
+            channel
+        },), $($rest)*)
+    };
+    (@connect $parts:ident, ($($done:expr,)*),
+        $ch:ident: $event:expr => $task:expr, fork $fork:expr; $($rest:tt)*
+    ) => {
+        $crate::ppi_network!(@connect $parts, ($($done,)* {
+            let mut channel = $parts.$ch;
+            $crate::ppi::ConfigurablePpi::set_event_endpoint(&mut channel, $event);
+            $crate::ppi::ConfigurablePpi::set_task_endpoint(&mut channel, $task);
+            $crate::ppi::Ppi::set_fork_task_endpoint(&mut channel, $fork);
+            $crate::ppi::Ppi::enable(&mut channel);
+            channel
+        },), $($rest)*)
+    };
+    (@connect $parts:ident, ($($done:expr,)*),
+        $ch:ident: $event:expr => $task:expr; $($rest:tt)*
+    ) => {
+        $crate::ppi_network!(@connect $parts, ($($done,)* {
+            let mut channel = $parts.$ch;
+            $crate::ppi::ConfigurablePpi::set_event_endpoint(&mut channel, $event);
+            $crate::ppi::ConfigurablePpi::set_task_endpoint(&mut channel, $task);
+            $crate::ppi::Ppi::enable(&mut channel);
+            channel
+        },), $($rest)*)
+    };
+}
+
 macro_rules! ppi {
     (
         not_fixed: [ $(
@@ -135,6 +299,10 @@ macro_rules! ppi {
             ($ppix:ident, $PpixType:ident, $ch:expr),)+
         ],
         fixed: [$(($ppix_fixed:ident, $PpixTypeFixed:ident, $ch_fixed:expr),)+],
+        groups: [ $(
+            $(#[$grp_attr:meta])*
+            ($chgx:ident, $ChgxType:ident, $grp:expr),)+
+        ],
     ) => {
 
         $(
@@ -164,7 +332,20 @@ macro_rules! ppi {
             }
         )+
 
-        /// Type that abstracts all the PPI channels.
+        $(
+            /// PPI channel group.
+            $(#[$grp_attr])*
+            pub struct $ChgxType {
+                _private: (),
+            }
+
+            $(#[$grp_attr])*
+            impl Group for $ChgxType {
+                const GRP: usize = $grp;
+            }
+        )+
+
+        /// Type that abstracts all the PPI channels and channel groups.
         pub struct Parts {
             $(
                 $(#[$attr])*
@@ -173,6 +354,10 @@ macro_rules! ppi {
             $(
                 pub $ppix_fixed: $PpixTypeFixed,
             )+
+            $(
+                $(#[$grp_attr])*
+                pub $chgx: $ChgxType,
+            )+
         }
 
         impl Parts {
@@ -191,6 +376,12 @@ macro_rules! ppi {
                             _private: (),
                         },
                     )+
+                    $(
+                        $(#[$grp_attr])*
+                        $chgx: $ChgxType {
+                            _private: (),
+                        },
+                    )+
                 }
             }
         }
@@ -238,4 +429,14 @@ ppi!(
         (ppi30, Ppi30, 30),
         (ppi31, Ppi31, 31),
     ],
+    groups: [
+        (chg0, Chg0, 0),
+        (chg1, Chg1, 1),
+        (chg2, Chg2, 2),
+        (chg3, Chg3, 3),
+        #[cfg(not(feature = "51"))]
+        (chg4, Chg4, 4),
+        #[cfg(not(feature = "51"))]
+        (chg5, Chg5, 5),
+    ],
 );
@@ -0,0 +1,286 @@
+//! HAL interface to the QDEC peripheral.
+//!
+//! The quadrature decoder (QDEC) samples the phase A and B signals of a rotary encoder and
+//! accumulates its movement in hardware, so no interrupt is needed per step. Each sample adds
+//! -1, 0 or +1 to the accumulator; a sample where both phases changed is a double transition,
+//! which can't be decoded and is counted separately.
+//!
+//! The LED of an optical encoder is switched on only for a configurable time before each sample,
+//! see [`Qdec::led`]. With a short LED period and the longest sample period the encoder's speed
+//! allows, the LED is off most of the time and the decoder draws little current between samples.
+//!
+//! [`Position`] integrates the accumulator into an absolute position, updated when the decoder
+//! reports movement:
+//!
+//! ```ignore
+//! let mut position = Position::new(qdec);
+//! loop {
+//!     let value = position.wait_for_change().await;
+//! }
+//! ```
+//!
+//! See product specification, chapter 43.
+
+use crate::{
+    gpio::{Floating, Input, Output, Pin, PushPull},
+    pac::{qdec, QDEC},
+};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static WAKER: WakerSlot = WakerSlot::new();
+
+pub use qdec::{
+    ledpol::LEDPOL_A as LedPolarity, reportper::REPORTPER_A as ReportPeriod,
+    sampleper::SAMPLEPER_A as SamplePeriod,
+};
+
+/// Maximum time the LED is switched on before a sample, in microseconds.
+pub const MAX_LED_PRE_US: u16 = 511;
+
+/// `PSEL` value of a disconnected pin.
+const DISCONNECTED: u32 = 1 << 31;
+
+/// Pins of the QDEC interface.
+pub struct Pins {
+    pub a: Pin<Input<Floating>>,
+    pub b: Pin<Input<Floating>>,
+    /// Output driving the LED of an optical encoder.
+    pub led: Option<Pin<Output<PushPull>>>,
+}
+
+/// Interface to the QDEC peripheral.
+pub struct Qdec {
+    qdec: QDEC,
+    pins: Pins,
+}
+
+impl Qdec {
+    /// Configures the decoder to sample the phases every `sample_period`. It is enabled but
+    /// stopped; call `start` to begin sampling.
+    pub fn new(qdec: QDEC, pins: Pins, sample_period: SamplePeriod) -> Self {
+        unsafe {
+            qdec.psel.a.write(|w| w.bits(pins.a.psel_bits()));
+            qdec.psel.b.write(|w| w.bits(pins.b.psel_bits()));
+            qdec.psel
+                .led
+                .write(|w| w.bits(pins.led.as_ref().map_or(DISCONNECTED, Pin::psel_bits)));
+        }
+        qdec.sampleper
+            .write(|w| w.sampleper().variant(sample_period));
+        qdec.enable.write(|w| w.enable().enabled());
+
+        Qdec { qdec, pins }
+    }
+
+    /// Sets the number of samples after which the `REPORTRDY` event is generated, if the
+    /// accumulator is not zero.
+    pub fn report_period(&mut self, report_period: ReportPeriod) -> &mut Self {
+        self.qdec
+            .reportper
+            .write(|w| w.reportper().variant(report_period));
+        self
+    }
+
+    /// Configures the LED output: its active level, and the time in microseconds it is switched
+    /// on before each sample, at most `MAX_LED_PRE_US`.
+    ///
+    /// The LED is pulsed only if the sample period is longer than `pre_us`, otherwise it stays
+    /// on. The default is 16 µs, active low.
+    pub fn led(&mut self, polarity: LedPolarity, pre_us: u16) -> &mut Self {
+        self.qdec.ledpol.write(|w| w.ledpol().variant(polarity));
+        self.qdec
+            .ledpre
+            .write(|w| unsafe { w.ledpre().bits(pre_us.min(MAX_LED_PRE_US)) });
+        self
+    }
+
+    /// Enables or disables the debounce filter, which ignores phase changes that are not stable
+    /// for at least 4 samples.
+    pub fn debounce(&mut self, enable: bool) -> &mut Self {
+        self.qdec.dbfen.write(|w| w.dbfen().bit(enable));
+        self
+    }
+
+    /// Enables the `QDEC` interrupt when movement is reported, or when the accumulator
+    /// overflows.
+    pub fn enable_interrupt(&mut self) -> &mut Self {
+        self.qdec
+            .intenset
+            .write(|w| w.reportrdy().set().accof().set());
+        self
+    }
+
+    /// Disables the `QDEC` interrupt.
+    pub fn disable_interrupt(&mut self) -> &mut Self {
+        self.qdec
+            .intenclr
+            .write(|w| w.reportrdy().clear().accof().clear());
+        self
+    }
+
+    /// Starts sampling.
+    pub fn start(&mut self) {
+        self.qdec.tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stops sampling. The accumulators are kept.
+    pub fn stop(&mut self) {
+        self.qdec.events_stopped.reset();
+        self.qdec.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.qdec.events_stopped.read().bits() == 0 {}
+        self.qdec.events_stopped.reset();
+    }
+
+    /// Returns the movement accumulated since the last read, and clears the accumulators.
+    ///
+    /// The accumulator saturates at -1024 and 1023.
+    pub fn read(&mut self) -> i16 {
+        self.qdec.tasks_readclracc.write(|w| unsafe { w.bits(1) });
+        self.qdec.accread.read().bits() as i32 as i16
+    }
+
+    /// Returns the number of double transitions counted until the last `read`. It saturates at
+    /// 15.
+    ///
+    /// Double transitions mean the sample period is too long for the speed of the encoder.
+    pub fn double_transitions(&self) -> u8 {
+        self.qdec.accdblread.read().accdblread().bits()
+    }
+
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &qdec::TASKS_START {
+        &self.qdec.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &qdec::TASKS_STOP {
+        &self.qdec.tasks_stop
+    }
+
+    /// Returns reference to the `READCLRACC` task endpoint for PPI.
+    pub fn task_read_clear_acc(&self) -> &qdec::TASKS_READCLRACC {
+        &self.qdec.tasks_readclracc
+    }
+
+    /// Returns reference to the `SAMPLERDY` event endpoint for PPI.
+    pub fn event_sample_ready(&self) -> &qdec::EVENTS_SAMPLERDY {
+        &self.qdec.events_samplerdy
+    }
+
+    /// Returns reference to the `REPORTRDY` event endpoint for PPI.
+    pub fn event_report_ready(&self) -> &qdec::EVENTS_REPORTRDY {
+        &self.qdec.events_reportrdy
+    }
+
+    /// Returns reference to the `ACCOF` event endpoint for PPI.
+    pub fn event_acc_overflow(&self) -> &qdec::EVENTS_ACCOF {
+        &self.qdec.events_accof
+    }
+
+    /// Disables the decoder, and returns the peripheral and the pins.
+    pub fn free(self) -> (QDEC, Pins) {
+        self.qdec.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.qdec.enable.write(|w| w.enable().disabled());
+        (self.qdec, self.pins)
+    }
+}
+
+/// Absolute position of an encoder, integrating the movement accumulated by the decoder.
+pub struct Position {
+    qdec: Qdec,
+    value: i64,
+    overflowed: bool,
+}
+
+impl Position {
+    /// Starts the decoder, at position 0.
+    pub fn new(mut qdec: Qdec) -> Self {
+        qdec.read();
+        qdec.qdec.events_reportrdy.reset();
+        qdec.qdec.events_accof.reset();
+        qdec.start();
+        Position {
+            qdec,
+            value: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Adds the movement accumulated since the last update to the position, and returns it.
+    ///
+    /// Must be called often enough for the accumulator not to overflow, e.g. from the `QDEC`
+    /// interrupt handler after `Qdec::enable_interrupt`.
+    pub fn update(&mut self) -> i64 {
+        let qdec = &self.qdec.qdec;
+        qdec.events_reportrdy.reset();
+        if qdec.events_accof.read().bits() != 0 {
+            qdec.events_accof.reset();
+            self.overflowed = true;
+        }
+        self.value += self.qdec.read() as i64;
+        self.value
+    }
+
+    /// Returns the position as of the last update.
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Sets the position, discarding the movement since the last update.
+    pub fn set(&mut self, value: i64) {
+        self.qdec.read();
+        self.value = value;
+    }
+
+    /// Returns `true` if the accumulator has overflowed since the last call, in which case steps
+    /// have been lost and the position is off.
+    pub fn take_overflow(&mut self) -> bool {
+        core::mem::replace(&mut self.overflowed, false)
+    }
+
+    /// Waits until the decoder reports movement or the accumulator overflows, then updates the
+    /// position and returns it.
+    ///
+    /// The `QDEC` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    #[cfg(feature = "async")]
+    pub async fn wait_for_change(&mut self) -> i64 {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            let qdec = &self.qdec.qdec;
+            if qdec.events_reportrdy.read().bits() != 0 || qdec.events_accof.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.qdec.enable_interrupt();
+                Poll::Pending
+            }
+        })
+        .await;
+        self.update()
+    }
+
+    /// Stops the decoder and returns it.
+    pub fn free(mut self) -> Qdec {
+        self.qdec.stop();
+        self.qdec
+    }
+}
+
+/// Handles the QDEC events for `Position::wait_for_change`.
+///
+/// Must be called from the `QDEC` interrupt handler.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let qdec = unsafe { &*QDEC::ptr() };
+    if qdec.events_reportrdy.read().bits() != 0 || qdec.events_accof.read().bits() != 0 {
+        // Leave the events set for the position to observe, but keep them from firing again.
+        qdec.intenclr
+            .write(|w| w.reportrdy().clear().accof().clear());
+        WAKER.wake();
+    }
+}
@@ -0,0 +1,721 @@
+//! HAL interface to the QSPI peripheral.
+//!
+//! The QSPI peripheral talks to an external serial NOR flash. It transfers data between the
+//! flash and RAM with EasyDMA, and maps the flash into the execute-in-place (XIP) window starting
+//! at [`XIP_START`], where it can be read like internal flash.
+//!
+//! See product specification, chapter 43.
+
+use core::{
+    slice,
+    sync::atomic::{compiler_fence, Ordering::SeqCst},
+};
+
+use embedded_storage::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+use crate::error::ErrorKind;
+use crate::{
+    gpio::{Output, Pin, PushPull},
+    pac::QSPI,
+    slice_in_ram, slice_in_xip,
+};
+
+/// Start of the XIP window, the address at which the external flash is mapped.
+pub const XIP_START: usize = 0x1200_0000;
+
+/// End of the XIP window, which spans 128 MiB.
+pub const XIP_END: usize = 0x1A00_0000;
+
+/// Size of the RAM buffer through which data not in RAM is written.
+const COPY_BUFFER_SIZE: usize = 256;
+
+/// Register of the workaround for erratum 122: QSPI uses current after being disabled.
+const ERRATA_122_REG: usize = 0x4002_9054;
+
+/// Size of the smallest erasable sector.
+pub const SECTOR_SIZE: usize = 4 * 1024;
+
+/// Size of the largest erasable block.
+pub const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Maximum number of data bytes of a custom instruction.
+pub const MAX_INSTRUCTION_DATA: usize = 8;
+
+/// Common flash opcodes.
+const OPCODE_READ_STATUS: u8 = 0x05;
+const OPCODE_WRITE_STATUS: u8 = 0x01;
+const OPCODE_READ_JEDEC_ID: u8 = 0x9f;
+
+/// `CINSTRCONF` flags.
+const CINSTRCONF_LIO2: u32 = 1 << 12;
+const CINSTRCONF_LIO3: u32 = 1 << 13;
+const CINSTRCONF_WIPWAIT: u32 = 1 << 15;
+const CINSTRCONF_WREN: u32 = 1 << 16;
+const CINSTRCONF_LFEN: u32 = 1 << 17;
+const CINSTRCONF_LFSTOP: u32 = 1 << 18;
+
+/// `IFCONFIG0` flag enabling deep power-down support.
+const IFCONFIG0_DPMENABLE: u32 = 1 << 7;
+
+/// `IFCONFIG1` flag putting the flash into deep power-down.
+const IFCONFIG1_DPMEN: u32 = 1 << 24;
+
+/// `STATUS` flag set while the flash is in deep power-down.
+const STATUS_DPM: u32 = 1 << 2;
+
+/// Unit of the `DPMDUR` durations, in microseconds.
+const DPMDUR_UNIT_US: u32 = 16;
+
+/// `ERASE.LEN` values.
+const ERASE_LEN_4KB: u32 = 0;
+const ERASE_LEN_64KB: u32 = 1;
+const ERASE_LEN_ALL: u32 = 2;
+
+/// Pins of the QSPI interface.
+pub struct Pins {
+    pub sck: Pin<Output<PushPull>>,
+    pub csn: Pin<Output<PushPull>>,
+    pub io0: Pin<Output<PushPull>>,
+    pub io1: Pin<Output<PushPull>>,
+    /// Only needed by the quad read and write operations.
+    pub io2: Option<Pin<Output<PushPull>>>,
+    /// Only needed by the quad read and write operations.
+    pub io3: Option<Pin<Output<PushPull>>>,
+}
+
+/// Opcode used to read from the flash.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadOpcode {
+    /// Single data line SPI, `FAST_READ` (0x0B).
+    FastRead = 0,
+    /// Dual data line SPI, `READ2O` (0x3B).
+    Read2O = 1,
+    /// Dual data and address lines, `READ2IO` (0xBB).
+    Read2IO = 2,
+    /// Quad data line SPI, `READ4O` (0x6B).
+    Read4O = 3,
+    /// Quad data and address lines, `READ4IO` (0xEB).
+    Read4IO = 4,
+}
+
+/// Opcode used to program the flash.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WriteOpcode {
+    /// Single data line SPI, `PP` (0x02).
+    Pp = 0,
+    /// Dual data line SPI, `PP2O` (0xA2).
+    Pp2O = 1,
+    /// Quad data line SPI, `PP4O` (0x32).
+    Pp4O = 2,
+    /// Quad data and address lines, `PP4IO` (0x38).
+    Pp4IO = 3,
+}
+
+/// Width of the flash addresses.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressMode {
+    Bit24,
+    Bit32,
+}
+
+/// Configuration of the QSPI interface.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    pub read_opcode: ReadOpcode,
+    pub write_opcode: WriteOpcode,
+    pub address_mode: AddressMode,
+    /// Page size of the flash is 512 bytes instead of 256.
+    pub page_size_512: bool,
+    /// SCK frequency is 32 MHz / (`sck_divider` + 1), with `sck_divider` up to 15.
+    pub sck_divider: u8,
+    /// Minimum time between CSN going high and low again, in units of 62.5 ns.
+    pub sck_delay: u8,
+    /// Use SPI mode 3 instead of mode 0.
+    pub spi_mode_3: bool,
+}
+
+impl Default for Config {
+    /// Quad I/O at 32 MHz, 24 bit addresses and 256 byte pages, which most flashes support.
+    fn default() -> Self {
+        Config {
+            read_opcode: ReadOpcode::Read4IO,
+            write_opcode: WriteOpcode::Pp4IO,
+            address_mode: AddressMode::Bit24,
+            page_size_512: false,
+            sck_divider: 0,
+            sck_delay: 1,
+            spi_mode_3: false,
+        }
+    }
+}
+
+/// Size of an erase operation.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EraseLen {
+    /// A 4 KiB sector.
+    Sector4K,
+    /// A 64 KiB block.
+    Block64K,
+    /// The whole chip.
+    All,
+}
+
+/// Options of a custom instruction.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InstructionConfig {
+    /// Wait until the flash has finished its current operation before sending the instruction.
+    pub wait_ready: bool,
+    /// Send `WREN` (0x06) before the instruction, as required by write and erase instructions.
+    pub write_enable: bool,
+    /// Level of IO2 during the instruction, which is the WP pin of many flashes.
+    pub io2_high: bool,
+    /// Level of IO3 during the instruction, which is the HOLD or RESET pin of many flashes.
+    pub io3_high: bool,
+}
+
+impl Default for InstructionConfig {
+    fn default() -> Self {
+        InstructionConfig {
+            wait_ready: true,
+            write_enable: false,
+            io2_high: true,
+            io3_high: true,
+        }
+    }
+}
+
+impl InstructionConfig {
+    fn bits(&self) -> u32 {
+        let mut bits = 0;
+        if self.wait_ready {
+            bits |= CINSTRCONF_WIPWAIT;
+        }
+        if self.write_enable {
+            bits |= CINSTRCONF_WREN;
+        }
+        if self.io2_high {
+            bits |= CINSTRCONF_LIO2;
+        }
+        if self.io3_high {
+            bits |= CINSTRCONF_LIO3;
+        }
+        bits
+    }
+}
+
+/// QSPI error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Address, buffer or length not aligned to 4 bytes, or an erase address not aligned to the
+    /// erase size.
+    Unaligned,
+    /// EasyDMA can only write to data memory.
+    DMABufferNotInDataMemory,
+    /// The range doesn't fit into the XIP window.
+    OutOfBounds,
+    /// A custom instruction carries more than 8 data bytes.
+    InstructionTooLong,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Unaligned | Error::OutOfBounds => ErrorKind::InvalidArgument,
+            Error::DMABufferNotInDataMemory => ErrorKind::BufferNotInRam,
+            Error::InstructionTooLong => ErrorKind::BufferLength,
+        }
+    }
+}
+
+/// Interface to the QSPI peripheral.
+pub struct Qspi {
+    qspi: QSPI,
+    pins: Pins,
+}
+
+impl Qspi {
+    /// Configures the interface and activates it.
+    pub fn new(qspi: QSPI, pins: Pins, config: Config) -> Self {
+        let psel = |pin: &Pin<Output<PushPull>>| pin.pin() as u32 | (pin.port().bit() as u32) << 5;
+        unsafe {
+            qspi.psel.sck.write(|w| w.bits(psel(&pins.sck)));
+            qspi.psel.csn.write(|w| w.bits(psel(&pins.csn)));
+            qspi.psel.io0.write(|w| w.bits(psel(&pins.io0)));
+            qspi.psel.io1.write(|w| w.bits(psel(&pins.io1)));
+            // Bit 31 disconnects a pin.
+            let io2 = pins.io2.as_ref().map_or(1 << 31, psel);
+            qspi.psel.io2.write(|w| w.bits(io2));
+            let io3 = pins.io3.as_ref().map_or(1 << 31, psel);
+            qspi.psel.io3.write(|w| w.bits(io3));
+
+            qspi.ifconfig0.write(|w| {
+                w.bits(
+                    config.read_opcode as u32
+                        | (config.write_opcode as u32) << 3
+                        | ((config.address_mode == AddressMode::Bit32) as u32) << 6
+                        | (config.page_size_512 as u32) << 12,
+                )
+            });
+            qspi.ifconfig1.write(|w| {
+                w.bits(
+                    config.sck_delay as u32
+                        | (config.spi_mode_3 as u32) << 25
+                        | ((config.sck_divider & 0xf) as u32) << 28,
+                )
+            });
+        }
+
+        qspi.enable.write(|w| w.enable().enabled());
+        let mut qspi = Qspi { qspi, pins };
+        qspi.activate();
+        qspi
+    }
+
+    /// Deactivates and disables the interface, and returns the peripheral and the pins.
+    pub fn free(self) -> (QSPI, Pins) {
+        // Workaround for erratum 122.
+        unsafe { core::ptr::write_volatile(ERRATA_122_REG as *mut u32, 1) };
+        self.qspi.tasks_deactivate.write(|w| unsafe { w.bits(1) });
+        self.qspi.enable.write(|w| w.enable().disabled());
+        (self.qspi, self.pins)
+    }
+
+    /// Reads from the flash at `address` into `buf`.
+    ///
+    /// `address`, the length of `buf` and its start in RAM must be multiples of 4.
+    pub fn read(&mut self, address: u32, buf: &mut [u8]) -> Result<(), Error> {
+        if address % 4 != 0 || buf.len() % 4 != 0 || buf.as_ptr() as usize % 4 != 0 {
+            return Err(Error::Unaligned);
+        }
+        if !slice_in_ram(buf) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        unsafe {
+            self.qspi.read.src.write(|w| w.bits(address));
+            self.qspi
+                .read
+                .dst
+                .write(|w| w.bits(buf.as_mut_ptr() as u32));
+            self.qspi.read.cnt.write(|w| w.bits(buf.len() as u32));
+        }
+        self.start(|qspi| qspi.tasks_readstart.write(|w| unsafe { w.bits(1) }));
+        Ok(())
+    }
+
+    /// Programs `data` into the flash at `address`, which must be erased.
+    ///
+    /// `address` and the length of `data` must be multiples of 4. Data that is not in RAM,
+    /// including data read from the XIP window, is copied to RAM first.
+    pub fn write(&mut self, address: u32, data: &[u8]) -> Result<(), Error> {
+        if address % 4 != 0 || data.len() % 4 != 0 {
+            return Err(Error::Unaligned);
+        }
+
+        // The flash cannot be read through the XIP window while it is written, so such data is
+        // copied before each write starts, like data from internal flash.
+        if slice_in_ram(data) && !slice_in_xip(data) && data.as_ptr() as usize % 4 == 0 {
+            self.write_ram(address, data);
+        } else {
+            let mut buf = [0u32; COPY_BUFFER_SIZE / 4];
+            for (i, chunk) in data.chunks(COPY_BUFFER_SIZE).enumerate() {
+                let bytes =
+                    unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, chunk.len()) };
+                bytes.copy_from_slice(chunk);
+                self.write_ram(address + (i * COPY_BUFFER_SIZE) as u32, bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// Erases `len` at `address`, which must be aligned to the erase size.
+    ///
+    /// This takes up to a few hundred milliseconds for a 64 KiB block, and much longer for the
+    /// whole chip.
+    pub fn erase(&mut self, len: EraseLen, address: u32) -> Result<(), Error> {
+        let (align, len) = match len {
+            EraseLen::Sector4K => (0x1000, ERASE_LEN_4KB),
+            EraseLen::Block64K => (0x1_0000, ERASE_LEN_64KB),
+            EraseLen::All => (1, ERASE_LEN_ALL),
+        };
+        if address % align != 0 {
+            return Err(Error::Unaligned);
+        }
+
+        unsafe {
+            self.qspi.erase.ptr.write(|w| w.bits(address));
+            self.qspi.erase.len.write(|w| w.bits(len));
+        }
+        self.start(|qspi| qspi.tasks_erasestart.write(|w| unsafe { w.bits(1) }));
+        Ok(())
+    }
+
+    /// Returns `len` bytes of the flash at `address`, read through the XIP window.
+    ///
+    /// Fails with `OutOfBounds` if the bytes are not mapped into the window, see
+    /// `set_xip_offset`.
+    ///
+    /// The slice borrows the interface, so no write or erase can change the flash while it is
+    /// alive. Code executed from the XIP window is also cached by the instruction cache, see
+    /// [`ICache`](crate::nvmc::ICache); disable it while rewriting such code.
+    pub fn xip_slice(&self, address: u32, len: usize) -> Result<&[u8], Error> {
+        // The window starts with the flash at `xip_offset`.
+        let start = address
+            .checked_sub(self.xip_offset())
+            .and_then(|offset| XIP_START.checked_add(offset as usize));
+        match start.and_then(|start| Some((start, start.checked_add(len)?))) {
+            Some((start, end)) if end <= XIP_END => {
+                Ok(unsafe { slice::from_raw_parts(start as *const u8, len) })
+            }
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Returns the flash address mapped to the start of the XIP window.
+    pub fn xip_offset(&self) -> u32 {
+        self.qspi.xipoffset.read().bits()
+    }
+
+    /// Maps the flash starting at `offset` to the start of the XIP window.
+    pub fn set_xip_offset(&mut self, offset: u32) {
+        self.qspi.xipoffset.write(|w| unsafe { w.bits(offset) });
+    }
+
+    /// Sends the custom instruction `opcode` with the bytes of `write`, and reads as many bytes as
+    /// `read` holds, in single data line SPI.
+    ///
+    /// Up to `MAX_INSTRUCTION_DATA` bytes are exchanged: the bytes clocked out are `write`
+    /// padded with zeros, and `read` receives the bytes clocked in at the same time.
+    pub fn custom_instruction(
+        &mut self,
+        opcode: u8,
+        config: InstructionConfig,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error> {
+        self.instruction(opcode, config.bits(), write, read)
+    }
+
+    /// Starts a long frame with the custom instruction `opcode`, keeping CSN low so more data
+    /// can be exchanged than fits into a single instruction.
+    pub fn long_frame(
+        &mut self,
+        opcode: u8,
+        config: InstructionConfig,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<LongFrame<'_>, Error> {
+        let bits = config.bits() | CINSTRCONF_LFEN;
+        self.instruction(opcode, bits, write, read)?;
+        Ok(LongFrame {
+            qspi: self,
+            bits,
+            opcode,
+            finished: false,
+        })
+    }
+
+    /// Reads the JEDEC ID of the flash: the manufacturer, memory type and capacity codes.
+    pub fn read_jedec_id(&mut self) -> [u8; 3] {
+        let mut id = [0; 3];
+        self.instruction(
+            OPCODE_READ_JEDEC_ID,
+            InstructionConfig::default().bits(),
+            &[],
+            &mut id,
+        )
+        .ok();
+        id
+    }
+
+    /// Reads the first status register of the flash.
+    pub fn read_status(&mut self) -> u8 {
+        let mut status = [0];
+        self.instruction(
+            OPCODE_READ_STATUS,
+            InstructionConfig::default().bits(),
+            &[],
+            &mut status,
+        )
+        .ok();
+        status[0]
+    }
+
+    /// Writes the status registers of the flash, e.g. to set the quad enable bit, whose
+    /// location depends on the flash.
+    pub fn write_status(&mut self, status: &[u8]) -> Result<(), Error> {
+        let config = InstructionConfig {
+            write_enable: true,
+            ..InstructionConfig::default()
+        };
+        self.instruction(OPCODE_WRITE_STATUS, config.bits(), status, &mut [])
+    }
+
+    fn instruction(
+        &mut self,
+        opcode: u8,
+        bits: u32,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error> {
+        let len = write.len().max(read.len());
+        if len > MAX_INSTRUCTION_DATA {
+            return Err(Error::InstructionTooLong);
+        }
+
+        let mut data = [0u8; MAX_INSTRUCTION_DATA];
+        data[..write.len()].copy_from_slice(write);
+        let word = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        unsafe {
+            self.qspi.cinstrdat0.write(|w| w.bits(word(&data[..4])));
+            self.qspi.cinstrdat1.write(|w| w.bits(word(&data[4..])));
+        }
+
+        // `LENGTH` counts the opcode along with the data bytes.
+        let conf = bits | (len as u32 + 1) << 8 | opcode as u32;
+        self.start(|qspi| qspi.cinstrconf.write(|w| unsafe { w.bits(conf) }));
+
+        data[..4].copy_from_slice(&self.qspi.cinstrdat0.read().bits().to_le_bytes());
+        data[4..].copy_from_slice(&self.qspi.cinstrdat1.read().bits().to_le_bytes());
+        read.copy_from_slice(&data[..read.len()]);
+        Ok(())
+    }
+
+    /// Enables deep power-down of the flash, with the times it takes to enter and to exit it,
+    /// from the flash's datasheet.
+    ///
+    /// The times are rounded up to multiples of 16 µs.
+    pub fn enable_deep_power_down(&mut self, enter_us: u32, exit_us: u32) {
+        let duration = |us: u32| ((us + DPMDUR_UNIT_US - 1) / DPMDUR_UNIT_US).min(0xffff);
+        self.qspi
+            .dpmdur
+            .write(|w| unsafe { w.bits(duration(enter_us) | duration(exit_us) << 16) });
+        self.qspi
+            .ifconfig0
+            .modify(|r, w| unsafe { w.bits(r.bits() | IFCONFIG0_DPMENABLE) });
+    }
+
+    /// Disables deep power-down, waking the flash first if it is asleep.
+    pub fn disable_deep_power_down(&mut self) {
+        self.wake();
+        self.qspi
+            .ifconfig0
+            .modify(|r, w| unsafe { w.bits(r.bits() & !IFCONFIG0_DPMENABLE) });
+    }
+
+    /// Puts the flash into deep power-down, where it draws about a microampere, and waits until
+    /// it has entered it.
+    ///
+    /// Deep power-down must have been enabled with `enable_deep_power_down`. The flash ignores
+    /// all accesses until it is woken again with `wake`, including reads through the XIP
+    /// window.
+    pub fn sleep(&mut self) {
+        self.qspi
+            .ifconfig1
+            .modify(|r, w| unsafe { w.bits(r.bits() | IFCONFIG1_DPMEN) });
+        while !self.is_sleeping() {}
+    }
+
+    /// Wakes the flash from deep power-down, and waits until it has left it.
+    pub fn wake(&mut self) {
+        self.qspi
+            .ifconfig1
+            .modify(|r, w| unsafe { w.bits(r.bits() & !IFCONFIG1_DPMEN) });
+        while self.is_sleeping() {}
+    }
+
+    /// Returns `true` if the flash is in deep power-down.
+    pub fn is_sleeping(&self) -> bool {
+        self.qspi.status.read().bits() & STATUS_DPM != 0
+    }
+
+    /// Activates the interface, waiting until it is ready.
+    fn activate(&mut self) {
+        self.start(|qspi| qspi.tasks_activate.write(|w| unsafe { w.bits(1) }));
+    }
+
+    /// Programs `data`, which is in RAM and word aligned, at `address`.
+    fn write_ram(&mut self, address: u32, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        unsafe {
+            self.qspi.write.src.write(|w| w.bits(data.as_ptr() as u32));
+            self.qspi.write.dst.write(|w| w.bits(address));
+            self.qspi.write.cnt.write(|w| w.bits(data.len() as u32));
+        }
+        self.start(|qspi| qspi.tasks_writestart.write(|w| unsafe { w.bits(1) }));
+    }
+
+    /// Triggers a task with `trigger` and waits for the `READY` event.
+    fn start(&mut self, trigger: impl FnOnce(&QSPI)) {
+        self.qspi.events_ready.reset();
+        compiler_fence(SeqCst);
+        trigger(&self.qspi);
+        while self.qspi.events_ready.read().bits() == 0 {}
+        self.qspi.events_ready.reset();
+        compiler_fence(SeqCst);
+    }
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::Unaligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Error::DMABufferNotInDataMemory | Error::InstructionTooLong => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// The external flash of a known size, implementing the `embedded-storage` traits.
+///
+/// Erases use 64 KiB blocks where the range allows, and 4 KiB sectors elsewhere. Reads of any
+/// alignment are supported by reading through a RAM buffer.
+pub struct QspiFlash {
+    qspi: Qspi,
+    capacity: usize,
+}
+
+impl QspiFlash {
+    /// Wraps the interface to a flash of `capacity` bytes.
+    pub fn new(qspi: Qspi, capacity: usize) -> Self {
+        QspiFlash { qspi, capacity }
+    }
+
+    /// Returns the interface.
+    pub fn free(self) -> Qspi {
+        self.qspi
+    }
+
+    fn check(&self, offset: u32, len: usize) -> Result<(), Error> {
+        match (offset as usize).checked_add(len) {
+            Some(end) if end <= self.capacity => Ok(()),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+}
+
+impl ErrorType for QspiFlash {
+    type Error = Error;
+}
+
+impl ReadNorFlash for QspiFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len())?;
+
+        let aligned = offset % 4 == 0 && bytes.len() % 4 == 0 && bytes.as_ptr() as usize % 4 == 0;
+        if aligned && slice_in_ram(bytes) {
+            return self.qspi.read(offset, bytes);
+        }
+
+        let mut buf = [0u32; COPY_BUFFER_SIZE / 4];
+        let mut address = offset;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            // Read the aligned words covering the next chunk.
+            let skip = (address % 4) as usize;
+            let len = bytes.len().min(COPY_BUFFER_SIZE - skip);
+            let words = (skip + len + 3) / 4 * 4;
+            let raw = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, words) };
+            self.qspi.read(address - skip as u32, raw)?;
+
+            let (chunk, rest) = bytes.split_at_mut(len);
+            chunk.copy_from_slice(&raw[skip..skip + len]);
+            bytes = rest;
+            address += len as u32;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl NorFlash for QspiFlash {
+    const WRITE_SIZE: usize = 4;
+
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if from > to {
+            return Err(Error::OutOfBounds);
+        }
+        self.check(from, (to - from) as usize)?;
+        if from as usize % SECTOR_SIZE != 0 || to as usize % SECTOR_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+
+        let mut address = from;
+        while address < to {
+            let remaining = (to - address) as usize;
+            if address as usize % BLOCK_SIZE == 0 && remaining >= BLOCK_SIZE {
+                self.qspi.erase(EraseLen::Block64K, address)?;
+                address += BLOCK_SIZE as u32;
+            } else {
+                self.qspi.erase(EraseLen::Sector4K, address)?;
+                address += SECTOR_SIZE as u32;
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len())?;
+        self.qspi.write(offset, bytes)
+    }
+}
+
+// Bits of a NOR flash word can be cleared by repeated writes without erasing it.
+impl MultiwriteNorFlash for QspiFlash {}
+
+/// A long frame of custom instructions, started with `Qspi::long_frame`.
+///
+/// CSN is kept low between the transfers, and released by `finish`, or when the frame is
+/// dropped.
+pub struct LongFrame<'a> {
+    qspi: &'a mut Qspi,
+    bits: u32,
+    opcode: u8,
+    finished: bool,
+}
+
+impl LongFrame<'_> {
+    /// Exchanges up to `MAX_INSTRUCTION_DATA` more bytes.
+    pub fn transfer(&mut self, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        self.qspi.instruction(self.opcode, self.bits, write, read)
+    }
+
+    /// Exchanges the last bytes and ends the frame.
+    pub fn finish(mut self, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        self.qspi
+            .instruction(self.opcode, self.bits | CINSTRCONF_LFSTOP, write, read)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for LongFrame<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.qspi
+                .instruction(self.opcode, self.bits | CINSTRCONF_LFSTOP, &[0], &mut [])
+                .ok();
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Bluetooth Low Energy advertising.
+//!
+//! A minimal LE 1M advertiser transmitting non-connectable undirected advertisements
+//! (`ADV_NONCONN_IND`) on the three primary advertising channels, and a passive scanner receiving
+//! advertising PDUs. This is enough to implement beacon formats like iBeacon or Eddystone on top
+//! of the HAL.
+
+use core::marker::PhantomData;
+
+use super::{disable, prepare, set_crc, wait_disabled, CrcConfig, TxPower, RADIO};
+use crate::clocks::{Clocks, ExternalOscillator};
+
+/// Access address used by all advertising channel packets.
+pub const ADVERTISING_ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+
+/// Maximum length of the advertising data carried in a legacy advertising PDU.
+pub const MAX_ADV_DATA_LEN: usize = 31;
+
+/// Size of the advertiser address.
+const ADV_ADDRESS_LEN: usize = 6;
+
+/// Size of the PDU header.
+const HEADER_LEN: usize = 2;
+
+/// Size of a buffer able to hold any legacy advertising PDU, including its header.
+pub const MAX_PDU_LEN: usize = HEADER_LEN + ADV_ADDRESS_LEN + MAX_ADV_DATA_LEN;
+
+/// `ADV_NONCONN_IND` PDU type.
+const ADV_NONCONN_IND: u8 = 0b0010;
+
+/// One of the three primary advertising channels.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdvertisingChannel {
+    /// 2402 MHz
+    _37 = 37,
+    /// 2426 MHz
+    _38 = 38,
+    /// 2480 MHz
+    _39 = 39,
+}
+
+impl AdvertisingChannel {
+    /// All primary advertising channels, in the order they are used.
+    pub const ALL: [AdvertisingChannel; 3] = [
+        AdvertisingChannel::_37,
+        AdvertisingChannel::_38,
+        AdvertisingChannel::_39,
+    ];
+
+    /// Offset of the channel frequency from 2400 MHz, as written to the `FREQUENCY` register.
+    pub(crate) fn frequency_offset(self) -> u8 {
+        match self {
+            AdvertisingChannel::_37 => 2,
+            AdvertisingChannel::_38 => 26,
+            AdvertisingChannel::_39 => 80,
+        }
+    }
+
+    /// Returns the channel index, which is also the data whitening initial value.
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Type of the advertiser address.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressType {
+    /// A public device address.
+    Public,
+    /// A random device address.
+    Random,
+}
+
+/// Configures the radio for LE 1M advertising channel packets.
+///
+/// The on-air format is a one byte S0 field holding the first header byte, followed by an 8 bit
+/// length field, the payload and a 3 byte CRC.
+pub(crate) fn configure_advertising(radio: &RADIO) {
+    radio.mode.write(|w| w.mode().ble_1mbit());
+    radio.modecnf0.write(|w| w.ru().fast().dtx().center());
+
+    radio.pcnf0.write(|w| unsafe {
+        w.lflen()
+            .bits(8)
+            .s0len()
+            .set_bit()
+            .s1len()
+            .bits(0)
+            .plen()
+            ._8bit()
+    });
+    radio.pcnf1.write(|w| unsafe {
+        w.maxlen()
+            .bits((ADV_ADDRESS_LEN + MAX_ADV_DATA_LEN) as u8)
+            .statlen()
+            .bits(0)
+            .balen()
+            .bits(3)
+            .endian()
+            .little()
+            .whiteen()
+            .enabled()
+    });
+
+    // Logical address 0 is the access address: 3 base address bytes and the prefix byte.
+    radio
+        .base0
+        .write(|w| unsafe { w.bits(ADVERTISING_ACCESS_ADDRESS << 8) });
+    radio
+        .prefix0
+        .write(|w| unsafe { w.ap0().bits((ADVERTISING_ACCESS_ADDRESS >> 24) as u8) });
+    radio.txaddress.write(|w| unsafe { w.txaddress().bits(0) });
+    radio.rxaddresses.write(|w| w.addr0().enabled());
+
+    set_crc(radio, &CrcConfig::BLE_ADVERTISING);
+}
+
+/// Tunes the radio to an advertising channel and sets the matching data whitening value.
+pub(crate) fn set_advertising_channel(radio: &RADIO, channel: AdvertisingChannel) {
+    radio.frequency.write(|w| unsafe {
+        w.frequency()
+            .bits(channel.frequency_offset())
+            .map()
+            .default()
+    });
+    radio
+        .datawhiteiv
+        .write(|w| unsafe { w.datawhiteiv().bits(channel.index()) });
+}
+
+/// A BLE advertiser sending non-connectable advertisements.
+pub struct Advertiser<'c> {
+    radio: RADIO,
+    pdu: [u8; MAX_PDU_LEN],
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> Advertiser<'c> {
+    /// Configures the radio to advertise with the given device address at 0 dBm.
+    ///
+    /// `address` is in little endian byte order, as it is transmitted over the air. The
+    /// advertising data is initially empty.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>,
+        address: [u8; 6],
+        address_type: AddressType,
+    ) -> Self {
+        disable(&radio);
+        configure_advertising(&radio);
+        radio.txpower.write(|w| w.txpower().variant(TxPower::_0DBM));
+
+        let mut pdu = [0; MAX_PDU_LEN];
+        pdu[0] = ADV_NONCONN_IND;
+        if address_type == AddressType::Random {
+            // TxAdd
+            pdu[0] |= 1 << 6;
+        }
+        pdu[1] = ADV_ADDRESS_LEN as u8;
+        pdu[HEADER_LEN..HEADER_LEN + ADV_ADDRESS_LEN].copy_from_slice(&address);
+
+        Advertiser {
+            radio,
+            pdu,
+            _clocks: PhantomData,
+        }
+    }
+
+    /// Changes the transmission power.
+    pub fn set_txpower(&mut self, power: TxPower) {
+        self.radio.txpower.write(|w| w.txpower().variant(power));
+    }
+
+    /// Sets the advertising data, which must already be encoded as a sequence of AD structures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is longer than `MAX_ADV_DATA_LEN` bytes.
+    pub fn set_data(&mut self, data: &[u8]) {
+        assert!(data.len() <= MAX_ADV_DATA_LEN);
+        let start = HEADER_LEN + ADV_ADDRESS_LEN;
+        self.pdu[start..start + data.len()].copy_from_slice(data);
+        self.pdu[1] = (ADV_ADDRESS_LEN + data.len()) as u8;
+    }
+
+    /// Transmits the advertisement once on `channel`, blocking until it has been sent.
+    pub fn advertise_on(&mut self, channel: AdvertisingChannel) {
+        set_advertising_channel(&self.radio, channel);
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.pdu.as_ptr() as u32) });
+        self.radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+
+        prepare(&self.radio);
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+    }
+
+    /// Transmits one advertising event, i.e. the advertisement on channels 37, 38 and 39.
+    pub fn advertise(&mut self) {
+        for channel in AdvertisingChannel::ALL.iter() {
+            self.advertise_on(*channel);
+        }
+    }
+
+    /// Disables the radio and returns the raw `RADIO` peripheral.
+    pub fn free(self) -> RADIO {
+        disable(&self.radio);
+        self.radio
+    }
+}
+
+/// Information about an advertising PDU received by the [`Scanner`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AdvReport {
+    /// Channel the PDU was received on.
+    pub channel: AdvertisingChannel,
+    /// Received signal strength in dBm.
+    pub rssi: i8,
+    /// PDU type from the header, e.g. `0b0000` for `ADV_IND`.
+    pub pdu_type: u8,
+    /// Whether the advertiser address is a random address (TxAdd).
+    pub address_type: AddressType,
+    /// Length of the PDU in the buffer, including its 2 byte header.
+    pub len: usize,
+}
+
+/// A passive BLE scanner.
+///
+/// Only packets sent with the advertising access address are received, and packets with an
+/// invalid CRC are discarded by the driver.
+pub struct Scanner<'c> {
+    radio: RADIO,
+    channel: AdvertisingChannel,
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> Scanner<'c> {
+    /// Configures the radio to scan on channel 37.
+    pub fn new<L, LSTAT>(radio: RADIO, _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>) -> Self {
+        disable(&radio);
+        configure_advertising(&radio);
+        set_advertising_channel(&radio, AdvertisingChannel::_37);
+
+        Scanner {
+            radio,
+            channel: AdvertisingChannel::_37,
+            _clocks: PhantomData,
+        }
+    }
+
+    /// Changes the channel to scan on.
+    pub fn set_channel(&mut self, channel: AdvertisingChannel) {
+        set_advertising_channel(&self.radio, channel);
+        self.channel = channel;
+    }
+
+    /// Returns the channel currently scanned.
+    pub fn channel(&self) -> AdvertisingChannel {
+        self.channel
+    }
+
+    /// Receives an advertising PDU into `buf`, blocking until one with a valid CRC has arrived.
+    ///
+    /// The PDU, starting with its header, is written to the beginning of `buf`.
+    pub fn recv(&mut self, buf: &mut [u8; MAX_PDU_LEN]) -> AdvReport {
+        loop {
+            self.radio
+                .packetptr
+                .write(|w| unsafe { w.bits(buf.as_mut_ptr() as u32) });
+            self.radio.shorts.write(|w| {
+                w.ready_start()
+                    .enabled()
+                    .address_rssistart()
+                    .enabled()
+                    .end_disable()
+                    .enabled()
+                    .disabled_rssistop()
+                    .enabled()
+            });
+
+            prepare(&self.radio);
+            self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+            wait_disabled(&self.radio);
+
+            if self.radio.crcstatus.read().crcstatus().is_crcok() {
+                let rssi = self.radio.rssisample.read().rssisample().bits();
+                let address_type = if buf[0] & (1 << 6) != 0 {
+                    AddressType::Random
+                } else {
+                    AddressType::Public
+                };
+
+                return AdvReport {
+                    channel: self.channel,
+                    rssi: -(rssi as i8),
+                    pdu_type: buf[0] & 0x0f,
+                    address_type,
+                    len: HEADER_LEN + buf[1].min((MAX_PDU_LEN - HEADER_LEN) as u8) as usize,
+                };
+            }
+        }
+    }
+
+    /// Disables the radio and returns the raw `RADIO` peripheral.
+    pub fn free(self) -> RADIO {
+        disable(&self.radio);
+        self.radio
+    }
+}
@@ -0,0 +1,249 @@
+//! Direction finding.
+//!
+//! Extends [`proprietary::Radio`](super::proprietary::Radio) with constant tone extension (CTE)
+//! transmission and IQ sampling, for Angle of Arrival (AoA) and Angle of Departure (AoD)
+//! measurements. The antenna array is switched through up to 8 GPIOs driven by the radio,
+//! following a user-supplied switching pattern.
+//!
+//! With direction finding enabled, `transmit` appends a CTE to each packet, and
+//! `receive_with_iq` samples the CTE of received packets into a buffer.
+
+use super::proprietary::{Error, Radio};
+use super::radio;
+use crate::gpio::{Output, Pin, PushPull};
+
+/// Number of GPIOs available for antenna switching.
+pub const NUM_ANTENNA_PINS: usize = 8;
+
+/// Maximum number of entries in the antenna switching pattern.
+pub const MAX_PATTERN_LEN: usize = 40;
+
+/// Direction finding method.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DfeMode {
+    /// Angle of Arrival: the receiver switches antennas.
+    Aoa = 3,
+    /// Angle of Departure: the transmitter switches antennas.
+    Aod = 2,
+}
+
+/// Interval between antenna switches or IQ samples.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Spacing {
+    /// 4 µs
+    _4us = 1,
+    /// 2 µs
+    _2us = 2,
+    /// 1 µs
+    _1us = 3,
+    /// 500 ns, sampling only.
+    _500ns = 4,
+    /// 250 ns, sampling only.
+    _250ns = 5,
+    /// 125 ns, sampling only.
+    _125ns = 6,
+}
+
+/// Format of the samples written to the IQ buffer.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SampleType {
+    /// 16 bit I and Q values.
+    Iq = 0,
+    /// 16 bit magnitude and phase values.
+    MagPhase = 1,
+}
+
+/// Direction finding configuration.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DfeConfig {
+    pub mode: DfeMode,
+    /// Length of the CTE in units of 8 µs, 2 to 20. Ignored if `inline` is set.
+    pub cte_len: u8,
+    /// Whether the CTE length is taken from the CTEInfo field of the packet (BLE data channel
+    /// PDUs), instead of `cte_len`.
+    pub inline: bool,
+    /// Antenna switching interval. Must be `_4us`, `_2us` or `_1us`.
+    pub switch_spacing: Spacing,
+    /// Sampling interval during the reference period.
+    pub sample_spacing_ref: Spacing,
+    /// Sampling interval after the reference period.
+    pub sample_spacing: Spacing,
+    pub sample_type: SampleType,
+    /// Signed offset of the first antenna switch, in 16 MHz ticks.
+    pub switch_offset: i16,
+    /// Signed offset of the first sample, in 16 MHz ticks.
+    pub sample_offset: i16,
+}
+
+impl Default for DfeConfig {
+    /// A 160 µs AoA CTE sampled every 1 µs with antenna switching every 2 µs, as in BLE.
+    fn default() -> Self {
+        DfeConfig {
+            mode: DfeMode::Aoa,
+            cte_len: 20,
+            inline: false,
+            switch_spacing: Spacing::_2us,
+            sample_spacing_ref: Spacing::_1us,
+            sample_spacing: Spacing::_1us,
+            sample_type: SampleType::Iq,
+            switch_offset: 0,
+            sample_offset: 0,
+        }
+    }
+}
+
+/// Information about the CTE of the last received packet.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CteStatus {
+    /// CTE length in units of 8 µs.
+    pub time: u8,
+    /// CTE type from the CTEInfo field.
+    pub cte_type: u8,
+}
+
+impl<'c> Radio<'c> {
+    /// Connects `pin` to antenna switching output `index`.
+    ///
+    /// The radio drives the pin according to the switching pattern while direction finding is
+    /// active. The pin must stay configured as an output for as long as it is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `NUM_ANTENNA_PINS`.
+    pub fn set_antenna_pin(&mut self, index: usize, pin: &Pin<Output<PushPull>>) {
+        assert!(index < NUM_ANTENNA_PINS);
+        self.regs().psel.dfegpio[index].write(|w| unsafe { w.bits(pin.psel_bits()) });
+    }
+
+    /// Disconnects antenna switching output `index`.
+    pub fn clear_antenna_pin(&mut self, index: usize) {
+        assert!(index < NUM_ANTENNA_PINS);
+        self.regs().psel.dfegpio[index].write(|w| w.connect().disconnected());
+    }
+
+    /// Sets the antenna switching pattern.
+    ///
+    /// Each entry holds the states of the 8 antenna GPIOs. The first entry is used outside of the
+    /// CTE, the second one during the reference period and the following ones are cycled through
+    /// during the switching period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is shorter than 3 or longer than `MAX_PATTERN_LEN` entries.
+    pub fn set_switch_pattern(&mut self, pattern: &[u8]) {
+        assert!((3..=MAX_PATTERN_LEN).contains(&pattern.len()));
+
+        let regs = self.regs();
+        regs.clearpattern.write(|w| w.clearpattern().set_bit());
+        for state in pattern {
+            regs.switchpattern
+                .write(|w| unsafe { w.switchpattern().bits(*state) });
+        }
+    }
+
+    /// Enables CTE transmission and IQ sampling.
+    pub fn enable_direction_finding(&mut self, config: &DfeConfig) {
+        assert!(config.inline || (2..=20).contains(&config.cte_len));
+
+        let regs = self.regs();
+        // The CTE follows the CRC.
+        regs.dfectrl1.write(|w| unsafe {
+            w.numberof8us()
+                .bits(config.cte_len & 0x3f)
+                .dfeinextension()
+                .set_bit()
+                .tswitchspacing()
+                .bits(config.switch_spacing as u8)
+                .tsamplespacingref()
+                .bits(config.sample_spacing_ref as u8)
+                .sampletype()
+                .bit(config.sample_type == SampleType::MagPhase)
+                .tsamplespacing()
+                .bits(config.sample_spacing as u8)
+        });
+        regs.dfectrl2.write(|w| unsafe {
+            w.tswitchoffset()
+                .bits(config.switch_offset as u16 & 0x1fff)
+                .tsampleoffset()
+                .bits(config.sample_offset as u16 & 0x0fff)
+        });
+
+        if config.inline {
+            // Look for the CP bit of the data channel PDU header in S0, and the CTEInfo in S1.
+            regs.cteinlineconf.modify(|_, w| unsafe {
+                w.cteinlinectrlen()
+                    .set_bit()
+                    .cteinfoins1()
+                    .set_bit()
+                    .s0conf()
+                    .bits(0x20)
+                    .s0mask()
+                    .bits(0x20)
+            });
+        } else {
+            regs.cteinlineconf
+                .modify(|_, w| w.cteinlinectrlen().clear_bit());
+        }
+
+        regs.dfemode.write(|w| match config.mode {
+            DfeMode::Aoa => w.dfeopmode().ao_a(),
+            DfeMode::Aod => w.dfeopmode().ao_d(),
+        });
+    }
+
+    /// Disables CTE transmission and IQ sampling.
+    pub fn disable_direction_finding(&mut self) {
+        self.regs().dfemode.write(|w| w.dfeopmode().disabled());
+    }
+
+    /// Receives a packet into `buf` and the IQ samples of its CTE into `iq`, blocking until a
+    /// packet has arrived.
+    ///
+    /// Returns the logical address the packet was received on and the number of samples written
+    /// to `iq`.
+    pub fn receive_with_iq(
+        &mut self,
+        buf: &mut [u8],
+        iq: &mut [u32],
+    ) -> Result<(u8, usize), Error> {
+        let regs = self.regs();
+        regs.dfepacket
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(iq.as_mut_ptr() as u32) });
+        regs.dfepacket
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(iq.len().min(0xffff) as u16) });
+
+        let result = self.receive(buf);
+
+        let regs = self.regs();
+        let amount = regs.dfepacket.amount.read().amount().bits() as usize;
+        // Detach `iq`, so the CTE of packets received later is not sampled into it.
+        regs.dfepacket
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(0) });
+        regs.dfepacket.ptr.write(|w| unsafe { w.ptr().bits(0) });
+
+        let address = result?;
+        Ok((address, amount.min(iq.len())))
+    }
+
+    /// Returns the CTE information of the last received packet.
+    pub fn cte_status(&self) -> CteStatus {
+        let status = self.regs().ctestatus.read();
+        CteStatus {
+            time: status.ctetime().bits(),
+            cte_type: status.ctetype().bits(),
+        }
+    }
+
+    /// Returns reference to the `CTEPRESENT` event endpoint for PPI.
+    pub fn event_ctepresent(&self) -> &radio::EVENTS_CTEPRESENT {
+        &self.regs().events_ctepresent
+    }
+}
@@ -0,0 +1,535 @@
+//! Enhanced ShockBurst.
+//!
+//! ESB is Nordic's proprietary 2.4 GHz protocol with dynamic payload length, automatic
+//! acknowledgement (optionally carrying a payload back to the transmitter) and automatic
+//! retransmission. The on-air format matches the nRF24L01+ with dynamic payload length enabled,
+//! so both devices can talk to each other.
+//!
+//! A primary transmitter ([`Ptx`]) sends packets to one of up to 8 pipes of a primary receiver
+//! ([`Prx`]). The transmitter uses a TIMER and two PPI channels to bound the time it waits for an
+//! acknowledgement.
+
+use core::marker::PhantomData;
+
+use super::{disable, prepare, set_crc, wait_disabled, CrcConfig, TxPower, RADIO};
+use crate::error::ErrorKind;
+use crate::{
+    clocks::{Clocks, ExternalOscillator},
+    ppi::{ConfigurablePpi, Ppi},
+    timer,
+};
+
+/// Maximum payload length, as supported by the nRF24L01+.
+pub const MAX_PAYLOAD_LEN: usize = 32;
+
+/// Number of pipes.
+pub const NUM_PIPES: usize = 8;
+
+/// Size of a packet in RAM: the length and S1 (PID and ACK request) fields, then the payload.
+const PACKET_LEN: usize = 2 + MAX_PAYLOAD_LEN;
+
+/// On-air data rate.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Bitrate {
+    /// 1 Mbit/s
+    _1Mbit,
+    /// 2 Mbit/s
+    _2Mbit,
+}
+
+/// Length of the CRC appended to each packet.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CrcLen {
+    /// 8 bit CRC.
+    One,
+    /// 16 bit CRC.
+    Two,
+}
+
+/// Pipe addresses.
+///
+/// Pipe 0 uses `base0`, pipes 1 to 7 use `base1`; each pipe adds its own prefix byte. With an
+/// address length of `n` bytes, the first `n - 1` bytes of the base addresses are used. Addresses
+/// are given in the byte order used by the nRF24L01+.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Addresses {
+    pub base0: [u8; 4],
+    pub base1: [u8; 4],
+    pub prefixes: [u8; NUM_PIPES],
+}
+
+impl Default for Addresses {
+    /// The reset addresses of the nRF24L01+ pipes 0 and 1, extended to 8 pipes.
+    fn default() -> Self {
+        Addresses {
+            base0: [0xE7, 0xE7, 0xE7, 0xE7],
+            base1: [0xC2, 0xC2, 0xC2, 0xC2],
+            prefixes: [0xE7, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7, 0xC8],
+        }
+    }
+}
+
+/// ESB configuration, shared by the transmitter and the receiver.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct EsbConfig {
+    pub bitrate: Bitrate,
+    pub crc: CrcLen,
+    /// Total address length, 3 to 5 bytes.
+    pub address_len: u8,
+    /// RF channel, the frequency being 2400 MHz plus this value in MHz. At most 100.
+    pub rf_channel: u8,
+    pub tx_power: TxPower,
+    /// Time the transmitter waits for an acknowledgement after each transmission, in µs.
+    pub retransmit_delay_us: u32,
+    /// Number of retransmissions before the transmitter gives up.
+    pub max_retransmits: u8,
+}
+
+impl Default for EsbConfig {
+    fn default() -> Self {
+        EsbConfig {
+            bitrate: Bitrate::_2Mbit,
+            crc: CrcLen::Two,
+            address_len: 5,
+            rf_channel: 2,
+            tx_power: TxPower::_0DBM,
+            retransmit_delay_us: 500,
+            max_retransmits: 3,
+        }
+    }
+}
+
+/// ESB errors.
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The payload is longer than `MAX_PAYLOAD_LEN`.
+    PayloadTooLong,
+    /// The pipe number is not below `NUM_PIPES`.
+    InvalidPipe,
+    /// No acknowledgement was received after the configured number of retransmissions.
+    MaxRetransmits,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::PayloadTooLong => ErrorKind::BufferLength,
+            Error::InvalidPipe => ErrorKind::InvalidArgument,
+            Error::MaxRetransmits => ErrorKind::Nack,
+        }
+    }
+}
+
+/// Reverses the bit order in each byte, as the nRF24L01+ transmits addresses MSB first.
+fn bytewise_bit_swap(bytes: [u8; 4]) -> u32 {
+    u32::from_le_bytes(bytes).reverse_bits().swap_bytes()
+}
+
+/// Converts a base address to the `BASEn` register layout.
+fn base_address(bytes: [u8; 4]) -> u32 {
+    bytewise_bit_swap(bytes).swap_bytes()
+}
+
+/// Configures the radio for ESB.
+fn configure(radio: &RADIO, config: &EsbConfig, addresses: &Addresses) {
+    assert!((3..=5).contains(&config.address_len));
+    assert!(config.rf_channel <= 100);
+
+    match config.bitrate {
+        Bitrate::_1Mbit => radio.mode.write(|w| w.mode().nrf_1mbit()),
+        Bitrate::_2Mbit => radio.mode.write(|w| w.mode().nrf_2mbit()),
+    }
+    radio
+        .txpower
+        .write(|w| w.txpower().variant(config.tx_power));
+    radio
+        .frequency
+        .write(|w| unsafe { w.frequency().bits(config.rf_channel).map().default() });
+
+    // 6 bit payload length followed by the 2 bit PID and the NO_ACK flag.
+    radio.pcnf0.write(|w| unsafe {
+        w.lflen()
+            .bits(6)
+            .s0len()
+            .clear_bit()
+            .s1len()
+            .bits(3)
+            .plen()
+            ._8bit()
+    });
+    radio.pcnf1.write(|w| unsafe {
+        w.maxlen()
+            .bits(MAX_PAYLOAD_LEN as u8)
+            .statlen()
+            .bits(0)
+            .balen()
+            .bits(config.address_len - 1)
+            .endian()
+            .big()
+            .whiteen()
+            .disabled()
+    });
+
+    set_crc(
+        radio,
+        match config.crc {
+            CrcLen::One => &CrcConfig::CCITT_8,
+            CrcLen::Two => &CrcConfig::CCITT_16,
+        },
+    );
+
+    radio
+        .base0
+        .write(|w| unsafe { w.bits(base_address(addresses.base0)) });
+    radio
+        .base1
+        .write(|w| unsafe { w.bits(base_address(addresses.base1)) });
+    let mut prefix0 = [0; 4];
+    let mut prefix1 = [0; 4];
+    prefix0.copy_from_slice(&addresses.prefixes[..4]);
+    prefix1.copy_from_slice(&addresses.prefixes[4..]);
+    radio
+        .prefix0
+        .write(|w| unsafe { w.bits(bytewise_bit_swap(prefix0)) });
+    radio
+        .prefix1
+        .write(|w| unsafe { w.bits(bytewise_bit_swap(prefix1)) });
+}
+
+/// ESB primary transmitter.
+///
+/// `A` and `B` are two configurable PPI channels. They connect the TIMER's `COMPARE[0]` event to
+/// the radio's `DISABLE` task, ending the acknowledgement window, and the radio's `ADDRESS` event
+/// to the TIMER's `STOP` task, so an acknowledgement that has started is received completely.
+pub struct Ptx<'c, T, A, B> {
+    radio: RADIO,
+    timer: T,
+    ppi: (A, B),
+    config: EsbConfig,
+    pids: [u8; NUM_PIPES],
+    tx_buf: [u8; PACKET_LEN],
+    rx_buf: [u8; PACKET_LEN],
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c, T, A, B> Ptx<'c, T, A, B>
+where
+    T: timer::Instance,
+    A: Ppi + ConfigurablePpi,
+    B: Ppi + ConfigurablePpi,
+{
+    /// Configures the radio, TIMER and PPI channels for ESB transmission.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        timer: T,
+        ppi: (A, B),
+        _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>,
+        config: EsbConfig,
+        addresses: &Addresses,
+    ) -> Self {
+        disable(&radio);
+        configure(&radio, &config, addresses);
+
+        // 1 MHz, stopping itself at the end of the acknowledgement window.
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+        regs.prescaler.write(|w| unsafe { w.prescaler().bits(4) });
+        regs.shorts
+            .write(|w| w.compare0_clear().enabled().compare0_stop().enabled());
+        regs.cc[0].write(|w| unsafe { w.bits(config.retransmit_delay_us) });
+
+        let (mut a, mut b) = ppi;
+        a.set_event_endpoint(&regs.events_compare[0]);
+        a.set_task_endpoint(&radio.tasks_disable);
+        b.set_event_endpoint(&radio.events_address);
+        b.set_task_endpoint(&regs.tasks_stop);
+
+        Ptx {
+            radio,
+            timer,
+            ppi: (a, b),
+            config,
+            pids: [0; NUM_PIPES],
+            tx_buf: [0; PACKET_LEN],
+            rx_buf: [0; PACKET_LEN],
+            _clocks: PhantomData,
+        }
+    }
+
+    /// Sends `payload` to `pipe`, blocking until it has been acknowledged.
+    ///
+    /// The packet is retransmitted up to `max_retransmits` times if no acknowledgement is
+    /// received. On success, the payload of the acknowledgement is returned, which is empty if the
+    /// receiver had nothing to send back.
+    pub fn send(&mut self, pipe: u8, payload: &[u8]) -> Result<&[u8], Error> {
+        self.prepare_packet(pipe, payload, true)?;
+
+        for _ in 0..=self.config.max_retransmits {
+            if let Some(len) = self.transmit(pipe, true) {
+                self.pids[pipe as usize] = (self.pids[pipe as usize] + 1) % 4;
+                return Ok(&self.rx_buf[2..2 + len]);
+            }
+        }
+
+        // The receiver may still have got the packet, so use a fresh PID for the next one.
+        self.pids[pipe as usize] = (self.pids[pipe as usize] + 1) % 4;
+        Err(Error::MaxRetransmits)
+    }
+
+    /// Sends `payload` to `pipe` once, without requesting an acknowledgement.
+    pub fn send_no_ack(&mut self, pipe: u8, payload: &[u8]) -> Result<(), Error> {
+        self.prepare_packet(pipe, payload, false)?;
+        self.transmit(pipe, false);
+        self.pids[pipe as usize] = (self.pids[pipe as usize] + 1) % 4;
+        Ok(())
+    }
+
+    /// Disables the radio and returns the raw peripherals.
+    pub fn free(self) -> (RADIO, T, (A, B)) {
+        disable(&self.radio);
+        let (mut a, mut b) = self.ppi;
+        a.disable();
+        b.disable();
+        self.timer.as_timer0().shorts.reset();
+        (self.radio, self.timer, (a, b))
+    }
+
+    fn prepare_packet(&mut self, pipe: u8, payload: &[u8], ack: bool) -> Result<(), Error> {
+        if pipe as usize >= NUM_PIPES {
+            return Err(Error::InvalidPipe);
+        }
+        if payload.len() > MAX_PAYLOAD_LEN {
+            return Err(Error::PayloadTooLong);
+        }
+
+        self.tx_buf[0] = payload.len() as u8;
+        self.tx_buf[1] = (self.pids[pipe as usize] << 1) | ack as u8;
+        self.tx_buf[2..2 + payload.len()].copy_from_slice(payload);
+        Ok(())
+    }
+
+    /// Transmits the packet in `tx_buf` once. If `ack` is set, waits for an acknowledgement and
+    /// returns the length of its payload.
+    fn transmit(&mut self, pipe: u8, ack: bool) -> Option<usize> {
+        self.radio
+            .txaddress
+            .write(|w| unsafe { w.txaddress().bits(pipe) });
+        self.radio
+            .rxaddresses
+            .write(|w| unsafe { w.bits(1 << pipe) });
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.tx_buf.as_ptr() as u32) });
+
+        if ack {
+            // Switch to RX right after the transmission.
+            self.radio.shorts.write(|w| {
+                w.ready_start()
+                    .enabled()
+                    .end_disable()
+                    .enabled()
+                    .disabled_rxen()
+                    .enabled()
+            });
+        } else {
+            self.radio
+                .shorts
+                .write(|w| w.ready_start().enabled().end_disable().enabled());
+        }
+
+        prepare(&self.radio);
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        while self.radio.events_disabled.read().bits() == 0 {}
+        self.radio.events_disabled.reset();
+
+        if !ack {
+            self.radio.shorts.reset();
+            return None;
+        }
+
+        // The radio is now ramping up for RX: receive a single packet into `rx_buf`, or give up
+        // when the TIMER disables the radio.
+        self.radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.rx_buf.as_mut_ptr() as u32) });
+        self.radio.events_end.reset();
+
+        let regs = self.timer.as_timer0();
+        regs.events_compare[0].reset();
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        self.ppi.0.enable();
+        self.ppi.1.enable();
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        wait_disabled(&self.radio);
+
+        self.ppi.0.disable();
+        self.ppi.1.disable();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        let received = self.radio.events_end.read().bits() != 0
+            && self.radio.crcstatus.read().crcstatus().is_crcok();
+        self.radio.events_end.reset();
+
+        if received {
+            Some((self.rx_buf[0] as usize).min(MAX_PAYLOAD_LEN))
+        } else {
+            None
+        }
+    }
+}
+
+/// Information about a packet received by the [`Prx`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RxPacket {
+    /// Pipe the packet was addressed to.
+    pub pipe: u8,
+    /// Length of the payload.
+    pub len: usize,
+}
+
+/// ESB primary receiver.
+///
+/// Packets requesting an acknowledgement are acknowledged automatically, with the payload
+/// queued for the pipe through `set_ack_payload`, if any. Retransmissions of an already received
+/// packet are acknowledged again but not reported.
+pub struct Prx<'c> {
+    radio: RADIO,
+    enabled_pipes: u8,
+    last: [Option<(u8, u32)>; NUM_PIPES],
+    rx_buf: [u8; PACKET_LEN],
+    ack_bufs: [[u8; PACKET_LEN]; NUM_PIPES],
+    empty_ack: [u8; 2],
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> Prx<'c> {
+    /// Configures the radio for ESB reception on the pipes set in the `enabled_pipes` bit mask.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>,
+        config: EsbConfig,
+        addresses: &Addresses,
+        enabled_pipes: u8,
+    ) -> Self {
+        disable(&radio);
+        configure(&radio, &config, addresses);
+
+        Prx {
+            radio,
+            enabled_pipes,
+            last: [None; NUM_PIPES],
+            rx_buf: [0; PACKET_LEN],
+            ack_bufs: [[0; PACKET_LEN]; NUM_PIPES],
+            empty_ack: [0; 2],
+            _clocks: PhantomData,
+        }
+    }
+
+    /// Sets the payload sent with the acknowledgement of the next new packet on `pipe`.
+    pub fn set_ack_payload(&mut self, pipe: u8, payload: &[u8]) -> Result<(), Error> {
+        if pipe as usize >= NUM_PIPES {
+            return Err(Error::InvalidPipe);
+        }
+        if payload.len() > MAX_PAYLOAD_LEN {
+            return Err(Error::PayloadTooLong);
+        }
+
+        let buf = &mut self.ack_bufs[pipe as usize];
+        buf[0] = payload.len() as u8;
+        buf[2..2 + payload.len()].copy_from_slice(payload);
+        Ok(())
+    }
+
+    /// Receives a packet, blocking until a new one has arrived, and copies its payload to the
+    /// beginning of `buf`.
+    pub fn recv(&mut self, buf: &mut [u8; MAX_PAYLOAD_LEN]) -> RxPacket {
+        loop {
+            self.radio
+                .rxaddresses
+                .write(|w| unsafe { w.bits(self.enabled_pipes as u32) });
+            self.radio
+                .packetptr
+                .write(|w| unsafe { w.bits(self.rx_buf.as_mut_ptr() as u32) });
+            // Turn around to TX right after the reception, for the acknowledgement.
+            self.radio.shorts.write(|w| {
+                w.ready_start()
+                    .enabled()
+                    .end_disable()
+                    .enabled()
+                    .disabled_txen()
+                    .enabled()
+            });
+
+            prepare(&self.radio);
+            self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+            while self.radio.events_disabled.read().bits() == 0 {}
+            self.radio.events_disabled.reset();
+
+            let pipe = self.radio.rxmatch.read().rxmatch().bits();
+            let pid = (self.rx_buf[1] >> 1) & 0b11;
+            let ack = self.rx_buf[1] & 1 != 0;
+            let crc = self.radio.rxcrc.read().rxcrc().bits();
+            let crc_ok = self.radio.crcstatus.read().crcstatus().is_crcok();
+
+            if !crc_ok || !ack {
+                // Abort the turnaround to TX.
+                disable(&self.radio);
+                if crc_ok {
+                    return self.deliver(pipe, buf);
+                }
+                continue;
+            }
+
+            // A packet we have already seen means our acknowledgement got lost. Acknowledge it
+            // again, but keep the queued payload for the next new packet.
+            let new = self.last[pipe as usize] != Some((pid, crc));
+            let ack_buf = if new {
+                &mut self.ack_bufs[pipe as usize][..]
+            } else {
+                &mut self.empty_ack[..]
+            };
+            ack_buf[1] = pid << 1;
+            self.radio
+                .txaddress
+                .write(|w| unsafe { w.txaddress().bits(pipe) });
+            self.radio
+                .packetptr
+                .write(|w| unsafe { w.bits(ack_buf.as_ptr() as u32) });
+            self.radio
+                .shorts
+                .write(|w| w.ready_start().enabled().end_disable().enabled());
+            wait_disabled(&self.radio);
+
+            if new {
+                // The acknowledgement payload is only sent once.
+                self.ack_bufs[pipe as usize][0] = 0;
+                self.last[pipe as usize] = Some((pid, crc));
+                return self.deliver(pipe, buf);
+            }
+        }
+    }
+
+    /// Disables the radio and returns the raw `RADIO` peripheral.
+    pub fn free(self) -> RADIO {
+        disable(&self.radio);
+        self.radio
+    }
+
+    fn deliver(&self, pipe: u8, buf: &mut [u8; MAX_PAYLOAD_LEN]) -> RxPacket {
+        let len = (self.rx_buf[0] as usize).min(MAX_PAYLOAD_LEN);
+        buf[..len].copy_from_slice(&self.rx_buf[2..2 + len]);
+        RxPacket { pipe, len }
+    }
+}
@@ -0,0 +1,134 @@
+//! Channel hopping.
+//!
+//! [`ChannelHopper`] walks through a user-supplied channel map, retuning the radio's frequency and
+//! data whitening value on each hop. The map can be replaced at any time, e.g. to drop channels
+//! with bad link quality for adaptive frequency hopping.
+//!
+//! [`TimedHopper`] additionally schedules the hops with a periodic TIMER.
+
+use embedded_hal::timer::{Cancel, CountDown};
+use void::Void;
+
+use super::proprietary::Radio;
+use crate::timer::{self, Periodic, Timer};
+
+/// An entry of the channel map.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Channel {
+    /// Frequency offset from 2400 MHz in MHz, 0 to 100.
+    pub frequency: u8,
+    /// Data whitening initial value used on this channel, e.g. the BLE channel index.
+    pub whitening_iv: u8,
+}
+
+/// Hops through a channel map.
+pub struct ChannelHopper<'m> {
+    map: &'m [Channel],
+    index: usize,
+    increment: usize,
+}
+
+impl<'m> ChannelHopper<'m> {
+    /// Creates a hopper visiting the channels of `map` in order, starting with the first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` is empty.
+    pub fn new(map: &'m [Channel]) -> Self {
+        Self::with_increment(map, 1)
+    }
+
+    /// Creates a hopper advancing by `increment` entries of `map` on each hop, wrapping around at
+    /// the end, like the BLE channel selection algorithm #1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` is empty.
+    pub fn with_increment(map: &'m [Channel], increment: usize) -> Self {
+        assert!(!map.is_empty());
+        ChannelHopper {
+            map,
+            index: 0,
+            increment,
+        }
+    }
+
+    /// Replaces the channel map. The next hop continues from the current position, wrapped to
+    /// the length of the new map.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` is empty.
+    pub fn set_map(&mut self, map: &'m [Channel]) {
+        assert!(!map.is_empty());
+        self.map = map;
+        self.index %= map.len();
+    }
+
+    /// Returns the current channel.
+    pub fn current(&self) -> Channel {
+        self.map[self.index]
+    }
+
+    /// Tunes `radio` to the current channel.
+    ///
+    /// The new frequency takes effect the next time the radio is enabled.
+    pub fn tune(&self, radio: &mut Radio) {
+        let channel = self.current();
+        radio.set_frequency(channel.frequency);
+        radio.set_whitening_iv(channel.whitening_iv);
+    }
+
+    /// Advances to the next channel, tunes `radio` to it and returns it.
+    pub fn hop(&mut self, radio: &mut Radio) -> Channel {
+        self.index = (self.index + self.increment) % self.map.len();
+        self.tune(radio);
+        self.current()
+    }
+}
+
+/// Hops through a channel map at a fixed interval, timed by a TIMER.
+pub struct TimedHopper<'m, T> {
+    hopper: ChannelHopper<'m>,
+    timer: Timer<T, Periodic>,
+}
+
+impl<'m, T> TimedHopper<'m, T>
+where
+    T: timer::Instance,
+{
+    /// Tunes `radio` to the first channel of `hopper` and starts hopping every `interval_us`
+    /// microseconds.
+    pub fn new(
+        hopper: ChannelHopper<'m>,
+        timer: Timer<T, Periodic>,
+        radio: &mut Radio,
+        interval_us: u32,
+    ) -> Self {
+        let mut hopper = TimedHopper { hopper, timer };
+        hopper.hopper.tune(radio);
+        hopper.timer.start(interval_us);
+        hopper
+    }
+
+    /// Returns the underlying hopper, e.g. to change the channel map.
+    pub fn hopper(&mut self) -> &mut ChannelHopper<'m> {
+        &mut self.hopper
+    }
+
+    /// Hops to the next channel once the interval has elapsed (non-blocking).
+    ///
+    /// Returns the new channel. Call this from the main loop, or from the TIMER interrupt handler
+    /// after enabling the timer interrupt.
+    pub fn poll(&mut self, radio: &mut Radio) -> nb::Result<Channel, Void> {
+        self.timer.wait()?;
+        Ok(self.hopper.hop(radio))
+    }
+
+    /// Stops hopping and returns the hopper and the timer.
+    pub fn free(mut self) -> (ChannelHopper<'m>, Timer<T, Periodic>) {
+        let _ = self.timer.cancel();
+        (self.hopper, self.timer)
+    }
+}
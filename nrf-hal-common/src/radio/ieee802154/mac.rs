@@ -0,0 +1,442 @@
+//! Unslotted CSMA-CA and automatic acknowledgements.
+//!
+//! Timing critical steps are handled in hardware: a TIMER started by the radio's `PHYEND` event
+//! through PPI either turns the radio around to send an acknowledgement exactly one turnaround
+//! time after a received frame, or ends the wait for an acknowledgement of a sent frame.
+
+use super::{Error, Packet, Radio};
+use crate::{
+    ppi::{ConfigurablePpi, Ppi},
+    radio::{prepare, wait_disabled},
+    timer,
+};
+
+/// Duration of a backoff period (20 symbols), in µs.
+const UNIT_BACKOFF_US: u32 = 320;
+
+/// Turnaround time between a frame and its acknowledgement (12 symbols), in µs.
+const TURNAROUND_US: u32 = 192;
+
+/// Time to wait for an acknowledgement after a frame has been sent (54 symbols), in µs.
+const ACK_WAIT_US: u32 = 864;
+
+/// Duration of the radio's fast TX ramp-up, in µs.
+const RAMP_UP_US: u32 = 40;
+
+/// Capture/compare register used for the turnaround and the acknowledgement wait.
+const CC_PPI: usize = 0;
+
+/// Capture/compare register used for backoff delays.
+const CC_BACKOFF: usize = 1;
+
+/// Frame type of acknowledgement frames.
+const FRAME_TYPE_ACK: u16 = 0b010;
+
+/// Frame control field bits.
+const FCF_FRAME_PENDING: u16 = 1 << 4;
+const FCF_ACK_REQUEST: u16 = 1 << 5;
+
+/// Broadcast PAN ID and short address.
+const BROADCAST: u16 = 0xFFFF;
+
+/// CSMA-CA and retransmission parameters.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CsmaConfig {
+    /// Initial backoff exponent, `macMinBE`.
+    pub min_be: u8,
+    /// Maximum backoff exponent, `macMaxBE`.
+    pub max_be: u8,
+    /// Number of busy clear channel assessments before giving up, `macMaxCSMABackoffs`.
+    pub max_backoffs: u8,
+    /// Number of retransmissions of a frame that was not acknowledged, `macMaxFrameRetries`.
+    pub max_frame_retries: u8,
+}
+
+impl Default for CsmaConfig {
+    /// The defaults of the standard.
+    fn default() -> Self {
+        CsmaConfig {
+            min_be: 3,
+            max_be: 5,
+            max_backoffs: 4,
+            max_frame_retries: 3,
+        }
+    }
+}
+
+/// Result of a successful transmission.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxInfo {
+    /// Whether the acknowledgement had the frame pending bit set, i.e. the recipient has data
+    /// for us. Always `false` for frames without an acknowledgement request.
+    pub frame_pending: bool,
+}
+
+/// Destination of a received frame.
+enum Destination {
+    /// The frame is addressed to this device.
+    Unicast,
+    /// The frame is a broadcast, or has no destination address.
+    Broadcast,
+    /// The frame is addressed to another device.
+    Other,
+}
+
+/// MAC layer functions on top of the IEEE 802.15.4 [`Radio`].
+///
+/// `A` and `B` are two configurable PPI channels used to connect the radio and the TIMER.
+pub struct Mac<'c, T, A, B> {
+    radio: Radio<'c>,
+    timer: T,
+    ppi: (A, B),
+    config: CsmaConfig,
+    pan_id: u16,
+    short_address: u16,
+    extended_address: u64,
+    frame_pending: bool,
+    random: u32,
+    ack: Packet,
+}
+
+impl<'c, T, A, B> Mac<'c, T, A, B>
+where
+    T: timer::Instance,
+    A: Ppi + ConfigurablePpi,
+    B: Ppi + ConfigurablePpi,
+{
+    /// Creates the MAC layer.
+    ///
+    /// `seed` initializes the pseudo random generator used for the backoff delays, and should
+    /// differ between devices, e.g. by taking it from the RNG peripheral.
+    pub fn new(radio: Radio<'c>, timer: T, ppi: (A, B), config: CsmaConfig, seed: u32) -> Self {
+        // 1 MHz, stopping at the end of the turnaround or acknowledgement wait.
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.mode.write(|w| w.mode().timer());
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+        regs.prescaler.write(|w| unsafe { w.prescaler().bits(4) });
+        regs.shorts
+            .write(|w| w.compare0_clear().enabled().compare0_stop().enabled());
+
+        Mac {
+            radio,
+            timer,
+            ppi,
+            config,
+            pan_id: BROADCAST,
+            short_address: BROADCAST,
+            extended_address: 0,
+            frame_pending: false,
+            // xorshift gets stuck at zero.
+            random: seed | 1,
+            ack: Packet::new(),
+        }
+    }
+
+    /// Sets the addresses used to decide which received frames are acknowledged.
+    pub fn set_addresses(&mut self, pan_id: u16, short_address: u16, extended_address: u64) {
+        self.pan_id = pan_id;
+        self.short_address = short_address;
+        self.extended_address = extended_address;
+    }
+
+    /// Sets the frame pending bit of the acknowledgements sent from now on, e.g. when data is
+    /// queued for a sleepy child polling this device.
+    pub fn set_frame_pending(&mut self, pending: bool) {
+        self.frame_pending = pending;
+    }
+
+    /// Returns a reference to the underlying radio, e.g. to change the channel.
+    pub fn radio(&mut self) -> &mut Radio<'c> {
+        &mut self.radio
+    }
+
+    /// Sends `packet` using unslotted CSMA-CA.
+    ///
+    /// If the frame requests an acknowledgement, waits for it and retransmits the frame up to
+    /// `max_frame_retries` times.
+    pub fn send(&mut self, packet: &mut Packet) -> Result<TxInfo, Error> {
+        let fcf = frame_control(packet);
+        let ack_request = fcf & FCF_ACK_REQUEST != 0;
+        let sequence = packet.get(2).copied();
+
+        for _ in 0..=self.config.max_frame_retries {
+            self.send_csma(packet, ack_request)?;
+            if !ack_request {
+                return Ok(TxInfo {
+                    frame_pending: false,
+                });
+            }
+            if let Some(frame_pending) = self.wait_ack(sequence) {
+                return Ok(TxInfo { frame_pending });
+            }
+        }
+
+        Err(Error::NoAck)
+    }
+
+    /// Receives a frame addressed to this device into `packet`, blocking until one has arrived.
+    ///
+    /// Frames addressed to other devices are dropped. If the frame requests an acknowledgement,
+    /// it is sent one turnaround time after the frame.
+    ///
+    /// Returns the FCS of the frame.
+    pub fn recv(&mut self, packet: &mut Packet) -> Result<u16, Error> {
+        loop {
+            self.timer_reset(TURNAROUND_US - RAMP_UP_US);
+            self.ppi.0.set_event_endpoint(self.radio.event_phyend());
+            self.ppi
+                .0
+                .set_task_endpoint(&self.timer.as_timer0().tasks_start);
+            self.ppi
+                .1
+                .set_event_endpoint(&self.timer.as_timer0().events_compare[CC_PPI]);
+            self.ppi.1.set_task_endpoint(&self.radio.radio.tasks_txen);
+            self.ppi.0.enable();
+
+            let result = self.radio.recv(packet);
+            self.ppi.0.disable();
+
+            let crc = match result {
+                Ok(crc) => crc,
+                Err(e) => {
+                    self.timer_stop();
+                    return Err(e);
+                }
+            };
+
+            let destination = self.destination(packet);
+            if let Destination::Other = destination {
+                self.timer_stop();
+                continue;
+            }
+
+            let fcf = frame_control(packet);
+            if fcf & FCF_ACK_REQUEST != 0 {
+                if let Destination::Unicast = destination {
+                    self.send_ack(packet[2]);
+                }
+            }
+            self.timer_stop();
+            return Ok(crc);
+        }
+    }
+
+    /// Returns the radio, TIMER and PPI channels.
+    pub fn free(self) -> (Radio<'c>, T, (A, B)) {
+        let (mut a, mut b) = self.ppi;
+        a.disable();
+        b.disable();
+        let regs = self.timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.shorts.reset();
+        (self.radio, self.timer, (a, b))
+    }
+
+    /// Performs the CSMA-CA backoffs and clear channel assessments, and sends the frame once
+    /// the channel is idle.
+    ///
+    /// If `ack_request` is set, the TIMER is started at the end of the frame to bound the wait
+    /// for the acknowledgement.
+    fn send_csma(&mut self, packet: &mut Packet, ack_request: bool) -> Result<(), Error> {
+        let mut be = self.config.min_be;
+
+        for _ in 0..=self.config.max_backoffs {
+            let periods = self.next_random() & ((1 << be) - 1);
+            self.backoff(periods * UNIT_BACKOFF_US);
+
+            if ack_request {
+                self.timer_reset(ACK_WAIT_US);
+                self.ppi.0.set_event_endpoint(self.radio.event_phyend());
+                self.ppi
+                    .0
+                    .set_task_endpoint(&self.timer.as_timer0().tasks_start);
+                self.ppi.0.enable();
+            }
+
+            let result = self.radio.try_send(packet);
+            self.ppi.0.disable();
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(Error::ChannelBusy) => be = (be + 1).min(self.config.max_be),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(Error::ChannelAccessFailure)
+    }
+
+    /// Receives the acknowledgement of the frame with the given sequence number, if any, and
+    /// returns its frame pending bit.
+    fn wait_ack(&mut self, sequence: Option<u8>) -> Option<bool> {
+        // The TIMER was started at the end of the frame, and disables the radio once the wait is
+        // over.
+        self.ppi
+            .1
+            .set_event_endpoint(&self.timer.as_timer0().events_compare[CC_PPI]);
+        self.ppi
+            .1
+            .set_task_endpoint(&self.radio.radio.tasks_disable);
+        self.ppi.1.enable();
+
+        let radio = &self.radio.radio;
+        radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.ack.as_ptr()) });
+        radio
+            .shorts
+            .write(|w| w.rxready_start().enabled().phyend_disable().enabled());
+        // Clear the event left over from the transmission.
+        radio.events_phyend.reset();
+        prepare(radio);
+        radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(radio);
+
+        let received =
+            radio.events_phyend.read().bits() != 0 && radio.crcstatus.read().crcstatus().is_crcok();
+        radio.events_phyend.reset();
+
+        self.ppi.1.disable();
+        self.timer_stop();
+        if !received {
+            return None;
+        }
+
+        let fcf = frame_control(&self.ack);
+        if self.ack.len() == 3 && fcf & 0b111 == FRAME_TYPE_ACK && Some(self.ack[2]) == sequence {
+            Some(fcf & FCF_FRAME_PENDING != 0)
+        } else {
+            None
+        }
+    }
+
+    /// Sends the acknowledgement of a received frame. The TIMER started at the end of the frame
+    /// enables the transmitter through PPI.
+    fn send_ack(&mut self, sequence: u8) {
+        let fcf = FRAME_TYPE_ACK
+            | if self.frame_pending {
+                FCF_FRAME_PENDING
+            } else {
+                0
+            };
+        let fcf = fcf.to_le_bytes();
+        self.ack.copy_from_slice(&[fcf[0], fcf[1], sequence]);
+
+        let radio = &self.radio.radio;
+        radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.ack.as_ptr()) });
+        radio
+            .shorts
+            .write(|w| w.txready_start().enabled().phyend_disable().enabled());
+        prepare(radio);
+        self.ppi.1.enable();
+
+        let regs = self.timer.as_timer0();
+        if regs.events_compare[CC_PPI].read().bits() != 0
+            && radio.state.read().state().is_disabled()
+        {
+            // Too late, the turnaround time is over. The sender will retransmit the frame.
+            self.ppi.1.disable();
+            radio.shorts.reset();
+            return;
+        }
+
+        wait_disabled(radio);
+        self.ppi.1.disable();
+    }
+
+    /// Returns where a received frame is addressed to.
+    fn destination(&self, frame: &[u8]) -> Destination {
+        let fcf = frame_control(frame);
+        let (address_len, address) = match (fcf >> 10) & 0b11 {
+            0b10 => (2, frame.get(5..7)),
+            0b11 => (8, frame.get(5..13)),
+            _ => return Destination::Broadcast,
+        };
+        let (pan_id, address) = match (frame.get(3..5), address) {
+            (Some(pan_id), Some(address)) => (u16::from_le_bytes([pan_id[0], pan_id[1]]), address),
+            _ => return Destination::Other,
+        };
+
+        if pan_id != self.pan_id && pan_id != BROADCAST {
+            return Destination::Other;
+        }
+        if address_len == 2 {
+            match u16::from_le_bytes([address[0], address[1]]) {
+                BROADCAST => Destination::Broadcast,
+                a if a == self.short_address => Destination::Unicast,
+                _ => Destination::Other,
+            }
+        } else {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(address);
+            if u64::from_le_bytes(bytes) == self.extended_address {
+                Destination::Unicast
+            } else {
+                Destination::Other
+            }
+        }
+    }
+
+    /// Waits `us` microseconds, using the TIMER.
+    fn backoff(&mut self, us: u32) {
+        if us == 0 {
+            return;
+        }
+
+        let regs = self.timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        // The shorts would stop the TIMER at the turnaround or acknowledgement wait still held in
+        // `CC_PPI`, before a longer backoff is over.
+        regs.shorts.reset();
+        regs.cc[CC_BACKOFF].write(|w| unsafe { w.bits(us) });
+        regs.events_compare[CC_BACKOFF].reset();
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+        while regs.events_compare[CC_BACKOFF].read().bits() == 0 {}
+        regs.events_compare[CC_BACKOFF].reset();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.events_compare[CC_PPI].reset();
+        regs.shorts
+            .write(|w| w.compare0_clear().enabled().compare0_stop().enabled());
+    }
+
+    /// Stops and clears the TIMER, and prepares it to be started through PPI.
+    fn timer_reset(&mut self, us: u32) {
+        let regs = self.timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        regs.cc[CC_PPI].write(|w| unsafe { w.bits(us) });
+        regs.events_compare[CC_PPI].reset();
+    }
+
+    /// Stops and clears the TIMER.
+    fn timer_stop(&mut self) {
+        let regs = self.timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        regs.events_compare[CC_PPI].reset();
+    }
+
+    /// Returns the next pseudo random number (xorshift32).
+    fn next_random(&mut self) -> u32 {
+        let mut x = self.random;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random = x;
+        x
+    }
+}
+
+/// Returns the frame control field of a frame, or 0 if the frame is too short.
+fn frame_control(frame: &[u8]) -> u16 {
+    match frame.get(0..2) {
+        Some(fcf) => u16::from_le_bytes([fcf[0], fcf[1]]),
+        None => 0,
+    }
+}
@@ -0,0 +1,400 @@
+//! IEEE 802.15.4 radio driver.
+//!
+//! Supports frame transmission and reception with hardware CRC (FCS) handling, clear channel
+//! assessment and energy detection, as available on the nRF52833 and nRF52840.
+//!
+//! Reception can be timestamped by connecting [`Radio::event_framestart`] to a TIMER capture task
+//! through PPI.
+//!
+//! [`Mac`] builds unslotted CSMA-CA and automatic acknowledgements on top of the [`Radio`].
+
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use super::{disable, prepare, wait_disabled, TxPower};
+use crate::error::ErrorKind;
+use crate::{
+    clocks::{Clocks, ExternalOscillator},
+    pac::{radio, RADIO},
+};
+
+mod mac;
+pub use mac::{CsmaConfig, Mac, TxInfo};
+
+/// Maximum size of the PSDU, including the two byte FCS.
+pub const MAX_PSDU_SIZE: u8 = 127;
+
+/// Size of the frame check sequence appended by the hardware.
+const FCS_SIZE: u8 = 2;
+
+/// Start-of-frame delimiter.
+const SFD: u8 = 0xA7;
+
+/// An IEEE 802.15.4 frame buffer.
+///
+/// The buffer holds the PHY header (the frame length) and the PSDU. The FCS is appended and checked
+/// by the hardware and is not part of the payload. On reception, the radio writes the link quality
+/// indicator over the first FCS byte, so the received FCS is only available as returned by
+/// `Radio::recv`.
+pub struct Packet {
+    buffer: [u8; 1 + MAX_PSDU_SIZE as usize],
+}
+
+impl Packet {
+    /// Maximum number of payload bytes, excluding the FCS.
+    pub const CAPACITY: u8 = MAX_PSDU_SIZE - FCS_SIZE;
+
+    /// Creates an empty packet.
+    pub fn new() -> Self {
+        let mut packet = Packet {
+            buffer: [0; 1 + MAX_PSDU_SIZE as usize],
+        };
+        packet.set_len(0);
+        packet
+    }
+
+    /// Copies `payload` into the packet and sets the length accordingly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `payload` is longer than `Packet::CAPACITY`.
+    pub fn copy_from_slice(&mut self, payload: &[u8]) {
+        assert!(payload.len() <= Self::CAPACITY as usize);
+        self.buffer[1..1 + payload.len()].copy_from_slice(payload);
+        self.set_len(payload.len() as u8);
+    }
+
+    /// Returns the length of the payload, excluding the FCS.
+    ///
+    /// The PHY header of a received frame is clamped to `MAX_PSDU_SIZE`, as the radio receives no
+    /// more than that.
+    pub fn len(&self) -> u8 {
+        self.buffer[0].min(MAX_PSDU_SIZE).saturating_sub(FCS_SIZE)
+    }
+
+    /// Returns `true` if the packet has no payload.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets the length of the payload, excluding the FCS.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than `Packet::CAPACITY`.
+    pub fn set_len(&mut self, len: u8) {
+        assert!(len <= Self::CAPACITY);
+        self.buffer[0] = len + FCS_SIZE;
+    }
+
+    /// Returns the link quality indicator of a received packet.
+    pub fn lqi(&self) -> u8 {
+        // Written by the radio in place of the first FCS byte.
+        self.buffer[1 + self.len() as usize]
+    }
+
+    fn as_ptr(&self) -> u32 {
+        self.buffer.as_ptr() as u32
+    }
+}
+
+impl Default for Packet {
+    fn default() -> Self {
+        Packet::new()
+    }
+}
+
+impl Deref for Packet {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[1..1 + self.len() as usize]
+    }
+}
+
+impl DerefMut for Packet {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len() as usize;
+        &mut self.buffer[1..1 + len]
+    }
+}
+
+/// IEEE 802.15.4 channels in the 2.4 GHz band.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channel {
+    /// 2405 MHz
+    _11 = 11,
+    /// 2410 MHz
+    _12,
+    /// 2415 MHz
+    _13,
+    /// 2420 MHz
+    _14,
+    /// 2425 MHz
+    _15,
+    /// 2430 MHz
+    _16,
+    /// 2435 MHz
+    _17,
+    /// 2440 MHz
+    _18,
+    /// 2445 MHz
+    _19,
+    /// 2450 MHz
+    _20,
+    /// 2455 MHz
+    _21,
+    /// 2460 MHz
+    _22,
+    /// 2465 MHz
+    _23,
+    /// 2470 MHz
+    _24,
+    /// 2475 MHz
+    _25,
+    /// 2480 MHz
+    _26,
+}
+
+impl Channel {
+    /// Offset of the channel frequency from 2400 MHz, as written to the `FREQUENCY` register.
+    fn frequency_offset(self) -> u8 {
+        5 + 5 * (self as u8 - 11)
+    }
+}
+
+/// Clear channel assessment method.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Cca {
+    /// The channel is busy if an IEEE 802.15.4 signal is detected.
+    CarrierSense,
+    /// The channel is busy if the measured energy is above the threshold.
+    EnergyDetection {
+        /// Threshold, in the same units as returned by `energy_detection_scan`.
+        ed_threshold: u8,
+    },
+}
+
+/// IEEE 802.15.4 radio errors.
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A frame was received, but its FCS did not match. Contains the received FCS.
+    Crc(u16),
+    /// The clear channel assessment found the channel busy.
+    ChannelBusy,
+    /// CSMA-CA gave up after finding the channel busy too many times.
+    ChannelAccessFailure,
+    /// No acknowledgement was received after the maximum number of frame retries.
+    NoAck,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Crc(_) => ErrorKind::Corrupted,
+            Error::ChannelBusy | Error::ChannelAccessFailure => ErrorKind::Busy,
+            Error::NoAck => ErrorKind::Nack,
+        }
+    }
+}
+
+/// An IEEE 802.15.4 radio.
+pub struct Radio<'c> {
+    radio: RADIO,
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> Radio<'c> {
+    /// Configures the radio for IEEE 802.15.4 operation on channel 11 at 0 dBm.
+    pub fn init<L, LSTAT>(radio: RADIO, _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>) -> Self {
+        disable(&radio);
+
+        radio.mode.write(|w| w.mode().ieee802154_250kbit());
+        radio.modecnf0.write(|w| w.ru().fast().dtx().center());
+
+        // The length field is 8 bits wide and counts the FCS, preceded by a 32 bit zero preamble.
+        radio.pcnf0.write(|w| unsafe {
+            w.lflen()
+                .bits(8)
+                .s0len()
+                .clear_bit()
+                .s1len()
+                .bits(0)
+                .plen()
+                ._32bit_zero()
+                .crcinc()
+                .include()
+        });
+        radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits(MAX_PSDU_SIZE)
+                .statlen()
+                .bits(0)
+                .balen()
+                .bits(0)
+                .endian()
+                .little()
+                .whiteen()
+                .disabled()
+        });
+
+        // CRC-16/KERMIT as required by the standard, computed over the whole PSDU.
+        radio
+            .crccnf
+            .write(|w| w.len().two().skipaddr().ieee802154());
+        radio
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(0x0001_1021) });
+        radio.crcinit.write(|w| unsafe { w.crcinit().bits(0) });
+
+        radio.sfd.write(|w| unsafe { w.sfd().bits(SFD) });
+
+        let mut radio = Radio {
+            radio,
+            _clocks: PhantomData,
+        };
+        radio.set_channel(Channel::_11);
+        radio.set_cca(Cca::CarrierSense);
+        radio.set_txpower(TxPower::_0DBM);
+        radio
+    }
+
+    /// Changes the channel used for transmission and reception.
+    pub fn set_channel(&mut self, channel: Channel) {
+        self.radio.frequency.write(|w| unsafe {
+            w.frequency()
+                .bits(channel.frequency_offset())
+                .map()
+                .default()
+        });
+    }
+
+    /// Changes the clear channel assessment method used by `try_send`.
+    pub fn set_cca(&mut self, cca: Cca) {
+        match cca {
+            Cca::CarrierSense => self.radio.ccactrl.write(|w| w.ccamode().carrier_mode()),
+            Cca::EnergyDetection { ed_threshold } => self
+                .radio
+                .ccactrl
+                .write(|w| unsafe { w.ccamode().ed_mode().ccaedthres().bits(ed_threshold) }),
+        }
+    }
+
+    /// Changes the transmission power.
+    pub fn set_txpower(&mut self, power: TxPower) {
+        self.radio.txpower.write(|w| w.txpower().variant(power));
+    }
+
+    /// Measures the energy on the current channel for about `duration_us` microseconds and
+    /// returns the peak value.
+    ///
+    /// The measurement is performed in steps of 128 µs.
+    pub fn energy_detection_scan(&mut self, duration_us: u32) -> u8 {
+        self.radio
+            .edcnt
+            .write(|w| unsafe { w.edcnt().bits((duration_us / 128).saturating_sub(1)) });
+
+        self.radio.events_edend.reset();
+        self.radio
+            .shorts
+            .write(|w| w.ready_edstart().enabled().edend_disable().enabled());
+        prepare(&self.radio);
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+
+        self.radio.events_edend.reset();
+        self.radio.edsample.read().edlvl().bits()
+    }
+
+    /// Receives a frame into `packet`, blocking until one has arrived.
+    ///
+    /// Returns the FCS of the frame if it was correct. The FCS isn't kept in `packet`, where the
+    /// radio replaces it with the link quality indicator.
+    pub fn recv(&mut self, packet: &mut Packet) -> Result<u16, Error> {
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(packet.as_ptr()) });
+        self.radio
+            .shorts
+            .write(|w| w.rxready_start().enabled().phyend_disable().enabled());
+        prepare(&self.radio);
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+
+        let crc = self.radio.rxcrc.read().rxcrc().bits() as u16;
+        if self.radio.crcstatus.read().crcstatus().is_crcok() {
+            Ok(crc)
+        } else {
+            Err(Error::Crc(crc))
+        }
+    }
+
+    /// Transmits `packet` after a clear channel assessment.
+    ///
+    /// Returns `Error::ChannelBusy` without transmitting if the channel is in use.
+    pub fn try_send(&mut self, packet: &mut Packet) -> Result<(), Error> {
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(packet.as_ptr()) });
+        self.radio.events_ccabusy.reset();
+        self.radio.shorts.write(|w| {
+            w.rxready_ccastart()
+                .enabled()
+                .ccaidle_txen()
+                .enabled()
+                .txready_start()
+                .enabled()
+                .ccabusy_disable()
+                .enabled()
+                .phyend_disable()
+                .enabled()
+        });
+        prepare(&self.radio);
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+
+        if self.radio.events_ccabusy.read().bits() != 0 {
+            self.radio.events_ccabusy.reset();
+            Err(Error::ChannelBusy)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Transmits `packet` without a clear channel assessment.
+    pub fn send(&mut self, packet: &mut Packet) {
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(packet.as_ptr()) });
+        self.radio
+            .shorts
+            .write(|w| w.txready_start().enabled().phyend_disable().enabled());
+        prepare(&self.radio);
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+    }
+
+    /// Returns reference to the `FRAMESTART` event endpoint for PPI.
+    ///
+    /// The event is generated right after the PHY header has been sent or received.
+    pub fn event_framestart(&self) -> &radio::EVENTS_FRAMESTART {
+        &self.radio.events_framestart
+    }
+
+    /// Returns reference to the `PHYEND` event endpoint for PPI.
+    pub fn event_phyend(&self) -> &radio::EVENTS_PHYEND {
+        &self.radio.events_phyend
+    }
+
+    /// Disables the radio and returns the raw `RADIO` peripheral.
+    pub fn free(self) -> RADIO {
+        disable(&self.radio);
+        self.radio
+    }
+}
@@ -0,0 +1,132 @@
+//! HAL interface to the RADIO peripheral.
+//!
+//! The 2.4 GHz radio is shared by several protocol drivers, each living in its own submodule and
+//! taking ownership of the `RADIO` peripheral. All of them require the high frequency clock to be
+//! driven from the external crystal, which is checked at compile time through the
+//! [`Clocks`](crate::clocks::Clocks) type state.
+
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+#[cfg(feature = "5340-net")]
+use crate::pac::{radio_ns as radio, RADIO_NS as RADIO};
+
+#[cfg(not(feature = "5340-net"))]
+use crate::pac::{radio, RADIO};
+
+#[cfg(not(feature = "51"))]
+pub mod ble;
+#[cfg(any(feature = "52820", feature = "52833"))]
+pub mod direction_finding;
+#[cfg(not(any(feature = "51", feature = "5340-net")))]
+pub mod esb;
+#[cfg(not(feature = "51"))]
+pub mod hopping;
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+pub mod ieee802154;
+#[cfg(not(feature = "51"))]
+pub mod proprietary;
+#[cfg(not(feature = "51"))]
+pub mod radio_test;
+#[cfg(not(any(feature = "51", feature = "5340-net")))]
+pub mod timestamp;
+
+pub use self::radio::{
+    crccnf::{LEN_A as CrcLen, SKIPADDR_A as CrcSkipAddress},
+    mode::MODE_A as Mode,
+    txpower::TXPOWER_A as TxPower,
+};
+
+/// CRC configuration.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct CrcConfig {
+    /// Number of CRC bytes, or `CrcLen::DISABLED`.
+    pub len: CrcLen,
+    /// Whether the address is included in the CRC calculation.
+    pub skip_address: CrcSkipAddress,
+    /// Polynomial, where bit `n` is the coefficient of `x^n`. The coefficient of the highest
+    /// term is implied.
+    pub poly: u32,
+    /// Initial value.
+    pub init: u32,
+}
+
+impl CrcConfig {
+    /// No CRC.
+    pub const DISABLED: CrcConfig = CrcConfig {
+        len: CrcLen::DISABLED,
+        skip_address: CrcSkipAddress::INCLUDE,
+        poly: 0,
+        init: 0,
+    };
+
+    /// The 24 bit BLE CRC with the initial value used on the advertising channels.
+    pub const BLE_ADVERTISING: CrcConfig = CrcConfig::ble(0x0055_5555);
+
+    /// The 24 bit BLE CRC, with the initial value of a connection.
+    pub const fn ble(init: u32) -> CrcConfig {
+        CrcConfig {
+            len: CrcLen::THREE,
+            skip_address: CrcSkipAddress::SKIP,
+            poly: 0x0000_065B,
+            init,
+        }
+    }
+
+    /// The 16 bit CRC-CCITT, as used by the nRF24L01+, computed over the address.
+    pub const CCITT_16: CrcConfig = CrcConfig {
+        len: CrcLen::TWO,
+        skip_address: CrcSkipAddress::INCLUDE,
+        poly: 0x0001_1021,
+        init: 0xFFFF,
+    };
+
+    /// The 8 bit CRC used by the nRF24L01+, computed over the address.
+    pub const CCITT_8: CrcConfig = CrcConfig {
+        len: CrcLen::ONE,
+        skip_address: CrcSkipAddress::INCLUDE,
+        poly: 0x107,
+        init: 0xFF,
+    };
+}
+
+/// Applies a CRC configuration.
+pub(crate) fn set_crc(radio: &RADIO, crc: &CrcConfig) {
+    radio.crccnf.write(|w| {
+        w.len()
+            .variant(crc.len)
+            .skipaddr()
+            .variant(crc.skip_address)
+    });
+    radio
+        .crcpoly
+        .write(|w| unsafe { w.crcpoly().bits(crc.poly) });
+    radio
+        .crcinit
+        .write(|w| unsafe { w.crcinit().bits(crc.init) });
+}
+
+/// Disables the radio and waits until it has reached the `DISABLED` state.
+pub(crate) fn disable(radio: &RADIO) {
+    radio.shorts.reset();
+    radio.events_disabled.reset();
+    radio.tasks_disable.write(|w| unsafe { w.bits(1) });
+    while radio.events_disabled.read().bits() == 0 {}
+    radio.events_disabled.reset();
+}
+
+/// Clears the `DISABLED` event before a task is triggered.
+pub(crate) fn prepare(radio: &RADIO) {
+    // Make sure the packet buffer is written before EasyDMA reads it.
+    compiler_fence(SeqCst);
+    radio.events_disabled.reset();
+}
+
+/// Waits until the configured shortcuts have disabled the radio again.
+pub(crate) fn wait_disabled(radio: &RADIO) {
+    while radio.events_disabled.read().bits() == 0 {}
+    radio.events_disabled.reset();
+    radio.shorts.reset();
+
+    // Make sure the packet buffer is read after EasyDMA has written it.
+    compiler_fence(SeqCst);
+}
@@ -0,0 +1,543 @@
+//! Radio driver with a configurable packet format.
+//!
+//! This exposes the radio's packet engine directly, so custom protocols can be implemented on top
+//! of the Nordic proprietary modes (or any other mode supported by the chip) without touching the
+//! registers.
+//!
+//! A packet in RAM consists of the optional S0 field, the length field, the optional S1 field and
+//! the payload, each field occupying a whole number of bytes. The address, preamble and CRC are
+//! handled by the hardware.
+//!
+//! On the nRF52833 and nRF52840, the BLE Long Range modes (LE Coded PHY at 125 kbit/s and
+//! 500 kbit/s) are available by selecting `Mode::BLE_LR125KBIT` or `Mode::BLE_LR500KBIT` together
+//! with the `Preamble::LONG_RANGE` packet format, see `PacketConfig::ble_long_range`. The coding
+//! indicator and TERM fields are then handled by the driver.
+
+use core::marker::PhantomData;
+
+use void::Void;
+
+use super::{disable, prepare, radio, set_crc, wait_disabled, CrcConfig, Mode, TxPower, RADIO};
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::error::ErrorKind;
+use crate::slice_in_ram_or;
+
+pub use super::radio::pcnf0::PLEN_A as Preamble;
+pub use super::radio::pcnf1::ENDIAN_A as Endian;
+
+/// On-air packet format.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct PacketConfig {
+    /// Length of the preamble.
+    pub preamble: Preamble,
+    /// Length of the S0 field in bytes, 0 or 1.
+    pub s0_len: u8,
+    /// Length of the length field in bits, 0 to 15.
+    pub length_bits: u8,
+    /// Length of the S1 field in bits, 0 to 15.
+    pub s1_len: u8,
+    /// Whether the S1 field is stored in RAM even if `s1_len` is 0.
+    pub s1_include: bool,
+    /// Maximum payload length in bytes.
+    pub max_len: u8,
+    /// Number of bytes added to the payload length given by the length field.
+    pub static_len: u8,
+    /// Length of the base address in bytes, 2 to 4.
+    pub base_address_len: u8,
+    /// Byte order of the S0, length, S1 and payload fields on air.
+    pub endian: Endian,
+    /// Whether data whitening is enabled.
+    pub whitening: bool,
+}
+
+impl PacketConfig {
+    /// The BLE packet format on the LE Coded PHY, with a 1 byte S0 field holding the first header
+    /// byte, an 8 bit length field and up to 255 bytes of payload.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn ble_long_range() -> Self {
+        PacketConfig {
+            preamble: Preamble::LONG_RANGE,
+            s0_len: 1,
+            length_bits: 8,
+            s1_len: 0,
+            s1_include: false,
+            max_len: 255,
+            static_len: 0,
+            base_address_len: 3,
+            endian: Endian::LITTLE,
+            whitening: true,
+        }
+    }
+
+    /// Number of bytes preceding the payload in RAM.
+    pub fn header_len(&self) -> usize {
+        let s1_bytes = if self.s1_len == 0 {
+            self.s1_include as usize
+        } else {
+            (self.s1_len as usize + 7) / 8
+        };
+        self.s0_len as usize + (self.length_bits as usize + 7) / 8 + s1_bytes
+    }
+
+    /// Size of the largest packet in RAM.
+    pub fn max_packet_len(&self) -> usize {
+        self.header_len() + self.max_len as usize
+    }
+
+    /// Size of `packet` in RAM, as read by EasyDMA: the header and the payload announced by the
+    /// length field. `packet` must hold at least the header.
+    fn packet_len(&self, packet: &[u8]) -> usize {
+        let start = self.s0_len as usize;
+        let length_bytes = (self.length_bits as usize + 7) / 8;
+        let length = packet[start..start + length_bytes]
+            .iter()
+            .rev()
+            .fold(0u32, |length, byte| length << 8 | u32::from(*byte))
+            & ((1 << self.length_bits) - 1);
+        let payload_len = (length + u32::from(self.static_len)).min(u32::from(self.max_len));
+        self.header_len() + payload_len as usize
+    }
+}
+
+impl Default for PacketConfig {
+    /// An 8 bit length field, up to 255 bytes of little endian payload, 4 byte base addresses.
+    fn default() -> Self {
+        PacketConfig {
+            preamble: Preamble::_8BIT,
+            s0_len: 0,
+            length_bits: 8,
+            s1_len: 0,
+            s1_include: false,
+            max_len: 255,
+            static_len: 0,
+            base_address_len: 4,
+            endian: Endian::LITTLE,
+            whitening: false,
+        }
+    }
+}
+
+/// LE Coded PHY coding scheme.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Coding {
+    /// S=8 coding, 125 kbit/s.
+    S8,
+    /// S=2 coding, 500 kbit/s.
+    S2,
+}
+
+/// Number of 1 MHz channels the radio can be tuned to, from 2400 MHz to 2500 MHz.
+pub const NUM_FREQUENCIES: usize = 101;
+
+/// Radio errors.
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The buffer can't hold the largest packet allowed by the packet configuration.
+    BufferTooSmall,
+    /// The packet is shorter than its header and the payload announced by its length field.
+    PacketTooShort,
+    /// The packet to transmit is not in RAM, where EasyDMA can read it.
+    BufferNotInRam,
+    /// A packet was received, but its CRC did not match.
+    Crc,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::BufferTooSmall | Error::PacketTooShort => ErrorKind::BufferLength,
+            Error::BufferNotInRam => ErrorKind::BufferNotInRam,
+            Error::Crc => ErrorKind::Corrupted,
+        }
+    }
+}
+
+/// A radio with a configurable packet format.
+pub struct Radio<'c> {
+    radio: RADIO,
+    config: PacketConfig,
+    bit_counter: bool,
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> Radio<'c> {
+    /// Configures the radio for `mode` and the given packet format.
+    ///
+    /// The radio is tuned to 2400 MHz and transmits at 0 dBm, on logical address 0. It receives on
+    /// logical address 0.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>,
+        mode: Mode,
+        config: PacketConfig,
+    ) -> Self {
+        disable(&radio);
+
+        let mut radio = Radio {
+            radio,
+            config,
+            bit_counter: false,
+            _clocks: PhantomData,
+        };
+        radio.set_mode(mode);
+        radio.set_packet_config(config);
+        radio.set_frequency(0);
+        radio.set_txpower(TxPower::_0DBM);
+        radio.set_tx_address(0);
+        radio.set_rx_addresses(1);
+        radio.set_crc(CrcConfig::DISABLED);
+        radio
+    }
+
+    /// Changes the mode, i.e. the modulation and data rate.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.radio.mode.write(|w| w.mode().variant(mode));
+    }
+
+    /// Changes the packet format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any field is out of the range documented on `PacketConfig`.
+    pub fn set_packet_config(&mut self, config: PacketConfig) {
+        assert!(config.s0_len <= 1);
+        assert!(config.length_bits <= 15);
+        assert!(config.s1_len <= 15);
+        assert!((2..=4).contains(&config.base_address_len));
+
+        self.radio.pcnf0.write(|w| unsafe {
+            w.lflen()
+                .bits(config.length_bits)
+                .s0len()
+                .bit(config.s0_len == 1)
+                .s1len()
+                .bits(config.s1_len)
+                .s1incl()
+                .bit(config.s1_include)
+                .plen()
+                .variant(config.preamble)
+        });
+        #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+        if config.preamble == Preamble::LONG_RANGE {
+            // 2 bit coding indicator and 3 bit TERM1 field, as defined by the LE Coded PHY.
+            self.radio
+                .pcnf0
+                .modify(|_, w| unsafe { w.cilen().bits(2).termlen().bits(3) });
+        }
+        self.radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits(config.max_len)
+                .statlen()
+                .bits(config.static_len)
+                .balen()
+                .bits(config.base_address_len)
+                .endian()
+                .variant(config.endian)
+                .whiteen()
+                .bit(config.whitening)
+        });
+        self.config = config;
+    }
+
+    /// Returns the current packet format.
+    pub fn packet_config(&self) -> &PacketConfig {
+        &self.config
+    }
+
+    /// Tunes the radio to 2400 MHz plus `offset` MHz.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is greater than 100.
+    pub fn set_frequency(&mut self, offset: u8) {
+        assert!(offset <= 100);
+        self.radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(offset).map().default() });
+    }
+
+    /// Changes the CRC configuration. CRC checking is disabled after `new`.
+    pub fn set_crc(&mut self, crc: CrcConfig) {
+        set_crc(&self.radio, &crc);
+    }
+
+    /// Sets the initial value of the data whitening LFSR, if whitening is enabled in the packet
+    /// configuration.
+    ///
+    /// Only the lower 6 bits are used, bit 6 is always set by the hardware. For BLE, this is the
+    /// channel index.
+    pub fn set_whitening_iv(&mut self, iv: u8) {
+        self.radio
+            .datawhiteiv
+            .write(|w| unsafe { w.datawhiteiv().bits(iv & 0x3f) });
+    }
+
+    /// Changes the transmission power.
+    pub fn set_txpower(&mut self, power: TxPower) {
+        self.radio.txpower.write(|w| w.txpower().variant(power));
+    }
+
+    /// Sets the base addresses. Logical address 0 uses `base0`, logical addresses 1 to 7 use
+    /// `base1`.
+    pub fn set_base_addresses(&mut self, base0: u32, base1: u32) {
+        self.radio.base0.write(|w| unsafe { w.bits(base0) });
+        self.radio.base1.write(|w| unsafe { w.bits(base1) });
+    }
+
+    /// Sets the prefix bytes of logical addresses 0 to 7.
+    pub fn set_prefixes(&mut self, prefixes: [u8; 8]) {
+        let prefix0 = u32::from_le_bytes([prefixes[0], prefixes[1], prefixes[2], prefixes[3]]);
+        let prefix1 = u32::from_le_bytes([prefixes[4], prefixes[5], prefixes[6], prefixes[7]]);
+        self.radio.prefix0.write(|w| unsafe { w.bits(prefix0) });
+        self.radio.prefix1.write(|w| unsafe { w.bits(prefix1) });
+    }
+
+    /// Selects the logical address used for transmission.
+    pub fn set_tx_address(&mut self, address: u8) {
+        assert!(address < 8);
+        self.radio
+            .txaddress
+            .write(|w| unsafe { w.txaddress().bits(address) });
+    }
+
+    /// Selects the logical addresses packets are received on, as a bit mask.
+    pub fn set_rx_addresses(&mut self, mask: u8) {
+        self.radio
+            .rxaddresses
+            .write(|w| unsafe { w.bits(mask as u32) });
+    }
+
+    /// Transmits `packet`, blocking until it has been sent.
+    ///
+    /// `packet` must be laid out as described in the module documentation, and hold at least as
+    /// many payload bytes as its length field announces.
+    pub fn transmit(&mut self, packet: &[u8]) -> Result<(), Error> {
+        slice_in_ram_or(packet, Error::BufferNotInRam)?;
+        if packet.len() < self.config.header_len() || packet.len() < self.config.packet_len(packet)
+        {
+            return Err(Error::PacketTooShort);
+        }
+
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(packet.as_ptr() as u32) });
+        self.set_shorts();
+
+        prepare(&self.radio);
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+        Ok(())
+    }
+
+    /// Receives a packet into `buf`, blocking until one has arrived.
+    ///
+    /// Returns the logical address the packet was received on.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<u8, Error> {
+        if buf.len() < self.config.max_packet_len() {
+            return Err(Error::BufferTooSmall);
+        }
+
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(buf.as_mut_ptr() as u32) });
+        self.set_shorts();
+
+        prepare(&self.radio);
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        wait_disabled(&self.radio);
+
+        if self.radio.crccnf.read().len().is_disabled()
+            || self.radio.crcstatus.read().crcstatus().is_crcok()
+        {
+            Ok(self.radio.rxmatch.read().rxmatch().bits())
+        } else {
+            Err(Error::Crc)
+        }
+    }
+
+    /// Enables the receiver, if needed, and kicks off an RSSI measurement.
+    ///
+    /// The result can be retrieved by calling `rssi_sample`. The receiver stays enabled until
+    /// `rssi_stop` is called.
+    pub fn rssi_start(&mut self) {
+        let state = self.radio.state.read().state();
+        if !(state.is_rx_idle() || state.is_rx()) {
+            self.radio.shorts.reset();
+            self.radio.events_ready.reset();
+            self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+            while self.radio.events_ready.read().bits() == 0 {}
+            self.radio.events_ready.reset();
+        }
+
+        self.radio.events_rssiend.reset();
+        self.radio.tasks_rssistart.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Tries to read a started RSSI measurement (non-blocking).
+    ///
+    /// Before calling this, `rssi_start` must be called.
+    ///
+    /// Returns the received signal strength in dBm.
+    pub fn rssi_sample(&mut self) -> nb::Result<i8, Void> {
+        if self.radio.events_rssiend.read().bits() == 0 {
+            Err(nb::Error::WouldBlock)
+        } else {
+            self.radio.events_rssiend.reset();
+            let sample = self.radio.rssisample.read().rssisample().bits();
+            Ok(-(sample as i8))
+        }
+    }
+
+    /// Stops RSSI measurements and disables the receiver.
+    pub fn rssi_stop(&mut self) {
+        self.radio.tasks_rssistop.write(|w| unsafe { w.bits(1) });
+        disable(&self.radio);
+    }
+
+    /// Sweeps all frequencies from 2400 MHz to 2500 MHz, taking `samples` RSSI measurements on
+    /// each, and returns the strongest signal seen per frequency in dBm.
+    ///
+    /// The radio is left disabled and tuned to 2400 MHz.
+    pub fn energy_scan(&mut self, samples: u8) -> [i8; NUM_FREQUENCIES] {
+        let mut energy = [i8::MIN; NUM_FREQUENCIES];
+
+        for (offset, peak) in energy.iter_mut().enumerate() {
+            disable(&self.radio);
+            self.set_frequency(offset as u8);
+            for _ in 0..samples {
+                self.rssi_start();
+                let rssi = nb::block!(self.rssi_sample()).unwrap();
+                *peak = (*peak).max(rssi);
+            }
+        }
+
+        self.rssi_stop();
+        self.set_frequency(0);
+        energy
+    }
+
+    /// Returns the coding scheme of the last packet received on the LE Coded PHY.
+    ///
+    /// When receiving in `Mode::BLE_LR125KBIT`, the radio switches to S=2 coding on its own if the
+    /// coding indicator requests it, generating the `RATEBOOST` event.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn received_coding(&self) -> Coding {
+        if self.radio.pdustat.read().cistat().is_lr500kbit() {
+            Coding::S2
+        } else {
+            Coding::S8
+        }
+    }
+
+    /// Returns reference to the `RATEBOOST` event endpoint for PPI.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn event_rateboost(&self) -> &radio::EVENTS_RATEBOOST {
+        &self.radio.events_rateboost
+    }
+
+    /// Sets the inter frame spacing in µs, i.e. the time between the end of a packet and the
+    /// start of the next one when the radio is turned around with the `DISABLED_TXEN` or
+    /// `DISABLED_RXEN` shortcuts, e.g. 150 µs for BLE.
+    pub fn set_tifs(&mut self, us: u16) {
+        self.radio.tifs.write(|w| unsafe { w.bits(us as u32) });
+    }
+
+    /// Starts the bit counter on the `ADDRESS` event of every following transfer, generating the
+    /// `BCMATCH` event once `bits` bits after the address have been sent or received.
+    ///
+    /// The compare value can be updated while a packet is in flight with
+    /// `set_bit_counter_compare`, to generate further `BCMATCH` events.
+    pub fn enable_bit_counter(&mut self, bits: u32) {
+        self.set_bit_counter_compare(bits);
+        self.bit_counter = true;
+    }
+
+    /// Stops starting the bit counter on the `ADDRESS` event.
+    pub fn disable_bit_counter(&mut self) {
+        self.radio.tasks_bcstop.write(|w| unsafe { w.bits(1) });
+        self.bit_counter = false;
+    }
+
+    /// Sets the number of bits after the address at which `BCMATCH` is generated.
+    pub fn set_bit_counter_compare(&mut self, bits: u32) {
+        self.radio.bcc.write(|w| unsafe { w.bcc().bits(bits) });
+    }
+
+    /// Returns reference to the `TXEN` task endpoint for PPI.
+    pub fn task_txen(&self) -> &radio::TASKS_TXEN {
+        &self.radio.tasks_txen
+    }
+
+    /// Returns reference to the `RXEN` task endpoint for PPI.
+    pub fn task_rxen(&self) -> &radio::TASKS_RXEN {
+        &self.radio.tasks_rxen
+    }
+
+    /// Returns reference to the `DISABLE` task endpoint for PPI.
+    pub fn task_disable(&self) -> &radio::TASKS_DISABLE {
+        &self.radio.tasks_disable
+    }
+
+    /// Returns reference to the `BCSTART` task endpoint for PPI.
+    pub fn task_bcstart(&self) -> &radio::TASKS_BCSTART {
+        &self.radio.tasks_bcstart
+    }
+
+    /// Returns reference to the `BCSTOP` task endpoint for PPI.
+    pub fn task_bcstop(&self) -> &radio::TASKS_BCSTOP {
+        &self.radio.tasks_bcstop
+    }
+
+    /// Returns reference to the `READY` event endpoint for PPI.
+    pub fn event_ready(&self) -> &radio::EVENTS_READY {
+        &self.radio.events_ready
+    }
+
+    /// Returns reference to the `ADDRESS` event endpoint for PPI.
+    pub fn event_address(&self) -> &radio::EVENTS_ADDRESS {
+        &self.radio.events_address
+    }
+
+    /// Returns reference to the `END` event endpoint for PPI.
+    pub fn event_end(&self) -> &radio::EVENTS_END {
+        &self.radio.events_end
+    }
+
+    /// Returns reference to the `DISABLED` event endpoint for PPI.
+    pub fn event_disabled(&self) -> &radio::EVENTS_DISABLED {
+        &self.radio.events_disabled
+    }
+
+    /// Returns reference to the `BCMATCH` event endpoint for PPI.
+    pub fn event_bcmatch(&self) -> &radio::EVENTS_BCMATCH {
+        &self.radio.events_bcmatch
+    }
+
+    /// Disables the radio and returns the raw `RADIO` peripheral.
+    pub fn free(self) -> RADIO {
+        disable(&self.radio);
+        self.radio
+    }
+
+    /// Returns the register block, for the extensions in other radio modules.
+    #[cfg_attr(not(any(feature = "52820", feature = "52833")), allow(dead_code))]
+    pub(super) fn regs(&self) -> &RADIO {
+        &self.radio
+    }
+
+    /// Configures the shortcuts for a single transfer.
+    fn set_shorts(&self) {
+        self.radio.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .address_bcstart()
+                .bit(self.bit_counter)
+        });
+    }
+}
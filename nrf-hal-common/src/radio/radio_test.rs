@@ -0,0 +1,164 @@
+//! Radio test modes for regulatory certification.
+//!
+//! Provides the signals usually required for FCC and ETSI testing: an unmodulated carrier, a
+//! continuously modulated carrier carrying a PRBS9 sequence, and continuous reception for RX
+//! sensitivity measurements.
+//!
+//! Frequencies are given as an offset in MHz from 2400 MHz, from 0 to 100.
+
+use core::{marker::PhantomData, mem::ManuallyDrop, ptr};
+
+use super::{disable, set_crc, CrcConfig, Mode, TxPower, RADIO};
+use crate::clocks::{Clocks, ExternalOscillator};
+
+/// Size of the test packet payload, and of the buffer taken by `RadioTest::new`.
+pub const PACKET_LEN: usize = 255;
+
+/// Fills `buf` with the PRBS9 sequence (x^9 + x^5 + 1), LSB first.
+fn prbs9(buf: &mut [u8]) {
+    let mut state: u16 = 0x1FF;
+    for byte in buf.iter_mut() {
+        let mut value = 0;
+        for bit in 0..8 {
+            let out = (state & 1) as u8;
+            value |= out << bit;
+            let feedback = (state ^ (state >> 5)) & 1;
+            state = (state >> 1) | (feedback << 8);
+        }
+        *byte = value;
+    }
+}
+
+/// Radio test mode controller.
+///
+/// Dropping it stops the current test.
+pub struct RadioTest<'c> {
+    radio: RADIO,
+    // Keeps being accessed by EasyDMA while a test runs.
+    packet: &'static mut [u8; PACKET_LEN],
+    // The external crystal must keep running while the radio is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> RadioTest<'c> {
+    /// Takes ownership of the radio, which stays disabled until a test is started, and of the
+    /// buffer used for the test packets.
+    pub fn new<L, LSTAT>(
+        radio: RADIO,
+        packet: &'static mut [u8; PACKET_LEN],
+        _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>,
+    ) -> Self {
+        disable(&radio);
+
+        // Static length packets without a length field, whitening or CRC, so the PRBS9 sequence
+        // is sent as is.
+        radio
+            .pcnf0
+            .write(|w| unsafe { w.lflen().bits(0).s1len().bits(0) });
+        radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits(PACKET_LEN as u8)
+                .statlen()
+                .bits(PACKET_LEN as u8)
+                .balen()
+                .bits(4)
+                .endian()
+                .little()
+                .whiteen()
+                .disabled()
+        });
+        set_crc(&radio, &CrcConfig::DISABLED);
+        radio.base0.write(|w| unsafe { w.bits(0xE7E7_E7E7) });
+        radio.prefix0.write(|w| unsafe { w.ap0().bits(0xE7) });
+        radio.txaddress.write(|w| unsafe { w.txaddress().bits(0) });
+        radio.rxaddresses.write(|w| w.addr0().enabled());
+
+        RadioTest {
+            radio,
+            packet,
+            _clocks: PhantomData,
+        }
+    }
+
+    /// Starts transmitting an unmodulated carrier.
+    pub fn start_unmodulated_carrier(&mut self, frequency: u8, power: TxPower, mode: Mode) {
+        self.configure(frequency, power, mode);
+
+        // The radio transmits the bare carrier while idling in TX, before any packet is started.
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Starts transmitting a carrier continuously modulated with a PRBS9 sequence.
+    pub fn start_modulated_carrier(&mut self, frequency: u8, power: TxPower, mode: Mode) {
+        self.configure(frequency, power, mode);
+
+        // The buffer may have been overwritten by `start_rx`.
+        prbs9(&mut self.packet[..]);
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.packet.as_ptr() as u32) });
+        self.radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_start().enabled());
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Starts receiving continuously, e.g. for RX sensitivity measurements against a signal
+    /// generator sending the modulated carrier.
+    ///
+    /// Received packets are written to the buffer given to `new`; `packet_received` reports them.
+    pub fn start_rx(&mut self, frequency: u8, mode: Mode) {
+        self.configure(frequency, TxPower::_0DBM, mode);
+
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.packet.as_mut_ptr() as u32) });
+        self.radio.events_end.reset();
+        self.radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_start().enabled());
+        self.radio.tasks_rxen.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns `true` if a packet has been received since the last call, while `start_rx` is
+    /// active.
+    pub fn packet_received(&mut self) -> bool {
+        if self.radio.events_end.read().bits() != 0 {
+            self.radio.events_end.reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Stops the current test and disables the radio.
+    pub fn stop(&mut self) {
+        disable(&self.radio);
+    }
+
+    /// Disables the radio and returns the raw `RADIO` peripheral and the packet buffer.
+    pub fn free(self) -> (RADIO, &'static mut [u8; PACKET_LEN]) {
+        let this = ManuallyDrop::new(self);
+        disable(&this.radio);
+        // The fields are moved out exactly once, and `Drop` does not run.
+        unsafe { (ptr::read(&this.radio), ptr::read(&this.packet)) }
+    }
+
+    fn configure(&mut self, frequency: u8, power: TxPower, mode: Mode) {
+        assert!(frequency <= 100);
+
+        disable(&self.radio);
+        self.radio.mode.write(|w| w.mode().variant(mode));
+        self.radio.txpower.write(|w| w.txpower().variant(power));
+        self.radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(frequency).map().default() });
+    }
+}
+
+impl Drop for RadioTest<'_> {
+    fn drop(&mut self) {
+        // Clears the END to START short, so EasyDMA is done with the buffer once disabled.
+        disable(&self.radio);
+    }
+}
@@ -0,0 +1,123 @@
+//! Hardware packet timestamping.
+//!
+//! Connects the radio's `ADDRESS` and `END` events to capture tasks of a free running 1 MHz TIMER
+//! through two PPI channels, so every packet is timestamped by hardware, without interrupt
+//! latency. This is the basis for time synchronization protocols and for accurate inter-packet
+//! timing measurements.
+
+use super::proprietary::{Error, Radio};
+use crate::{
+    ppi::{ConfigurablePpi, Ppi},
+    timer,
+};
+
+/// Capture register used for the `ADDRESS` event.
+const CC_ADDRESS: usize = 2;
+
+/// Capture register used for the `END` event.
+const CC_END: usize = 3;
+
+/// Capture register used by `now`.
+const CC_NOW: usize = 1;
+
+/// TIMER values captured for a packet, in µs.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamps {
+    /// Time at which the address was sent or received, i.e. the start of the packet.
+    pub address: u32,
+    /// Time at which the packet ended.
+    pub end: u32,
+}
+
+/// A packet received with `Timestamper::receive`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimestampedPacket {
+    /// Logical address the packet was received on.
+    pub address: u8,
+    pub timestamps: Timestamps,
+}
+
+/// Timestamps radio packets using a TIMER and two configurable PPI channels.
+pub struct Timestamper<T, A, B> {
+    timer: T,
+    ppi: (A, B),
+}
+
+impl<T, A, B> Timestamper<T, A, B>
+where
+    T: timer::Instance,
+    A: Ppi + ConfigurablePpi,
+    B: Ppi + ConfigurablePpi,
+{
+    /// Starts `timer` as a free running 32 bit counter at 1 MHz and connects the events of `radio`
+    /// to its capture tasks.
+    pub fn new(timer: T, ppi: (A, B), radio: &Radio) -> Self {
+        let regs = timer.as_timer0();
+        regs.tasks_stop.write(|w| unsafe { w.bits(1) });
+        regs.shorts.reset();
+        regs.mode.write(|w| w.mode().timer());
+        regs.bitmode.write(|w| w.bitmode()._32bit());
+        regs.prescaler.write(|w| unsafe { w.prescaler().bits(4) });
+        regs.tasks_clear.write(|w| unsafe { w.bits(1) });
+        regs.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        let (mut a, mut b) = ppi;
+        a.set_event_endpoint(radio.event_address());
+        a.set_task_endpoint(&regs.tasks_capture[CC_ADDRESS]);
+        a.enable();
+        b.set_event_endpoint(radio.event_end());
+        b.set_task_endpoint(&regs.tasks_capture[CC_END]);
+        b.enable();
+
+        Timestamper { timer, ppi: (a, b) }
+    }
+
+    /// Returns the current TIMER value, to relate timestamps to the present.
+    pub fn now(&self) -> u32 {
+        let regs = self.timer.as_timer0();
+        regs.tasks_capture[CC_NOW].write(|w| unsafe { w.bits(1) });
+        regs.cc[CC_NOW].read().bits()
+    }
+
+    /// Returns the timestamps of the last packet sent or received.
+    pub fn last(&self) -> Timestamps {
+        let regs = self.timer.as_timer0();
+        Timestamps {
+            address: regs.cc[CC_ADDRESS].read().bits(),
+            end: regs.cc[CC_END].read().bits(),
+        }
+    }
+
+    /// Receives a packet with `radio` into `buf`, and returns it with its timestamps.
+    pub fn receive(
+        &mut self,
+        radio: &mut Radio,
+        buf: &mut [u8],
+    ) -> Result<TimestampedPacket, Error> {
+        let address = radio.receive(buf)?;
+        Ok(TimestampedPacket {
+            address,
+            timestamps: self.last(),
+        })
+    }
+
+    /// Transmits `packet` with `radio`, and returns its timestamps.
+    pub fn transmit(&mut self, radio: &mut Radio, packet: &[u8]) -> Result<Timestamps, Error> {
+        radio.transmit(packet)?;
+        Ok(self.last())
+    }
+
+    /// Stops the TIMER, disables the PPI channels and returns them.
+    pub fn free(self) -> (T, (A, B)) {
+        let (mut a, mut b) = self.ppi;
+        a.disable();
+        b.disable();
+        self.timer
+            .as_timer0()
+            .tasks_stop
+            .write(|w| unsafe { w.bits(1) });
+        (self.timer, (a, b))
+    }
+}
@@ -0,0 +1,103 @@
+//! HAL interface to the REGULATORS peripheral.
+//!
+//! On the nRF9160 and nRF5340, the REGULATORS peripheral takes over the supply configuration and
+//! System OFF of the POWER peripheral of the nRF52 series. Each regulator is an LDO by default,
+//! and can be switched to its DC/DC converter when the external inductor is fitted, reducing the
+//! current consumption. The nRF9160 only has the main regulator; the nRF5340 adds the radio and
+//! high voltage regulators.
+//!
+//! See product specification, chapter REGULATORS.
+
+use crate::pac::REGULATORS_NS as REGULATORS;
+
+/// `DCDCEN` bit of the regulator configuration registers.
+const DCDCEN: u32 = 1;
+
+/// A regulator with a DC/DC converter.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Regulator {
+    /// Main regulator, supplying the chip from VDD.
+    Main,
+    /// Radio regulator, supplying the radio of the network core.
+    #[cfg(feature = "5340-app")]
+    Radio,
+    /// High voltage regulator, supplying VDD from VDDH.
+    #[cfg(feature = "5340-app")]
+    High,
+}
+
+/// Interface to the REGULATORS peripheral.
+pub struct Regulators(REGULATORS);
+
+impl Regulators {
+    pub fn new(regulators: REGULATORS) -> Self {
+        Self(regulators)
+    }
+
+    /// Returns the raw `REGULATORS` peripheral.
+    pub fn free(self) -> REGULATORS {
+        self.0
+    }
+
+    /// Switches `regulator` to its DC/DC converter.
+    ///
+    /// Only enable the converter when the inductor it needs is fitted on the board.
+    pub fn enable_dcdc(&mut self, regulator: Regulator) -> &mut Self {
+        self.write_dcdcen(regulator, DCDCEN);
+        self
+    }
+
+    /// Switches `regulator` back to its LDO.
+    pub fn disable_dcdc(&mut self, regulator: Regulator) -> &mut Self {
+        self.write_dcdcen(regulator, 0);
+        self
+    }
+
+    /// Returns `true` if `regulator` uses its DC/DC converter.
+    pub fn is_dcdc_enabled(&self, regulator: Regulator) -> bool {
+        let bits = match regulator {
+            #[cfg(feature = "9160")]
+            Regulator::Main => self.0.dcdcen.read().bits(),
+            #[cfg(feature = "5340-app")]
+            Regulator::Main => self.0.vregmain.dcdcen.read().bits(),
+            #[cfg(feature = "5340-app")]
+            Regulator::Radio => self.0.vregradio.dcdcen.read().bits(),
+            #[cfg(feature = "5340-app")]
+            Regulator::High => self.0.vregh.dcdcen.read().bits(),
+        };
+        bits & DCDCEN != 0
+    }
+
+    /// Returns `true` if the chip is supplied through VDDH, i.e. the high voltage regulator is
+    /// in use.
+    #[cfg(feature = "5340-app")]
+    pub fn is_high_voltage_mode(&self) -> bool {
+        self.0.mainregstatus.read().bits() & 1 != 0
+    }
+
+    /// Enters System OFF, the deepest power saving mode.
+    ///
+    /// Only the configured wake up sources, like a GPIO `DETECT` signal, are kept running.
+    /// Waking up resets the chip. While a debugger is attached, System OFF is emulated and the
+    /// CPU keeps spinning here.
+    pub fn system_off(&mut self) -> ! {
+        self.0.systemoff.write(|w| unsafe { w.bits(1) });
+        loop {
+            cortex_m::asm::wfe();
+        }
+    }
+
+    fn write_dcdcen(&mut self, regulator: Regulator, bits: u32) {
+        match regulator {
+            #[cfg(feature = "9160")]
+            Regulator::Main => self.0.dcdcen.write(|w| unsafe { w.bits(bits) }),
+            #[cfg(feature = "5340-app")]
+            Regulator::Main => self.0.vregmain.dcdcen.write(|w| unsafe { w.bits(bits) }),
+            #[cfg(feature = "5340-app")]
+            Regulator::Radio => self.0.vregradio.dcdcen.write(|w| unsafe { w.bits(bits) }),
+            #[cfg(feature = "5340-app")]
+            Regulator::High => self.0.vregh.dcdcen.write(|w| unsafe { w.bits(bits) }),
+        }
+    }
+}
@@ -4,7 +4,7 @@
 
 use rand_core::{CryptoRng, RngCore};
 
-use crate::pac::RNG;
+use crate::pac::{rng, RNG};
 
 /// Interface to the RNG peripheral.
 ///
@@ -34,6 +34,21 @@ impl Rng {
         self.0.tasks_stop.write(|w| unsafe { w.bits(1) });
     }
 
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &rng::TASKS_START {
+        &self.0.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &rng::TASKS_STOP {
+        &self.0.tasks_stop
+    }
+
+    /// Returns reference to the `VALRDY` event endpoint for PPI.
+    pub fn event_value_ready(&self) -> &rng::EVENTS_VALRDY {
+        &self.0.events_valrdy
+    }
+
     /// Return a random `u8`.
     pub fn random_u8(&mut self) -> u8 {
         let mut buf = [0; 1];
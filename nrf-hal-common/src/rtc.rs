@@ -1,11 +1,12 @@
 //! A high level interface for RTC peripherals.
 
+use crate::error::ErrorKind;
 use core::ops::Deref;
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
 use crate::pac::{rtc0_ns as rtc0, Interrupt, NVIC, RTC0_NS as RTC0, RTC1_NS as RTC1};
 
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 use crate::pac::{rtc0, Interrupt, NVIC, RTC0, RTC1};
 
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
@@ -218,6 +219,53 @@ where
         }
     }
 
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &rtc0::TASKS_START {
+        &self.periph.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &rtc0::TASKS_STOP {
+        &self.periph.tasks_stop
+    }
+
+    /// Returns reference to the `CLEAR` task endpoint for PPI.
+    pub fn task_clear(&self) -> &rtc0::TASKS_CLEAR {
+        &self.periph.tasks_clear
+    }
+
+    /// Returns reference to the `TRIGOVRFLW` task endpoint for PPI.
+    pub fn task_trigger_overflow(&self) -> &rtc0::TASKS_TRIGOVRFLW {
+        &self.periph.tasks_trigovrflw
+    }
+
+    /// Returns reference to the `TICK` event endpoint for PPI.
+    ///
+    /// The event must also be enabled with `enable_event` to be routed to PPI.
+    pub fn event_tick(&self) -> &rtc0::EVENTS_TICK {
+        &self.periph.events_tick
+    }
+
+    /// Returns reference to the `OVRFLW` event endpoint for PPI.
+    ///
+    /// The event must also be enabled with `enable_event` to be routed to PPI.
+    pub fn event_overflow(&self) -> &rtc0::EVENTS_OVRFLW {
+        &self.periph.events_ovrflw
+    }
+
+    /// Returns reference to the `COMPARE` event endpoint of the given compare register for PPI.
+    ///
+    /// The event must also be enabled with `enable_event` to be routed to PPI.
+    pub fn event_compare(&self, reg: RtcCompareReg) -> &rtc0::EVENTS_COMPARE {
+        let n = match reg {
+            RtcCompareReg::Compare0 => 0,
+            RtcCompareReg::Compare1 => 1,
+            RtcCompareReg::Compare2 => 2,
+            RtcCompareReg::Compare3 => 3,
+        };
+        &self.periph.events_compare[n]
+    }
+
     /// Destructure the high level interface. Does not reset any configuration made
     /// to the given RTC peripheral.
     pub fn release(self) -> T {
@@ -227,11 +275,20 @@ where
 
 /// Error types associated with the RTC peripheral interface.
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     PrescalerOutOfRange,
     CompareOutOfRange,
 }
 
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::PrescalerOutOfRange | Error::CompareOutOfRange => ErrorKind::InvalidArgument,
+        }
+    }
+}
+
 impl<T> Rtc<T, Stopped>
 where
     T: Instance,
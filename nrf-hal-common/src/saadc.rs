@@ -1,17 +1,16 @@
 //! HAL interface to the SAADC peripheral.
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app"))]
 use crate::pac::{saadc_ns as saadc, SAADC_NS as SAADC};
 
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app")))]
 use crate::pac::{saadc, SAADC};
 
-use crate::gpio::{Floating, Input};
+use crate::gpio::{analog_pin_table, Analog};
 use core::{
     hint::unreachable_unchecked,
     sync::atomic::{compiler_fence, Ordering::SeqCst},
 };
-use embedded_hal::adc::{Channel, OneShot};
 
 pub use saadc::{
     ch::config::{GAIN_A as Gain, REFSEL_A as Reference, RESP_A as Resistor, TACQ_A as Time},
@@ -23,10 +22,19 @@ pub use saadc::{
 // multiple channels should work (See "scan mode" in the datasheet).
 // Issue: https://github.com/nrf-rs/nrf-hal/issues/82
 
-pub struct Saadc(SAADC);
+/// A safe wrapper around the `SAADC` peripheral, sampling the input `P`.
+pub struct Saadc<P> {
+    saadc: SAADC,
+    input: P,
+}
 
-impl Saadc {
-    pub fn new(saadc: SAADC, config: SaadcConfig) -> Self {
+impl<P> Saadc<P>
+where
+    P: Input,
+{
+    /// Takes ownership of the `SAADC` peripheral and the input to sample, returning a safe
+    /// wrapper.
+    pub fn new(saadc: SAADC, input: P, config: SaadcConfig) -> Self {
         // The write enums do not implement clone/copy/debug, only the
         // read ones, hence the need to pull out and move the values.
         let SaadcConfig {
@@ -61,77 +69,47 @@ impl Saadc {
         saadc.tasks_calibrateoffset.write(|w| unsafe { w.bits(1) });
         while saadc.events_calibratedone.read().bits() == 0 {}
 
-        Saadc(saadc)
-    }
-}
-
-pub struct SaadcConfig {
-    pub resolution: Resolution,
-    pub oversample: Oversample,
-    pub reference: Reference,
-    pub gain: Gain,
-    pub resistor: Resistor,
-    pub time: Time,
-}
-
-// 0 volts reads as 0, VDD volts reads as u16::MAX
-impl Default for SaadcConfig {
-    fn default() -> Self {
-        SaadcConfig {
-            resolution: Resolution::_14BIT,
-            oversample: Oversample::OVER8X,
-            reference: Reference::VDD1_4,
-            gain: Gain::GAIN1_4,
-            resistor: Resistor::BYPASS,
-            time: Time::_20US,
-        }
+        Saadc { saadc, input }
     }
-}
 
-impl<PIN> OneShot<Saadc, i16, PIN> for Saadc
-where
-    PIN: Channel<Saadc, ID = u8>,
-{
-    type Error = ();
-    fn read(&mut self, _pin: &mut PIN) -> nb::Result<i16, Self::Error> {
-        match PIN::channel() {
-            0 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input0()),
-            1 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input1()),
-            2 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input2()),
-            3 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input3()),
-            4 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input4()),
-            5 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input5()),
-            6 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input6()),
-            7 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input7()),
-            #[cfg(not(feature = "9160"))]
-            8 => self.0.ch[0].pselp.write(|w| w.pselp().vdd()),
+    /// Samples the input, blocking until the conversion is done.
+    pub fn read(&mut self) -> nb::Result<i16, ()> {
+        let saadc = &self.saadc;
+        match P::CHANNEL {
+            0 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input0()),
+            1 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input1()),
+            2 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input2()),
+            3 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input3()),
+            4 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input4()),
+            5 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input5()),
+            6 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input6()),
+            7 => saadc.ch[0].pselp.write(|w| w.pselp().analog_input7()),
+            #[cfg(not(any(feature = "9160", feature = "5340-app")))]
+            8 => saadc.ch[0].pselp.write(|w| w.pselp().vdd()),
             // This can never happen the only analog pins have already been defined
-            // PAY CLOSE ATTENTION TO ANY CHANGES TO THIS IMPL OR THE `channel_mappings!` MACRO
+            // PAY CLOSE ATTENTION TO ANY CHANGES TO THIS IMPL OR THE `saadc_inputs!` MACRO
             _ => unsafe { unreachable_unchecked() },
         }
 
         let mut val: i16 = 0;
-        self.0
+        saadc
             .result
             .ptr
             .write(|w| unsafe { w.ptr().bits(((&mut val) as *mut _) as u32) });
-        self.0
-            .result
-            .maxcnt
-            .write(|w| unsafe { w.maxcnt().bits(1) });
+        saadc.result.maxcnt.write(|w| unsafe { w.maxcnt().bits(1) });
 
         // Conservative compiler fence to prevent starting the ADC before the
         // pointer and maxcount have been set.
         compiler_fence(SeqCst);
 
-        self.0.tasks_start.write(|w| unsafe { w.bits(1) });
-        self.0.tasks_sample.write(|w| unsafe { w.bits(1) });
+        saadc.tasks_start.write(|w| unsafe { w.bits(1) });
+        saadc.tasks_sample.write(|w| unsafe { w.bits(1) });
 
-        while self.0.events_end.read().bits() == 0 {}
-        self.0.events_end.reset();
+        while saadc.events_end.read().bits() == 0 {}
+        saadc.events_end.reset();
 
         // Will only occur if more than one channel has been enabled.
-        if self.0.result.amount.read().bits() != 1 {
+        if saadc.result.amount.read().bits() != 1 {
             return Err(nb::Error::Other(()));
         }
 
@@ -142,45 +120,85 @@ where
     }
 }
 
-macro_rules! channel_mappings {
-    ($($n:expr => $pin:path),*) => {
-        $(
-            impl Channel<Saadc> for $pin {
-                type ID = u8;
+impl<P> Saadc<P> {
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &saadc::TASKS_START {
+        &self.saadc.tasks_start
+    }
+
+    /// Returns reference to the `SAMPLE` task endpoint for PPI.
+    pub fn task_sample(&self) -> &saadc::TASKS_SAMPLE {
+        &self.saadc.tasks_sample
+    }
 
-                fn channel() -> <Self as embedded_hal::adc::Channel<Saadc>>::ID {
-                    $n
-                }
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &saadc::TASKS_STOP {
+        &self.saadc.tasks_stop
+    }
+
+    /// Returns reference to the `END` event endpoint for PPI.
+    pub fn event_end(&self) -> &saadc::EVENTS_END {
+        &self.saadc.events_end
+    }
+
+    /// Returns reference to the `DONE` event endpoint for PPI.
+    pub fn event_done(&self) -> &saadc::EVENTS_DONE {
+        &self.saadc.events_done
+    }
+
+    /// Disables the SAADC and returns the raw peripheral and the input.
+    pub fn free(self) -> (SAADC, P) {
+        self.saadc.enable.write(|w| w.enable().disabled());
+        (self.saadc, self.input)
+    }
+}
+
+pub struct SaadcConfig {
+    pub resolution: Resolution,
+    pub oversample: Oversample,
+    pub reference: Reference,
+    pub gain: Gain,
+    pub resistor: Resistor,
+    pub time: Time,
+}
+
+// 0 volts reads as 0, VDD volts reads as u16::MAX
+impl Default for SaadcConfig {
+    fn default() -> Self {
+        SaadcConfig {
+            resolution: Resolution::_14BIT,
+            oversample: Oversample::OVER8X,
+            reference: Reference::VDD1_4,
+            gain: Gain::GAIN1_4,
+            resistor: Resistor::BYPASS,
+            time: Time::_20US,
+        }
+    }
+}
+
+/// An input the SAADC can sample: an analog pin or, where available, [`InternalVdd`].
+pub trait Input {
+    /// The SAADC channel (`AINx`, or 8 for VDD) of this input.
+    const CHANNEL: u8;
+}
+
+macro_rules! saadc_inputs {
+    ($($n:expr => $pin:ident,)+) => {
+        $(
+            impl Input for crate::gpio::p0::$pin<Analog> {
+                const CHANNEL: u8 = $n;
             }
-        )*
+        )+
     };
 }
 
-#[cfg(feature = "9160")]
-channel_mappings! {
-    0 => crate::gpio::p0::P0_13<Input<Floating>>,
-    1 => crate::gpio::p0::P0_14<Input<Floating>>,
-    2 => crate::gpio::p0::P0_15<Input<Floating>>,
-    3 => crate::gpio::p0::P0_16<Input<Floating>>,
-    4 => crate::gpio::p0::P0_17<Input<Floating>>,
-    5 => crate::gpio::p0::P0_18<Input<Floating>>,
-    6 => crate::gpio::p0::P0_19<Input<Floating>>,
-    7 => crate::gpio::p0::P0_20<Input<Floating>>
-}
+analog_pin_table!(saadc_inputs);
 
-#[cfg(not(feature = "9160"))]
-channel_mappings! {
-    0 => crate::gpio::p0::P0_02<Input<Floating>>,
-    1 => crate::gpio::p0::P0_03<Input<Floating>>,
-    2 => crate::gpio::p0::P0_04<Input<Floating>>,
-    3 => crate::gpio::p0::P0_05<Input<Floating>>,
-    4 => crate::gpio::p0::P0_28<Input<Floating>>,
-    5 => crate::gpio::p0::P0_29<Input<Floating>>,
-    6 => crate::gpio::p0::P0_30<Input<Floating>>,
-    7 => crate::gpio::p0::P0_31<Input<Floating>>,
-    8 => InternalVdd
+#[cfg(not(any(feature = "9160", feature = "5340-app")))]
+impl Input for InternalVdd {
+    const CHANNEL: u8 = 8;
 }
 
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app")))]
 /// Channel that doesn't sample a pin, but the internal VDD voltage.
 pub struct InternalVdd;
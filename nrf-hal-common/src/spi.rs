@@ -1,6 +1,7 @@
 //! HAL interface to the SPI peripheral.
 use core::ops::Deref;
 
+use crate::error::ErrorKind;
 use crate::{
     gpio::{Floating, Input, Output, Pin, PushPull},
     pac::{spi0, SPI0, SPI1},
@@ -122,11 +123,20 @@ pub struct Pins {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Transmit,
     Receive,
 }
 
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Transmit | Error::Receive => ErrorKind::Other,
+        }
+    }
+}
+
 /// Trait implemented by all SPI peripheral instances.
 pub trait Instance: Deref<Target = spi0::RegisterBlock> {}
 
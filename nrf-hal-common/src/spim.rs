@@ -5,10 +5,10 @@
 use core::ops::Deref;
 use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
 use crate::pac::{spim0_ns as spim0, SPIM0_NS as SPIM0};
 
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 use crate::pac::{spim0, SPIM0};
 
 pub use embedded_hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
@@ -16,24 +16,48 @@ pub use spim0::frequency::FREQUENCY_A as Frequency;
 
 use core::iter::repeat_with;
 
+#[cfg(feature = "52820")]
+use crate::pac::SPIM1;
+
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
 use crate::pac::{SPIM1, SPIM2};
 
 #[cfg(any(feature = "52833", feature = "52840"))]
 use crate::pac::SPIM3;
 
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+use crate::pac::{SPIM1_NS as SPIM1, SPIM2_NS as SPIM2, SPIM3_NS as SPIM3};
+
+#[cfg(feature = "5340-app")]
+use crate::pac::SPIM4_NS as SPIM4;
+
+use crate::error::ErrorKind;
 use crate::gpio::{Floating, Input, Output, Pin, PushPull};
 use crate::target_constants::{EASY_DMA_SIZE, FORCE_COPY_BUFFER_SIZE};
 use crate::{slice_in_ram, slice_in_ram_or, DmaSlice};
+use embedded_dma::{ReadBuffer, WriteBuffer};
 use embedded_hal::digital::v2::OutputPin;
 
+#[cfg(feature = "async")]
+use crate::waker::{OnDrop, WakerSlot};
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WAKER: WakerSlot = WakerSlot::new();
+
+/// Wakers of each instance.
+#[cfg(feature = "async")]
+static WAKERS: [WakerSlot; 5] = [EMPTY_WAKER; 5];
+
 /// Interface to a SPIM instance.
 ///
 /// This is a very basic interface that comes with the following limitations:
 /// - The SPIM instances share the same address space with instances of SPIS,
 ///   SPI, TWIM, TWIS, and TWI. You need to make sure that conflicting instances
 ///   are disabled before using `Spim`. See product specification, section 15.2.
-pub struct Spim<T>(T);
+pub struct Spim<T>(T, Pins);
 
 impl<T> embedded_hal::blocking::spi::Transfer<u8> for Spim<T>
 where
@@ -79,6 +103,43 @@ where
         words.chunks(chunk_sz).try_for_each(|c| step(self, c))
     }
 }
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::spi::ErrorType for Spim<T> {
+    type Error = Error;
+}
+
+/// The bus only drives SCK, MOSI and MISO. Use `embedded-hal-bus` or a similar crate to share it
+/// between devices with their own chip select pins.
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::spi::SpiBus<u8> for Spim<T>
+where
+    T: Instance,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+        self.transfer_uneven(&[], words)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+        embedded_hal::blocking::spi::Write::write(self, words)
+    }
+
+    /// Extra outgoing bytes are filled with the `orc` value.
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        slice_in_ram_or(write, Error::DMABufferNotInDataMemory)?;
+        self.transfer_uneven(write, read)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+        embedded_hal::blocking::spi::Transfer::transfer(self, words).map(|_| ())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // Transfers complete before the methods above return.
+        Ok(())
+    }
+}
+
 impl<T> Spim<T>
 where
     T: Instance,
@@ -98,24 +159,39 @@ where
         // Select pins.
         spim.psel.sck.write(|w| {
             let w = unsafe { w.pin().bits(pins.sck.pin()) };
-            #[cfg(any(feature = "52843", feature = "52840"))]
+            #[cfg(any(
+                feature = "52833",
+                feature = "52840",
+                feature = "5340-app",
+                feature = "5340-net"
+            ))]
             let w = w.port().bit(pins.sck.port().bit());
             w.connect().connected()
         });
 
         match pins.mosi {
-            Some(mosi) => spim.psel.mosi.write(|w| {
+            Some(ref mosi) => spim.psel.mosi.write(|w| {
                 let w = unsafe { w.pin().bits(mosi.pin()) };
-                #[cfg(any(feature = "52843", feature = "52840"))]
+                #[cfg(any(
+                    feature = "52833",
+                    feature = "52840",
+                    feature = "5340-app",
+                    feature = "5340-net"
+                ))]
                 let w = w.port().bit(mosi.port().bit());
                 w.connect().connected()
             }),
             None => spim.psel.mosi.write(|w| w.connect().disconnected()),
         }
         match pins.miso {
-            Some(miso) => spim.psel.miso.write(|w| {
+            Some(ref miso) => spim.psel.miso.write(|w| {
                 let w = unsafe { w.pin().bits(miso.pin()) };
-                #[cfg(any(feature = "52843", feature = "52840"))]
+                #[cfg(any(
+                    feature = "52833",
+                    feature = "52840",
+                    feature = "5340-app",
+                    feature = "5340-net"
+                ))]
                 let w = w.port().bit(miso.port().bit());
                 w.connect().connected()
             }),
@@ -157,11 +233,60 @@ where
             // there.
             unsafe { w.orc().bits(orc) });
 
-        Spim(spim)
+        Spim(spim, pins)
     }
 
     /// Internal helper function to setup and execute SPIM DMA transfer.
     fn do_spi_dma_transfer(&mut self, tx: DmaSlice, rx: DmaSlice) -> Result<(), Error> {
+        self.start_spi_dma_transfer(&tx, &rx);
+
+        // Wait for END event.
+        //
+        // This event is triggered once both transmitting and receiving are
+        // done.
+        while self.0.events_end.read().bits() == 0 {}
+
+        self.finish_spi_dma_transfer(tx, rx)
+    }
+
+    /// Internal helper function to setup and execute SPIM DMA transfer, waiting for its
+    /// completion without blocking.
+    ///
+    /// The transfer is stopped if the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    async fn do_spi_dma_transfer_async(&mut self, tx: DmaSlice, rx: DmaSlice) -> Result<(), Error> {
+        self.0.intenset.write(|w| w.end().set());
+        self.start_spi_dma_transfer(&tx, &rx);
+
+        let on_drop = OnDrop::new(|| {
+            let spim = unsafe { &*T::ptr() };
+            spim.intenclr.write(|w| w.end().clear());
+            // A finished transfer is already stopped, and `STOPPED` may never come.
+            if spim.events_end.read().bits() == 0 {
+                spim.tasks_stop.write(|w| unsafe { w.bits(1) });
+                while spim.events_stopped.read().bits() == 0 {}
+                spim.events_stopped.reset();
+            }
+            spim.events_end.reset();
+        });
+
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            if self.0.events_end.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.0.intenset.write(|w| w.end().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        on_drop.defuse();
+
+        self.finish_spi_dma_transfer(tx, rx)
+    }
+
+    /// Sets up the DMA pointers and starts the transfer.
+    fn start_spi_dma_transfer(&mut self, tx: &DmaSlice, rx: &DmaSlice) {
         // Conservative compiler fence to prevent optimizations that do not
         // take in to account actions by DMA. The fence has been placed here,
         // before any DMA action has started.
@@ -191,18 +316,11 @@ where
         self.0.tasks_start.write(|w|
             // `1` is a valid value to write to task registers.
             unsafe { w.bits(1) });
+    }
 
-        // Conservative compiler fence to prevent optimizations that do not
-        // take in to account actions by DMA. The fence has been placed here,
-        // after all possible DMA actions have completed.
-        compiler_fence(SeqCst);
-
-        // Wait for END event.
-        //
-        // This event is triggered once both transmitting and receiving are
-        // done.
-        while self.0.events_end.read().bits() == 0 {}
-
+    /// Resets the `END` event of a completed transfer and checks the amount of bytes
+    /// transferred.
+    fn finish_spi_dma_transfer(&mut self, tx: DmaSlice, rx: DmaSlice) -> Result<(), Error> {
         // Reset the event, otherwise it will always read `1` from now on.
         self.0.events_end.write(|w| w);
 
@@ -220,6 +338,62 @@ where
         Ok(())
     }
 
+    /// Transmits all bytes in `tx_buffer` while receiving bytes until `rx_buffer` is full, without
+    /// touching any chip select pin. `tx_buffer` must be in RAM.
+    fn transfer_uneven(&mut self, tx_buffer: &[u8], rx_buffer: &mut [u8]) -> Result<(), Error> {
+        // For the tx and rx, we want to return Some(chunk)
+        // as long as there is data to send. We then chain a repeat to
+        // the end so once all chunks have been exhausted, we will keep
+        // getting Nones out of the iterators.
+        let txi = tx_buffer
+            .chunks(EASY_DMA_SIZE)
+            .map(Some)
+            .chain(repeat_with(|| None));
+
+        let rxi = rx_buffer
+            .chunks_mut(EASY_DMA_SIZE)
+            .map(Some)
+            .chain(repeat_with(|| None));
+
+        // We then chain the iterators together, and once BOTH are feeding
+        // back Nones, then we are done sending and receiving.
+        txi.zip(rxi)
+            .take_while(|(t, r)| t.is_some() || r.is_some())
+            // We also turn the slices into either a DmaSlice (if there was data), or a null
+            // DmaSlice (if there is no data).
+            .map(|(t, r)| {
+                (
+                    t.map(|t| DmaSlice::from_slice(t))
+                        .unwrap_or_else(DmaSlice::null),
+                    r.map(|r| DmaSlice::from_slice(r))
+                        .unwrap_or_else(DmaSlice::null),
+                )
+            })
+            .try_for_each(|(t, r)| self.do_spi_dma_transfer(t, r))
+    }
+
+    /// Like `transfer_uneven`, but waits for the transfers without blocking.
+    #[cfg(feature = "async")]
+    async fn transfer_uneven_async(
+        &mut self,
+        tx_buffer: &[u8],
+        rx_buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let mut txi = tx_buffer.chunks(EASY_DMA_SIZE);
+        let mut rxi = rx_buffer.chunks_mut(EASY_DMA_SIZE);
+        loop {
+            let (t, r) = match (txi.next(), rxi.next()) {
+                (None, None) => return Ok(()),
+                (t, r) => (
+                    t.map(DmaSlice::from_slice).unwrap_or_else(DmaSlice::null),
+                    r.map(|r| DmaSlice::from_slice(r))
+                        .unwrap_or_else(DmaSlice::null),
+                ),
+            };
+            self.do_spi_dma_transfer_async(t, r).await?;
+        }
+    }
+
     /// Read from an SPI slave.
     ///
     /// This method is deprecated. Consider using `transfer` or `transfer_split`.
@@ -319,40 +493,10 @@ where
         // slice can only be built from data located in RAM.
         slice_in_ram_or(tx_buffer, Error::DMABufferNotInDataMemory)?;
 
-        // For the tx and rx, we want to return Some(chunk)
-        // as long as there is data to send. We then chain a repeat to
-        // the end so once all chunks have been exhausted, we will keep
-        // getting Nones out of the iterators.
-        let txi = tx_buffer
-            .chunks(EASY_DMA_SIZE)
-            .map(Some)
-            .chain(repeat_with(|| None));
-
-        let rxi = rx_buffer
-            .chunks_mut(EASY_DMA_SIZE)
-            .map(Some)
-            .chain(repeat_with(|| None));
-
         chip_select.set_low().unwrap();
 
-        // We then chain the iterators together, and once BOTH are feeding
-        // back Nones, then we are done sending and receiving.
-        //
         // Don't return early, as we must reset the CS pin.
-        let res = txi
-            .zip(rxi)
-            .take_while(|(t, r)| t.is_some() || r.is_some())
-            // We also turn the slices into either a DmaSlice (if there was data), or a null
-            // DmaSlice (if there is no data).
-            .map(|(t, r)| {
-                (
-                    t.map(|t| DmaSlice::from_slice(t))
-                        .unwrap_or_else(DmaSlice::null),
-                    r.map(|r| DmaSlice::from_slice(r))
-                        .unwrap_or_else(DmaSlice::null),
-                )
-            })
-            .try_for_each(|(t, r)| self.do_spi_dma_transfer(t, r));
+        let res = self.transfer_uneven(tx_buffer, rx_buffer);
 
         chip_select.set_high().unwrap();
 
@@ -373,9 +517,186 @@ where
         self.transfer_split_uneven(chip_select, tx_buffer, &mut [0u8; 0])
     }
 
-    /// Return the raw interface to the underlying SPIM peripheral.
-    pub fn free(self) -> T {
-        self.0
+    /// Starts transmitting `tx` while receiving into `rx`, without touching any chip select pin,
+    /// and returns a handle that owns the interface and both buffers until the transfer is done.
+    ///
+    /// As the buffers are `'static` and the handle stops the transfer when dropped, EasyDMA never
+    /// accesses a buffer that has been released, even if the transfer is abandoned. `tx` must be
+    /// in RAM, and both buffers must be at most `EASY_DMA_SIZE` bytes long. On error, the
+    /// interface and the buffers are returned unchanged.
+    pub fn dma_transfer<TxB, RxB>(
+        mut self,
+        tx: TxB,
+        mut rx: RxB,
+    ) -> Result<Transfer<T, TxB, RxB>, (Error, Self, TxB, RxB)>
+    where
+        TxB: ReadBuffer<Word = u8>,
+        RxB: WriteBuffer<Word = u8>,
+    {
+        // The buffers are owned by the transfer until EasyDMA is done with them.
+        let (tx_ptr, tx_len) = unsafe { tx.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rx.write_buffer() };
+        let tx_slice = unsafe { core::slice::from_raw_parts(tx_ptr, tx_len) };
+        let rx_slice = unsafe { core::slice::from_raw_parts(rx_ptr as *const u8, rx_len) };
+
+        if tx_len > EASY_DMA_SIZE {
+            return Err((Error::TxBufferTooLong, self, tx, rx));
+        }
+        if rx_len > EASY_DMA_SIZE {
+            return Err((Error::RxBufferTooLong, self, tx, rx));
+        }
+        if !slice_in_ram(tx_slice) {
+            return Err((Error::DMABufferNotInDataMemory, self, tx, rx));
+        }
+
+        let tx_dma = DmaSlice::from_slice(tx_slice);
+        let rx_dma = DmaSlice::from_slice(rx_slice);
+        self.start_spi_dma_transfer(&tx_dma, &rx_dma);
+
+        Ok(Transfer {
+            inner: Some(TransferInner {
+                spim: self,
+                tx,
+                rx,
+                tx_dma,
+                rx_dma,
+            }),
+        })
+    }
+
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &spim0::TASKS_START {
+        &self.0.tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &spim0::TASKS_STOP {
+        &self.0.tasks_stop
+    }
+
+    /// Returns reference to the `STARTED` event endpoint for PPI.
+    pub fn event_started(&self) -> &spim0::EVENTS_STARTED {
+        &self.0.events_started
+    }
+
+    /// Returns reference to the `END` event endpoint for PPI.
+    pub fn event_end(&self) -> &spim0::EVENTS_END {
+        &self.0.events_end
+    }
+
+    /// Returns reference to the `STOPPED` event endpoint for PPI.
+    pub fn event_stopped(&self) -> &spim0::EVENTS_STOPPED {
+        &self.0.events_stopped
+    }
+
+    /// Disable the SPIM and return the raw peripheral together with the pins.
+    ///
+    /// The pins are disconnected from the peripheral and returned in the mode they were passed to
+    /// `new` in, so they can be reused by other drivers or as plain GPIOs.
+    pub fn free(self) -> (T, Pins) {
+        self.0.enable.write(|w| w.enable().disabled());
+        self.0.psel.sck.write(|w| w.connect().disconnected());
+        self.0.psel.mosi.write(|w| w.connect().disconnected());
+        self.0.psel.miso.write(|w| w.connect().disconnected());
+        (self.0, self.1)
+    }
+}
+
+/// A transfer started with [`Spim::dma_transfer`].
+///
+/// Dropping the transfer before it is done stops it, and drops the interface and the buffers.
+pub struct Transfer<T, TxB, RxB>
+where
+    T: Instance,
+{
+    inner: Option<TransferInner<T, TxB, RxB>>,
+}
+
+struct TransferInner<T, TxB, RxB> {
+    spim: Spim<T>,
+    tx: TxB,
+    rx: RxB,
+    tx_dma: DmaSlice,
+    rx_dma: DmaSlice,
+}
+
+impl<T, TxB, RxB> Transfer<T, TxB, RxB>
+where
+    T: Instance,
+{
+    /// Returns `true` once both buffers have been transferred.
+    pub fn is_done(&self) -> bool {
+        let inner = self.inner.as_ref().unwrap();
+        inner.spim.0.events_end.read().bits() != 0
+    }
+
+    /// Blocks until the transfer is done, and returns the interface and the buffers.
+    pub fn wait(mut self) -> Result<(Spim<T>, TxB, RxB), (Error, Spim<T>, TxB, RxB)> {
+        let inner = self.inner.take().unwrap();
+        while inner.spim.0.events_end.read().bits() == 0 {}
+        inner.finish()
+    }
+
+    /// Waits until the transfer is done without blocking, and returns the interface and the
+    /// buffers.
+    ///
+    /// [`on_interrupt`] has to be called from the interrupt handler of the instance. The transfer
+    /// is stopped if the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(mut self) -> Result<(Spim<T>, TxB, RxB), (Error, Spim<T>, TxB, RxB)> {
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            let spim = &self.inner.as_ref().unwrap().spim.0;
+            if spim.events_end.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                spim.intenset.write(|w| w.end().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        let inner = self.inner.take().unwrap();
+        inner.spim.0.intenclr.write(|w| w.end().clear());
+        inner.finish()
+    }
+}
+
+impl<T, TxB, RxB> TransferInner<T, TxB, RxB>
+where
+    T: Instance,
+{
+    fn finish(self) -> Result<(Spim<T>, TxB, RxB), (Error, Spim<T>, TxB, RxB)> {
+        let TransferInner {
+            mut spim,
+            tx,
+            rx,
+            tx_dma,
+            rx_dma,
+        } = self;
+        match spim.finish_spi_dma_transfer(tx_dma, rx_dma) {
+            Ok(()) => Ok((spim, tx, rx)),
+            Err(e) => Err((e, spim, tx, rx)),
+        }
+    }
+}
+
+impl<T, TxB, RxB> Drop for Transfer<T, TxB, RxB>
+where
+    T: Instance,
+{
+    fn drop(&mut self) {
+        if let Some(inner) = &self.inner {
+            let spim = &inner.spim.0;
+            spim.intenclr.write(|w| w.end().clear());
+            if spim.events_end.read().bits() == 0 {
+                // Stop EasyDMA before the buffers are released.
+                spim.tasks_stop.write(|w| unsafe { w.bits(1) });
+                while spim.events_stopped.read().bits() == 0 {}
+                spim.events_stopped.reset();
+            }
+            spim.events_end.reset();
+            compiler_fence(SeqCst);
+        }
     }
 }
 
@@ -394,6 +715,7 @@ pub struct Pins {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     TxBufferTooLong,
     RxBufferTooLong,
@@ -403,16 +725,139 @@ pub enum Error {
     Receive,
 }
 
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::TxBufferTooLong | Error::RxBufferTooLong => ErrorKind::BufferLength,
+            Error::DMABufferNotInDataMemory => ErrorKind::BufferNotInRam,
+            Error::Transmit | Error::Receive => ErrorKind::Dma,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::spi::Error for Error {
+    fn kind(&self) -> embedded_hal_1::spi::ErrorKind {
+        use embedded_hal_1::spi::ErrorKind as SpiErrorKind;
+
+        // SPIM has no overrun or mode fault detection; `Transmit` and `Receive` only report
+        // EasyDMA transfers that were cut short.
+        match self {
+            Error::TxBufferTooLong | Error::RxBufferTooLong => SpiErrorKind::Other,
+            Error::DMABufferNotInDataMemory => SpiErrorKind::Other,
+            Error::Transmit | Error::Receive => SpiErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> embedded_hal_async::spi::SpiBus<u8> for Spim<T>
+where
+    T: Instance,
+{
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+        self.transfer_uneven_async(&[], words).await
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+        if slice_in_ram(words) {
+            return self.transfer_uneven_async(words, &mut []).await;
+        }
+        let mut buf = [0u8; FORCE_COPY_BUFFER_SIZE];
+        for chunk in words.chunks(FORCE_COPY_BUFFER_SIZE) {
+            buf[..chunk.len()].copy_from_slice(chunk);
+            self.transfer_uneven_async(&buf[..chunk.len()], &mut [])
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        slice_in_ram_or(write, Error::DMABufferNotInDataMemory)?;
+        self.transfer_uneven_async(write, read).await
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+        for chunk in words.chunks(EASY_DMA_SIZE) {
+            self.do_spi_dma_transfer_async(
+                DmaSlice::from_slice(chunk),
+                DmaSlice::from_slice(chunk),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Handles the SPIM events for the `embedded_hal_async::spi::SpiBus` implementation and
+/// [`Transfer::wait_async`].
+///
+/// Must be called from the interrupt handler of the instance `T`.
+#[cfg(feature = "async")]
+pub fn on_interrupt<T: Instance>() {
+    let spim = unsafe { &*T::ptr() };
+    if spim.events_end.read().bits() != 0 {
+        // Leave the event set for the transfer to observe, but keep it from firing again.
+        spim.intenclr.write(|w| w.end().clear());
+        WAKERS[T::INDEX].wake();
+    }
+}
+
 /// Implemented by all SPIM instances.
-pub trait Instance: Deref<Target = spim0::RegisterBlock> {}
+pub trait Instance: Deref<Target = spim0::RegisterBlock> {
+    /// Number of the instance.
+    const INDEX: usize;
 
-impl Instance for SPIM0 {}
+    /// Returns the registers of the instance.
+    fn ptr() -> *const spim0::RegisterBlock;
+}
 
-#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
-impl Instance for SPIM1 {}
+macro_rules! impl_instance {
+    ($($name:ident => $index:expr,)*) => {
+        $(
+            impl Instance for $name {
+                const INDEX: usize = $index;
 
-#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
-impl Instance for SPIM2 {}
+                fn ptr() -> *const spim0::RegisterBlock {
+                    $name::ptr() as *const _
+                }
+            }
+        )*
+    }
+}
 
-#[cfg(any(feature = "52833", feature = "52840"))]
-impl Instance for SPIM3 {}
+impl_instance!(SPIM0 => 0,);
+
+#[cfg(any(
+    feature = "52820",
+    feature = "52832",
+    feature = "52833",
+    feature = "52840",
+    feature = "9160",
+    feature = "5340-app"
+))]
+impl_instance!(SPIM1 => 1,);
+
+#[cfg(any(
+    feature = "52832",
+    feature = "52833",
+    feature = "52840",
+    feature = "9160",
+    feature = "5340-app"
+))]
+impl_instance!(SPIM2 => 2,);
+
+#[cfg(any(
+    feature = "52833",
+    feature = "52840",
+    feature = "9160",
+    feature = "5340-app"
+))]
+impl_instance!(SPIM3 => 3,);
+
+#[cfg(feature = "5340-app")]
+impl_instance!(SPIM4 => 4,);
@@ -0,0 +1,408 @@
+//! HAL interface to the SPU peripheral.
+//!
+//! The system protection unit (SPU) splits the chip into a secure and a non-secure domain for
+//! TrustZone. It assigns regions of flash and RAM, peripherals and GPIO pins to either domain, and
+//! sets the access permissions of the memory regions. The SPU is only accessible from the secure
+//! domain, so this driver is used by the secure firmware before it starts the non-secure
+//! application:
+//!
+//! ```ignore
+//! let mut spu = Spu::new(p.SPU_S);
+//! // The upper half of the flash holds the non-secure application.
+//! spu.configure_flash(0x8_0000..0x10_0000, Permissions::NON_SECURE)?;
+//! spu.configure_ram(0x2002_0000..0x2004_0000, Permissions::NON_SECURE)?;
+//! spu.configure_peripheral(peripheral_id(UARTE0_S::ptr()), Security::NonSecure, false)?;
+//! spu.configure_pin(&led, Security::NonSecure)?;
+//! ```
+//!
+//! Memory regions and pins are configured as a whole: if any of them is locked, nothing is
+//! written and `Error::Locked` is returned.
+//!
+//! See product specification, chapter SPU.
+
+use core::ops::Range;
+
+use crate::error::ErrorKind;
+use crate::gpio::{Pin, Port};
+use crate::pac::SPU_S as SPU;
+
+/// Size of a flash region in bytes.
+#[cfg(feature = "9160")]
+pub const FLASH_REGION_SIZE: u32 = 32 * 1024;
+
+/// Size of a flash region in bytes.
+#[cfg(feature = "5340-app")]
+pub const FLASH_REGION_SIZE: u32 = 16 * 1024;
+
+/// Size of a RAM region in bytes.
+pub const RAM_REGION_SIZE: u32 = 8 * 1024;
+
+/// Start address of the RAM.
+const RAM_START: u32 = 0x2000_0000;
+
+// Bits of the `PERM` registers of the flash and RAM regions.
+const PERM_EXECUTE: u32 = 1 << 0;
+const PERM_WRITE: u32 = 1 << 1;
+const PERM_READ: u32 = 1 << 2;
+const PERM_SECATTR: u32 = 1 << 4;
+const PERM_LOCK: u32 = 1 << 8;
+
+// Bits of the `PERM` registers of the peripherals.
+const PERIPH_SECUREMAPPING_MASK: u32 = 0b11;
+const PERIPH_SECUREMAPPING_NON_SECURE: u32 = 0;
+const PERIPH_SECUREMAPPING_SECURE: u32 = 1;
+const PERIPH_DMA_MASK: u32 = 0b11 << 2;
+const PERIPH_DMA_SEPARATE: u32 = 2 << 2;
+const PERIPH_SECATTR: u32 = 1 << 4;
+const PERIPH_DMASEC: u32 = 1 << 5;
+const PERIPH_LOCK: u32 = 1 << 8;
+const PERIPH_PRESENT: u32 = 1 << 31;
+
+/// The domain a memory region, peripheral or pin belongs to.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Security {
+    Secure,
+    NonSecure,
+}
+
+/// Access permissions of a flash or RAM region.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Permissions {
+    /// Domain the region belongs to. The secure domain can access non-secure regions too.
+    pub security: Security,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// Keeps the permissions from being changed until the next reset.
+    pub lock: bool,
+}
+
+impl Permissions {
+    /// Full access from the secure domain only, the configuration after reset.
+    pub const SECURE: Self = Permissions {
+        security: Security::Secure,
+        read: true,
+        write: true,
+        execute: true,
+        lock: false,
+    };
+
+    /// Full access from both domains.
+    pub const NON_SECURE: Self = Permissions {
+        security: Security::NonSecure,
+        read: true,
+        write: true,
+        execute: true,
+        lock: false,
+    };
+
+    fn bits(self) -> u32 {
+        let mut bits = 0;
+        if self.security == Security::Secure {
+            bits |= PERM_SECATTR;
+        }
+        if self.read {
+            bits |= PERM_READ;
+        }
+        if self.write {
+            bits |= PERM_WRITE;
+        }
+        if self.execute {
+            bits |= PERM_EXECUTE;
+        }
+        if self.lock {
+            bits |= PERM_LOCK;
+        }
+        bits
+    }
+}
+
+/// A kind of access violation detected by the SPU.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AccessError {
+    /// `RAMACCERR`: a RAM region was accessed against its permissions.
+    Ram,
+    /// `FLASHACCERR`: a flash region was accessed against its permissions.
+    Flash,
+    /// `PERIPHACCERR`: a secure peripheral was accessed from the non-secure domain.
+    Peripheral,
+}
+
+impl AccessError {
+    fn bit(self) -> u32 {
+        match self {
+            AccessError::Ram => 1 << 0,
+            AccessError::Flash => 1 << 1,
+            AccessError::Peripheral => 1 << 2,
+        }
+    }
+}
+
+/// Interface to the SPU peripheral.
+pub struct Spu(SPU);
+
+impl Spu {
+    pub fn new(spu: SPU) -> Self {
+        Self(spu)
+    }
+
+    /// Returns the raw `SPU` peripheral.
+    pub fn free(self) -> SPU {
+        self.0
+    }
+
+    /// Sets the permissions of the flash regions covering `range`, which must be aligned to
+    /// `FLASH_REGION_SIZE`.
+    pub fn configure_flash(
+        &mut self,
+        range: Range<u32>,
+        permissions: Permissions,
+    ) -> Result<(), Error> {
+        let regions = Self::regions(range, 0, FLASH_REGION_SIZE, self.0.flashregion.len())?;
+        if regions
+            .clone()
+            .any(|n| self.0.flashregion[n].perm.read().bits() & PERM_LOCK != 0)
+        {
+            return Err(Error::Locked);
+        }
+        for n in regions {
+            self.0.flashregion[n]
+                .perm
+                .write(|w| unsafe { w.bits(permissions.bits()) });
+        }
+        Ok(())
+    }
+
+    /// Sets the permissions of the RAM regions covering `range`, given as addresses of the data
+    /// RAM and aligned to `RAM_REGION_SIZE`.
+    pub fn configure_ram(
+        &mut self,
+        range: Range<u32>,
+        permissions: Permissions,
+    ) -> Result<(), Error> {
+        let regions = Self::regions(range, RAM_START, RAM_REGION_SIZE, self.0.ramregion.len())?;
+        if regions
+            .clone()
+            .any(|n| self.0.ramregion[n].perm.read().bits() & PERM_LOCK != 0)
+        {
+            return Err(Error::Locked);
+        }
+        for n in regions {
+            self.0.ramregion[n]
+                .perm
+                .write(|w| unsafe { w.bits(permissions.bits()) });
+        }
+        Ok(())
+    }
+
+    /// Returns the permissions of the flash region containing `address`.
+    pub fn flash_permissions(&self, address: u32) -> Result<Permissions, Error> {
+        let n = (address / FLASH_REGION_SIZE) as usize;
+        let region = self.0.flashregion.get(n).ok_or(Error::OutOfBounds)?;
+        Ok(Self::permissions(region.perm.read().bits()))
+    }
+
+    /// Returns the permissions of the RAM region containing `address`.
+    pub fn ram_permissions(&self, address: u32) -> Result<Permissions, Error> {
+        let offset = address.checked_sub(RAM_START).ok_or(Error::OutOfBounds)?;
+        let n = (offset / RAM_REGION_SIZE) as usize;
+        let region = self.0.ramregion.get(n).ok_or(Error::OutOfBounds)?;
+        Ok(Self::permissions(region.perm.read().bits()))
+    }
+
+    /// Assigns the peripheral `id` to a domain, together with its EasyDMA accesses. With `lock`,
+    /// the assignment can't be changed until the next reset.
+    ///
+    /// Peripherals that are always secure or always non-secure only accept their fixed domain.
+    pub fn configure_peripheral(
+        &mut self,
+        id: u8,
+        security: Security,
+        lock: bool,
+    ) -> Result<(), Error> {
+        let perm = &self.0.periphid[id as usize].perm;
+        let current = perm.read().bits();
+        if current & PERIPH_PRESENT == 0 {
+            return Err(Error::NotPresent);
+        }
+        let fixed = match current & PERIPH_SECUREMAPPING_MASK {
+            PERIPH_SECUREMAPPING_NON_SECURE => Some(Security::NonSecure),
+            PERIPH_SECUREMAPPING_SECURE => Some(Security::Secure),
+            _ => None,
+        };
+        if let Some(fixed) = fixed {
+            return if fixed == security {
+                Ok(())
+            } else {
+                Err(Error::FixedSecurity)
+            };
+        }
+        if current & PERIPH_LOCK != 0 {
+            return Err(Error::Locked);
+        }
+
+        let mut bits = 0;
+        if security == Security::Secure {
+            bits |= PERIPH_SECATTR;
+            if current & PERIPH_DMA_MASK == PERIPH_DMA_SEPARATE {
+                bits |= PERIPH_DMASEC;
+            }
+        }
+        if lock {
+            bits |= PERIPH_LOCK;
+        }
+        perm.write(|w| unsafe { w.bits(bits) });
+        Ok(())
+    }
+
+    /// Returns the domain the peripheral `id` is assigned to, or `None` if it does not exist.
+    pub fn peripheral_security(&self, id: u8) -> Option<Security> {
+        let bits = self.0.periphid[id as usize].perm.read().bits();
+        if bits & PERIPH_PRESENT == 0 {
+            None
+        } else if bits & PERIPH_SECATTR != 0 {
+            Some(Security::Secure)
+        } else {
+            Some(Security::NonSecure)
+        }
+    }
+
+    /// Assigns `pin` to a domain. Non-secure pins can only be controlled through the non-secure
+    /// GPIO instance, and secure pins only through the secure one.
+    pub fn configure_pin<MODE>(
+        &mut self,
+        pin: &Pin<MODE>,
+        security: Security,
+    ) -> Result<(), Error> {
+        let port = &self.0.gpioport[port_index(pin.port())];
+        if port.lock.read().bits() & 1 != 0 {
+            return Err(Error::Locked);
+        }
+        let mask = 1u32 << pin.pin();
+        port.perm.modify(|r, w| unsafe {
+            w.bits(match security {
+                Security::Secure => r.bits() | mask,
+                Security::NonSecure => r.bits() & !mask,
+            })
+        });
+        Ok(())
+    }
+
+    /// Locks the domain of all pins until the next reset.
+    pub fn lock_pins(&mut self) {
+        for port in self.0.gpioport.iter() {
+            port.lock.write(|w| unsafe { w.bits(1) });
+        }
+    }
+
+    /// Returns `true` if an access violation of `kind` has been detected.
+    pub fn is_access_error(&self, kind: AccessError) -> bool {
+        let bits = match kind {
+            AccessError::Ram => self.0.events_ramaccerr.read().bits(),
+            AccessError::Flash => self.0.events_flashaccerr.read().bits(),
+            AccessError::Peripheral => self.0.events_periphaccerr.read().bits(),
+        };
+        bits != 0
+    }
+
+    /// Clears the event of `kind`.
+    pub fn reset_access_error(&mut self, kind: AccessError) {
+        match kind {
+            AccessError::Ram => self.0.events_ramaccerr.reset(),
+            AccessError::Flash => self.0.events_flashaccerr.reset(),
+            AccessError::Peripheral => self.0.events_periphaccerr.reset(),
+        }
+    }
+
+    /// Enables the interrupt for access violations of `kind`.
+    pub fn enable_interrupt(&mut self, kind: AccessError) -> &mut Self {
+        self.0.intenset.write(|w| unsafe { w.bits(kind.bit()) });
+        self
+    }
+
+    /// Disables the interrupt for access violations of `kind`.
+    pub fn disable_interrupt(&mut self, kind: AccessError) -> &mut Self {
+        self.0.intenclr.write(|w| unsafe { w.bits(kind.bit()) });
+        self
+    }
+
+    /// Returns the indices of the regions of `size` bytes, starting at `base`, that cover `range`.
+    fn regions(
+        range: Range<u32>,
+        base: u32,
+        size: u32,
+        count: usize,
+    ) -> Result<Range<usize>, Error> {
+        if range.start < base || range.end < range.start {
+            return Err(Error::OutOfBounds);
+        }
+        let (start, end) = (range.start - base, range.end - base);
+        if start % size != 0 || end % size != 0 {
+            return Err(Error::Unaligned);
+        }
+        let (first, last) = ((start / size) as usize, (end / size) as usize);
+        if last > count {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(first..last)
+    }
+
+    fn permissions(bits: u32) -> Permissions {
+        Permissions {
+            security: if bits & PERM_SECATTR != 0 {
+                Security::Secure
+            } else {
+                Security::NonSecure
+            },
+            read: bits & PERM_READ != 0,
+            write: bits & PERM_WRITE != 0,
+            execute: bits & PERM_EXECUTE != 0,
+            lock: bits & PERM_LOCK != 0,
+        }
+    }
+}
+
+/// Returns the ID of the peripheral with the registers at `registers`, e.g. `UARTE0_S::ptr()`,
+/// for `Spu::configure_peripheral`.
+///
+/// The ID is the same for the secure and the non-secure instance of a peripheral.
+pub fn peripheral_id<T>(registers: *const T) -> u8 {
+    (registers as usize >> 12) as u8
+}
+
+fn port_index(port: Port) -> usize {
+    match port {
+        Port::Port0 => 0,
+        #[cfg(feature = "5340-app")]
+        Port::Port1 => 1,
+    }
+}
+
+/// SPU error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The range is not aligned to the region size.
+    Unaligned,
+    /// The range extends beyond the flash or RAM.
+    OutOfBounds,
+    /// The configuration is locked until the next reset.
+    Locked,
+    /// No peripheral has this ID.
+    NotPresent,
+    /// The peripheral always belongs to the other domain.
+    FixedSecurity,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Unaligned | Error::OutOfBounds | Error::NotPresent => ErrorKind::InvalidArgument,
+            Error::FixedSecurity => ErrorKind::Unsupported,
+            Error::Locked => ErrorKind::Other,
+        }
+    }
+}
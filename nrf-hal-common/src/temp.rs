@@ -1,9 +1,100 @@
 //! Temperature sensor interface.
 
-use crate::pac::TEMP;
+use crate::pac::{temp, TEMP};
+#[cfg(not(any(feature = "51", feature = "9160")))]
+use crate::{
+    ppi::{ConfigurablePpi, Ppi},
+    rtc::{self, Rtc, RtcCompareReg, RtcInterrupt, Started, Stopped},
+};
+use core::fmt;
 use fixed::types::I30F2;
 use void::Void;
 
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static WAKER: WakerSlot = WakerSlot::new();
+
+/// A temperature in °C, with the 0.25 °C resolution of the sensor.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+pub struct Celsius(I30F2);
+
+impl Celsius {
+    /// Creates a temperature from the value of the `TEMP` register, in units of 0.25 °C.
+    pub fn from_raw(raw: i32) -> Self {
+        Celsius(I30F2::from_bits(raw))
+    }
+
+    /// Returns the temperature in units of 0.25 °C.
+    pub fn to_raw(self) -> i32 {
+        self.0.to_bits()
+    }
+
+    /// Returns the temperature as a fixed-point number.
+    pub fn to_fixed(self) -> I30F2 {
+        self.0
+    }
+
+    /// Returns the temperature in m°C.
+    pub fn to_millidegrees(self) -> i32 {
+        self.0.to_bits() * 250
+    }
+
+    /// Returns the temperature as a floating point number.
+    pub fn to_f32(self) -> f32 {
+        self.0.to_bits() as f32 * 0.25
+    }
+}
+
+impl From<Celsius> for I30F2 {
+    fn from(temp: Celsius) -> I30F2 {
+        temp.0
+    }
+}
+
+impl From<Celsius> for f32 {
+    fn from(temp: Celsius) -> f32 {
+        temp.to_f32()
+    }
+}
+
+impl fmt::Display for Celsius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} °C", self.0)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Celsius {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=f32} °C", self.0.to_num::<f32>())
+    }
+}
+
+/// A sensor measuring temperature.
+pub trait TemperatureSensor {
+    type Error;
+
+    /// Performs a measurement and returns the temperature.
+    fn read_temperature(&mut self) -> Result<Celsius, Self::Error>;
+}
+
+/// Handles the TEMP interrupt for `Temp::measure_async`.
+///
+/// Must be called from the `TEMP` interrupt handler.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let temp = unsafe { &*TEMP::ptr() };
+    if temp.events_datardy.read().bits() != 0 {
+        // Leave the event set for the future to observe, but keep it from firing again.
+        temp.intenclr.write(|w| w.datardy().clear());
+        WAKER.wake();
+    }
+}
+
 /// Integrated temperature sensor.
 pub struct Temp(TEMP);
 
@@ -16,13 +107,39 @@ impl Temp {
     /// Starts a new measurement and blocks until completion.
     ///
     /// If a measurement was already started, it will be canceled.
-    pub fn measure(&mut self) -> I30F2 {
+    pub fn measure(&mut self) -> Celsius {
         self.stop_measurement();
         self.start_measurement();
 
         nb::block!(self.read()).unwrap()
     }
 
+    /// Starts a new measurement and waits for its completion without blocking.
+    ///
+    /// The `TEMP` interrupt must be unmasked in the NVIC and its handler has to call
+    /// [`on_interrupt`].
+    ///
+    /// If a measurement was already started, it will be canceled.
+    #[cfg(feature = "async")]
+    pub async fn measure_async(&mut self) -> Celsius {
+        self.stop_measurement();
+        self.0.intenset.write(|w| w.datardy().set());
+        self.start_measurement();
+
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+
+            match self.read() {
+                Ok(temp) => {
+                    self.0.intenclr.write(|w| w.datardy().clear());
+                    Poll::Ready(temp)
+                }
+                Err(_) => Poll::Pending,
+            }
+        })
+        .await
+    }
+
     /// Kicks off a temperature measurement.
     ///
     /// The measurement can be retrieved by calling `read`.
@@ -40,18 +157,105 @@ impl Temp {
         }
     }
 
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &temp::TASKS_START {
+        &self.0.tasks_start
+    }
+
+    /// Returns reference to the `DATARDY` event endpoint for PPI.
+    pub fn event_data_ready(&self) -> &temp::EVENTS_DATARDY {
+        &self.0.events_datardy
+    }
+
     /// Tries to read a started measurement (non-blocking).
     ///
     /// Before calling this, `start_measurement` must be called.
     ///
     /// Returns the measured temperature in °C.
-    pub fn read(&mut self) -> nb::Result<I30F2, Void> {
+    pub fn read(&mut self) -> nb::Result<Celsius, Void> {
         if self.0.events_datardy.read().bits() == 0 {
             Err(nb::Error::WouldBlock)
         } else {
             self.0.events_datardy.reset(); // clear event
             let raw = self.0.temp.read().bits();
-            Ok(I30F2::from_bits(raw as i32))
+            Ok(Celsius::from_raw(raw as i32))
         }
     }
 }
+
+/// Periodic temperature measurements, started by an RTC through PPI.
+///
+/// The `COMPARE[0]` event of the RTC starts a measurement and, through the PPI fork, clears the
+/// RTC counter, so measurements are taken without any CPU involvement. The `DATARDY` interrupt is
+/// enabled, so the `TEMP` interrupt handler can fetch each result with `read`, e.g. to trigger an
+/// RC oscillator calibration when the temperature has changed.
+#[cfg(not(any(feature = "51", feature = "9160")))]
+pub struct PeriodicTemp<R, P> {
+    temp: Temp,
+    rtc: Rtc<R, Started>,
+    ppi: P,
+}
+
+#[cfg(not(any(feature = "51", feature = "9160")))]
+impl<R, P> PeriodicTemp<R, P>
+where
+    R: rtc::Instance,
+    P: Ppi + ConfigurablePpi,
+{
+    /// Starts measuring every `period_ticks` ticks of `rtc`.
+    pub fn new(
+        temp: Temp,
+        mut rtc: Rtc<R, Stopped>,
+        mut ppi: P,
+        period_ticks: u32,
+    ) -> Result<Self, rtc::Error> {
+        rtc.set_compare(RtcCompareReg::Compare0, period_ticks)?;
+        rtc.enable_event(RtcInterrupt::Compare0);
+        rtc.clear_counter();
+
+        ppi.set_event_endpoint(rtc.event_compare(RtcCompareReg::Compare0));
+        ppi.set_task_endpoint(temp.task_start());
+        ppi.set_fork_task_endpoint(rtc.task_clear());
+        ppi.enable();
+
+        temp.0.events_datardy.reset();
+        temp.0.intenset.write(|w| w.datardy().set());
+
+        Ok(PeriodicTemp {
+            temp,
+            rtc: rtc.enable_counter(),
+            ppi,
+        })
+    }
+
+    /// Returns the result of the last measurement, if it hasn't been read yet (non-blocking).
+    pub fn read(&mut self) -> nb::Result<Celsius, Void> {
+        self.temp.read()
+    }
+
+    /// Stops the measurements and returns the peripherals.
+    pub fn free(self) -> (Temp, Rtc<R, Stopped>, P) {
+        let PeriodicTemp {
+            mut temp,
+            rtc,
+            mut ppi,
+        } = self;
+
+        let mut rtc = rtc.disable_counter();
+        rtc.disable_event(RtcInterrupt::Compare0);
+        ppi.disable();
+        ppi.clear_fork_task_endpoint();
+        temp.0.intenclr.write(|w| w.datardy().clear());
+        temp.stop_measurement();
+
+        (temp, rtc, ppi)
+    }
+}
+
+impl TemperatureSensor for Temp {
+    type Error = Void;
+
+    fn read_temperature(&mut self) -> Result<Celsius, Void> {
+        Ok(self.measure())
+    }
+}
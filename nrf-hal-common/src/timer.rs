@@ -2,11 +2,13 @@
 //!
 //! See product specification, chapter 24.
 
-#[cfg(feature = "9160")]
-use crate::pac::{Interrupt, TIMER0_NS as TIMER0, TIMER1_NS as TIMER1, TIMER2_NS as TIMER2};
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+use crate::pac::{
+    timer0_ns as timer0, Interrupt, TIMER0_NS as TIMER0, TIMER1_NS as TIMER1, TIMER2_NS as TIMER2,
+};
 
-#[cfg(not(feature = "9160"))]
-use crate::pac::{Interrupt, TIMER0, TIMER1, TIMER2};
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
+use crate::pac::{timer0, Interrupt, TIMER0, TIMER1, TIMER2};
 
 use cast::u32;
 use embedded_hal::{
@@ -17,14 +19,50 @@ use embedded_hal::{
 use nb::{self, block};
 use void::{unreachable, Void};
 
+#[cfg(feature = "52820")]
+use crate::pac::TIMER3;
+
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
 use crate::pac::{TIMER3, TIMER4};
 
 use core::marker::PhantomData;
 
+#[cfg(feature = "async")]
+use crate::waker::{OnDrop, WakerSlot};
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WAKER: WakerSlot = WakerSlot::new();
+
+/// Wakers of each instance.
+#[cfg(feature = "async")]
+static WAKERS: [WakerSlot; 5] = [EMPTY_WAKER; 5];
+
 pub struct OneShot;
 pub struct Periodic;
 
+/// Capture/compare registers available on every TIMER instance.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TimerCcReg {
+    Cc0,
+    Cc1,
+    Cc2,
+    Cc3,
+}
+
+impl TimerCcReg {
+    fn index(self) -> usize {
+        match self {
+            TimerCcReg::Cc0 => 0,
+            TimerCcReg::Cc1 => 1,
+            TimerCcReg::Cc2 => 2,
+            TimerCcReg::Cc3 => 3,
+        }
+    }
+}
+
 /// Interface to a TIMER instance.
 ///
 /// Right now, this is a very basic interface. The timer will always be
@@ -116,6 +154,41 @@ where
         self.0.disable_interrupt();
     }
 
+    /// Returns reference to the `START` task endpoint for PPI.
+    pub fn task_start(&self) -> &timer0::TASKS_START {
+        &self.0.as_timer0().tasks_start
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &timer0::TASKS_STOP {
+        &self.0.as_timer0().tasks_stop
+    }
+
+    /// Returns reference to the `CLEAR` task endpoint for PPI.
+    pub fn task_clear(&self) -> &timer0::TASKS_CLEAR {
+        &self.0.as_timer0().tasks_clear
+    }
+
+    /// Returns reference to the `COUNT` task endpoint for PPI.
+    pub fn task_count(&self) -> &timer0::TASKS_COUNT {
+        &self.0.as_timer0().tasks_count
+    }
+
+    /// Returns reference to the `CAPTURE` task endpoint of the given register for PPI.
+    ///
+    /// CC[0] and CC[1] are used by this driver, so only CC[2] and CC[3] should be used for PPI
+    /// driven captures.
+    pub fn task_capture(&self, reg: TimerCcReg) -> &timer0::TASKS_CAPTURE {
+        &self.0.as_timer0().tasks_capture[reg.index()]
+    }
+
+    /// Returns reference to the `COMPARE` event endpoint of the given register for PPI.
+    ///
+    /// `COMPARE[0]` is generated when the period given to `start` has elapsed.
+    pub fn event_compare(&self, reg: TimerCcReg) -> &timer0::EVENTS_COMPARE {
+        &self.0.as_timer0().events_compare[reg.index()]
+    }
+
     pub fn delay(&mut self, cycles: u32) {
         self.start(cycles);
         match block!(self.wait()) {
@@ -123,6 +196,43 @@ where
             Err(x) => unreachable(x),
         }
     }
+
+    /// Waits for `cycles` timer cycles, i.e. microseconds, without blocking.
+    ///
+    /// Uses the interrupt for this timer, which has to be handled by calling [`on_interrupt`]
+    /// from the interrupt handler of the instance, and leaves it disabled. The timer is stopped
+    /// if the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    pub async fn delay_async(&mut self, cycles: u32) {
+        // A compare value of 0 is only matched after the counter wraps around.
+        if cycles == 0 {
+            return;
+        }
+
+        self.0.timer_start(cycles);
+        self.0.enable_interrupt();
+
+        let on_drop = OnDrop::new(|| {
+            let timer = unsafe { &*T::ptr() };
+            timer.intenclr.write(|w| w.compare0().clear());
+            timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+            timer.events_compare[0].reset();
+        });
+
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            if self.0.timer_running() {
+                self.0.enable_interrupt();
+                Poll::Pending
+            } else {
+                self.0.timer_reset_event();
+                Poll::Ready(())
+            }
+        })
+        .await;
+        on_drop.defuse();
+        self.0.disable_interrupt();
+    }
 }
 
 impl<T, U> timer::CountDown for Timer<T, U>
@@ -231,11 +341,71 @@ where
     }
 }
 
+/// Delays are rounded up to whole microseconds.
+#[cfg(feature = "embedded-hal-1")]
+impl<T, U> embedded_hal_1::delay::DelayNs for Timer<T, U>
+where
+    T: Instance,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        let us = ns / 1_000 + u32::from(ns % 1_000 != 0);
+        // A compare value of 0 is only matched after the counter wraps around.
+        if us != 0 {
+            self.delay(us);
+        }
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        if us != 0 {
+            self.delay(us);
+        }
+    }
+}
+
+/// Delays are rounded up to whole microseconds.
+#[cfg(feature = "async")]
+impl<T, U> embedded_hal_async::delay::DelayNs for Timer<T, U>
+where
+    T: Instance,
+{
+    async fn delay_ns(&mut self, ns: u32) {
+        self.delay_async(ns / 1_000 + u32::from(ns % 1_000 != 0))
+            .await;
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.delay_async(us).await;
+    }
+}
+
+/// Handles the timer event for `Timer::delay_async`.
+///
+/// Must be called from the interrupt handler of the instance `T`.
+#[cfg(feature = "async")]
+pub fn on_interrupt<T: Instance>() {
+    let timer = unsafe { &*T::ptr() };
+    if timer.events_compare[0].read().bits() != 0 {
+        // Leave the event set for the delay to observe, but keep it from firing again.
+        timer.intenclr.write(|w| w.compare0().clear());
+        WAKERS[T::INDEX].wake();
+    }
+}
+
 /// Implemented by all `timer0::TIMER` instances.
 pub trait Instance {
     /// This interrupt associated with this RTC instance.
     const INTERRUPT: Interrupt;
 
+    /// Number of the instance.
+    const INDEX: usize;
+
+    /// Returns the registers of the instance.
+    fn ptr() -> *const timer0::RegisterBlock;
+
+    /// Returns the registers of the instance. All instances share the layout of `TIMER0` for the
+    /// registers used by this driver.
+    fn as_timer0(&self) -> &timer0::RegisterBlock;
+
     fn timer_start<Time>(&self, cycles: Time)
     where
         Time: Into<u32>;
@@ -262,10 +432,19 @@ pub trait Instance {
 }
 
 macro_rules! impl_instance {
-    ($($name:ident,)*) => {
+    ($($name:ident => $index:expr,)*) => {
         $(
             impl Instance for $name {
                 const INTERRUPT: Interrupt = Interrupt::$name;
+                const INDEX: usize = $index;
+
+                fn ptr() -> *const timer0::RegisterBlock {
+                    $name::ptr() as *const _
+                }
+
+                fn as_timer0(&self) -> &timer0::RegisterBlock {
+                    unsafe { &*($name::ptr() as *const timer0::RegisterBlock) }
+                }
 
                 fn timer_start<Time>(&self, cycles: Time)
                 where
@@ -359,7 +538,17 @@ macro_rules! impl_instance {
     }
 }
 
-impl_instance!(TIMER0, TIMER1, TIMER2,);
+impl_instance!(
+    TIMER0 => 0,
+    TIMER1 => 1,
+    TIMER2 => 2,
+);
+
+#[cfg(feature = "52820")]
+impl_instance!(TIMER3 => 3,);
 
 #[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
-impl_instance!(TIMER3, TIMER4,);
+impl_instance!(
+    TIMER3 => 3,
+    TIMER4 => 4,
+);
@@ -2,6 +2,7 @@
 
 use core::ops::Deref;
 
+use crate::error::ErrorKind;
 use crate::{
     gpio::{Floating, Input, Pin},
     pac::{twi0, GPIO, TWI0, TWI1},
@@ -290,11 +291,20 @@ pub struct Pins {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Transmit,
     Receive,
 }
 
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Transmit | Error::Receive => ErrorKind::Other,
+        }
+    }
+}
+
 /// Implemented by all TWIM instances.
 pub trait Instance: Deref<Target = twi0::RegisterBlock> {}
 
@@ -7,21 +7,48 @@
 use core::ops::Deref;
 use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
 
-#[cfg(feature = "9160")]
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
 use crate::pac::{twim0_ns as twim0, P0_NS as P0, TWIM0_NS as TWIM0};
 
-#[cfg(not(feature = "9160"))]
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 use crate::pac::{twim0, P0, TWIM0};
 
-#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
+#[cfg(any(
+    feature = "52820",
+    feature = "52832",
+    feature = "52833",
+    feature = "52840"
+))]
 use crate::pac::TWIM1;
 
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+use crate::pac::{TWIM1_NS as TWIM1, TWIM2_NS as TWIM2, TWIM3_NS as TWIM3};
+
 use crate::{
+    error::ErrorKind,
     gpio::{Floating, Input, Pin},
     slice_in_ram, slice_in_ram_or,
     target_constants::{EASY_DMA_SIZE, FORCE_COPY_BUFFER_SIZE},
 };
 
+#[cfg(feature = "embedded-hal-1")]
+use crate::DmaSlice;
+#[cfg(feature = "embedded-hal-1")]
+use embedded_hal_1::i2c::Operation;
+
+#[cfg(feature = "async")]
+use crate::waker::{OnDrop, WakerSlot};
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WAKER: WakerSlot = WakerSlot::new();
+
+/// Wakers of each instance.
+#[cfg(feature = "async")]
+static WAKERS: [WakerSlot; 4] = [EMPTY_WAKER; 4];
+
 pub use twim0::frequency::FREQUENCY_A as Frequency;
 
 /// Interface to a TWIM instance.
@@ -33,7 +60,7 @@ pub use twim0::frequency::FREQUENCY_A as Frequency;
 /// conflicting instances are disabled before using `Twim`. Please refer to the
 /// product specification for more information (section 15.2 for nRF52832,
 /// section 6.1.2 for nRF52840).
-pub struct Twim<T>(T);
+pub struct Twim<T>(T, Pins);
 
 impl<T> Twim<T>
 where
@@ -66,13 +93,13 @@ where
         // Select pins.
         twim.psel.scl.write(|w| {
             let w = unsafe { w.pin().bits(pins.scl.pin()) };
-            #[cfg(feature = "52840")]
+            #[cfg(any(feature = "52840", feature = "5340-app", feature = "5340-net"))]
             let w = w.port().bit(pins.scl.port().bit());
             w.connect().connected()
         });
         twim.psel.sda.write(|w| {
             let w = unsafe { w.pin().bits(pins.sda.pin()) };
-            #[cfg(feature = "52840")]
+            #[cfg(any(feature = "52840", feature = "5340-app", feature = "5340-net"))]
             let w = w.port().bit(pins.sda.port().bit());
             w.connect().connected()
         });
@@ -83,7 +110,7 @@ where
         // Configure frequency.
         twim.frequency.write(|w| w.frequency().variant(frequency));
 
-        Twim(twim)
+        Twim(twim, pins)
     }
 
     /// Write to an I2C slave.
@@ -492,9 +519,73 @@ where
         Ok(())
     }
 
-    /// Return the raw interface to the underlying TWIM peripheral.
-    pub fn free(self) -> T {
-        self.0
+    /// Returns reference to the `STARTRX` task endpoint for PPI.
+    pub fn task_start_rx(&self) -> &twim0::TASKS_STARTRX {
+        &self.0.tasks_startrx
+    }
+
+    /// Returns reference to the `STARTTX` task endpoint for PPI.
+    pub fn task_start_tx(&self) -> &twim0::TASKS_STARTTX {
+        &self.0.tasks_starttx
+    }
+
+    /// Returns reference to the `STOP` task endpoint for PPI.
+    pub fn task_stop(&self) -> &twim0::TASKS_STOP {
+        &self.0.tasks_stop
+    }
+
+    /// Returns reference to the `SUSPEND` task endpoint for PPI.
+    pub fn task_suspend(&self) -> &twim0::TASKS_SUSPEND {
+        &self.0.tasks_suspend
+    }
+
+    /// Returns reference to the `RESUME` task endpoint for PPI.
+    pub fn task_resume(&self) -> &twim0::TASKS_RESUME {
+        &self.0.tasks_resume
+    }
+
+    /// Returns reference to the `STOPPED` event endpoint for PPI.
+    pub fn event_stopped(&self) -> &twim0::EVENTS_STOPPED {
+        &self.0.events_stopped
+    }
+
+    /// Returns reference to the `ERROR` event endpoint for PPI.
+    pub fn event_error(&self) -> &twim0::EVENTS_ERROR {
+        &self.0.events_error
+    }
+
+    /// Returns reference to the `SUSPENDED` event endpoint for PPI.
+    pub fn event_suspended(&self) -> &twim0::EVENTS_SUSPENDED {
+        &self.0.events_suspended
+    }
+
+    /// Returns reference to the `LASTRX` event endpoint for PPI.
+    pub fn event_last_rx(&self) -> &twim0::EVENTS_LASTRX {
+        &self.0.events_lastrx
+    }
+
+    /// Returns reference to the `LASTTX` event endpoint for PPI.
+    pub fn event_last_tx(&self) -> &twim0::EVENTS_LASTTX {
+        &self.0.events_lasttx
+    }
+
+    /// Disable the TWIM and return the raw peripheral together with the pins.
+    ///
+    /// The pins are disconnected from the peripheral and returned in the mode they were passed to
+    /// `new` in, so they can be reused by other drivers or as plain GPIOs.
+    ///
+    /// `new` switches them to open drain with pull-ups, which is undone here.
+    pub fn free(self) -> (T, Pins) {
+        self.0.enable.write(|w| w.enable().disabled());
+        self.0.psel.scl.write(|w| w.connect().disconnected());
+        self.0.psel.sda.write(|w| w.connect().disconnected());
+
+        let Pins { scl, sda } = self.1;
+        let pins = Pins {
+            scl: scl.into_floating_input(),
+            sda: sda.into_floating_input(),
+        };
+        (self.0, pins)
     }
 }
 
@@ -549,6 +640,396 @@ where
     }
 }
 
+/// One TWIM transfer of an `embedded_hal_1::i2c::I2c` transaction: the consecutive reads at the
+/// start of the remaining operations, followed by the consecutive writes after them.
+///
+/// Consecutive operations of the same kind are merged into a single buffer, as required for them
+/// to appear on the bus without a repeated start in between.
+#[cfg(feature = "embedded-hal-1")]
+struct Step {
+    rx: DmaSlice,
+    tx: DmaSlice,
+    /// Number of read operations of the step.
+    reads: usize,
+    /// Number of write operations of the step.
+    writes: usize,
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl Step {
+    /// Prepares the step at the start of `operations`. Several reads are received into
+    /// `rx_buf` and handed out by `scatter_reads`, several writes or a write from flash are copied
+    /// into `tx_buf`.
+    fn prepare(
+        operations: &mut [Operation<'_>],
+        tx_buf: &mut [u8],
+        rx_buf: &mut [u8],
+    ) -> Result<Self, Error> {
+        let reads = operations
+            .iter()
+            .take_while(|op| matches!(op, Operation::Read(_)))
+            .count();
+        let writes = operations[reads..]
+            .iter()
+            .take_while(|op| matches!(op, Operation::Write(_)))
+            .count();
+
+        let rx = match &mut operations[..reads] {
+            [] => DmaSlice::null(),
+            [Operation::Read(buffer)] if buffer.len() > EASY_DMA_SIZE => {
+                return Err(Error::RxBufferTooLong)
+            }
+            [Operation::Read(buffer)] => DmaSlice::from_slice(buffer),
+            ops => {
+                let len = ops.iter().map(op_len).sum();
+                if len > rx_buf.len() {
+                    return Err(Error::RxBufferTooLong);
+                }
+                DmaSlice::from_slice(&rx_buf[..len])
+            }
+        };
+
+        let tx = match &operations[reads..reads + writes] {
+            [] => DmaSlice::null(),
+            [Operation::Write(bytes)] if slice_in_ram(bytes) => {
+                if bytes.len() > EASY_DMA_SIZE {
+                    return Err(Error::TxBufferTooLong);
+                }
+                DmaSlice::from_slice(bytes)
+            }
+            ops => {
+                let mut len = 0;
+                for op in ops {
+                    if let Operation::Write(bytes) = op {
+                        let end = len + bytes.len();
+                        if end > tx_buf.len() {
+                            return Err(if ops.len() == 1 {
+                                Error::DMABufferNotInDataMemory
+                            } else {
+                                Error::TxBufferTooLong
+                            });
+                        }
+                        tx_buf[len..end].copy_from_slice(bytes);
+                        len = end;
+                    }
+                }
+                DmaSlice::from_slice(&tx_buf[..len])
+            }
+        };
+
+        Ok(Step {
+            rx,
+            tx,
+            reads,
+            writes,
+        })
+    }
+
+    /// Copies the bytes received into `rx_buf` to the read operations of the step, if they were
+    /// merged.
+    fn scatter_reads(&self, operations: &mut [Operation<'_>], rx_buf: &[u8]) {
+        if self.reads > 1 {
+            let mut start = 0;
+            for op in &mut operations[..self.reads] {
+                if let Operation::Read(buffer) = op {
+                    buffer.copy_from_slice(&rx_buf[start..start + buffer.len()]);
+                    start += buffer.len();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+fn op_len(op: &Operation<'_>) -> usize {
+    match op {
+        Operation::Read(buffer) => buffer.len(),
+        Operation::Write(bytes) => bytes.len(),
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> Twim<T>
+where
+    T: Instance,
+{
+    /// Starts a step of a transaction.
+    ///
+    /// A read is followed by the write through the `LASTRX_STARTTX` short. Unless `last` is set,
+    /// the step ends with the bus suspended after the write, so the next step can be set up and
+    /// started with a repeated start condition, which `resume` does.
+    fn start_step(&mut self, step: &Step, resume: bool, last: bool) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started.
+        compiler_fence(SeqCst);
+
+        self.0
+            .txd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(step.tx.ptr) });
+        self.0
+            .txd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(step.tx.len as _) });
+        self.0
+            .rxd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(step.rx.ptr) });
+        self.0
+            .rxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(step.rx.len as _) });
+
+        self.0.events_stopped.reset();
+        self.0.events_suspended.reset();
+        self.0.events_error.reset();
+
+        // Reads are only followed by a stop or by the writes of the same step.
+        self.0
+            .shorts
+            .write(|w| match (step.reads, step.writes, last) {
+                (_, 0, _) => w.lastrx_stop().enabled(),
+                (0, _, true) => w.lasttx_stop().enabled(),
+                (0, _, false) => w.lasttx_suspend().enabled(),
+                (_, _, true) => w.lastrx_starttx().enabled().lasttx_stop().enabled(),
+                (_, _, false) => w.lastrx_starttx().enabled().lasttx_suspend().enabled(),
+            });
+
+        if step.reads != 0 {
+            self.0.tasks_startrx.write(|w| unsafe { w.bits(1) });
+        } else {
+            self.0.tasks_starttx.write(|w| unsafe { w.bits(1) });
+        }
+        if resume {
+            self.0.tasks_resume.write(|w| unsafe { w.bits(1) });
+        }
+    }
+
+    /// Returns `true` once the current step has stopped or suspended the bus, and ends the
+    /// transaction on an error.
+    fn step_ended(&self) -> bool {
+        if self.0.events_error.read().bits() != 0 {
+            // A NACK or overrun: end the transaction here.
+            self.0.events_error.reset();
+            self.0.tasks_stop.write(|w| unsafe { w.bits(1) });
+        }
+        self.0.events_stopped.read().bits() != 0 || self.0.events_suspended.read().bits() != 0
+    }
+
+    /// Checks the outcome of a step that has ended, and stops the transaction if it failed.
+    fn finish_step(&mut self, step: &Step) -> Result<(), Error> {
+        let suspended = self.0.events_stopped.read().bits() == 0;
+        self.0.events_stopped.reset();
+        self.0.events_suspended.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed.
+        compiler_fence(SeqCst);
+
+        let result = if self.0.errorsrc.read().anack().is_received() {
+            Err(Error::AddressNack)
+        } else if step.reads != 0 && self.0.rxd.amount.read().bits() != step.rx.len {
+            Err(Error::Receive)
+        } else if step.writes != 0 && self.0.txd.amount.read().bits() != step.tx.len {
+            Err(Error::Transmit)
+        } else {
+            Ok(())
+        };
+        if result.is_err() && suspended {
+            self.stop_suspended();
+        }
+        result
+    }
+
+    /// Ends a transaction that is suspended after a step.
+    fn stop_suspended(&mut self) {
+        // The STOP task only takes effect once the TWIM is resumed.
+        self.0.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.0.tasks_resume.write(|w| unsafe { w.bits(1) });
+        while self.0.events_stopped.read().bits() == 0 {}
+        self.0.events_stopped.reset();
+    }
+
+    /// Sets up a transaction, returning `false` if it has no operations.
+    fn start_transaction(&mut self, address: u8, operations: &[Operation<'_>]) -> bool {
+        if operations.is_empty() {
+            return false;
+        }
+        self.0
+            .address
+            .write(|w| unsafe { w.address().bits(address) });
+        // Clear address NACK.
+        self.0.errorsrc.write(|w| w.anack().bit(true));
+        true
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::i2c::ErrorType for Twim<T> {
+    type Error = Error;
+}
+
+/// Any sequence of operations is supported. Consecutive operations of the same kind are merged
+/// through a RAM buffer of `FORCE_COPY_BUFFER_SIZE` bytes, which also takes writes from flash.
+/// Operations that are not merged can be up to `EASY_DMA_SIZE` bytes long.
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::i2c::I2c for Twim<T>
+where
+    T: Instance,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        mut operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        if !self.start_transaction(address, operations) {
+            return Ok(());
+        }
+
+        let mut tx_buf = [0; FORCE_COPY_BUFFER_SIZE];
+        let mut rx_buf = [0; FORCE_COPY_BUFFER_SIZE];
+        let mut resume = false;
+        loop {
+            let step = match Step::prepare(operations, &mut tx_buf, &mut rx_buf) {
+                Ok(step) => step,
+                Err(e) => {
+                    if resume {
+                        self.stop_suspended();
+                    }
+                    self.0.shorts.reset();
+                    return Err(e);
+                }
+            };
+            let len = step.reads + step.writes;
+            let last = len == operations.len();
+
+            self.start_step(&step, resume, last);
+            while !self.step_ended() {}
+            let result = self.finish_step(&step);
+            if result.is_err() || last {
+                self.0.shorts.reset();
+            }
+            result?;
+
+            let (done, rest) = core::mem::take(&mut operations).split_at_mut(len);
+            step.scatter_reads(done, &rx_buf);
+            if last {
+                return Ok(());
+            }
+            operations = rest;
+            resume = true;
+        }
+    }
+}
+
+/// Supports the same transactions as the blocking `embedded_hal_1::i2c::I2c` implementation.
+#[cfg(feature = "async")]
+impl<T> embedded_hal_async::i2c::I2c for Twim<T>
+where
+    T: Instance,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        mut operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        if !self.start_transaction(address, operations) {
+            return Ok(());
+        }
+
+        let mut tx_buf = [0; FORCE_COPY_BUFFER_SIZE];
+        let mut rx_buf = [0; FORCE_COPY_BUFFER_SIZE];
+        let mut resume = false;
+        loop {
+            let step = match Step::prepare(operations, &mut tx_buf, &mut rx_buf) {
+                Ok(step) => step,
+                Err(e) => {
+                    if resume {
+                        self.stop_suspended();
+                    }
+                    self.0.shorts.reset();
+                    return Err(e);
+                }
+            };
+            let len = step.reads + step.writes;
+            let last = len == operations.len();
+
+            self.start_step(&step, resume, last);
+            self.wait_step_async().await;
+            let result = self.finish_step(&step);
+            if result.is_err() || last {
+                self.0.shorts.reset();
+            }
+            result?;
+
+            let (done, rest) = core::mem::take(&mut operations).split_at_mut(len);
+            step.scatter_reads(done, &rx_buf);
+            if last {
+                return Ok(());
+            }
+            operations = rest;
+            resume = true;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Twim<T>
+where
+    T: Instance,
+{
+    /// Waits for the current step to end without blocking.
+    ///
+    /// The transaction is stopped if the returned future is dropped before the step has ended.
+    async fn wait_step_async(&mut self) {
+        let on_drop = OnDrop::new(|| {
+            let twim = unsafe { &*T::ptr() };
+            twim.intenclr
+                .write(|w| w.stopped().clear().suspended().clear().error().clear());
+            twim.tasks_stop.write(|w| unsafe { w.bits(1) });
+            twim.tasks_resume.write(|w| unsafe { w.bits(1) });
+            while twim.events_stopped.read().bits() == 0 {}
+            twim.events_stopped.reset();
+            twim.events_suspended.reset();
+            twim.events_error.reset();
+            twim.shorts.reset();
+        });
+
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            if self.step_ended() {
+                Poll::Ready(())
+            } else {
+                self.0
+                    .intenset
+                    .write(|w| w.stopped().set().suspended().set().error().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        on_drop.defuse();
+    }
+}
+
+/// Handles the TWIM events for the `embedded_hal_async::i2c::I2c` implementation.
+///
+/// Must be called from the interrupt handler of the instance `T`.
+#[cfg(feature = "async")]
+pub fn on_interrupt<T: Instance>() {
+    let twim = unsafe { &*T::ptr() };
+    if twim.events_stopped.read().bits() != 0
+        || twim.events_suspended.read().bits() != 0
+        || twim.events_error.read().bits() != 0
+    {
+        // Leave the events set for the transfer to observe, but keep them from firing again.
+        twim.intenclr
+            .write(|w| w.stopped().clear().suspended().clear().error().clear());
+        WAKERS[T::INDEX].wake();
+    }
+}
+
 /// The pins used by the TWIM peripheral.
 ///
 /// Currently, only P0 pins are supported.
@@ -561,6 +1042,7 @@ pub struct Pins {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     TxBufferTooLong,
     RxBufferTooLong,
@@ -570,10 +1052,66 @@ pub enum Error {
     AddressNack,
 }
 
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::TxBufferTooLong | Error::RxBufferTooLong => ErrorKind::BufferLength,
+            Error::DMABufferNotInDataMemory => ErrorKind::BufferNotInRam,
+            Error::Transmit | Error::Receive => ErrorKind::Dma,
+            Error::AddressNack => ErrorKind::Nack,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::i2c::Error for Error {
+    fn kind(&self) -> embedded_hal_1::i2c::ErrorKind {
+        use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        match self {
+            Error::AddressNack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            _ => embedded_hal_1::i2c::Error::kind(&crate::error::Error::kind(self)),
+        }
+    }
+}
+
 /// Implemented by all TWIM instances
-pub trait Instance: Deref<Target = twim0::RegisterBlock> {}
+pub trait Instance: Deref<Target = twim0::RegisterBlock> {
+    /// Number of the instance.
+    const INDEX: usize;
+
+    /// Returns the registers of the instance.
+    fn ptr() -> *const twim0::RegisterBlock;
+}
+
+macro_rules! impl_instance {
+    ($($name:ident => $index:expr,)*) => {
+        $(
+            impl Instance for $name {
+                const INDEX: usize = $index;
 
-impl Instance for TWIM0 {}
+                fn ptr() -> *const twim0::RegisterBlock {
+                    $name::ptr() as *const _
+                }
+            }
+        )*
+    }
+}
 
-#[cfg(any(feature = "52832", feature = "52833", feature = "52840"))]
-impl Instance for TWIM1 {}
+impl_instance!(TWIM0 => 0,);
+
+#[cfg(any(
+    feature = "52820",
+    feature = "52832",
+    feature = "52833",
+    feature = "52840",
+    feature = "9160",
+    feature = "5340-app"
+))]
+impl_instance!(TWIM1 => 1,);
+
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+impl_instance!(
+    TWIM2 => 2,
+    TWIM3 => 3,
+);
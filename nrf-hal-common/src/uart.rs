@@ -6,6 +6,7 @@ use core::ops::Deref;
 use nb::block;
 use void::Void;
 
+use crate::error::ErrorKind;
 use crate::gpio::{Floating, Input, Output, Pin, PushPull};
 use crate::pac::{uart0, UART0};
 
@@ -16,8 +17,15 @@ pub use uart0::{baudrate::BAUDRATE_A as Baudrate, config::PARITY_A as Parity};
 pub struct Uart<T>(T);
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {}
 
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match *self {}
+    }
+}
+
 impl<T> Uart<T>
 where
     T: Instance,
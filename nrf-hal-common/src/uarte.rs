@@ -8,23 +8,43 @@ use core::fmt;
 use core::ops::Deref;
 use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
 
+use embedded_dma::{ReadBuffer, WriteBuffer};
 use embedded_hal::digital::v2::OutputPin;
 
 #[cfg(any(feature = "52833", feature = "52840"))]
 use crate::pac::UARTE1;
 
-#[cfg(feature = "9160")]
-use crate::pac::{uarte0_ns as uarte0, UARTE0_NS as UARTE0, UARTE1_NS as UARTE1};
+#[cfg(any(feature = "9160", feature = "5340-app", feature = "5340-net"))]
+use crate::pac::{uarte0_ns as uarte0, UARTE0_NS as UARTE0};
 
-#[cfg(not(feature = "9160"))]
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+use crate::pac::{UARTE1_NS as UARTE1, UARTE2_NS as UARTE2, UARTE3_NS as UARTE3};
+
+#[cfg(not(any(feature = "9160", feature = "5340-app", feature = "5340-net")))]
 use crate::pac::{uarte0, UARTE0};
 
+use crate::error::ErrorKind;
 use crate::gpio::{Floating, Input, Output, Pin, PushPull};
 use crate::prelude::*;
 use crate::slice_in_ram_or;
 use crate::target_constants::EASY_DMA_SIZE;
 use crate::timer::{self, Timer};
 
+#[cfg(feature = "async")]
+use crate::waker::{OnDrop, WakerSlot};
+#[cfg(feature = "async")]
+use crate::{slice_in_ram, target_constants::FORCE_COPY_BUFFER_SIZE};
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WAKER: WakerSlot = WakerSlot::new();
+
+/// Wakers of each instance.
+#[cfg(feature = "async")]
+static WAKERS: [WakerSlot; 4] = [EMPTY_WAKER; 4];
+
 // Re-export SVD variants to allow user to directly set values.
 pub use uarte0::{baudrate::BAUDRATE_A as Baudrate, config::PARITY_A as Parity};
 
@@ -36,7 +56,7 @@ pub use uarte0::{baudrate::BAUDRATE_A as Baudrate, config::PARITY_A as Parity};
 ///   are disabled before using `Uarte`. See product specification:
 ///     - nrf52832: Section 15.2
 ///     - nrf52840: Section 6.1.2
-pub struct Uarte<T>(T);
+pub struct Uarte<T>(T, Pins);
 
 impl<T> Uarte<T>
 where
@@ -46,14 +66,24 @@ where
         // Select pins
         uarte.psel.rxd.write(|w| {
             let w = unsafe { w.pin().bits(pins.rxd.pin()) };
-            #[cfg(any(feature = "52833", feature = "52840"))]
+            #[cfg(any(
+                feature = "52833",
+                feature = "52840",
+                feature = "5340-app",
+                feature = "5340-net"
+            ))]
             let w = w.port().bit(pins.rxd.port().bit());
             w.connect().connected()
         });
         pins.txd.set_high().unwrap();
         uarte.psel.txd.write(|w| {
             let w = unsafe { w.pin().bits(pins.txd.pin()) };
-            #[cfg(any(feature = "52833", feature = "52840"))]
+            #[cfg(any(
+                feature = "52833",
+                feature = "52840",
+                feature = "5340-app",
+                feature = "5340-net"
+            ))]
             let w = w.port().bit(pins.txd.port().bit());
             w.connect().connected()
         });
@@ -62,7 +92,12 @@ where
         uarte.psel.cts.write(|w| {
             if let Some(ref pin) = pins.cts {
                 let w = unsafe { w.pin().bits(pin.pin()) };
-                #[cfg(any(feature = "52833", feature = "52840"))]
+                #[cfg(any(
+                    feature = "52833",
+                    feature = "52840",
+                    feature = "5340-app",
+                    feature = "5340-net"
+                ))]
                 let w = w.port().bit(pin.port().bit());
                 w.connect().connected()
             } else {
@@ -73,7 +108,12 @@ where
         uarte.psel.rts.write(|w| {
             if let Some(ref pin) = pins.rts {
                 let w = unsafe { w.pin().bits(pin.pin()) };
-                #[cfg(any(feature = "52833", feature = "52840"))]
+                #[cfg(any(
+                    feature = "52833",
+                    feature = "52840",
+                    feature = "5340-app",
+                    feature = "5340-net"
+                ))]
                 let w = w.port().bit(pin.port().bit());
                 w.connect().connected()
             } else {
@@ -93,7 +133,7 @@ where
         // Configure frequency.
         uarte.baudrate.write(|w| w.baudrate().variant(baudrate));
 
-        Uarte(uarte)
+        Uarte(uarte, pins)
     }
 
     /// Write via UARTE.
@@ -103,6 +143,17 @@ where
     /// The buffer must have a length of at most 255 bytes on the nRF52832
     /// and at most 65535 bytes on the nRF52840.
     pub fn write(&mut self, tx_buffer: &[u8]) -> Result<(), Error> {
+        self.start_write(tx_buffer)?;
+
+        // Wait for transmission to end.
+        while !Self::write_ended(&self.0) {}
+
+        self.finalize_write()
+    }
+
+    /// Start a UARTE write transaction by setting the control
+    /// values and triggering a write task.
+    fn start_write(&mut self, tx_buffer: &[u8]) -> Result<(), Error> {
         if tx_buffer.len() > EASY_DMA_SIZE {
             return Err(Error::TxBufferTooLong);
         }
@@ -142,23 +193,22 @@ where
             // `1` is a valid value to write to task registers.
             unsafe { w.bits(1) });
 
-        // Wait for transmission to end.
-        let mut endtx;
-        let mut txstopped;
-        loop {
-            endtx = self.0.events_endtx.read().bits() != 0;
-            txstopped = self.0.events_txstopped.read().bits() != 0;
-            if endtx || txstopped {
-                break;
-            }
-        }
+        Ok(())
+    }
 
+    /// Returns `true` once a write transaction has ended or has been stopped.
+    fn write_ended(uarte: &uarte0::RegisterBlock) -> bool {
+        uarte.events_endtx.read().bits() != 0 || uarte.events_txstopped.read().bits() != 0
+    }
+
+    /// Finalize a UARTE write transaction that has ended, and disable the transmitter.
+    fn finalize_write(&mut self) -> Result<(), Error> {
         // Conservative compiler fence to prevent optimizations that do not
         // take in to account actions by DMA. The fence has been placed here,
         // after all possible DMA actions have completed.
         compiler_fence(SeqCst);
 
-        if txstopped {
+        if self.0.events_txstopped.read().bits() != 0 {
             return Err(Error::Transmit);
         }
 
@@ -176,7 +226,8 @@ where
     /// This method fills all bytes in `rx_buffer`, and blocks
     /// until the buffer is full.
     ///
-    /// The buffer must have a length of at most 255 bytes.
+    /// The buffer must have a length of at most 255 bytes on the nRF52832
+    /// and at most 65535 bytes on the nRF52840.
     pub fn read(&mut self, rx_buffer: &mut [u8]) -> Result<(), Error> {
         self.start_read(rx_buffer)?;
 
@@ -205,7 +256,8 @@ where
     /// and in cases where a timeout does NOT occur, the timer will be left running
     /// until completion.
     ///
-    /// The buffer must have a length of at most 255 bytes.
+    /// The buffer must have a length of at most 255 bytes on the nRF52832
+    /// and at most 65535 bytes on the nRF52840.
     pub fn read_timeout<I>(
         &mut self,
         rx_buffer: &mut [u8],
@@ -235,7 +287,7 @@ where
 
         if !event_complete {
             // Cancel the reception if it did not complete until now.
-            self.cancel_read();
+            Self::cancel_read(&self.0);
         }
 
         // Cleanup, even in the error case.
@@ -254,13 +306,124 @@ where
         Ok(())
     }
 
+    /// Write via UARTE without blocking.
+    ///
+    /// Works like `write`, but waits for the `ENDTX` or `TXSTOPPED` event, which has to be handled
+    /// by calling [`on_interrupt`] from the interrupt handler of the instance. The transmission is
+    /// stopped if the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    pub async fn write_async(&mut self, tx_buffer: &[u8]) -> Result<(), Error> {
+        if tx_buffer.len() > EASY_DMA_SIZE {
+            return Err(Error::TxBufferTooLong);
+        }
+
+        // We can only DMA out of RAM.
+        slice_in_ram_or(tx_buffer, Error::BufferNotInRAM)?;
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started.
+        compiler_fence(SeqCst);
+
+        self.0.events_endtx.reset();
+        self.0.events_txstopped.reset();
+        self.0
+            .txd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(tx_buffer.as_ptr() as u32) });
+        self.0
+            .txd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(tx_buffer.len() as _) });
+        self.0.intenset.write(|w| w.endtx().set().txstopped().set());
+        self.0.tasks_starttx.write(|w| unsafe { w.bits(1) });
+
+        let on_drop = OnDrop::new(|| {
+            let uarte = unsafe { &*T::ptr() };
+            uarte
+                .intenclr
+                .write(|w| w.endtx().clear().txstopped().clear());
+            uarte.tasks_stoptx.write(|w| unsafe { w.bits(1) });
+            while uarte.events_txstopped.read().bits() == 0 {}
+        });
+
+        let txstopped = poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            let endtx = self.0.events_endtx.read().bits() != 0;
+            let txstopped = self.0.events_txstopped.read().bits() != 0;
+            if endtx || txstopped {
+                Poll::Ready(txstopped)
+            } else {
+                self.0.intenset.write(|w| w.endtx().set().txstopped().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        on_drop.defuse();
+        self.0
+            .intenclr
+            .write(|w| w.endtx().clear().txstopped().clear());
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed.
+        compiler_fence(SeqCst);
+
+        if txstopped {
+            return Err(Error::Transmit);
+        }
+
+        // Lower power consumption by disabling the transmitter once we're
+        // finished.
+        self.0.tasks_stoptx.write(|w| unsafe { w.bits(1) });
+
+        Ok(())
+    }
+
+    /// Read via UARTE without blocking.
+    ///
+    /// Works like `read`, but waits for the `ENDRX` event, which has to be handled by calling
+    /// [`on_interrupt`] from the interrupt handler of the instance. The reception is stopped if
+    /// the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    pub async fn read_async(&mut self, rx_buffer: &mut [u8]) -> Result<(), Error> {
+        self.start_read(rx_buffer)?;
+        self.0.intenset.write(|w| w.endrx().set());
+
+        let on_drop = OnDrop::new(|| {
+            let uarte = unsafe { &*T::ptr() };
+            uarte.intenclr.write(|w| w.endrx().clear());
+            Self::cancel_read(uarte);
+            uarte.events_endrx.reset();
+        });
+
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            if self.0.events_endrx.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                self.0.intenset.write(|w| w.endrx().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        on_drop.defuse();
+        self.0.intenclr.write(|w| w.endrx().clear());
+
+        self.finalize_read();
+
+        if self.0.rxd.amount.read().bits() != rx_buffer.len() as u32 {
+            return Err(Error::Receive);
+        }
+
+        Ok(())
+    }
+
     /// Start a UARTE read transaction by setting the control
     /// values and triggering a read task.
     fn start_read(&mut self, rx_buffer: &mut [u8]) -> Result<(), Error> {
-        // This is overly restrictive. See (similar SPIM issue):
-        // https://github.com/nrf-rs/nrf52/issues/17
-        if rx_buffer.len() > u8::max_value() as usize {
-            return Err(Error::TxBufferTooLong);
+        if rx_buffer.len() > EASY_DMA_SIZE {
+            return Err(Error::RxBufferTooLong);
         }
 
         // NOTE: RAM slice check is not necessary, as a mutable slice can only be
@@ -309,28 +472,124 @@ where
     }
 
     /// Stop an unfinished UART read transaction and flush FIFO to DMA buffer.
-    fn cancel_read(&mut self) {
+    fn cancel_read(uarte: &uarte0::RegisterBlock) {
         // Stop reception.
-        self.0.tasks_stoprx.write(|w| unsafe { w.bits(1) });
+        uarte.tasks_stoprx.write(|w| unsafe { w.bits(1) });
 
         // Wait for the reception to have stopped.
-        while self.0.events_rxto.read().bits() == 0 {}
+        while uarte.events_rxto.read().bits() == 0 {}
 
         // Reset the event flag.
-        self.0.events_rxto.write(|w| w);
+        uarte.events_rxto.write(|w| w);
 
         // Ask UART to flush FIFO to DMA buffer.
-        self.0.tasks_flushrx.write(|w| unsafe { w.bits(1) });
+        uarte.tasks_flushrx.write(|w| unsafe { w.bits(1) });
 
         // Wait for the flush to complete.
-        while self.0.events_endrx.read().bits() == 0 {}
+        while uarte.events_endrx.read().bits() == 0 {}
 
         // The event flag itself is later reset by `finalize_read`.
     }
 
-    /// Return the raw interface to the underlying UARTE peripheral.
-    pub fn free(self) -> T {
-        self.0
+    /// Starts transmitting `tx_buffer`, and returns a handle that owns the interface and the
+    /// buffer until the transmission is done.
+    ///
+    /// As the buffer is `'static` and the handle stops the transmission when dropped, EasyDMA
+    /// never reads a buffer that has been released, even if the transmission is abandoned. The
+    /// buffer has the same restrictions as for `write`. On error, the interface and the buffer
+    /// are returned unchanged.
+    pub fn dma_write<B>(mut self, tx_buffer: B) -> Result<TxTransfer<T, B>, (Error, Self, B)>
+    where
+        B: ReadBuffer<Word = u8>,
+    {
+        // The buffer is owned by the transfer until EasyDMA is done with it.
+        let (ptr, len) = unsafe { tx_buffer.read_buffer() };
+        match self.start_write(unsafe { core::slice::from_raw_parts(ptr, len) }) {
+            Ok(()) => Ok(TxTransfer {
+                inner: Some((self, tx_buffer)),
+            }),
+            Err(e) => Err((e, self, tx_buffer)),
+        }
+    }
+
+    /// Starts receiving into `rx_buffer`, and returns a handle that owns the interface and the
+    /// buffer until the buffer is full.
+    ///
+    /// As the buffer is `'static` and the handle stops the reception when dropped, EasyDMA never
+    /// writes to a buffer that has been released, even if the reception is abandoned. The buffer
+    /// must have a length of at most `EASY_DMA_SIZE` bytes. On error, the interface and the
+    /// buffer are returned unchanged.
+    pub fn dma_read<B>(mut self, mut rx_buffer: B) -> Result<RxTransfer<T, B>, (Error, Self, B)>
+    where
+        B: WriteBuffer<Word = u8>,
+    {
+        // The buffer is owned by the transfer until EasyDMA is done with it.
+        let (ptr, len) = unsafe { rx_buffer.write_buffer() };
+        match self.start_read(unsafe { core::slice::from_raw_parts_mut(ptr, len) }) {
+            Ok(()) => Ok(RxTransfer {
+                inner: Some((self, rx_buffer)),
+                len,
+            }),
+            Err(e) => Err((e, self, rx_buffer)),
+        }
+    }
+
+    /// Returns reference to the `STARTRX` task endpoint for PPI.
+    pub fn task_start_rx(&self) -> &uarte0::TASKS_STARTRX {
+        &self.0.tasks_startrx
+    }
+
+    /// Returns reference to the `STOPRX` task endpoint for PPI.
+    pub fn task_stop_rx(&self) -> &uarte0::TASKS_STOPRX {
+        &self.0.tasks_stoprx
+    }
+
+    /// Returns reference to the `STARTTX` task endpoint for PPI.
+    pub fn task_start_tx(&self) -> &uarte0::TASKS_STARTTX {
+        &self.0.tasks_starttx
+    }
+
+    /// Returns reference to the `STOPTX` task endpoint for PPI.
+    pub fn task_stop_tx(&self) -> &uarte0::TASKS_STOPTX {
+        &self.0.tasks_stoptx
+    }
+
+    /// Returns reference to the `ENDRX` event endpoint for PPI.
+    pub fn event_end_rx(&self) -> &uarte0::EVENTS_ENDRX {
+        &self.0.events_endrx
+    }
+
+    /// Returns reference to the `ENDTX` event endpoint for PPI.
+    pub fn event_end_tx(&self) -> &uarte0::EVENTS_ENDTX {
+        &self.0.events_endtx
+    }
+
+    /// Returns reference to the `RXDRDY` event endpoint for PPI.
+    pub fn event_rx_ready(&self) -> &uarte0::EVENTS_RXDRDY {
+        &self.0.events_rxdrdy
+    }
+
+    /// Returns reference to the `ERROR` event endpoint for PPI.
+    pub fn event_error(&self) -> &uarte0::EVENTS_ERROR {
+        &self.0.events_error
+    }
+
+    /// Returns reference to the `RXTO` event endpoint for PPI.
+    pub fn event_rx_timeout(&self) -> &uarte0::EVENTS_RXTO {
+        &self.0.events_rxto
+    }
+
+    /// Disable the UARTE and return the raw peripheral together with the pins.
+    ///
+    /// The pins are disconnected from the peripheral and returned in the mode they were passed to
+    /// `new` in, so they can be reused by other drivers or as plain GPIOs.
+    pub fn free(self) -> (T, Pins) {
+        self.0.enable.write(|w| w.enable().disabled());
+        self.0.psel.rxd.write(|w| w.connect().disconnected());
+        self.0.psel.txd.write(|w| w.connect().disconnected());
+        self.0.psel.cts.write(|w| w.connect().disconnected());
+        self.0.psel.rts.write(|w| w.connect().disconnected());
+        (self.0, self.1)
     }
 }
 
@@ -351,6 +610,236 @@ where
     }
 }
 
+#[cfg(feature = "async")]
+impl<T> embedded_io_async::ErrorType for Uarte<T> {
+    type Error = Error;
+}
+
+#[cfg(feature = "async")]
+impl<T> embedded_io_async::Read for Uarte<T>
+where
+    T: Instance,
+{
+    /// Receives a single byte, as the UARTE can't tell how many more bytes are coming. Use
+    /// `read_exact` to receive a known amount of bytes more efficiently.
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.read_async(&mut buf[..1]).await?;
+        Ok(1)
+    }
+
+    async fn read_exact(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(), embedded_io_async::ReadExactError<Error>> {
+        for chunk in buf.chunks_mut(EASY_DMA_SIZE) {
+            self.read_async(chunk).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> embedded_io_async::Write for Uarte<T>
+where
+    T: Instance,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if slice_in_ram(buf) {
+            let len = buf.len().min(EASY_DMA_SIZE);
+            self.write_async(&buf[..len]).await?;
+            Ok(len)
+        } else {
+            // Copy the data into an on-stack buffer, as EasyDMA can't read from flash.
+            let len = buf.len().min(FORCE_COPY_BUFFER_SIZE);
+            let mut copy = [0; FORCE_COPY_BUFFER_SIZE];
+            copy[..len].copy_from_slice(&buf[..len]);
+            self.write_async(&copy[..len]).await?;
+            Ok(len)
+        }
+    }
+}
+
+/// Handles the UARTE events for the async methods and the `wait_async` methods of the transfers.
+///
+/// Must be called from the interrupt handler of the instance `T`.
+#[cfg(feature = "async")]
+pub fn on_interrupt<T: Instance>() {
+    let uarte = unsafe { &*T::ptr() };
+    let inten = uarte.inten.read();
+    let tx = inten.endtx().is_enabled()
+        && (uarte.events_endtx.read().bits() != 0 || uarte.events_txstopped.read().bits() != 0);
+    let rx = inten.endrx().is_enabled() && uarte.events_endrx.read().bits() != 0;
+    if tx || rx {
+        // Leave the events set for the transfers to observe, but keep them from firing again.
+        if tx {
+            uarte
+                .intenclr
+                .write(|w| w.endtx().clear().txstopped().clear());
+        }
+        if rx {
+            uarte.intenclr.write(|w| w.endrx().clear());
+        }
+        WAKERS[T::INDEX].wake();
+    }
+}
+
+/// A transmission started with [`Uarte::dma_write`].
+///
+/// Dropping the transfer before it is done stops it, and drops the interface and the buffer.
+pub struct TxTransfer<T, B>
+where
+    T: Instance,
+{
+    inner: Option<(Uarte<T>, B)>,
+}
+
+impl<T, B> TxTransfer<T, B>
+where
+    T: Instance,
+{
+    /// Returns `true` once the buffer has been transmitted.
+    pub fn is_done(&self) -> bool {
+        let (uarte, _) = self.inner.as_ref().unwrap();
+        Uarte::<T>::write_ended(&uarte.0)
+    }
+
+    /// Blocks until the buffer has been transmitted, and returns the interface and the buffer.
+    pub fn wait(mut self) -> Result<(Uarte<T>, B), (Error, Uarte<T>, B)> {
+        let (mut uarte, tx_buffer) = self.inner.take().unwrap();
+        while !Uarte::<T>::write_ended(&uarte.0) {}
+        match uarte.finalize_write() {
+            Ok(()) => Ok((uarte, tx_buffer)),
+            Err(e) => Err((e, uarte, tx_buffer)),
+        }
+    }
+
+    /// Waits until the buffer has been transmitted without blocking, and returns the interface
+    /// and the buffer.
+    ///
+    /// [`on_interrupt`] has to be called from the interrupt handler of the instance. The
+    /// transmission is stopped if the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(mut self) -> Result<(Uarte<T>, B), (Error, Uarte<T>, B)> {
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            let (uarte, _) = self.inner.as_ref().unwrap();
+            if Uarte::<T>::write_ended(&uarte.0) {
+                Poll::Ready(())
+            } else {
+                uarte
+                    .0
+                    .intenset
+                    .write(|w| w.endtx().set().txstopped().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        let (uarte, _) = self.inner.as_ref().unwrap();
+        uarte
+            .0
+            .intenclr
+            .write(|w| w.endtx().clear().txstopped().clear());
+        self.wait()
+    }
+}
+
+impl<T, B> Drop for TxTransfer<T, B>
+where
+    T: Instance,
+{
+    fn drop(&mut self) {
+        if let Some((uarte, _)) = &self.inner {
+            uarte
+                .0
+                .intenclr
+                .write(|w| w.endtx().clear().txstopped().clear());
+            if !Uarte::<T>::write_ended(&uarte.0) {
+                // Stop EasyDMA before the buffer is released.
+                uarte.0.tasks_stoptx.write(|w| unsafe { w.bits(1) });
+                while uarte.0.events_txstopped.read().bits() == 0 {}
+            }
+            compiler_fence(SeqCst);
+        }
+    }
+}
+
+/// A reception started with [`Uarte::dma_read`].
+///
+/// Dropping the transfer before it is done stops it, and drops the interface and the buffer.
+pub struct RxTransfer<T, B>
+where
+    T: Instance,
+{
+    inner: Option<(Uarte<T>, B)>,
+    len: usize,
+}
+
+impl<T, B> RxTransfer<T, B>
+where
+    T: Instance,
+{
+    /// Returns `true` once the buffer is full.
+    pub fn is_done(&self) -> bool {
+        let (uarte, _) = self.inner.as_ref().unwrap();
+        uarte.0.events_endrx.read().bits() != 0
+    }
+
+    /// Blocks until the buffer is full, and returns the interface and the buffer.
+    pub fn wait(mut self) -> Result<(Uarte<T>, B), (Error, Uarte<T>, B)> {
+        let (mut uarte, rx_buffer) = self.inner.take().unwrap();
+        while uarte.0.events_endrx.read().bits() == 0 {}
+        uarte.finalize_read();
+        if uarte.0.rxd.amount.read().bits() != self.len as u32 {
+            return Err((Error::Receive, uarte, rx_buffer));
+        }
+        Ok((uarte, rx_buffer))
+    }
+
+    /// Waits until the buffer is full without blocking, and returns the interface and the buffer.
+    ///
+    /// [`on_interrupt`] has to be called from the interrupt handler of the instance. The reception
+    /// is stopped if the returned future is dropped before it completes.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(mut self) -> Result<(Uarte<T>, B), (Error, Uarte<T>, B)> {
+        poll_fn(|cx| {
+            WAKERS[T::INDEX].register(cx.waker());
+            let (uarte, _) = self.inner.as_ref().unwrap();
+            if uarte.0.events_endrx.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                uarte.0.intenset.write(|w| w.endrx().set());
+                Poll::Pending
+            }
+        })
+        .await;
+        let (uarte, _) = self.inner.as_ref().unwrap();
+        uarte.0.intenclr.write(|w| w.endrx().clear());
+        self.wait()
+    }
+}
+
+impl<T, B> Drop for RxTransfer<T, B>
+where
+    T: Instance,
+{
+    fn drop(&mut self) {
+        if let Some((uarte, _)) = &mut self.inner {
+            uarte.0.intenclr.write(|w| w.endrx().clear());
+            if uarte.0.events_endrx.read().bits() == 0 {
+                // Stop EasyDMA before the buffer is released.
+                Uarte::<T>::cancel_read(&uarte.0);
+            }
+            uarte.finalize_read();
+        }
+    }
+}
+
 pub struct Pins {
     pub rxd: Pin<Input<Floating>>,
     pub txd: Pin<Output<PushPull>>,
@@ -359,6 +848,7 @@ pub struct Pins {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     TxBufferTooLong,
     RxBufferTooLong,
@@ -368,9 +858,59 @@ pub enum Error {
     BufferNotInRAM,
 }
 
-pub trait Instance: Deref<Target = uarte0::RegisterBlock> {}
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::TxBufferTooLong | Error::RxBufferTooLong => ErrorKind::BufferLength,
+            Error::Transmit | Error::Receive => ErrorKind::Dma,
+            Error::Timeout(_) => ErrorKind::Timeout,
+            Error::BufferNotInRAM => ErrorKind::BufferNotInRam,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_io_async::Error for Error {
+    fn kind(&self) -> embedded_io_async::ErrorKind {
+        embedded_io_async::Error::kind(&crate::error::Error::kind(self))
+    }
+}
+
+/// Implemented by all UARTE instances.
+pub trait Instance: Deref<Target = uarte0::RegisterBlock> {
+    /// Number of the instance.
+    const INDEX: usize;
 
-impl Instance for UARTE0 {}
+    /// Returns the registers of the instance.
+    fn ptr() -> *const uarte0::RegisterBlock;
+}
+
+macro_rules! impl_instance {
+    ($($name:ident => $index:expr,)*) => {
+        $(
+            impl Instance for $name {
+                const INDEX: usize = $index;
+
+                fn ptr() -> *const uarte0::RegisterBlock {
+                    $name::ptr() as *const _
+                }
+            }
+        )*
+    }
+}
 
-#[cfg(any(feature = "52833", feature = "52840", feature = "9160"))]
-impl Instance for UARTE1 {}
+impl_instance!(UARTE0 => 0,);
+
+#[cfg(any(
+    feature = "52833",
+    feature = "52840",
+    feature = "9160",
+    feature = "5340-app"
+))]
+impl_instance!(UARTE1 => 1,);
+
+#[cfg(any(feature = "9160", feature = "5340-app"))]
+impl_instance!(
+    UARTE2 => 2,
+    UARTE3 => 3,
+);
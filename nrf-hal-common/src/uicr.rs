@@ -5,8 +5,150 @@
 //! - nrf52810: Section 4.5
 //! - nrf52832: Section 14
 //! - nrf52840: Section 4.5
+//!
+//! The customer registers hold per-device data like serial numbers, keys or calibration values,
+//! programmed during production. [`Field`] gives them a typed home, and a [`Layout`] header in
+//! `CUSTOMER[0]` tells the firmware which arrangement of fields a device was provisioned with:
+//!
+//! ```ignore
+//! const LAYOUT: Layout = Layout::new(0x4e52, 1);
+//! const SERIAL: Field<u64> = Field::new(1);
+//! const KEY: Field<[u8; 16]> = Field::new(3);
+//!
+//! if LAYOUT.version(&uicr) != Some(1) {
+//!     SERIAL.write(&mut uicr, &mut nvmc, &serial)?;
+//!     KEY.write(&mut uicr, &mut nvmc, &key)?;
+//!     LAYOUT.write(&mut uicr, &mut nvmc)?;
+//! }
+//! let serial = SERIAL.read(&uicr);
+//! ```
+use core::{marker::PhantomData, ptr};
+
+use crate::error::ErrorKind;
 use crate::pac::{NVMC, UICR};
 
+/// A UICR register that can be programmed with `Uicr::write`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Register {
+    /// Customer register `n`, 0 to 31.
+    Customer(usize),
+    /// Reset pin selection `n`, 0 or 1. Both registers must hold the same pin.
+    #[cfg(not(feature = "51"))]
+    PselReset(usize),
+    /// Access port protection.
+    #[cfg(not(feature = "51"))]
+    Approtect,
+    /// Use of the NFC antenna pins as GPIOs.
+    #[cfg(not(any(
+        feature = "51",
+        feature = "52805",
+        feature = "52810",
+        feature = "52820"
+    )))]
+    NfcPins,
+    /// GPIO output voltage in high voltage mode.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    Regout0,
+}
+
+impl Register {
+    /// Returns the offset of the register from the start of the UICR.
+    fn offset(self) -> usize {
+        match self {
+            Register::Customer(n) => {
+                assert!(n < 32);
+                0x080 + 4 * n
+            }
+            #[cfg(not(feature = "51"))]
+            Register::PselReset(n) => {
+                assert!(n < 2);
+                0x200 + 4 * n
+            }
+            #[cfg(not(feature = "51"))]
+            Register::Approtect => 0x208,
+            #[cfg(not(any(
+                feature = "51",
+                feature = "52805",
+                feature = "52810",
+                feature = "52820"
+            )))]
+            Register::NfcPins => 0x20c,
+            #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+            Register::Regout0 => 0x304,
+        }
+    }
+}
+
+/// Outcome of a successful `Uicr::write`.
+#[must_use]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Written {
+    /// The register already held the value, nothing was written.
+    Unchanged,
+    /// The value was written. It only takes effect after the next reset.
+    ResetRequired,
+}
+
+/// UICR programming error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The value sets bits that are cleared in the register, which requires erasing the UICR.
+    EraseRequired,
+}
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::EraseRequired => ErrorKind::Other,
+        }
+    }
+}
+
+/// `NFCPINS` bit selecting the NFC antenna, cleared to use the pins as GPIOs.
+#[cfg(not(any(
+    feature = "51",
+    feature = "52805",
+    feature = "52810",
+    feature = "52820"
+)))]
+const NFCPINS_PROTECT: u32 = 1;
+
+/// `VOUT` field of `REGOUT0`.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+const REGOUT0_VOUT_MASK: u32 = 0b111;
+
+/// GPIO output voltage in high voltage mode, generated by REG0 from VDDH.
+#[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Vout {
+    /// 1.8 V, the default of an erased UICR.
+    V1_8 = 0,
+    V2_1 = 1,
+    V2_4 = 2,
+    V2_7 = 3,
+    V3_0 = 4,
+    V3_3 = 5,
+}
+
+/// Size of the UICR in words, covering all of its registers.
+#[cfg(feature = "51")]
+const UICR_WORDS: usize = 0x100 / 4;
+#[cfg(not(feature = "51"))]
+const UICR_WORDS: usize = 0x310 / 4;
+
+/// Returns the address of the UICR word at `offset`.
+fn word_addr(offset: usize) -> *mut u32 {
+    (UICR::ptr() as usize + offset) as *mut u32
+}
+
+fn wait_ready(nvmc: &NVMC) {
+    while nvmc.ready.read().ready().bit_is_clear() {}
+}
+
 /// Interface to a UICR instance.
 ///
 /// This is a very basic interface that comes with the following limitations:
@@ -38,6 +180,152 @@ impl Uicr {
         nvmc.config.reset()
     }
 
+    /// Reads a UICR register.
+    pub fn read(&self, register: Register) -> u32 {
+        unsafe { ptr::read_volatile(word_addr(register.offset())) }
+    }
+
+    /// Programs a UICR register.
+    ///
+    /// Flash bits can only be cleared by writing, so writing a value that sets a cleared bit
+    /// fails with `EraseRequired`. UICR values are only applied at reset, which is signalled by
+    /// `Written::ResetRequired`.
+    pub fn write(
+        &mut self,
+        nvmc: &mut NVMC,
+        register: Register,
+        value: u32,
+    ) -> Result<Written, Error> {
+        let current = self.read(register);
+        if current == value {
+            return Ok(Written::Unchanged);
+        }
+        if current & value != value {
+            return Err(Error::EraseRequired);
+        }
+        assert!(!nvmc.config.read().wen().is_een()); // write + erase is forbidden!
+
+        wait_ready(nvmc);
+        nvmc.config.write(|w| w.wen().wen());
+        unsafe { ptr::write_volatile(word_addr(register.offset()), value) };
+        wait_ready(nvmc);
+        nvmc.config.reset();
+        Ok(Written::ResetRequired)
+    }
+
+    /// Erases the UICR, then programs `register` with `value`.
+    ///
+    /// All other registers are read before the erase and programmed back afterwards. This
+    /// includes `NRFFW`, holding the bootloader address and the MBR parameter page, and `NRFHW`,
+    /// so a bootloader keeps working.
+    pub fn erase_and_write(&mut self, nvmc: &mut NVMC, register: Register, value: u32) -> Written {
+        if self.read(register) == value {
+            return Written::Unchanged;
+        }
+
+        let mut saved = [0xffff_ffff; UICR_WORDS];
+        for (n, word) in saved.iter_mut().enumerate() {
+            *word = unsafe { ptr::read_volatile(word_addr(4 * n)) };
+        }
+        saved[register.offset() / 4] = value;
+
+        wait_ready(nvmc);
+        self.erase(nvmc);
+        wait_ready(nvmc);
+
+        nvmc.config.write(|w| w.wen().wen());
+        for (n, word) in saved.iter().enumerate() {
+            // Erased words need no programming.
+            if *word != 0xffff_ffff {
+                unsafe { ptr::write_volatile(word_addr(4 * n), *word) };
+                wait_ready(nvmc);
+            }
+        }
+        nvmc.config.reset();
+        Written::ResetRequired
+    }
+
+    /// Returns `true` if P0.09 and P0.10 are used as GPIOs rather than as the NFC antenna.
+    #[cfg(not(any(
+        feature = "51",
+        feature = "52805",
+        feature = "52810",
+        feature = "52820"
+    )))]
+    pub fn nfc_pins_are_gpio(&self) -> bool {
+        self.read(Register::NfcPins) & NFCPINS_PROTECT == 0
+    }
+
+    /// Selects the use of P0.09 and P0.10 as GPIOs or as the NFC antenna, the default of an
+    /// erased UICR.
+    ///
+    /// Switching back to the NFC antenna fails with `EraseRequired`, see `erase_and_write`.
+    #[cfg(not(any(
+        feature = "51",
+        feature = "52805",
+        feature = "52810",
+        feature = "52820"
+    )))]
+    pub fn set_nfc_pins_as_gpio(&mut self, nvmc: &mut NVMC, gpio: bool) -> Result<Written, Error> {
+        let current = self.read(Register::NfcPins);
+        let value = if gpio {
+            current & !NFCPINS_PROTECT
+        } else {
+            current | NFCPINS_PROTECT
+        };
+        self.write(nvmc, Register::NfcPins, value)
+    }
+
+    /// Returns the GPIO output voltage used in high voltage mode, as programmed in `REGOUT0`.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn regout0(&self) -> Vout {
+        match self.read(Register::Regout0) & REGOUT0_VOUT_MASK {
+            1 => Vout::V2_1,
+            2 => Vout::V2_4,
+            3 => Vout::V2_7,
+            4 => Vout::V3_0,
+            5 => Vout::V3_3,
+            _ => Vout::V1_8,
+        }
+    }
+
+    /// Programs the GPIO output voltage used in high voltage mode into `REGOUT0`.
+    ///
+    /// Boards supplied through VDDH, e.g. from USB, start with 1.8 V GPIOs until this is done.
+    /// Only some changes are possible without erasing the UICR, the others fail with
+    /// `EraseRequired`, see `ensure_regout0`. The new voltage is used after the next reset.
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn set_regout0(&mut self, nvmc: &mut NVMC, vout: Vout) -> Result<Written, Error> {
+        if self.regout0() == vout {
+            return Ok(Written::Unchanged);
+        }
+        let value = (self.read(Register::Regout0) & !REGOUT0_VOUT_MASK) | vout as u32;
+        self.write(nvmc, Register::Regout0, value)
+    }
+
+    /// Makes sure `REGOUT0` selects `vout`, erasing the UICR if needed while keeping all other
+    /// registers, see `erase_and_write`.
+    ///
+    /// Call this early at startup. On `Written::ResetRequired`, reset the chip, e.g. with
+    /// `SCB::sys_reset`, for the voltage to take effect:
+    ///
+    /// ```ignore
+    /// if uicr.ensure_regout0(&mut nvmc, Vout::V3_3) == Written::ResetRequired {
+    ///     cortex_m::peripheral::SCB::sys_reset();
+    /// }
+    /// ```
+    #[cfg(any(feature = "52820", feature = "52833", feature = "52840"))]
+    pub fn ensure_regout0(&mut self, nvmc: &mut NVMC, vout: Vout) -> Written {
+        match self.set_regout0(nvmc, vout) {
+            Ok(written) => written,
+            Err(Error::EraseRequired) => {
+                // The other bits of the register are reserved, and erased like the rest.
+                let value = !REGOUT0_VOUT_MASK | vout as u32;
+                self.erase_and_write(nvmc, Register::Regout0, value)
+            }
+        }
+    }
+
     /// Store a slice of `&[u32]` values to the customer registers with given offset.
     ///
     /// - offset + slice length must be less than 32
@@ -82,3 +370,195 @@ impl Uicr {
         values
     }
 }
+
+/// Number of customer registers.
+pub const CUSTOMER_WORDS: usize = 32;
+
+/// A value stored in consecutive customer registers.
+///
+/// Unused bits are stored as 1s, the erased state, so they can still be programmed later.
+pub trait CustomerValue: Sized {
+    /// Number of registers the value takes.
+    const WORDS: usize;
+
+    /// Converts the value to the contents of its registers. `words` has `WORDS` elements.
+    fn to_words(&self, words: &mut [u32]);
+
+    /// Converts the contents of the registers back to a value. `words` has `WORDS` elements.
+    fn from_words(words: &[u32]) -> Self;
+}
+
+impl CustomerValue for u32 {
+    const WORDS: usize = 1;
+
+    fn to_words(&self, words: &mut [u32]) {
+        words[0] = *self;
+    }
+
+    fn from_words(words: &[u32]) -> Self {
+        words[0]
+    }
+}
+
+impl CustomerValue for i32 {
+    const WORDS: usize = 1;
+
+    fn to_words(&self, words: &mut [u32]) {
+        words[0] = *self as u32;
+    }
+
+    fn from_words(words: &[u32]) -> Self {
+        words[0] as i32
+    }
+}
+
+impl CustomerValue for u64 {
+    const WORDS: usize = 2;
+
+    fn to_words(&self, words: &mut [u32]) {
+        words[0] = *self as u32;
+        words[1] = (*self >> 32) as u32;
+    }
+
+    fn from_words(words: &[u32]) -> Self {
+        u64::from(words[0]) | u64::from(words[1]) << 32
+    }
+}
+
+/// Bytes are stored little endian, four per register.
+impl<const N: usize> CustomerValue for [u8; N] {
+    const WORDS: usize = (N + 3) / 4;
+
+    fn to_words(&self, words: &mut [u32]) {
+        for (word, chunk) in words.iter_mut().zip(self.chunks(4)) {
+            let mut bytes = [0xff; 4];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            *word = u32::from_le_bytes(bytes);
+        }
+    }
+
+    fn from_words(words: &[u32]) -> Self {
+        let mut value = [0; N];
+        for (chunk, word) in value.chunks_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes()[..chunk.len()]);
+        }
+        value
+    }
+}
+
+impl Uicr {
+    /// Reads a value from the customer registers, starting at `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value does not fit in the customer registers from `offset` on.
+    pub fn read_customer<T: CustomerValue>(&self, offset: usize) -> T {
+        assert!(offset + T::WORDS <= CUSTOMER_WORDS);
+        let mut words = [0; CUSTOMER_WORDS];
+        let words = &mut words[..T::WORDS];
+        for (n, word) in words.iter_mut().enumerate() {
+            *word = self.read(Register::Customer(offset + n));
+        }
+        T::from_words(words)
+    }
+
+    /// Programs a value into the customer registers, starting at `offset`.
+    ///
+    /// Nothing is written if any of the registers would need to be erased first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value does not fit in the customer registers from `offset` on.
+    pub fn write_customer<T: CustomerValue>(
+        &mut self,
+        nvmc: &mut NVMC,
+        offset: usize,
+        value: &T,
+    ) -> Result<Written, Error> {
+        assert!(offset + T::WORDS <= CUSTOMER_WORDS);
+        let mut words = [0; CUSTOMER_WORDS];
+        let words = &mut words[..T::WORDS];
+        value.to_words(words);
+
+        let registers = (offset..).map(Register::Customer);
+        if registers
+            .clone()
+            .zip(words.iter())
+            .any(|(register, word)| self.read(register) & word != *word)
+        {
+            return Err(Error::EraseRequired);
+        }
+        let mut written = Written::Unchanged;
+        for (register, word) in registers.zip(words.iter()) {
+            if self.write(nvmc, register, *word)? == Written::ResetRequired {
+                written = Written::ResetRequired;
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// A typed value at a fixed offset in the customer registers.
+pub struct Field<T> {
+    offset: usize,
+    _value: PhantomData<T>,
+}
+
+impl<T: CustomerValue> Field<T> {
+    /// Places the field at customer register `offset`. With a [`Layout`], `CUSTOMER[0]` is taken
+    /// by its header.
+    pub const fn new(offset: usize) -> Self {
+        Field {
+            offset,
+            _value: PhantomData,
+        }
+    }
+
+    /// Reads the field.
+    pub fn read(&self, uicr: &Uicr) -> T {
+        uicr.read_customer(self.offset)
+    }
+
+    /// Programs the field, see `Uicr::write_customer`.
+    pub fn write(&self, uicr: &mut Uicr, nvmc: &mut NVMC, value: &T) -> Result<Written, Error> {
+        uicr.write_customer(nvmc, self.offset, value)
+    }
+}
+
+/// A versioned arrangement of fields in the customer registers, identified by a header in
+/// `CUSTOMER[0]`.
+///
+/// The header holds the `magic` number of the application in its upper and the `version` of the
+/// layout in its lower 16 bits. Programming it last marks the device as provisioned.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Layout {
+    magic: u16,
+    version: u16,
+}
+
+impl Layout {
+    pub const fn new(magic: u16, version: u16) -> Self {
+        Layout { magic, version }
+    }
+
+    /// Returns the version of the layout the customer registers were programmed with, or `None`
+    /// if they hold no header with this magic number, e.g. after an erase.
+    pub fn version(&self, uicr: &Uicr) -> Option<u16> {
+        let header = uicr.read(Register::Customer(0));
+        if (header >> 16) as u16 == self.magic {
+            Some(header as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Programs the header of this layout.
+    ///
+    /// Moving to a different version usually requires erasing the UICR, as the header bits can
+    /// only be cleared.
+    pub fn write(&self, uicr: &mut Uicr, nvmc: &mut NVMC) -> Result<Written, Error> {
+        let header = u32::from(self.magic) << 16 | u32::from(self.version);
+        uicr.write(nvmc, Register::Customer(0), header)
+    }
+}
@@ -0,0 +1,174 @@
+//! USB DFU runtime class.
+//!
+//! [`DfuRuntime`] adds the DFU runtime interface to a USB device, so a host tool like
+//! `dfu-util` can ask the application to restart into its bootloader for an update:
+//!
+//! ```ignore
+//! let mut dfu = DfuRuntime::new(&usb_bus, 500);
+//! let mut device = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd)).build();
+//! loop {
+//!     device.poll(&mut [&mut dfu]);
+//!     if dfu.is_detach_requested() {
+//!         dfu.detach(&mut power);
+//!     }
+//! }
+//! ```
+//!
+//! The handoff uses the convention of the Nordic nRF5 SDK bootloader, see
+//! [`Power::request_dfu`].
+
+use cortex_m::peripheral::SCB;
+use usb_device::{
+    bus::{InterfaceNumber, UsbBus, UsbBusAllocator},
+    class::{ControlIn, ControlOut, UsbClass},
+    control::{Recipient, RequestType},
+    descriptor::DescriptorWriter,
+    Result,
+};
+
+use crate::power::Power;
+
+/// Interface class, subclass and protocol of the DFU runtime interface.
+const CLASS_APPLICATION_SPECIFIC: u8 = 0xfe;
+const SUBCLASS_DFU: u8 = 0x01;
+const PROTOCOL_RUNTIME: u8 = 0x01;
+
+/// DFU functional descriptor type.
+const DESCRIPTOR_DFU_FUNCTIONAL: u8 = 0x21;
+
+/// `bmAttributes` of the functional descriptor: the device detaches by itself, so the host
+/// doesn't need to reset the bus.
+const ATTR_WILL_DETACH: u8 = 0x08;
+
+/// Version of the DFU specification, 1.1.
+const DFU_VERSION: u16 = 0x0110;
+
+/// Maximum transfer size reported to the host. Transfers are handled by the bootloader.
+const TRANSFER_SIZE: u16 = 64;
+
+/// DFU class requests.
+const REQUEST_DETACH: u8 = 0;
+const REQUEST_GETSTATUS: u8 = 3;
+const REQUEST_GETSTATE: u8 = 5;
+
+/// DFU states of the runtime interface.
+const STATE_APP_IDLE: u8 = 0;
+const STATE_APP_DETACH: u8 = 1;
+
+/// The DFU runtime interface of a USB device.
+pub struct DfuRuntime {
+    interface: InterfaceNumber,
+    detach_timeout_ms: u16,
+    detach_requested: bool,
+}
+
+impl DfuRuntime {
+    /// Allocates the interface. `detach_timeout_ms` is the time the host waits for the device to
+    /// reappear as the bootloader.
+    pub fn new<B: UsbBus>(alloc: &UsbBusAllocator<B>, detach_timeout_ms: u16) -> Self {
+        DfuRuntime {
+            interface: alloc.interface(),
+            detach_timeout_ms,
+            detach_requested: false,
+        }
+    }
+
+    /// Returns `true` if the host has sent a `DFU_DETACH` request.
+    ///
+    /// The application should then call `detach`, after polling the device once more so the
+    /// request is acknowledged.
+    pub fn is_detach_requested(&self) -> bool {
+        self.detach_requested
+    }
+
+    /// Requests the bootloader to enter DFU mode and resets the chip into it.
+    ///
+    /// The reset also disconnects the device from the bus.
+    pub fn detach(&mut self, power: &mut Power) -> ! {
+        power.request_dfu();
+        SCB::sys_reset()
+    }
+
+    fn state(&self) -> u8 {
+        if self.detach_requested {
+            STATE_APP_DETACH
+        } else {
+            STATE_APP_IDLE
+        }
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for DfuRuntime {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
+        writer.interface(
+            self.interface,
+            CLASS_APPLICATION_SPECIFIC,
+            SUBCLASS_DFU,
+            PROTOCOL_RUNTIME,
+        )?;
+
+        let timeout = self.detach_timeout_ms.to_le_bytes();
+        let transfer_size = TRANSFER_SIZE.to_le_bytes();
+        let version = DFU_VERSION.to_le_bytes();
+        writer.write(
+            DESCRIPTOR_DFU_FUNCTIONAL,
+            &[
+                ATTR_WILL_DETACH,
+                timeout[0],
+                timeout[1],
+                transfer_size[0],
+                transfer_size[1],
+                version[0],
+                version[1],
+            ],
+        )
+    }
+
+    fn reset(&mut self) {
+        self.detach_requested = false;
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        let req = *xfer.request();
+        if req.request_type != RequestType::Class
+            || req.recipient != Recipient::Interface
+            || req.index != u8::from(self.interface) as u16
+        {
+            return;
+        }
+
+        match req.request {
+            REQUEST_GETSTATUS => {
+                // No error, no poll timeout, the current state and no status string.
+                let status = [0, 0, 0, 0, self.state(), 0];
+                xfer.accept_with(&status).ok();
+            }
+            REQUEST_GETSTATE => {
+                xfer.accept_with(&[self.state()]).ok();
+            }
+            _ => {
+                xfer.reject().ok();
+            }
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let req = *xfer.request();
+        if req.request_type != RequestType::Class
+            || req.recipient != Recipient::Interface
+            || req.index != u8::from(self.interface) as u16
+        {
+            return;
+        }
+
+        match req.request {
+            REQUEST_DETACH => {
+                self.detach_requested = true;
+                xfer.accept().ok();
+            }
+            _ => {
+                xfer.reject().ok();
+            }
+        }
+    }
+}
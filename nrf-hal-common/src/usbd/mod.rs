@@ -0,0 +1,1050 @@
+//! HAL interface to the USBD peripheral.
+//!
+//! [`Usbd`] implements the `UsbBus` trait of the [`usb-device`] crate, so the USB classes of its
+//! ecosystem can be used on top of it:
+//!
+//! ```ignore
+//! let clocks = Clocks::new(p.CLOCK).enable_ext_hfosc();
+//! let usb_bus = UsbBusAllocator::new(Usbd::new(p.USBD, &clocks));
+//! let mut serial = SerialPort::new(&usb_bus);
+//! let mut device = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd)).build();
+//! ```
+//!
+//! Endpoints 1 to 7 serve bulk and interrupt transfers of up to 64 bytes. Endpoint 8 is the
+//! isochronous endpoint, with a buffer of 1023 bytes that is split in halves of 512 bytes if it
+//! is used in both directions. Isochronous packets are exchanged once per frame: a packet written
+//! to the IN endpoint is sent in the frame following the next start-of-frame, and a packet
+//! received on the OUT endpoint can be read until the next start-of-frame. Isochronous data is
+//! transferred by EasyDMA without copying, so buffers passed to `write` must be in RAM.
+//!
+//! Bulk endpoints are double buffered: a packet written while the previous one is still waiting
+//! for the host is queued in RAM, and moved into the peripheral as soon as the host acknowledges
+//! the previous one. Likewise, a received packet is moved to RAM right away, so the peripheral can
+//! accept the next one before the first has been read. [`Usbd::write_packets`] queues several
+//! packets of a transfer at once.
+//!
+//! The USBD peripheral requires the external high frequency crystal, and the USB regulator to be
+//! ready. If VBUS is not present when the device is built, the peripheral stays disabled, and a
+//! [`UsbPowerManager`] must enable it once VBUS shows up. Devices that are not always powered
+//! from USB should use one anyway, to detach when the cable is removed.
+//!
+//! [`usb-device`]: https://crates.io/crates/usb-device
+
+use core::{
+    cell::RefCell,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+use cortex_m::interrupt::{self, CriticalSection, Mutex};
+use usb_device::{
+    bus::{PollResult, UsbBus},
+    endpoint::{EndpointAddress, EndpointType},
+    Result, UsbDirection, UsbError,
+};
+
+use crate::{
+    clocks::{Clocks, ExternalOscillator},
+    pac::{usbd, POWER, USBD},
+    slice_in_ram,
+};
+
+#[cfg(feature = "async")]
+use crate::waker::WakerSlot;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+static SOF_WAKER: WakerSlot = WakerSlot::new();
+
+mod dfu;
+mod power;
+#[cfg(feature = "usb-serial")]
+mod serial;
+
+pub use dfu::DfuRuntime;
+pub use power::UsbPowerManager;
+#[cfg(feature = "usb-serial")]
+pub use serial::{Error as UsbSerialError, UsbSerial};
+
+/// Maximum packet size of the control, bulk and interrupt endpoints.
+const MAX_PACKET_SIZE: usize = 64;
+
+/// The isochronous endpoint.
+const ISO_EP: usize = 8;
+
+/// Size of the isochronous buffer used by a single direction.
+const MAX_ISO_PACKET_SIZE: u16 = 1023;
+
+/// Size of the isochronous buffer of each direction, if both are used.
+const MAX_ISO_SPLIT_PACKET_SIZE: u16 = 512;
+
+/// `ISOSPLIT` value splitting the isochronous buffer between IN and OUT.
+const ISOSPLIT_HALF_IN: u32 = 0x80;
+
+/// `ISOINCONFIG` value answering IN tokens with a zero length packet if no data is available.
+const ISOINCONFIG_ZERO_DATA: u32 = 1;
+
+/// `SIZE.ISOOUT` flag marking a received zero length packet.
+const SIZE_ISOOUT_ZERO: u32 = 1 << 16;
+
+/// `EVENTCAUSE` flags.
+const EVENTCAUSE_SUSPEND: u32 = 1 << 8;
+const EVENTCAUSE_RESUME: u32 = 1 << 9;
+const EVENTCAUSE_USBWUALLOWED: u32 = 1 << 10;
+const EVENTCAUSE_READY: u32 = 1 << 11;
+
+/// `INTEN` flags of the events handled by `poll`.
+const INTEN_USBRESET: u32 = 1 << 0;
+const INTEN_EP0DATADONE: u32 = 1 << 10;
+const INTEN_SOF: u32 = 1 << 21;
+const INTEN_USBEVENT: u32 = 1 << 22;
+const INTEN_EP0SETUP: u32 = 1 << 23;
+const INTEN_EPDATA: u32 = 1 << 24;
+
+/// Mask of the 11 bit frame number.
+const FRAMECNTR_MASK: u16 = 0x7ff;
+
+/// `DPDMVALUE` value driving resume signaling onto the bus.
+const DPDMVALUE_RESUME: u32 = 1;
+
+/// `EPSTALL` and `DTOGGLE` flag selecting an IN endpoint.
+const EP_IN: u32 = 1 << 7;
+/// `DTOGGLE` value resetting the data toggle to DATA0.
+const DTOGGLE_DATA0: u32 = 1 << 8;
+
+/// Transfer state of the endpoints.
+struct State {
+    /// IN endpoints holding a packet not yet acknowledged by the host.
+    busy_in: u16,
+    /// OUT endpoints holding a received packet not yet read.
+    ready_out: u16,
+    /// Direction of the data stage of the current control transfer.
+    ep0_in: bool,
+    /// Bytes left in the data stage of the current control transfer.
+    ep0_remaining: u16,
+    /// Whether the last packet of a control read has been queued, so the status stage follows
+    /// once it has been acknowledged.
+    ep0_last: bool,
+    /// Frame number of the last start-of-frame not taken by `take_sof` yet.
+    sof: Option<u16>,
+    /// Bulk IN endpoints with a packet queued in `queued_in`, behind the one in the peripheral.
+    queued_in: u16,
+    /// Bulk OUT endpoints with a received packet moved to `staged_out`.
+    staged_out: u16,
+    /// Packets of the bulk endpoints 1 to 7, with their lengths.
+    in_bufs: [[u8; MAX_PACKET_SIZE]; 7],
+    in_lens: [u8; 7],
+    out_bufs: [[u8; MAX_PACKET_SIZE]; 7],
+    out_lens: [u8; 7],
+}
+
+impl State {
+    const fn new() -> Self {
+        State {
+            busy_in: 0,
+            ready_out: 0,
+            ep0_in: false,
+            ep0_remaining: 0,
+            ep0_last: false,
+            sof: None,
+            queued_in: 0,
+            staged_out: 0,
+            in_bufs: [[0; MAX_PACKET_SIZE]; 7],
+            in_lens: [0; 7],
+            out_bufs: [[0; MAX_PACKET_SIZE]; 7],
+            out_lens: [0; 7],
+        }
+    }
+}
+
+/// USB device bus driver for the USBD peripheral.
+pub struct Usbd<'c> {
+    // Owned for exclusive access. The bus is shared by reference, so the registers are accessed
+    // through `regs`.
+    _usbd: Mutex<USBD>,
+    used_in: u16,
+    used_out: u16,
+    max_packet_size_0: u16,
+    max_packet_size_iso_in: u16,
+    max_packet_size_iso_out: u16,
+    /// Maximum packet sizes of the IN endpoints 1 to 7.
+    max_packet_size_in: [u16; 7],
+    /// Polling intervals of the interrupt endpoints, 0 for other endpoints.
+    interval_in: [u8; 8],
+    interval_out: [u8; 8],
+    state: Mutex<RefCell<State>>,
+    /// EasyDMA can only access RAM, so data is sent and received through this buffer.
+    buf: Mutex<RefCell<[u8; MAX_PACKET_SIZE]>>,
+    // The external crystal must keep running while USB is in use.
+    _clocks: PhantomData<&'c ()>,
+}
+
+impl<'c> Usbd<'c> {
+    /// Creates the bus driver. The peripheral is enabled when the USB device is built.
+    pub fn new<L, LSTAT>(usbd: USBD, _clocks: &'c Clocks<ExternalOscillator, L, LSTAT>) -> Self {
+        Usbd {
+            _usbd: Mutex::new(usbd),
+            used_in: 0,
+            used_out: 0,
+            max_packet_size_0: 0,
+            max_packet_size_iso_in: 0,
+            max_packet_size_iso_out: 0,
+            max_packet_size_in: [0; 7],
+            interval_in: [0; 8],
+            interval_out: [0; 8],
+            state: Mutex::new(RefCell::new(State::new())),
+            buf: Mutex::new(RefCell::new([0; MAX_PACKET_SIZE])),
+            _clocks: PhantomData,
+        }
+    }
+
+    /// Returns the polling interval of the interrupt endpoint `ep_addr`, in frames of 1 ms, as
+    /// requested by its class when allocating it.
+    ///
+    /// Returns `None` if `ep_addr` is not an allocated interrupt endpoint. Sending reports at this
+    /// rate at most keeps an interrupt IN endpoint from returning `WouldBlock`.
+    pub fn interval(&self, ep_addr: EndpointAddress) -> Option<u8> {
+        let index = ep_addr.index();
+        let interval = match ep_addr.direction() {
+            UsbDirection::In => self.interval_in.get(index),
+            UsbDirection::Out => self.interval_out.get(index),
+        };
+        interval.copied().filter(|&interval| interval != 0)
+    }
+
+    /// Enables the interrupts of the events handled by `poll`, so the device can be polled from
+    /// the `USBD` interrupt handler.
+    ///
+    /// The `USBEVENT` interrupt also wakes the CPU when the host resumes the bus after suspending
+    /// it.
+    pub fn enable_interrupts(&self) {
+        let mut mask =
+            INTEN_USBRESET | INTEN_EP0DATADONE | INTEN_USBEVENT | INTEN_EP0SETUP | INTEN_EPDATA;
+        if (self.used_in | self.used_out) & (1 << ISO_EP) != 0 {
+            mask |= INTEN_SOF;
+        }
+        self.regs().intenset.write(|w| unsafe { w.bits(mask) });
+    }
+
+    /// Disables all interrupts of the peripheral.
+    pub fn disable_interrupts(&self) {
+        self.regs().intenclr.write(|w| unsafe { w.bits(!0) });
+    }
+
+    /// Returns the number of the current frame, from the last start-of-frame packet.
+    ///
+    /// The 11 bit frame number is incremented by the host every millisecond.
+    pub fn frame_number(&self) -> u16 {
+        self.regs().framecntr.read().bits() as u16 & FRAMECNTR_MASK
+    }
+
+    /// Returns the frame number of the last start-of-frame seen by `poll` since the previous
+    /// call, or `None` if there was none.
+    ///
+    /// Start-of-frame packets mark the 1 ms frames of the host, so audio and other timing
+    /// sensitive classes can synchronize their clocks to them.
+    pub fn take_sof(&self) -> Option<u16> {
+        interrupt::free(|cs| self.state.borrow(cs).borrow_mut().sof.take())
+    }
+
+    /// Enables the start-of-frame interrupt, so `poll` is called every frame.
+    pub fn enable_sof_interrupt(&self) {
+        self.regs().intenset.write(|w| unsafe { w.bits(INTEN_SOF) });
+    }
+
+    /// Disables the start-of-frame interrupt.
+    ///
+    /// The interrupt stays enabled by `enable_interrupts` if the isochronous endpoint is used.
+    pub fn disable_sof_interrupt(&self) {
+        self.regs().intenclr.write(|w| unsafe { w.bits(INTEN_SOF) });
+    }
+
+    /// Waits for the next start-of-frame and returns its frame number.
+    ///
+    /// Requires the start-of-frame interrupt, and the `USBD` interrupt handler to call
+    /// [`on_interrupt`] and poll the device.
+    #[cfg(feature = "async")]
+    pub async fn wait_sof(&self) -> u16 {
+        self.take_sof();
+        poll_fn(|cx| {
+            SOF_WAKER.register(cx.waker());
+            match self.take_sof() {
+                Some(frame) => Poll::Ready(frame),
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Returns `true` if the peripheral is in its low power mode, entered when the bus has been
+    /// suspended.
+    pub fn is_low_power(&self) -> bool {
+        self.regs().lowpower.read().bits() != 0
+    }
+
+    /// Signals remote wakeup to the host, which must have enabled it with a
+    /// `SET_FEATURE(DEVICE_REMOTE_WAKEUP)` request, see `UsbDevice::remote_wakeup_enabled`.
+    ///
+    /// Does nothing unless the bus is suspended. The peripheral leaves its low power mode and
+    /// drives the resume signaling for the duration required by the specification, after which
+    /// the host resumes the bus and `poll` reports `Resume`.
+    pub fn remote_wakeup(&self) {
+        let regs = self.regs();
+        if !self.is_low_power() {
+            return;
+        }
+
+        regs.lowpower.write(|w| unsafe { w.bits(0) });
+        while regs.eventcause.read().bits() & EVENTCAUSE_USBWUALLOWED == 0 {}
+        regs.eventcause
+            .write(|w| unsafe { w.bits(EVENTCAUSE_USBWUALLOWED) });
+
+        regs.dpdmvalue
+            .write(|w| unsafe { w.bits(DPDMVALUE_RESUME) });
+        regs.tasks_dpdmdrive.write(|w| unsafe { w.bits(1) });
+    }
+
+    fn regs(&self) -> &usbd::RegisterBlock {
+        unsafe { &*USBD::ptr() }
+    }
+
+    /// Sends `data` from the DMA buffer to IN endpoint `ep`, blocking until the transfer to the
+    /// peripheral's internal buffer is done.
+    fn dma_in(&self, cs: &CriticalSection, ep: usize, data: &[u8]) {
+        let mut buf = self.buf.borrow(cs).borrow_mut();
+        buf[..data.len()].copy_from_slice(data);
+        self.dma_in_ram(ep, &buf[..data.len()]);
+    }
+
+    /// Sends `data`, which must be in RAM, to IN endpoint `ep`.
+    fn dma_in_ram(&self, ep: usize, data: &[u8]) {
+        let regs = self.regs();
+        regs.epin[ep]
+            .ptr
+            .write(|w| unsafe { w.bits(data.as_ptr() as u32) });
+        regs.epin[ep]
+            .maxcnt
+            .write(|w| unsafe { w.bits(data.len() as u32) });
+        regs.events_endepin[ep].reset();
+
+        dma_start();
+        regs.tasks_startepin[ep].write(|w| unsafe { w.bits(1) });
+        while regs.events_endepin[ep].read().bits() == 0 {}
+        regs.events_endepin[ep].reset();
+        dma_end();
+    }
+
+    /// Reads the packet received on OUT endpoint `ep` into `data`, blocking until the transfer
+    /// from the peripheral's internal buffer is done.
+    fn dma_out(&self, cs: &CriticalSection, ep: usize, data: &mut [u8]) {
+        let mut buf = self.buf.borrow(cs).borrow_mut();
+        self.dma_out_ram(ep, &mut buf[..data.len()]);
+        data.copy_from_slice(&buf[..data.len()]);
+    }
+
+    /// Reads the packet received on OUT endpoint `ep` into `data`.
+    fn dma_out_ram(&self, ep: usize, data: &mut [u8]) {
+        let regs = self.regs();
+        regs.epout[ep]
+            .ptr
+            .write(|w| unsafe { w.bits(data.as_mut_ptr() as u32) });
+        regs.epout[ep]
+            .maxcnt
+            .write(|w| unsafe { w.bits(data.len() as u32) });
+        regs.events_endepout[ep].reset();
+
+        dma_start();
+        regs.tasks_startepout[ep].write(|w| unsafe { w.bits(1) });
+        while regs.events_endepout[ep].read().bits() == 0 {}
+        regs.events_endepout[ep].reset();
+        dma_end();
+    }
+
+    /// Returns `true` if `index` is one of the bulk endpoints 1 to 7.
+    fn is_bulk(&self, index: usize, is_in: bool) -> bool {
+        let interval = if is_in {
+            self.interval_in[index]
+        } else {
+            self.interval_out[index]
+        };
+        (1..8).contains(&index) && interval == 0
+    }
+
+    /// Moves the packet received on bulk OUT endpoint `index` to RAM, so the peripheral can
+    /// accept the next one.
+    fn stage_out(&self, state: &mut State, index: usize) {
+        let size = self.regs().size.epout[index].read().bits() as usize;
+        self.dma_out_ram(index, &mut state.out_bufs[index - 1][..size]);
+        state.out_lens[index - 1] = size as u8;
+        state.staged_out |= 1 << index;
+        state.ready_out &= !(1 << index);
+    }
+
+    /// Queues `buf` on bulk IN endpoint `index`, behind the packet in the peripheral if there is
+    /// one.
+    fn queue_in(&self, state: &mut State, index: usize, buf: &[u8]) -> Result<usize> {
+        let bit = 1 << index;
+        if state.busy_in & bit == 0 {
+            state.in_bufs[index - 1][..buf.len()].copy_from_slice(buf);
+            self.dma_in_ram(index, &state.in_bufs[index - 1][..buf.len()]);
+            state.busy_in |= bit;
+        } else if state.queued_in & bit == 0 {
+            state.in_bufs[index - 1][..buf.len()].copy_from_slice(buf);
+            state.in_lens[index - 1] = buf.len() as u8;
+            state.queued_in |= bit;
+        } else {
+            return Err(UsbError::WouldBlock);
+        }
+        Ok(buf.len())
+    }
+
+    /// Reads the packet of bulk OUT endpoint `index` staged in RAM, and stages the next one if
+    /// the peripheral has received it meanwhile.
+    fn read_staged(&self, state: &mut State, index: usize, buf: &mut [u8]) -> Result<usize> {
+        let bit = 1 << index;
+        if state.staged_out & bit == 0 {
+            return Err(UsbError::WouldBlock);
+        }
+        let size = state.out_lens[index - 1] as usize;
+        if size > buf.len() {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        buf[..size].copy_from_slice(&state.out_bufs[index - 1][..size]);
+        state.staged_out &= !bit;
+        if state.ready_out & bit != 0 {
+            self.stage_out(state, index);
+        }
+        Ok(size)
+    }
+
+    /// Queues as many packets of `data` as possible on the bulk IN endpoint `ep_addr`, and
+    /// returns the number of bytes queued.
+    ///
+    /// `data` is split into packets of the endpoint's maximum packet size. Up to two packets are
+    /// queued: one in the peripheral and one in RAM. A transfer ending with a full packet must be
+    /// terminated with a zero length packet, written with `write`.
+    pub fn write_packets(&self, ep_addr: EndpointAddress, data: &[u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        if !ep_addr.is_in() || self.used_in & (1 << index) == 0 || !self.is_bulk(index, true) {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        let max_packet_size = self.max_packet_size_in[index - 1] as usize;
+
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            let mut written = 0;
+            for packet in data.chunks(max_packet_size) {
+                match self.queue_in(&mut state, index, packet) {
+                    Ok(n) => written += n,
+                    Err(UsbError::WouldBlock) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            if written == 0 && !data.is_empty() {
+                Err(UsbError::WouldBlock)
+            } else {
+                Ok(written)
+            }
+        })
+    }
+
+    /// Sends `data` to the isochronous IN endpoint, blocking until the transfer to the
+    /// peripheral's internal buffer is done.
+    fn dma_iso_in(&self, data: &[u8]) {
+        let regs = self.regs();
+        regs.isoin
+            .ptr
+            .write(|w| unsafe { w.bits(data.as_ptr() as u32) });
+        regs.isoin
+            .maxcnt
+            .write(|w| unsafe { w.bits(data.len() as u32) });
+        regs.events_endisoin.reset();
+
+        dma_start();
+        regs.tasks_startisoin.write(|w| unsafe { w.bits(1) });
+        while regs.events_endisoin.read().bits() == 0 {}
+        regs.events_endisoin.reset();
+        dma_end();
+    }
+
+    /// Reads the packet received on the isochronous OUT endpoint into `data`, blocking until the
+    /// transfer from the peripheral's internal buffer is done.
+    fn dma_iso_out(&self, data: &mut [u8]) {
+        let regs = self.regs();
+        regs.isoout
+            .ptr
+            .write(|w| unsafe { w.bits(data.as_mut_ptr() as u32) });
+        regs.isoout
+            .maxcnt
+            .write(|w| unsafe { w.bits(data.len() as u32) });
+        regs.events_endisoout.reset();
+
+        dma_start();
+        regs.tasks_startisoout.write(|w| unsafe { w.bits(1) });
+        while regs.events_endisoout.read().bits() == 0 {}
+        regs.events_endisoout.reset();
+        dma_end();
+    }
+
+    /// Reads the setup packet of a control transfer into `buf`.
+    fn read_setup(&self, cs: &CriticalSection, buf: &mut [u8]) -> Result<usize> {
+        if buf.len() < 8 {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        let regs = self.regs();
+        regs.events_ep0setup.reset();
+        let setup = [
+            regs.bmrequesttype.read().bits() as u8,
+            regs.brequest.read().bits() as u8,
+            regs.wvaluel.read().bits() as u8,
+            regs.wvalueh.read().bits() as u8,
+            regs.windexl.read().bits() as u8,
+            regs.windexh.read().bits() as u8,
+            regs.wlengthl.read().bits() as u8,
+            regs.wlengthh.read().bits() as u8,
+        ];
+        buf[..8].copy_from_slice(&setup);
+
+        let mut state = self.state.borrow(cs).borrow_mut();
+        state.ep0_in = setup[0] & 0x80 != 0;
+        state.ep0_remaining = u16::from_le_bytes([setup[6], setup[7]]);
+        state.ep0_last = false;
+        state.busy_in &= !1;
+        state.ready_out &= !1;
+        if !state.ep0_in && state.ep0_remaining > 0 {
+            // Accept the first packet of the data stage.
+            regs.tasks_ep0rcvout.write(|w| unsafe { w.bits(1) });
+        }
+        Ok(8)
+    }
+
+    /// Enables the peripheral, which then starts its own power up.
+    fn start_power_up(&self) {
+        let regs = self.regs();
+        if regs.enable.read().enable().is_enabled() {
+            return;
+        }
+        errata_write(ERRATA_187_REG, 3);
+        errata_write(ERRATA_171_REG, 0xc0);
+        regs.enable.write(|w| w.enable().enabled());
+    }
+
+    /// Waits for the peripheral to be ready, enables the endpoints and attaches to the bus.
+    fn finish_power_up(&self) {
+        let regs = self.regs();
+        if regs.usbpullup.read().connect().is_enabled() {
+            return;
+        }
+        self.start_power_up();
+        while regs.eventcause.read().bits() & EVENTCAUSE_READY == 0 {}
+        regs.eventcause
+            .write(|w| unsafe { w.bits(EVENTCAUSE_READY) });
+        errata_write(ERRATA_171_REG, 0);
+        errata_write(ERRATA_187_REG, 0);
+
+        self.enable_endpoints();
+        regs.usbpullup.write(|w| w.connect().enabled());
+    }
+
+    /// Detaches from the bus and disables the peripheral.
+    fn power_down(&self) {
+        let regs = self.regs();
+        regs.usbpullup.write(|w| w.connect().disabled());
+        regs.enable.write(|w| w.enable().disabled());
+        interrupt::free(|cs| {
+            *self.state.borrow(cs).borrow_mut() = State::new();
+        });
+    }
+
+    /// Enables the endpoints allocated so far, resets their data toggles and lets the OUT
+    /// endpoints accept a packet.
+    fn enable_endpoints(&self) {
+        let regs = self.regs();
+        regs.epinen
+            .write(|w| unsafe { w.bits(self.used_in as u32) });
+        regs.epouten
+            .write(|w| unsafe { w.bits(self.used_out as u32) });
+
+        // Workaround for erratum 166: USBD cannot use the double buffering of the isochronous
+        // IN endpoint.
+        unsafe {
+            ptr::write_volatile(0x4002_7800 as *mut u32, 0x7e3);
+            ptr::write_volatile(0x4002_7804 as *mut u32, 0x40);
+        }
+
+        let iso = 1 << ISO_EP;
+        let split = if self.used_in & self.used_out & iso != 0 {
+            ISOSPLIT_HALF_IN
+        } else {
+            0
+        };
+        regs.isosplit.write(|w| unsafe { w.bits(split) });
+        regs.isoinconfig
+            .write(|w| unsafe { w.bits(ISOINCONFIG_ZERO_DATA) });
+
+        for index in 1..8 {
+            if self.used_in & (1 << index) != 0 {
+                self.reset_data_toggle(index, true);
+            }
+            if self.used_out & (1 << index) != 0 {
+                self.reset_data_toggle(index, false);
+                // Writing `SIZE.EPOUT` makes the endpoint acknowledge the next packet, instead of
+                // NAKing until a DMA transfer.
+                regs.size.epout[index].reset();
+            }
+        }
+    }
+
+    /// Queues `buf` on the isochronous IN endpoint, to be sent in the next frame.
+    fn write_iso(&self, buf: &[u8]) -> Result<usize> {
+        if buf.len() > self.max_packet_size_iso_in as usize {
+            return Err(UsbError::BufferOverflow);
+        }
+        if !buf.is_empty() && !slice_in_ram(buf) {
+            return Err(UsbError::Unsupported);
+        }
+
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            if state.busy_in & (1 << ISO_EP) != 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            self.dma_iso_in(buf);
+            state.busy_in |= 1 << ISO_EP;
+            Ok(buf.len())
+        })
+    }
+
+    /// Reads the packet received on the isochronous OUT endpoint in the last frame.
+    fn read_iso(&self, buf: &mut [u8]) -> Result<usize> {
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            if state.ready_out & (1 << ISO_EP) == 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            let size = (self.regs().size.isoout.read().bits() & 0x3ff) as usize;
+            if size > buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            if size > 0 {
+                self.dma_iso_out(&mut buf[..size]);
+            }
+            state.ready_out &= !(1 << ISO_EP);
+            Ok(size)
+        })
+    }
+
+    /// Resets the data toggle of endpoint `index` to DATA0, as required after configuring the
+    /// endpoint or clearing its halt condition.
+    fn reset_data_toggle(&self, index: usize, is_in: bool) {
+        let io = if is_in { EP_IN } else { 0 };
+        self.regs()
+            .dtoggle
+            .write(|w| unsafe { w.bits(index as u32 | io | DTOGGLE_DATA0) });
+    }
+}
+
+impl UsbBus for Usbd<'_> {
+    fn alloc_ep(
+        &mut self,
+        ep_dir: UsbDirection,
+        ep_addr: Option<EndpointAddress>,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        interval: u8,
+    ) -> Result<EndpointAddress> {
+        let used = match ep_dir {
+            UsbDirection::In => self.used_in,
+            UsbDirection::Out => self.used_out,
+        };
+
+        let index = match ep_type {
+            EndpointType::Control => {
+                if ep_addr.map_or(false, |addr| addr.index() != 0) || used & 1 != 0 {
+                    return Err(UsbError::InvalidEndpoint);
+                }
+                if max_packet_size as usize > MAX_PACKET_SIZE {
+                    return Err(UsbError::Unsupported);
+                }
+                self.max_packet_size_0 = max_packet_size;
+                0
+            }
+            EndpointType::Bulk | EndpointType::Interrupt => {
+                if max_packet_size as usize > MAX_PACKET_SIZE {
+                    return Err(UsbError::EndpointMemoryOverflow);
+                }
+                if ep_type == EndpointType::Interrupt && interval == 0 {
+                    // Full speed interrupt endpoints are polled every 1 to 255 frames.
+                    return Err(UsbError::Unsupported);
+                }
+                match ep_addr {
+                    Some(addr) => {
+                        let index = addr.index();
+                        if !(1..8).contains(&index) || used & (1 << index) != 0 {
+                            return Err(UsbError::InvalidEndpoint);
+                        }
+                        index
+                    }
+                    None => (1..8)
+                        .find(|index| used & (1 << index) == 0)
+                        .ok_or(UsbError::EndpointOverflow)?,
+                }
+            }
+            EndpointType::Isochronous => {
+                if ep_addr.map_or(false, |addr| addr.index() != ISO_EP) || used & (1 << ISO_EP) != 0
+                {
+                    return Err(UsbError::InvalidEndpoint);
+                }
+                if interval != 1 {
+                    // Full speed isochronous endpoints transfer a packet every frame.
+                    return Err(UsbError::Unsupported);
+                }
+                let other = match ep_dir {
+                    UsbDirection::In => self.max_packet_size_iso_out,
+                    UsbDirection::Out => self.max_packet_size_iso_in,
+                };
+                let limit = if other == 0 {
+                    MAX_ISO_PACKET_SIZE
+                } else {
+                    MAX_ISO_SPLIT_PACKET_SIZE
+                };
+                if max_packet_size > limit || other > limit {
+                    return Err(UsbError::EndpointMemoryOverflow);
+                }
+                match ep_dir {
+                    UsbDirection::In => self.max_packet_size_iso_in = max_packet_size,
+                    UsbDirection::Out => self.max_packet_size_iso_out = max_packet_size,
+                }
+                ISO_EP
+            }
+        };
+
+        let interval = if ep_type == EndpointType::Interrupt {
+            interval
+        } else {
+            0
+        };
+        match ep_dir {
+            UsbDirection::In => {
+                self.used_in |= 1 << index;
+                self.interval_in[index] = interval;
+                if (1..8).contains(&index) {
+                    self.max_packet_size_in[index - 1] = max_packet_size;
+                }
+            }
+            UsbDirection::Out => {
+                self.used_out |= 1 << index;
+                self.interval_out[index] = interval;
+            }
+        }
+        Ok(EndpointAddress::from_parts(index, ep_dir))
+    }
+
+    /// Enables the peripheral and attaches to the bus if the USB regulator is ready, otherwise
+    /// leaves it to a `UsbPowerManager`.
+    fn enable(&mut self) {
+        let power = unsafe { &*POWER::ptr() };
+        if power.usbregstatus.read().outputrdy().is_ready() {
+            self.start_power_up();
+            self.finish_power_up();
+        }
+    }
+
+    fn reset(&self) {
+        interrupt::free(|cs| {
+            *self.state.borrow(cs).borrow_mut() = State::new();
+        });
+        self.enable_endpoints();
+    }
+
+    fn set_device_address(&self, _addr: u8) {
+        // The peripheral handles SET_ADDRESS requests by itself.
+    }
+
+    fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        if self.used_in & (1 << index) == 0 {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        if index == ISO_EP {
+            return self.write_iso(buf);
+        }
+        if buf.len() > MAX_PACKET_SIZE {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            if self.is_bulk(index, true) {
+                return self.queue_in(&mut state, index, buf);
+            }
+            if index == 0 && !state.ep0_in {
+                // Status stage of a control write, or of a request without data stage.
+                self.regs().tasks_ep0status.write(|w| unsafe { w.bits(1) });
+                return Ok(0);
+            }
+            if state.busy_in & (1 << index) != 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            if index == 0 {
+                state.ep0_remaining = state.ep0_remaining.saturating_sub(buf.len() as u16);
+                state.ep0_last =
+                    buf.len() < self.max_packet_size_0 as usize || state.ep0_remaining == 0;
+            }
+
+            self.dma_in(cs, index, buf);
+            state.busy_in |= 1 << index;
+            Ok(buf.len())
+        })
+    }
+
+    fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        if self.used_out & (1 << index) == 0 {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        if index == ISO_EP {
+            return self.read_iso(buf);
+        }
+
+        interrupt::free(|cs| {
+            let regs = self.regs();
+            if index == 0 && regs.events_ep0setup.read().bits() != 0 {
+                return self.read_setup(cs, buf);
+            }
+
+            let mut state = self.state.borrow(cs).borrow_mut();
+            if self.is_bulk(index, false) {
+                return self.read_staged(&mut state, index, buf);
+            }
+            if state.ready_out & (1 << index) == 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            let size = regs.size.epout[index].read().bits() as usize;
+            if size > buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            self.dma_out(cs, index, &mut buf[..size]);
+            state.ready_out &= !(1 << index);
+            if index == 0 {
+                state.ep0_remaining = state.ep0_remaining.saturating_sub(size as u16);
+                if state.ep0_remaining > 0 {
+                    regs.tasks_ep0rcvout.write(|w| unsafe { w.bits(1) });
+                }
+            }
+            Ok(size)
+        })
+    }
+
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        let regs = self.regs();
+        let index = ep_addr.index();
+        if index == ISO_EP {
+            // Isochronous endpoints have no handshake, so they cannot be stalled.
+            return;
+        }
+        if index == 0 {
+            // EP0 is unstalled by the next setup packet.
+            if stalled {
+                regs.tasks_ep0stall.write(|w| unsafe { w.bits(1) });
+            }
+            return;
+        }
+
+        let io = if ep_addr.is_in() { EP_IN } else { 0 };
+        let stall = if stalled { 1 << 8 } else { 0 };
+        regs.epstall
+            .write(|w| unsafe { w.bits(index as u32 | io | stall) });
+        if !stalled {
+            // The transfer after a cleared halt starts with DATA0.
+            self.reset_data_toggle(index, ep_addr.is_in());
+        }
+    }
+
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        let regs = self.regs();
+        let index = ep_addr.index();
+        if index == ISO_EP {
+            return false;
+        }
+        if ep_addr.is_in() {
+            regs.halted.epin[index].read().bits() != 0
+        } else {
+            regs.halted.epout[index].read().bits() != 0
+        }
+    }
+
+    /// Puts the peripheral into its low power mode.
+    ///
+    /// To meet the suspend current limit of bus-powered devices, the application should also
+    /// sleep until the `USBD` interrupt signals the resume, see `enable_interrupts`.
+    fn suspend(&self) {
+        self.regs().lowpower.write(|w| unsafe { w.bits(1) });
+    }
+
+    fn resume(&self) {
+        self.regs().lowpower.write(|w| unsafe { w.bits(0) });
+    }
+
+    fn poll(&self) -> PollResult {
+        let regs = self.regs();
+
+        if regs.events_usbreset.read().bits() != 0 {
+            regs.events_usbreset.reset();
+            return PollResult::Reset;
+        }
+
+        if regs.events_usbevent.read().bits() != 0 {
+            regs.events_usbevent.reset();
+            // `READY` and `USBWUALLOWED` are waited for by the power up and the remote wakeup.
+            let cause =
+                regs.eventcause.read().bits() & !(EVENTCAUSE_READY | EVENTCAUSE_USBWUALLOWED);
+            regs.eventcause.write(|w| unsafe { w.bits(cause) });
+            if cause & EVENTCAUSE_SUSPEND != 0 {
+                return PollResult::Suspend;
+            }
+            if cause & EVENTCAUSE_RESUME != 0 {
+                return PollResult::Resume;
+            }
+        }
+
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            let mut ep_setup = 0;
+            let mut ep_out = 0;
+            let mut ep_in_complete = 0;
+
+            if regs.events_ep0setup.read().bits() != 0 {
+                ep_setup = 1;
+            }
+
+            if regs.events_ep0datadone.read().bits() != 0 {
+                regs.events_ep0datadone.reset();
+                if state.ep0_in {
+                    state.busy_in &= !1;
+                    ep_in_complete |= 1;
+                    if state.ep0_last {
+                        regs.tasks_ep0status.write(|w| unsafe { w.bits(1) });
+                        state.ep0_last = false;
+                    }
+                } else {
+                    state.ready_out |= 1;
+                }
+            }
+
+            if regs.events_epdata.read().bits() != 0 {
+                regs.events_epdata.reset();
+                let status = regs.epdatastatus.read().bits();
+                regs.epdatastatus.write(|w| unsafe { w.bits(status) });
+                let in_complete = (status & 0xfe) as u16;
+                state.busy_in &= !in_complete;
+                ep_in_complete |= in_complete;
+                state.ready_out |= ((status >> 16) & 0xfe) as u16;
+
+                for index in 1..8 {
+                    let bit = 1 << index;
+                    if in_complete & state.queued_in & bit != 0 {
+                        // Move the queued packet into the peripheral right away, the slot it
+                        // frees is reported as completed.
+                        let len = state.in_lens[index - 1] as usize;
+                        self.dma_in_ram(index, &state.in_bufs[index - 1][..len]);
+                        state.queued_in &= !bit;
+                        state.busy_in |= bit;
+                    }
+                    if state.ready_out & !state.staged_out & bit != 0 && self.is_bulk(index, false)
+                    {
+                        self.stage_out(&mut state, index);
+                    }
+                }
+            }
+
+            let iso = 1 << ISO_EP;
+            let sof = regs.events_sof.read().bits() != 0;
+            if sof {
+                regs.events_sof.reset();
+                state.sof = Some(regs.framecntr.read().bits() as u16 & FRAMECNTR_MASK);
+            }
+            if sof && (self.used_in | self.used_out) & iso != 0 {
+                // The packet written in the last frame has been sent, and the packet received in
+                // it is ready until the next start-of-frame.
+                if state.busy_in & iso != 0 {
+                    state.busy_in &= !iso;
+                    ep_in_complete |= iso;
+                }
+                state.ready_out &= !iso;
+                if self.used_out & iso != 0 {
+                    let size = regs.size.isoout.read().bits();
+                    if size & (SIZE_ISOOUT_ZERO | 0x3ff) != 0 {
+                        state.ready_out |= iso;
+                    }
+                }
+            }
+
+            // Packets not read yet are reported again.
+            ep_out |= state.ready_out | state.staged_out;
+
+            if ep_setup | ep_out | ep_in_complete != 0 {
+                PollResult::Data {
+                    ep_out,
+                    ep_in_complete,
+                    ep_setup,
+                }
+            } else {
+                PollResult::None
+            }
+        })
+    }
+}
+
+/// Wakes the task waiting in [`Usbd::wait_sof`] on a start-of-frame.
+///
+/// Must be called from the `USBD` interrupt handler, before polling the device. The event is
+/// left for `poll` to handle.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let usbd = unsafe { &*USBD::ptr() };
+    if usbd.events_sof.read().bits() != 0 {
+        SOF_WAKER.wake();
+    }
+}
+
+/// Register of the workaround for erratum 171: USBD might not reach its active state.
+const ERRATA_171_REG: u32 = 0x4006_ec14;
+/// Register of the workaround for erratum 187: USBD might not reach its active state.
+const ERRATA_187_REG: u32 = 0x4006_ed14;
+/// Unlocks the errata registers when written while they are locked, and locks them again when
+/// written while they are unlocked.
+const ERRATA_LOCK: u32 = 0x4006_ec00;
+
+/// Writes `value` to the errata register `reg`, following Nordic's reference sequence: the
+/// registers are only unlocked if they aren't already, and left in the state they were found in.
+fn errata_write(reg: u32, value: u32) {
+    interrupt::free(|_| unsafe {
+        if ptr::read_volatile(ERRATA_LOCK as *const u32) == 0 {
+            ptr::write_volatile(ERRATA_LOCK as *mut u32, 0x9375);
+            ptr::write_volatile(reg as *mut u32, value);
+            ptr::write_volatile(ERRATA_LOCK as *mut u32, 0x9375);
+        } else {
+            ptr::write_volatile(reg as *mut u32, value);
+        }
+    });
+}
+
+/// Prepares an EasyDMA transfer, including the workaround for erratum 199: USBD cannot receive
+/// tasks during DMA.
+fn dma_start() {
+    compiler_fence(Ordering::SeqCst);
+    unsafe { ptr::write_volatile(0x4002_7c1c as *mut u32, 0x82) };
+}
+
+/// Finishes an EasyDMA transfer.
+fn dma_end() {
+    unsafe { ptr::write_volatile(0x4002_7c1c as *mut u32, 0) };
+    compiler_fence(Ordering::SeqCst);
+}
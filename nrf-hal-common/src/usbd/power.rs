@@ -0,0 +1,76 @@
+//! USB power management.
+
+use crate::{
+    clocks::Clocks,
+    power::{Power, UsbPower, UsbPowerEvent, UsbPowerState},
+};
+
+use super::Usbd;
+
+/// Attaches the USB device to the bus and detaches it following the VBUS events.
+///
+/// On top of [`UsbPower`], which sequences the USB regulator and the crystal, this enables the
+/// USBD peripheral when VBUS is detected, enables the pull-up on D+ once the regulator and the
+/// crystal are ready, and detaches and disables the peripheral when VBUS is removed, in the order
+/// required by the reference manual.
+///
+/// ```ignore
+/// let mut usb_power = UsbPowerManager::new();
+/// usb_power.enable_interrupts(&mut power);
+///
+/// // In the `POWER_CLOCK` interrupt handler:
+/// while usb_power.poll(&mut power, &clocks, device.bus()).is_some() {}
+/// ```
+pub struct UsbPowerManager {
+    power: UsbPower,
+}
+
+impl UsbPowerManager {
+    /// Creates the manager in the `Disconnected` state.
+    pub fn new() -> Self {
+        UsbPowerManager {
+            power: UsbPower::new(),
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> UsbPowerState {
+        self.power.state()
+    }
+
+    /// Enables the `POWER_CLOCK` interrupt on the USB power events.
+    pub fn enable_interrupts(&mut self, power: &mut Power) {
+        self.power.enable_interrupts(power);
+    }
+
+    /// Disables the `POWER_CLOCK` interrupt on the USB power events.
+    pub fn disable_interrupts(&mut self, power: &mut Power) {
+        self.power.disable_interrupts(power);
+    }
+
+    /// Handles the pending USB power events and powers `usbd` up or down accordingly.
+    ///
+    /// Call this from the `POWER_CLOCK` interrupt handler, or poll it from the main loop, until
+    /// it returns `None`. Once the regulator is ready, this blocks until the crystal and the
+    /// peripheral are ready.
+    pub fn poll<H, L, LSTAT>(
+        &mut self,
+        power: &mut Power,
+        clocks: &Clocks<H, L, LSTAT>,
+        usbd: &Usbd,
+    ) -> Option<UsbPowerEvent> {
+        let event = self.power.poll(power, clocks)?;
+        match event {
+            UsbPowerEvent::Detected => usbd.start_power_up(),
+            UsbPowerEvent::Ready => usbd.finish_power_up(),
+            UsbPowerEvent::Removed => usbd.power_down(),
+        }
+        Some(event)
+    }
+}
+
+impl Default for UsbPowerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
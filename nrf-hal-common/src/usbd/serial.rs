@@ -0,0 +1,152 @@
+//! USB serial port.
+//!
+//! [`UsbSerial`] bundles a USB device with a single CDC-ACM serial port and implements the
+//! `embedded-io` traits on it:
+//!
+//! ```ignore
+//! let usb_bus = UsbBusAllocator::new(Usbd::new(p.USBD, &clocks));
+//! let mut serial = UsbSerial::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd), "Example", "Console", "1");
+//! writeln!(serial, "Hello")?;
+//! ```
+
+use usb_device::{
+    bus::UsbBusAllocator,
+    device::{UsbDevice, UsbDeviceBuilder, UsbDeviceState, UsbVidPid},
+    UsbError,
+};
+use usbd_serial::{SerialPort, USB_CLASS_CDC};
+
+use super::Usbd;
+use crate::error::ErrorKind;
+
+/// USB serial port error.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Error(pub UsbError);
+
+impl crate::error::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self.0 {
+            UsbError::WouldBlock => ErrorKind::Busy,
+            UsbError::BufferOverflow => ErrorKind::BufferLength,
+            UsbError::InvalidEndpoint => ErrorKind::InvalidArgument,
+            UsbError::Unsupported => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        use embedded_io::ErrorKind as IoErrorKind;
+
+        match self.0 {
+            UsbError::WouldBlock => IoErrorKind::Interrupted,
+            UsbError::BufferOverflow => IoErrorKind::OutOfMemory,
+            UsbError::InvalidEndpoint => IoErrorKind::InvalidInput,
+            UsbError::Unsupported => IoErrorKind::Unsupported,
+            _ => IoErrorKind::Other,
+        }
+    }
+}
+
+/// A USB device with a single CDC-ACM serial port.
+pub struct UsbSerial<'a, 'c> {
+    device: UsbDevice<'a, Usbd<'c>>,
+    port: SerialPort<'a, Usbd<'c>>,
+}
+
+impl<'a, 'c> UsbSerial<'a, 'c> {
+    /// Creates the USB device with the given IDs and strings.
+    pub fn new(
+        bus: &'a UsbBusAllocator<Usbd<'c>>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'static str,
+        product: &'static str,
+        serial_number: &'static str,
+    ) -> Self {
+        let port = SerialPort::new(bus);
+        let device = UsbDeviceBuilder::new(bus, vid_pid)
+            .manufacturer(manufacturer)
+            .product(product)
+            .serial_number(serial_number)
+            .device_class(USB_CLASS_CDC)
+            .build();
+        UsbSerial { device, port }
+    }
+
+    /// Handles USB events. Returns `true` if the serial port may have data to read or room to
+    /// write.
+    ///
+    /// Must be called at least every 10 ms, e.g. from the `USBD` interrupt handler. The blocking
+    /// `Read` and `Write` implementations call it while they wait.
+    pub fn poll(&mut self) -> bool {
+        self.device.poll(&mut [&mut self.port])
+    }
+
+    /// Returns `true` if the device has been configured by the host and a terminal has opened
+    /// the port.
+    pub fn is_connected(&self) -> bool {
+        self.device.state() == UsbDeviceState::Configured && self.port.dtr()
+    }
+
+    /// Returns the serial port, e.g. for non-blocking reads and writes.
+    pub fn port(&mut self) -> &mut SerialPort<'a, Usbd<'c>> {
+        &mut self.port
+    }
+
+    /// Returns the USB device.
+    pub fn device(&mut self) -> &mut UsbDevice<'a, Usbd<'c>> {
+        &mut self.device
+    }
+}
+
+impl embedded_io::ErrorType for UsbSerial<'_, '_> {
+    type Error = Error;
+}
+
+impl embedded_io::Read for UsbSerial<'_, '_> {
+    /// Blocks until at least one byte has been received.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.poll();
+            match self.port.read(buf) {
+                Ok(0) | Err(UsbError::WouldBlock) => {}
+                Ok(n) => return Ok(n),
+                Err(e) => return Err(Error(e)),
+            }
+        }
+    }
+}
+
+impl embedded_io::Write for UsbSerial<'_, '_> {
+    /// Blocks until at least one byte has been queued for sending.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.poll();
+            match self.port.write(buf) {
+                Ok(0) | Err(UsbError::WouldBlock) => {}
+                Ok(n) => return Ok(n),
+                Err(e) => return Err(Error(e)),
+            }
+        }
+    }
+
+    /// Blocks until all queued data has been sent.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        loop {
+            self.poll();
+            match self.port.flush() {
+                Ok(()) => return Ok(()),
+                Err(UsbError::WouldBlock) => {}
+                Err(e) => return Err(Error(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Storage for the wakers of async drivers, and helpers shared by them.
+//!
+//! Drivers register the waker of the task polling them, and the corresponding interrupt handler
+//! wakes the task once the awaited event has happened.
+
+use core::{cell::RefCell, task::Waker};
+use cortex_m::interrupt::{self, Mutex};
+
+/// A single waker slot that can be shared between thread mode and interrupt handlers.
+pub(crate) struct WakerSlot(Mutex<RefCell<Option<Waker>>>);
+
+impl WakerSlot {
+    pub(crate) const fn new() -> Self {
+        WakerSlot(Mutex::new(RefCell::new(None)))
+    }
+
+    /// Stores `waker`, replacing any previously registered waker.
+    pub(crate) fn register(&self, waker: &Waker) {
+        interrupt::free(|cs| {
+            let mut slot = self.0.borrow(cs).borrow_mut();
+            match *slot {
+                Some(ref current) if current.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Wakes the registered task, if any.
+    pub(crate) fn wake(&self) {
+        if let Some(waker) = interrupt::free(|cs| self.0.borrow(cs).borrow_mut().take()) {
+            waker.wake();
+        }
+    }
+}
+
+/// Runs a closure when dropped, unless defused.
+///
+/// Async drivers use it to stop a DMA transfer when the future driving it is dropped before
+/// completion, so the peripheral can't keep writing into a buffer that is no longer borrowed.
+pub(crate) struct OnDrop<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> OnDrop<F> {
+    pub(crate) fn new(f: F) -> Self {
+        OnDrop(Some(f))
+    }
+
+    /// Forgets the closure without running it.
+    pub(crate) fn defuse(mut self) {
+        self.0 = None;
+    }
+}
+
+impl<F: FnOnce()> Drop for OnDrop<F> {
+    fn drop(&mut self) {
+        if let Some(f) = self.0.take() {
+            f();
+        }
+    }
+}
@@ -3,7 +3,11 @@
 //! This HAL implements a basic watchdog timer with 1..=8 handles.
 //! Once the watchdog has been started, it cannot be stopped.
 
-use crate::pac::WDT;
+#[cfg(not(feature = "9160"))]
+use crate::pac::{wdt, WDT};
+
+#[cfg(feature = "9160")]
+use crate::pac::{wdt_ns as wdt, WDT_NS as WDT};
 use handles::*;
 
 /// A type state representing a watchdog that has not been started.
@@ -199,6 +203,11 @@ impl<T> Watchdog<T>
 where
     T: sealed::WdMode,
 {
+    /// Returns reference to the `TIMEOUT` event endpoint for PPI.
+    pub fn event_timeout(&self) -> &wdt::EVENTS_TIMEOUT {
+        &self.wdt.events_timeout
+    }
+
     /// Is the watchdog active?
     #[inline(always)]
     pub fn is_active(&self) -> bool {
@@ -0,0 +1,27 @@
+//! HAL for the nRF52805.
+//!
+//! The nRF52805 is a smaller variant of the nRF52810, without the COMP, PDM and PWM peripherals.
+//! It only comes in a WLCSP package with a reduced set of GPIOs, and of the SAADC inputs only
+//! AIN2 (P0.04) and AIN3 (P0.05) are bonded out.
+
+#![no_std]
+
+use embedded_hal as hal;
+pub use nrf_hal_common::*;
+
+pub mod prelude {
+    pub use crate::hal::prelude::*;
+    pub use nrf_hal_common::prelude::*;
+
+    pub use crate::time::U32Ext;
+}
+
+pub use crate::ccm::Ccm;
+pub use crate::clocks::Clocks;
+pub use crate::delay::Delay;
+pub use crate::ecb::Ecb;
+pub use crate::saadc::Saadc;
+pub use crate::spim::Spim;
+pub use crate::temp::Temp;
+pub use crate::timer::Timer;
+pub use crate::uarte::Uarte;
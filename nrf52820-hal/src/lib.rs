@@ -0,0 +1,27 @@
+//! HAL for the nRF52820.
+//!
+//! The nRF52820 is a smaller variant of the nRF52833 with USBD, but without the SAADC, LPCOMP,
+//! PDM, PWM, I2S and NFCT peripherals. It has TIMER0-3, SPIM0-1 and TWIM0-1, and all of its GPIOs
+//! are on port 0.
+
+#![no_std]
+
+use embedded_hal as hal;
+pub use nrf_hal_common::*;
+
+pub mod prelude {
+    pub use crate::hal::prelude::*;
+    pub use nrf_hal_common::prelude::*;
+
+    pub use crate::time::U32Ext;
+}
+
+pub use crate::ccm::Ccm;
+pub use crate::clocks::Clocks;
+pub use crate::delay::Delay;
+pub use crate::ecb::Ecb;
+pub use crate::spim::Spim;
+pub use crate::temp::Temp;
+pub use crate::timer::Timer;
+pub use crate::uarte::Uarte;
+pub use crate::usbd::Usbd;
@@ -0,0 +1,25 @@
+#![no_std]
+
+use embedded_hal as hal;
+pub use nrf_hal_common::*;
+
+pub mod prelude {
+    pub use crate::hal::prelude::*;
+    pub use nrf_hal_common::prelude::*;
+
+    pub use crate::time::U32Ext;
+}
+
+pub use crate::cache::Cache;
+pub use crate::clocks::Clocks;
+pub use crate::delay::Delay;
+pub use crate::dppi::Dppi;
+pub use crate::ipc::Ipc;
+pub use crate::regulators::Regulators;
+pub use crate::rtc::Rtc;
+pub use crate::saadc::Saadc;
+pub use crate::spim::Spim;
+pub use crate::spu::Spu;
+pub use crate::timer::Timer;
+pub use crate::twim::Twim;
+pub use crate::uarte::Uarte;
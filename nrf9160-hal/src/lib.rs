@@ -1,3 +1,10 @@
+//! HAL for the nRF9160.
+//!
+//! The application runs in the non-secure domain, so the drivers take the non-secure `_NS`
+//! instances of the peripherals. The secure firmware booting the application, e.g. the Secure
+//! Partition Manager, must assign these peripherals, and the flash and RAM regions the
+//! application uses, to the non-secure domain.
+
 #![no_std]
 
 use embedded_hal as hal;
@@ -12,9 +19,14 @@ pub mod prelude {
 
 pub use crate::clocks::Clocks;
 pub use crate::delay::Delay;
+pub use crate::dppi::Dppi;
+pub use crate::egu::Egu;
+pub use crate::ipc::Ipc;
+pub use crate::regulators::Regulators;
 pub use crate::rtc::Rtc;
 pub use crate::saadc::Saadc;
 pub use crate::spim::Spim;
+pub use crate::spu::Spu;
 pub use crate::timer::Timer;
 pub use crate::twim::Twim;
 pub use crate::uarte::Uarte;
@@ -9,8 +9,16 @@ echo Building nrf51-hal...
 cargo build --manifest-path nrf51-hal/Cargo.toml --target thumbv6m-none-eabi
 echo Building nrf9160-hal...
 cargo build --manifest-path nrf9160-hal/Cargo.toml --target thumbv8m.main-none-eabi
+echo Building nrf5340-app-hal...
+cargo build --manifest-path nrf5340-app-hal/Cargo.toml --target thumbv8m.main-none-eabihf
+echo Building nrf5340-net-hal...
+cargo build --manifest-path nrf5340-net-hal/Cargo.toml --target thumbv8m.main-none-eabi
+echo Building nrf52805-hal...
+cargo build --manifest-path nrf52805-hal/Cargo.toml --target thumbv7em-none-eabi
 echo Building nrf52810-hal...
 cargo build --manifest-path nrf52810-hal/Cargo.toml --target thumbv7em-none-eabi
+echo Building nrf52820-hal...
+cargo build --manifest-path nrf52820-hal/Cargo.toml --target thumbv7em-none-eabi
 echo Building nrf52832-hal...
 cargo build --manifest-path nrf52832-hal/Cargo.toml
 echo Building nrf52833-hal...
@@ -18,6 +26,30 @@ cargo build --manifest-path nrf52833-hal/Cargo.toml
 echo Building nrf52840-hal...
 cargo build --manifest-path nrf52840-hal/Cargo.toml
 
+# Build the optional features individually and combined, as the default build
+# above doesn't enable any of them. Chips whose peripheral set changes which
+# code is compiled are built separately.
+for feature in async embedded-hal-1 defmt flash-store "async,embedded-hal-1,defmt,flash-store"; do
+    echo Building nrf51-hal with features $feature...
+    cargo build --manifest-path nrf51-hal/Cargo.toml --target thumbv6m-none-eabi --features="$feature"
+    echo Building nrf9160-hal with features $feature...
+    cargo build --manifest-path nrf9160-hal/Cargo.toml --target thumbv8m.main-none-eabi --features="$feature"
+    echo Building nrf5340-app-hal with features $feature...
+    cargo build --manifest-path nrf5340-app-hal/Cargo.toml --target thumbv8m.main-none-eabihf --features="$feature"
+    echo Building nrf5340-net-hal with features $feature...
+    cargo build --manifest-path nrf5340-net-hal/Cargo.toml --target thumbv8m.main-none-eabi --features="$feature"
+    echo Building nrf52805-hal with features $feature...
+    cargo build --manifest-path nrf52805-hal/Cargo.toml --target thumbv7em-none-eabi --features="$feature"
+    echo Building nrf52832-hal with features $feature...
+    cargo build --manifest-path nrf52832-hal/Cargo.toml --features="$feature"
+done
+for feature in async embedded-hal-1 defmt flash-store usb-serial "async,embedded-hal-1,defmt,flash-store,usb-serial"; do
+    echo Building nrf52820-hal with features $feature...
+    cargo build --manifest-path nrf52820-hal/Cargo.toml --target thumbv7em-none-eabi --features="$feature"
+    echo Building nrf52840-hal with features $feature...
+    cargo build --manifest-path nrf52840-hal/Cargo.toml --features="$feature"
+done
+
 echo Building examples/rtic-demo...
 cargo build --manifest-path examples/rtic-demo/Cargo.toml
 echo Building examples/rtic-demo...
@@ -38,6 +70,10 @@ echo Building examples/ccm-demo...
 cargo build --manifest-path examples/ccm-demo/Cargo.toml --features=52832
 echo Building examples/ppi-demo...
 cargo build --manifest-path examples/ppi-demo/Cargo.toml --features=52832
+echo Building examples/ieee802154-demo...
+cargo build --manifest-path examples/ieee802154-demo/Cargo.toml --features=52833
+echo Building examples/ieee802154-demo...
+cargo build --manifest-path examples/ieee802154-demo/Cargo.toml --features=52840
 
 echo Checking source code formatting...
 cargo +stable fmt -- --check